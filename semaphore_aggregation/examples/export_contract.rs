@@ -0,0 +1,27 @@
+//! Wraps the proof of a small Plonky2 circuit and exports its EVM verifier with a Foundry test
+//! and a fuzzing harness, then checks the exported files on their own. Run it with
+//! `cargo run --release --example export_contract [dir]`, `dir` being `verifier` by default.
+use std::path::PathBuf;
+
+use anyhow::{ensure, Result};
+use semaphore_aggregation::snark::export::{export_artifacts, verify_artifacts};
+use semaphore_aggregation::snark::fixtures::fixture_proof;
+use semaphore_aggregation::snark::pipeline::PipelineConfig;
+use semaphore_aggregation::snark::verifier_api::verify_inside_snark_with_config;
+
+fn main() -> Result<()> {
+    let dir = PathBuf::from(std::env::args().nth(1).unwrap_or_else(|| "verifier".into()));
+    let config = PipelineConfig::new().allow_insecure();
+    let report = verify_inside_snark_with_config(fixture_proof()?, &config)?;
+    export_artifacts(&dir, &report)?;
+
+    // deploys the exported bytecode and calls it with the exported proof
+    let result = verify_artifacts(&dir)?;
+    ensure!(
+        result.success,
+        "the exported verifier rejects the exported proof"
+    );
+    println!("verifier exported to {}", dir.display());
+    println!("gas used: {}", result.gas_used);
+    Ok(())
+}
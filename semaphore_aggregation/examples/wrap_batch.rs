@@ -0,0 +1,28 @@
+//! Wraps several proofs of the same Plonky2 circuit into a single SNARK, verified by a single
+//! EVM verifier. Run it with `cargo run --release --example wrap_batch [batch size]`.
+use anyhow::Result;
+use semaphore_aggregation::snark::fixtures::fixture_proof;
+use semaphore_aggregation::snark::pipeline::PipelineConfig;
+use semaphore_aggregation::snark::verifier_api::verify_batch_inside_snark;
+
+fn main() -> Result<()> {
+    let batch_size: usize = match std::env::args().nth(1) {
+        Some(batch_size) => batch_size.parse()?,
+        None => 2,
+    };
+    let proofs = (0..batch_size)
+        .map(|_| fixture_proof())
+        .collect::<Result<Vec<_>>>()?;
+    let config = PipelineConfig::new().allow_insecure();
+    // the proofs share no public input prefix
+    let report = verify_batch_inside_snark(proofs, 0, &config)?;
+
+    println!("batch size: {batch_size}");
+    println!("k: {}", report.k);
+    println!("instances: {}", report.envelope.instances.len());
+    if let Some(gas_used) = report.gas_used {
+        println!("gas used: {gas_used}");
+        println!("gas used per proof: {}", gas_used / batch_size as u64);
+    }
+    Ok(())
+}
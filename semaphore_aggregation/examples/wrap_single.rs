@@ -0,0 +1,32 @@
+//! Wraps the proof of a small Plonky2 circuit into a SNARK verified by an EVM verifier. Run it
+//! with `cargo run --release --example wrap_single`.
+//!
+//! The SRS is generated locally, which is only fit for tests: load the Perpetual Powers of Tau
+//! SRS with `load_ceremony_srs`, or the SRS of another ceremony with `load_srs`, and drop
+//! `allow_insecure` to generate a verifier to deploy.
+use anyhow::Result;
+use semaphore_aggregation::snark::fixtures::fixture_proof;
+use semaphore_aggregation::snark::pipeline::PipelineConfig;
+use semaphore_aggregation::snark::verifier_api::verify_inside_snark_with_config;
+
+fn main() -> Result<()> {
+    let proof = fixture_proof()?;
+    let config = PipelineConfig::new()
+        .with_inner_proof_check(true)
+        .allow_insecure();
+    let report = verify_inside_snark_with_config(proof, &config)?;
+
+    println!("k: {}", report.k);
+    println!("instances: {}", report.envelope.instances.len());
+    println!("proof size: {} bytes", report.envelope.proof_bytes.len());
+    if let Some(size) = report.deployment_code_size() {
+        println!("deployment code size: {size} bytes");
+    }
+    if let Some(gas_used) = report.gas_used {
+        println!("gas used: {gas_used}");
+    }
+    for (stage, elapsed) in report.stages.iter() {
+        println!("{stage}: {elapsed:?}");
+    }
+    Ok(())
+}
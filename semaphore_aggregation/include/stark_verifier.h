@@ -0,0 +1,58 @@
+/* C interface of the wrapping pipeline, built with the `cffi` feature. See src/snark/ffi.rs. */
+
+#ifndef STARK_VERIFIER_H
+#define STARK_VERIFIER_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum {
+    STARK_VERIFIER_OK = 0,
+    STARK_VERIFIER_INVALID_ARGUMENT = 1,
+    STARK_VERIFIER_INVALID_INNER_PROOF = 2,
+    STARK_VERIFIER_MALFORMED_INPUT = 3,
+    STARK_VERIFIER_NOT_ENOUGH_ROWS = 4,
+    STARK_VERIFIER_INSECURE_SRS = 5,
+    STARK_VERIFIER_VERIFICATION_FAILED = 6,
+    STARK_VERIFIER_PIPELINE_ERROR = 7,
+    STARK_VERIFIER_PANIC = 8,
+} stark_verifier_status;
+
+typedef struct {
+    uint8_t *data;
+    size_t len;
+    size_t capacity;
+} stark_verifier_buffer;
+
+typedef struct {
+    uint32_t max_k;
+    bool allow_insecure_srs;
+    bool g2_constructor_args;
+} stark_verifier_config;
+
+/* Writes the JSON encoded envelope of the wrapped proof to `out`. `config` may be NULL. */
+stark_verifier_status stark_verifier_prove(
+    const uint8_t *proof, size_t proof_len,
+    const uint8_t *verifier_only, size_t verifier_only_len,
+    const uint8_t *common, size_t common_len,
+    const stark_verifier_config *config,
+    stark_verifier_buffer *out);
+
+/* Writes the deployment code of the EVM verifier, constructor arguments included, to `out`. */
+stark_verifier_status stark_verifier_gen_verifier(
+    const uint8_t *proof, size_t proof_len,
+    const uint8_t *verifier_only, size_t verifier_only_len,
+    const uint8_t *common, size_t common_len,
+    const stark_verifier_config *config,
+    stark_verifier_buffer *out);
+
+/* Runs the EVM verifier on `calldata`. `gas_used` may be NULL. */
+stark_verifier_status stark_verifier_verify(
+    const uint8_t *deployment_code, size_t deployment_code_len,
+    const uint8_t *calldata, size_t calldata_len,
+    uint64_t *gas_used);
+
+void stark_verifier_free_buffer(stark_verifier_buffer buffer);
+
+#endif
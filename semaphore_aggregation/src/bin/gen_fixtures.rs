@@ -0,0 +1,17 @@
+//! Writes the fixtures of `snark::fixtures` for hermetic integration tests. Run it with
+//! `cargo run --release --bin gen_fixtures [dir]`, `dir` being `tests/fixtures` by default.
+use std::path::PathBuf;
+
+use anyhow::Result;
+use semaphore_aggregation::snark::fixtures::{gen_fixtures, FIXTURES_DIR};
+
+fn main() -> Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR));
+    let manifest = gen_fixtures(&dir)?;
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
+    println!("fixtures written to {}", dir.display());
+    Ok(())
+}
@@ -0,0 +1,292 @@
+//! Example server wrapping Plonky2 proofs in the background. Run it with
+//! `cargo run --release --features server --bin server`, configured by the environment:
+//! - `STARK_VERIFIER_ADDR`, the address to listen on, `127.0.0.1:3000` by default
+//! - `STARK_VERIFIER_DATA_DIR`, the directory jobs are persisted to, `jobs` by default
+//! - `STARK_VERIFIER_SRS`, the Perpetual Powers of Tau SRS to prove with. Without it, proofs
+//!   are made with a locally generated SRS, only fit for tests.
+//! - `STARK_VERIFIER_MAX_JOBS`, the number of jobs proving at the same time, 1 by default
+//! - `STARK_VERIFIER_WEBHOOK`, a URL the events of every job are posted to, see
+//!   `semaphore_aggregation::snark::events`
+//!
+//! The SRS is loaded once at startup and shared by every job through the pipeline. Proving uses
+//! every core it is given and tens of GB of memory, so jobs beyond `STARK_VERIFIER_MAX_JOBS` wait
+//! in the queue, and the cores are split between the jobs proving at the same time.
+//!
+//! `POST /prove` takes the hex encoded Plonky2 proof, verifier data and common data, in the
+//! byte encoding of `plonky2::util::serialization`, and returns the id of the job.
+//! `GET /jobs/:id` returns its status, and the list of its artifacts once it is done, each
+//! served by `GET /jobs/:id/artifacts/:name`. A job still queued or running when the server
+//! stops is run again at the next start.
+use std::collections::HashMap;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::gate_serialization::default::DefaultGateSerializer;
+use semaphore_aggregation::snark::backend::fallback_to_portable;
+use semaphore_aggregation::snark::events::WebhookSink;
+use semaphore_aggregation::snark::export::{export_artifacts, Manifest, CONTRACT_NAME};
+use semaphore_aggregation::snark::pipeline::PipelineConfig;
+use semaphore_aggregation::snark::verifier_api::{
+    load_ceremony_srs, verify_inside_snark_with_config,
+};
+use semaphore_aggregation::ProofTuple;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+type ApiError = (StatusCode, String);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+struct ProveRequest {
+    proof: String,
+    verifier_only: String,
+    common: String,
+}
+
+impl ProveRequest {
+    fn decode(&self) -> Result<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+        let common =
+            CommonCircuitData::from_bytes(hex::decode(&self.common)?, &DefaultGateSerializer)
+                .map_err(|_| anyhow!("malformed common data"))?;
+        let verifier_only = VerifierOnlyCircuitData::from_bytes(hex::decode(&self.verifier_only)?)
+            .map_err(|_| anyhow!("malformed verifier data"))?;
+        let proof = ProofWithPublicInputs::from_bytes(hex::decode(&self.proof)?, &common)
+            .map_err(|_| anyhow!("malformed proof"))?;
+        Ok((proof, verifier_only, common))
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+enum JobStatus {
+    Queued,
+    Running,
+    Done { manifest: Manifest },
+    Failed { error: String },
+}
+
+#[derive(Serialize)]
+struct JobResponse {
+    id: String,
+    #[serde(flatten)]
+    status: JobStatus,
+    artifacts: Vec<String>,
+}
+
+struct AppState {
+    data_dir: PathBuf,
+    config: PipelineConfig,
+    webhook: Option<String>,
+    permits: Arc<Semaphore>,
+    jobs: Mutex<HashMap<String, JobStatus>>,
+}
+
+impl AppState {
+    fn job_dir(&self, id: &str) -> PathBuf {
+        self.data_dir.join(id)
+    }
+
+    /// Records the status of job `id` in memory and in its `status.json`
+    fn set_status(&self, id: &str, status: JobStatus) -> Result<()> {
+        fs::write(
+            self.job_dir(id).join("status.json"),
+            serde_json::to_vec(&status)?,
+        )?;
+        self.jobs.lock().unwrap().insert(id.to_string(), status);
+        Ok(())
+    }
+
+    /// Loads the persisted jobs, returning the ones to run again
+    fn load_jobs(&self) -> Result<Vec<(String, ProveRequest)>> {
+        let mut pending = Vec::new();
+        for entry in fs::read_dir(&self.data_dir)? {
+            let dir = entry?.path();
+            let id = dir.file_name().unwrap().to_string_lossy().into_owned();
+            let status: JobStatus = serde_json::from_slice(&fs::read(dir.join("status.json"))?)?;
+            if let JobStatus::Queued | JobStatus::Running = status {
+                let request = serde_json::from_slice(&fs::read(dir.join("request.json"))?)?;
+                pending.push((id.clone(), request));
+                self.set_status(&id, JobStatus::Queued)?;
+            } else {
+                self.jobs.lock().unwrap().insert(id, status);
+            }
+        }
+        Ok(pending)
+    }
+}
+
+fn internal_error(e: impl std::fmt::Display) -> ApiError {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+async fn prove(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ProveRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    // rejects malformed proofs before queueing them
+    request
+        .decode()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let id = format!("{:016x}", rand::random::<u64>());
+    fs::create_dir_all(state.job_dir(&id)).map_err(internal_error)?;
+    fs::write(
+        state.job_dir(&id).join("request.json"),
+        serde_json::to_vec(&request).map_err(internal_error)?,
+    )
+    .map_err(internal_error)?;
+    state
+        .set_status(&id, JobStatus::Queued)
+        .map_err(internal_error)?;
+    tokio::spawn(run_job(state, id.clone(), request));
+    Ok(Json(serde_json::json!({ "id": id })))
+}
+
+async fn job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<JobResponse>, ApiError> {
+    let status = state
+        .jobs
+        .lock()
+        .unwrap()
+        .get(&id)
+        .cloned()
+        .ok_or((StatusCode::NOT_FOUND, format!("no job {id}")))?;
+    let artifacts = match status {
+        JobStatus::Done { .. } => {
+            list_artifacts(&state.job_dir(&id).join("artifacts")).map_err(internal_error)?
+        }
+        _ => vec![],
+    };
+    Ok(Json(JobResponse {
+        id,
+        status,
+        artifacts,
+    }))
+}
+
+async fn artifact(
+    State(state): State<Arc<AppState>>,
+    Path((id, name)): Path<(String, String)>,
+) -> Result<Vec<u8>, ApiError> {
+    let known = state.jobs.lock().unwrap().contains_key(&id);
+    if !known || name.contains("..") || name.starts_with('/') {
+        return Err((StatusCode::NOT_FOUND, format!("no artifact {name}")));
+    }
+    fs::read(state.job_dir(&id).join("artifacts").join(&name))
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("no artifact {name}")))
+}
+
+/// Paths of the files under `dir`, relative to it
+fn list_artifacts(dir: &std::path::Path) -> std::io::Result<Vec<String>> {
+    let mut artifacts = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap().to_string_lossy().into_owned();
+        if path.is_dir() {
+            artifacts.extend(
+                list_artifacts(&path)?
+                    .into_iter()
+                    .map(|file| format!("{name}/{file}")),
+            );
+        } else {
+            artifacts.push(name);
+        }
+    }
+    artifacts.sort();
+    Ok(artifacts)
+}
+
+/// Waits for a proving slot, then wraps the proof of job `id` and exports its artifacts
+async fn run_job(state: Arc<AppState>, id: String, request: ProveRequest) {
+    let _permit = state
+        .permits
+        .clone()
+        .acquire_owned()
+        .await
+        .expect("job queue closed");
+    let status = match wrap_job(&state, &id, request).await {
+        Ok(manifest) => JobStatus::Done { manifest },
+        Err(e) => JobStatus::Failed {
+            error: e.to_string(),
+        },
+    };
+    if let Err(e) = state.set_status(&id, status) {
+        eprintln!("failed to record the status of job {id}: {e}");
+    }
+}
+
+async fn wrap_job(state: &AppState, id: &str, request: ProveRequest) -> Result<Manifest> {
+    state.set_status(id, JobStatus::Running)?;
+    let proof = request.decode()?;
+    let mut config = state.config.clone();
+    if let Some(url) = &state.webhook {
+        let sink = WebhookSink::new(url)
+            .with_job_id(id)
+            .with_error_handler(|_, e| eprintln!("{e}"));
+        config = config.with_event_sink(Arc::new(sink));
+    }
+    let report =
+        tokio::task::spawn_blocking(move || verify_inside_snark_with_config(proof, &config))
+            .await??;
+    export_artifacts(&state.job_dir(id).join("artifacts"), &report)?;
+    Ok(Manifest::new(CONTRACT_NAME, &report)?)
+}
+
+fn env_or(name: &str, default: &str) -> String {
+    std::env::var(name).unwrap_or_else(|_| default.to_string())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    fallback_to_portable()?;
+    let addr: SocketAddr = env_or("STARK_VERIFIER_ADDR", "127.0.0.1:3000").parse()?;
+    let data_dir = PathBuf::from(env_or("STARK_VERIFIER_DATA_DIR", "jobs"));
+    let max_jobs: usize = env_or("STARK_VERIFIER_MAX_JOBS", "1").parse()?;
+    fs::create_dir_all(&data_dir)?;
+
+    // jobs proving at the same time share the cores instead of competing for the global pool
+    let num_cores = std::thread::available_parallelism()?.get();
+    let mut config = PipelineConfig::new()
+        .with_inner_proof_check(true)
+        .with_num_threads((num_cores / max_jobs).max(1));
+    match std::env::var_os("STARK_VERIFIER_SRS") {
+        Some(path) => load_ceremony_srs(path.into())?,
+        None => {
+            eprintln!("STARK_VERIFIER_SRS is not set, proving with an insecure SRS");
+            config = config.allow_insecure();
+        }
+    }
+
+    let state = Arc::new(AppState {
+        data_dir,
+        config,
+        webhook: std::env::var("STARK_VERIFIER_WEBHOOK").ok(),
+        permits: Arc::new(Semaphore::new(max_jobs)),
+        jobs: Mutex::new(HashMap::new()),
+    });
+    for (id, request) in state.load_jobs()? {
+        tokio::spawn(run_job(state.clone(), id, request));
+    }
+
+    let app = Router::new()
+        .route("/prove", post(prove))
+        .route("/jobs/:id", get(job))
+        .route("/jobs/:id/artifacts/*name", get(artifact))
+        .with_state(state);
+    println!("listening on {addr}");
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
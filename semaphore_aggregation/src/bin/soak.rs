@@ -0,0 +1,90 @@
+//! Soak test wrapping the fixture proof in a loop, checking every wrapped proof natively and
+//! tracking the resident set size of the process, see `snark::soak`. Run it with
+//! `cargo run --release --bin soak`, configured by the environment:
+//! - `STARK_VERIFIER_SOAK_ITERATIONS`, the number of proofs to wrap, 100 by default
+//! - `STARK_VERIFIER_SOAK_WARMUP`, the number of first proofs ignored in the growth estimate,
+//!   3 by default
+//! - `STARK_VERIFIER_SOAK_MAX_GROWTH`, the growth of the resident set size per proof in bytes
+//!   past which memory is reported as leaking, 1 MiB by default
+//! - `STARK_VERIFIER_SRS`, the Perpetual Powers of Tau SRS to prove with. Without it, proofs
+//!   are made with the insecure SRS of the fixtures.
+//!
+//! Exits with an error if memory leaks, printing the resident set size after every proof.
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+
+use anyhow::{anyhow, bail, Result};
+use semaphore_aggregation::snark::backend::fallback_to_portable;
+use semaphore_aggregation::snark::fixtures::{fixture_proof, FIXTURE_SEED};
+use semaphore_aggregation::snark::instances::OrderedInstances;
+use semaphore_aggregation::snark::pipeline::PipelineConfig;
+use semaphore_aggregation::snark::soak::{rss_bytes, MemoryTracker};
+use semaphore_aggregation::snark::verifier_api::{
+    load_ceremony_srs, use_insecure_srs, verify_inside_snark_with_config,
+};
+
+const MB: f64 = (1 << 20) as f64;
+
+fn env_or<T: FromStr>(name: &str, default: T) -> Result<T> {
+    match env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| anyhow!("{name} is not a valid number: {value}")),
+        Err(_) => Ok(default),
+    }
+}
+
+fn main() -> Result<()> {
+    fallback_to_portable()?;
+    let iterations = env_or("STARK_VERIFIER_SOAK_ITERATIONS", 100usize)?;
+    let warmup = env_or("STARK_VERIFIER_SOAK_WARMUP", 3usize)?;
+    let max_growth = env_or("STARK_VERIFIER_SOAK_MAX_GROWTH", 1u64 << 20)?;
+    let config = match env::var("STARK_VERIFIER_SRS") {
+        Ok(path) => {
+            load_ceremony_srs(PathBuf::from(path))?;
+            PipelineConfig::new()
+        }
+        Err(_) => {
+            use_insecure_srs(FIXTURE_SEED);
+            PipelineConfig::new().allow_insecure()
+        }
+    };
+    if rss_bytes().is_none() {
+        bail!("the resident set size can't be read on this platform");
+    }
+
+    let proof = fixture_proof()?;
+    let instances = OrderedInstances::try_from_public_inputs(&proof.0.public_inputs)?;
+    let mut tracker = MemoryTracker::new(warmup);
+    for i in 0..iterations {
+        let start = Instant::now();
+        let report = verify_inside_snark_with_config(proof.clone(), &config)?;
+        if !report
+            .verifier_key
+            .verify(&instances, &report.envelope.proof_bytes)?
+        {
+            bail!("proof {i} was rejected by the native verifier");
+        }
+        drop(report);
+
+        let rss = rss_bytes().unwrap_or_default();
+        tracker.record(rss);
+        println!(
+            "proof {i}: {:.1}s, rss {:.1} MiB",
+            start.elapsed().as_secs_f64(),
+            rss as f64 / MB
+        );
+    }
+
+    let peak = tracker.peak().unwrap_or_default() as f64 / MB;
+    match tracker.growth_per_iteration() {
+        Some(growth) => println!("peak rss {peak:.1} MiB, growth {growth:.0} bytes per proof"),
+        None => println!("peak rss {peak:.1} MiB, too few proofs past the warmup for a growth"),
+    }
+    if tracker.leaks(max_growth) {
+        bail!("memory grows by more than {max_growth} bytes per proof");
+    }
+    Ok(())
+}
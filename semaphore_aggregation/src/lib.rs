@@ -1,8 +1,14 @@
-use plonky2::plonk::{
-    circuit_data::{CommonCircuitData, VerifierOnlyCircuitData},
-    proof::ProofWithPublicInputs,
+use plonky2::{
+    field::{extension::quartic::QuarticExtension, goldilocks_field::GoldilocksField},
+    hash::poseidon::PoseidonHash,
+    plonk::{
+        circuit_data::{CommonCircuitData, VerifierOnlyCircuitData},
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+        proof::ProofWithPublicInputs,
+    },
 };
 
+#[cfg(feature = "prover")]
 pub mod plonky2_semaphore;
 pub mod snark;
 
@@ -11,3 +17,43 @@ pub type ProofTuple<F, C, const D: usize> = (
     VerifierOnlyCircuitData<C, D>,
     CommonCircuitData<F, D>,
 );
+
+/// Configuration using Poseidon over the Goldilocks field with its quartic extension
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct PoseidonGoldilocksQuarticConfig;
+
+impl GenericConfig<4> for PoseidonGoldilocksQuarticConfig {
+    type F = GoldilocksField;
+    type FE = QuarticExtension<Self::F>;
+    type Hasher = PoseidonHash;
+    type InnerHasher = PoseidonHash;
+}
+
+/// `ProofTuple` whose extension degree `D` is only known at runtime. The verifier circuit only
+/// emulates the quadratic extension of Goldilocks: proofs with `D = 4` are checked natively and
+/// then rejected with `VerifierError::UnsupportedExtensionDegree`.
+pub enum AnyProofTuple {
+    D2(ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>),
+    D4(ProofTuple<GoldilocksField, PoseidonGoldilocksQuarticConfig, 4>),
+}
+
+impl AnyProofTuple {
+    pub fn extension_degree(&self) -> usize {
+        match self {
+            AnyProofTuple::D2(_) => 2,
+            AnyProofTuple::D4(_) => 4,
+        }
+    }
+}
+
+impl From<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>> for AnyProofTuple {
+    fn from(value: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>) -> Self {
+        AnyProofTuple::D2(value)
+    }
+}
+
+impl From<ProofTuple<GoldilocksField, PoseidonGoldilocksQuarticConfig, 4>> for AnyProofTuple {
+    fn from(value: ProofTuple<GoldilocksField, PoseidonGoldilocksQuarticConfig, 4>) -> Self {
+        AnyProofTuple::D4(value)
+    }
+}
@@ -15,7 +15,8 @@ use plonky2::plonk::circuit_data::{CircuitConfig, VerifierCircuitData};
 use plonky2::plonk::config::Hasher;
 use plonky2::plonk::proof::ProofWithPublicInputs;
 
-use crate::snark::verifier_api::verify_inside_snark;
+use crate::snark::pipeline::PipelineConfig;
+use crate::snark::verifier_api::verify_inside_snark_with_config;
 
 use super::report_elapsed;
 use super::signal::{Digest, Signal, C, F};
@@ -27,6 +28,16 @@ impl AccessSet {
         &self,
         signal: Signal,
         verifier_data: &VerifierCircuitData<F, C, 2>,
+    ) -> Result<()> {
+        self.verify_signal_with_config(signal, verifier_data, &PipelineConfig::default())
+    }
+
+    /// Same as `verify_signal`, with the pipeline options given by `config`
+    pub fn verify_signal_with_config(
+        &self,
+        signal: Signal,
+        verifier_data: &VerifierCircuitData<F, C, 2>,
+        config: &PipelineConfig,
     ) -> Result<()> {
         let public_inputs: Vec<F> = self
             .0
@@ -50,7 +61,7 @@ impl AccessSet {
             verifier_data.verifier_only.clone(),
             verifier_data.common.clone(),
         );
-        verify_inside_snark(proof);
+        verify_inside_snark_with_config(proof, config)?;
         Ok(())
     }
 
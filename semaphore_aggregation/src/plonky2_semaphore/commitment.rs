@@ -0,0 +1,82 @@
+//! Commitment to the signals of an aggregated Semaphore proof, computed identically by the
+//! relayer and by the contract consuming the proof: the Keccak256 of the number of roots as a
+//! big-endian `uint32`, the packed roots, then the packed nullifiers. A digest is packed into a
+//! `bytes32` word with its first element in the most significant bytes, so that the contract
+//! packs the four instances of a digest with `packDigest`.
+use plonky2::field::types::PrimeField64;
+
+use super::signal::{Digest, Signal};
+use crate::snark::utils::keccak256;
+
+/// Digest as a `bytes32` word, each element as a big-endian `uint64`
+pub fn pack_digest(digest: &Digest) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    for (bytes, element) in word.chunks_mut(8).zip(digest) {
+        bytes.copy_from_slice(&element.to_canonical_u64().to_be_bytes());
+    }
+    word
+}
+
+/// Commitment to the access set roots and nullifiers of a batch of signals
+pub fn hash_signals(roots: &[Digest], nullifiers: &[Digest]) -> [u8; 32] {
+    let mut packed = (roots.len() as u32).to_be_bytes().to_vec();
+    for digest in roots.iter().chain(nullifiers) {
+        packed.extend_from_slice(&pack_digest(digest));
+    }
+    keccak256(&packed)
+}
+
+/// `hash_signals` of the nullifiers of `signal`, all checked against the same `root`
+pub fn hash_signal(root: &Digest, signal: &Signal) -> [u8; 32] {
+    hash_signals(&[*root], &signal.nullifier)
+}
+
+/// Source of the Solidity library mirroring `hash_signals`
+pub fn signals_library(library_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+library {library_name}SignalHash {{
+    /// Digest made of four Goldilocks elements, each less than 2^64, as a bytes32 word
+    function packDigest(uint256 a, uint256 b, uint256 c, uint256 d) internal pure returns (bytes32) {{
+        require(a < 2**64 && b < 2**64 && c < 2**64 && d < 2**64, "not a Goldilocks digest");
+        return bytes32((a << 192) | (b << 128) | (c << 64) | d);
+    }}
+
+    function hashSignals(bytes32[] memory roots, bytes32[] memory nullifiers) internal pure returns (bytes32) {{
+        return keccak256(abi.encodePacked(uint32(roots.length), roots, nullifiers));
+    }}
+}}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use plonky2::field::types::Field;
+
+    use super::{hash_signals, pack_digest};
+    use crate::plonky2_semaphore::signal::F;
+
+    #[test]
+    fn test_hash_signals() {
+        // the last element is the largest Goldilocks element
+        let digest = [1, 2, 3, 0xffff_ffff_0000_0000].map(F::from_canonical_u64);
+        let word = pack_digest(&digest);
+        assert_eq!(word[7], 1);
+        assert_eq!(word[15], 2);
+        assert_eq!(word[24..], 0xffff_ffff_0000_0000u64.to_be_bytes());
+
+        let other = [4, 5, 6, 7].map(F::from_canonical_u64);
+        // the number of roots separates roots from nullifiers
+        assert_ne!(
+            hash_signals(&[digest], &[other]),
+            hash_signals(&[digest, other], &[])
+        );
+        assert_ne!(
+            hash_signals(&[digest], &[other]),
+            hash_signals(&[other], &[digest])
+        );
+    }
+}
@@ -4,6 +4,7 @@ use colored::Colorize;
 
 pub mod access_set;
 pub mod circuit;
+pub mod commitment;
 pub mod recursion;
 pub mod signal;
 
@@ -277,7 +277,10 @@ mod tests {
             recursion::report_elapsed,
             signal::{Digest, F},
         },
-        snark::verifier_api::{verify_inside_snark, verify_inside_snark_mock},
+        snark::{
+            pipeline::PipelineConfig,
+            verifier_api::{verify_inside_snark_mock, verify_inside_snark_with_config},
+        },
     };
 
     fn semaphore_aggregation(
@@ -347,11 +350,14 @@ mod tests {
                 .chain(final_signal.topics.clone().into_iter().flatten().to_owned())
                 .collect(),
         };
-        verify_inside_snark((
-            proof,
-            verifier_circuit_data.verifier_only.clone(),
-            verifier_circuit_data.common.clone(),
-        ));
+        verify_inside_snark_with_config(
+            (
+                proof,
+                verifier_circuit_data.verifier_only.clone(),
+                verifier_circuit_data.common.clone(),
+            ),
+            &PipelineConfig::new().allow_insecure(),
+        )?;
 
         Ok(())
     }
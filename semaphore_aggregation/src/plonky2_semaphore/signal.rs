@@ -24,6 +24,7 @@ mod tests {
 
     use crate::plonky2_semaphore::access_set::AccessSet;
     use crate::plonky2_semaphore::signal::{Digest, F};
+    use crate::snark::pipeline::PipelineConfig;
 
     #[test]
     fn test_semaphore() -> Result<()> {
@@ -43,6 +44,6 @@ mod tests {
         let topic = F::rand_array();
 
         let (signal, vd) = access_set.make_signal(private_keys[i], topic, i)?;
-        access_set.verify_signal(signal, &vd)
+        access_set.verify_signal_with_config(signal, &vd, &PipelineConfig::new().allow_insecure())
     }
 }
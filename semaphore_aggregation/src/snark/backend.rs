@@ -0,0 +1,154 @@
+//! Backend of the BN254 field arithmetic of the prover. With the `asm` feature, halo2curves is
+//! built with its x86-64 assembly, which needs the ADX and BMI2 extensions. The backend is
+//! selected when halo2curves is compiled, so the fallback on other CPUs is a build without
+//! `asm` shipped next to the `asm` one: `fallback_to_portable`, called at the start of `main`,
+//! runs it in place of the `asm` build on CPUs lacking the extensions.
+//!
+//! The MSMs of the commitment and opening phases always run on the CPU: `create_proof` of the
+//! pinned halo2 release calls its own multiexp, and offers no hook for a GPU backend.
+use std::env;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::process::{self, Command};
+
+use super::error::VerifierError;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldBackend {
+    /// x86-64 assembly of halo2curves
+    Asm,
+    /// Portable Rust arithmetic
+    Portable,
+}
+
+impl FieldBackend {
+    /// Backend the crate was built with
+    pub const fn compiled() -> Self {
+        if cfg!(feature = "asm") {
+            FieldBackend::Asm
+        } else {
+            FieldBackend::Portable
+        }
+    }
+
+    /// Whether the CPU has the extensions used by the assembly backend
+    pub fn cpu_supports_asm() -> bool {
+        #[cfg(target_arch = "x86_64")]
+        {
+            std::is_x86_feature_detected!("adx") && std::is_x86_feature_detected!("bmi2")
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        {
+            false
+        }
+    }
+
+    /// Backend in use, failing with `UnsupportedCpu` if the crate was built with the assembly
+    /// backend and the CPU can't run it
+    pub fn detect() -> Result<Self, VerifierError> {
+        match Self::compiled() {
+            FieldBackend::Asm if !Self::cpu_supports_asm() => Err(VerifierError::UnsupportedCpu),
+            backend => Ok(backend),
+        }
+    }
+}
+
+/// Environment variable holding the path of the portable build of the executable, see
+/// `portable_executable`
+pub const PORTABLE_EXECUTABLE_VAR: &str = "STARK_VERIFIER_PORTABLE_EXE";
+
+/// Portable build of the running executable: the path in `PORTABLE_EXECUTABLE_VAR` if set,
+/// otherwise the executable next to it named with a `-portable` suffix
+pub fn portable_executable() -> io::Result<PathBuf> {
+    if let Some(path) = env::var_os(PORTABLE_EXECUTABLE_VAR) {
+        return Ok(PathBuf::from(path));
+    }
+    let exe = env::current_exe()?;
+    let mut name = exe
+        .file_stem()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "executable has no file name"))?
+        .to_os_string();
+    name.push("-portable");
+    if let Some(extension) = exe.extension() {
+        name.push(".");
+        name.push(extension);
+    }
+    Ok(exe.with_file_name(name))
+}
+
+/// Returns if the CPU runs the backend the executable was built with. Otherwise runs the
+/// portable build of the executable, see `portable_executable`, with the same arguments and
+/// exits with its status, or fails if there is no portable build. Must be called before any
+/// field arithmetic, which would crash on such a CPU.
+pub fn fallback_to_portable() -> io::Result<()> {
+    if FieldBackend::detect().is_ok() {
+        return Ok(());
+    }
+    let portable = portable_executable()?;
+    if !portable.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "the CPU lacks the ADX and BMI2 extensions of the asm build, and there is no \
+                 portable build at {}",
+                portable.display()
+            ),
+        ));
+    }
+    let status = Command::new(portable)
+        .args(env::args_os().skip(1))
+        .status()?;
+    process::exit(status.code().unwrap_or(1))
+}
+
+impl fmt::Display for FieldBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FieldBackend::Asm => write!(f, "asm"),
+            FieldBackend::Portable => write!(f, "portable"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::{fallback_to_portable, portable_executable, FieldBackend, PORTABLE_EXECUTABLE_VAR};
+
+    #[test]
+    fn test_field_backend_detection() {
+        let backend = FieldBackend::detect();
+        if cfg!(feature = "asm") {
+            assert_eq!(backend.is_ok(), FieldBackend::cpu_supports_asm());
+        } else {
+            assert_eq!(backend.unwrap(), FieldBackend::Portable);
+        }
+    }
+
+    #[test]
+    fn test_portable_fallback() {
+        let portable = portable_executable().unwrap();
+        let exe = env::current_exe().unwrap();
+        assert_eq!(portable.parent(), exe.parent());
+        assert!(portable
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .contains("-portable"));
+
+        env::set_var(PORTABLE_EXECUTABLE_VAR, "/opt/verifier-portable");
+        assert_eq!(
+            portable_executable().unwrap(),
+            PathBuf::from("/opt/verifier-portable")
+        );
+        env::remove_var(PORTABLE_EXECUTABLE_VAR);
+
+        // without asm, or on a CPU supporting it, the running build is kept
+        if FieldBackend::detect().is_ok() {
+            fallback_to_portable().unwrap();
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Verifier circuit of several Plonky2 proofs whose public inputs start with the same values,
+//! e.g. the state root every proof of a rollup batch refers to. The shared values are exposed
+//! once, which saves instances and thus calldata and gas of the EVM verifier.
+//!
+//! The proofs may come from different upstream circuits, see `CircuitRegistry`. The shape of
+//! the verification of a proof depends on its common data, so a batch verifier is generated for
+//! a fixed sequence of common data, while the verification key of every proof is selected
+//! in-circuit among the ones registered for its common data.
+use halo2_proofs::{
+    arithmetic::Field,
+    circuit::{floor_planner::V1, Layouter, Value},
+    halo2curves::bn256::Fr,
+    plonk::{Circuit, ConstraintSystem, Error},
+};
+use halo2curves::goldilocks::fp::Goldilocks;
+use halo2wrong::RegionCtx;
+use halo2wrong_maingate::{big_to_fe, fe_to_big, MainGateInstructions, Term};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+use super::{
+    chip::range_table::RangeCheckMode,
+    cost,
+    instances::OrderedInstances,
+    types::{error::ConversionError, verification_key::VerificationKeyValues},
+    verifier_circuit::{MainGateWithRangeConfig, Verifier},
+};
+
+/// Upstream circuits whose proofs a multi-circuit batch accepts: the verification keys
+/// registered for every distinct common data
+#[derive(Clone, Debug, Default)]
+pub struct CircuitRegistry {
+    circuits: Vec<(
+        CommonCircuitData<GoldilocksField, 2>,
+        Vec<VerificationKeyValues<Fr>>,
+    )>,
+}
+
+impl CircuitRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the circuit with verifier data `vd` and common data `cd`
+    pub fn register(
+        &mut self,
+        vd: VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>,
+        cd: CommonCircuitData<GoldilocksField, 2>,
+    ) -> Result<(), ConversionError> {
+        let vk = VerificationKeyValues::try_from(vd)?;
+        let index = match self.circuits.iter().position(|(common, _)| *common == cd) {
+            Some(index) => index,
+            None => {
+                self.circuits.push((cd, vec![]));
+                self.circuits.len() - 1
+            }
+        };
+        let vks = &mut self.circuits[index].1;
+        if !vks
+            .iter()
+            .any(|registered| registered.elements() == vk.elements())
+        {
+            vks.push(vk);
+        }
+        Ok(())
+    }
+
+    /// Number of distinct common data
+    pub fn num_circuits(&self) -> usize {
+        self.circuits.len()
+    }
+
+    /// Verification keys registered for the common data `cd`
+    pub fn verification_keys(
+        &self,
+        cd: &CommonCircuitData<GoldilocksField, 2>,
+    ) -> &[VerificationKeyValues<Fr>] {
+        self.circuits
+            .iter()
+            .find(|(common, _)| common == cd)
+            .map_or(&[], |(_, vks)| vks.as_slice())
+    }
+}
+
+/// Verifies every proof of `verifiers`, and constrains the first `shared_prefix` public inputs
+/// of every proof to equal the ones of the first proof. The instance column holds the shared
+/// public inputs, followed by the other public inputs of every proof in order. Only the proofs
+/// and the strict canonical checks of `verifiers` are used, their nonce, instance layout and
+/// disclosure aren't.
+#[derive(Clone)]
+pub struct BatchVerifier {
+    verifiers: Vec<Verifier>,
+    shared_prefix: usize,
+    /// Verification keys every proof may be verified with, if they are selected in-circuit
+    vk_candidates: Option<Vec<Vec<VerificationKeyValues<Fr>>>>,
+}
+
+impl BatchVerifier {
+    /// Fails with the index of the first proof whose public inputs don't start with the
+    /// `shared_prefix` public inputs of the first proof
+    pub fn new(verifiers: Vec<Verifier>, shared_prefix: usize) -> Result<Self, usize> {
+        if let Some(first) = verifiers.first() {
+            if first.public_inputs().len() < shared_prefix {
+                return Err(0);
+            }
+            let prefix = &first.public_inputs()[..shared_prefix];
+            if let Some(index) = verifiers
+                .iter()
+                .position(|verifier| !verifier.public_inputs().starts_with(prefix))
+            {
+                return Err(index);
+            }
+        }
+        Ok(Self {
+            verifiers,
+            shared_prefix,
+            vk_candidates: None,
+        })
+    }
+
+    /// Constrains the verification key of the proof at index `i` to be one of `candidates[i]`,
+    /// selected in-circuit, instead of trusting the one the proof comes with. Fails with the
+    /// index of the first proof whose verification key isn't one of its candidates.
+    pub fn with_vk_candidates(
+        mut self,
+        candidates: Vec<Vec<VerificationKeyValues<Fr>>>,
+    ) -> Result<Self, usize> {
+        if candidates.len() != self.verifiers.len() {
+            return Err(candidates.len().min(self.verifiers.len()));
+        }
+        let is_candidate = |verifier: &Verifier, candidates: &[VerificationKeyValues<Fr>]| {
+            let elements = verifier.vk().elements();
+            candidates
+                .iter()
+                .all(|candidate| candidate.elements().len() == elements.len())
+                && candidates
+                    .iter()
+                    .any(|candidate| candidate.elements() == elements)
+        };
+        if let Some(index) = self
+            .verifiers
+            .iter()
+            .zip(&candidates)
+            .position(|(verifier, candidates)| !is_candidate(verifier, candidates))
+        {
+            return Err(index);
+        }
+        self.vk_candidates = Some(candidates);
+        Ok(self)
+    }
+
+    pub fn num_proofs(&self) -> usize {
+        self.verifiers.len()
+    }
+
+    pub fn shared_prefix(&self) -> usize {
+        self.shared_prefix
+    }
+
+    /// Values of the instance column: the shared public inputs, then the other public inputs
+    /// of every proof
+    pub fn instances(&self) -> Vec<Fr> {
+        let shared = self
+            .verifiers
+            .first()
+            .map(|verifier| verifier.public_inputs()[..self.shared_prefix].to_vec())
+            .unwrap_or_default();
+        shared
+            .into_iter()
+            .chain(
+                self.verifiers
+                    .iter()
+                    .flat_map(|verifier| verifier.public_inputs()[self.shared_prefix..].to_vec()),
+            )
+            .collect()
+    }
+
+    /// Smallest circuit size in which the verification of every proof fits
+    pub fn min_k(&self) -> u32 {
+        let selection_rows = self.vk_candidates.iter().flatten().map(|candidates| {
+            // a bit per candidate, and a composition of the bits per element
+            candidates.len() * (1 + candidates.first().map_or(0, |vk| vk.elements().len()))
+        });
+        cost::k_for_rows(
+            self.verifiers
+                .iter()
+                .map(Verifier::estimate_rows)
+                .chain(selection_rows)
+                .sum(),
+        )
+    }
+}
+
+impl BatchVerifier {
+    pub fn ordered_instances(&self) -> OrderedInstances {
+        OrderedInstances::new(self.instances())
+    }
+}
+
+impl Circuit<Fr> for BatchVerifier {
+    type Config = MainGateWithRangeConfig<Fr>;
+    type FloorPlanner = V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            verifiers: self
+                .verifiers
+                .iter()
+                .map(|verifier| verifier.without_witnesses())
+                .collect(),
+            shared_prefix: self.shared_prefix,
+            vk_candidates: self.vk_candidates.clone(),
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        MainGateWithRangeConfig::new(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let main_gate = config.main_gate();
+        let goldilocks_chip_config =
+            config.goldilocks_chip_config(RangeCheckMode::Decomposition)?;
+        let (public_inputs, vks): (Vec<_>, Vec<_>) = self
+            .verifiers
+            .iter()
+            .map(|verifier| {
+                verifier
+                    .verify(
+                        &goldilocks_chip_config,
+                        layouter.namespace(|| "Verify proof"),
+                    )
+                    .map(|(assigned, vk)| (assigned.public_inputs, vk))
+            })
+            .collect::<Result<Vec<_>, Error>>()?
+            .into_iter()
+            .unzip();
+        if let Some(candidates) = &self.vk_candidates {
+            layouter.assign_region(
+                || "Select verification keys",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    for ((verifier, vk), candidates) in
+                        self.verifiers.iter().zip(&vks).zip(candidates)
+                    {
+                        let elements = verifier.vk().elements();
+                        // one-hot selection of the candidate the proof is verified with
+                        let bits = candidates
+                            .iter()
+                            .map(|candidate| {
+                                let selected = candidate.elements() == elements;
+                                main_gate.assign_bit(ctx, Value::known(Fr::from(selected as u64)))
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        let terms = bits
+                            .iter()
+                            .map(|bit| Term::Assigned(bit, Fr::one()))
+                            .collect::<Vec<_>>();
+                        let num_selected = main_gate.compose(ctx, &terms, Fr::zero())?;
+                        main_gate.assert_one(ctx, &num_selected)?;
+                        let candidate_elements = candidates
+                            .iter()
+                            .map(VerificationKeyValues::elements)
+                            .collect::<Vec<_>>();
+                        for (i, element) in vk.elements().into_iter().enumerate() {
+                            let terms = bits
+                                .iter()
+                                .zip(&candidate_elements)
+                                .map(|(bit, candidate)| {
+                                    Term::Assigned(
+                                        bit,
+                                        big_to_fe(fe_to_big::<Goldilocks>(candidate[i])),
+                                    )
+                                })
+                                .collect::<Vec<_>>();
+                            let selected = main_gate.compose(ctx, &terms, Fr::zero())?;
+                            main_gate.assert_equal(ctx, element, &selected)?;
+                        }
+                    }
+                    Ok(())
+                },
+            )?;
+        }
+        let (first, others) = match public_inputs.split_first() {
+            Some(split) => split,
+            None => return Ok(()),
+        };
+        layouter.assign_region(
+            || "Check shared public inputs",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                for public_inputs in others.iter() {
+                    for (a, b) in first.iter().zip(public_inputs).take(self.shared_prefix) {
+                        main_gate.assert_equal(ctx, a, b)?;
+                    }
+                }
+                Ok(())
+            },
+        )?;
+        let exposed = first[..self.shared_prefix].iter().chain(
+            public_inputs
+                .iter()
+                .flat_map(|public_inputs| &public_inputs[self.shared_prefix..]),
+        );
+        for (row, public_input) in exposed.enumerate() {
+            main_gate.expose_public(layouter.namespace(|| ""), public_input.clone(), row)?;
+        }
+        Ok(())
+    }
+}
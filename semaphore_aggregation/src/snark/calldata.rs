@@ -0,0 +1,191 @@
+//! Order of the instances and of the proof in the calldata of the EVM verifier. snark-verifier
+//! generates verifiers reading the instances first, while some routers forward the proof bytes
+//! first: for those, the calldata offsets read by the generated Yul are remapped.
+use std::fmt;
+use std::ops::Range;
+
+#[cfg(feature = "prover")]
+use super::error::VerifierError;
+
+/// Layout of the calldata of the EVM verifier
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CalldataLayout {
+    /// The instances as 32-byte words followed by the proof, as laid out by snark-verifier
+    #[default]
+    InstancesFirst,
+    /// The proof followed by the instances as 32-byte words
+    ProofFirst,
+}
+
+impl CalldataLayout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CalldataLayout::InstancesFirst => "instances-first",
+            CalldataLayout::ProofFirst => "proof-first",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "instances-first" => Some(CalldataLayout::InstancesFirst),
+            "proof-first" => Some(CalldataLayout::ProofFirst),
+            _ => None,
+        }
+    }
+
+    /// Offset of the proof in the calldata of `num_instances` instances in this layout
+    pub fn proof_offset(&self, num_instances: usize) -> usize {
+        match self {
+            CalldataLayout::InstancesFirst => 32 * num_instances,
+            CalldataLayout::ProofFirst => 0,
+        }
+    }
+
+    /// Bytes of the calldata holding instance `index`, next to a proof of `proof_len` bytes
+    /// in this layout
+    pub fn instance_range(&self, index: usize, proof_len: usize) -> Range<usize> {
+        let start = match self {
+            CalldataLayout::InstancesFirst => 32 * index,
+            CalldataLayout::ProofFirst => proof_len + 32 * index,
+        };
+        start..start + 32
+    }
+
+    /// Calldata of `instances` and `proof` in this layout
+    pub fn encode(&self, instances: &[[u8; 32]], proof: &[u8]) -> Vec<u8> {
+        let instances = instances.iter().flatten();
+        match self {
+            CalldataLayout::InstancesFirst => instances.chain(proof).copied().collect(),
+            CalldataLayout::ProofFirst => proof.iter().chain(instances).copied().collect(),
+        }
+    }
+
+    /// Remaps the calldata reads of `yul_code`, generated by snark-verifier for
+    /// `num_instances` instances, to this layout. The generated verifier reads every word of
+    /// the calldata once at a literal offset, so the proof ends at the last word read. Fails
+    /// with `VerifierError::CalldataRemap` if a read isn't at a literal offset, or if no word of
+    /// the proof is read.
+    #[cfg(feature = "prover")]
+    pub fn remap_yul(&self, yul_code: &str, num_instances: usize) -> Result<String, VerifierError> {
+        const CALLDATALOAD: &str = "calldataload(";
+        if *self == CalldataLayout::InstancesFirst {
+            return Ok(yul_code.to_string());
+        }
+        let instances_len = 32 * num_instances;
+        let offsets = calldata_offsets(yul_code)?;
+        let proof_len = offsets
+            .iter()
+            .max()
+            .and_then(|offset| (offset + 32).checked_sub(instances_len))
+            .ok_or_else(|| {
+                VerifierError::CalldataRemap(format!(
+                    "the verifier reads no word of the proof after the {num_instances} instances"
+                ))
+            })?;
+
+        let mut remapped = String::with_capacity(yul_code.len());
+        let mut rest = yul_code;
+        for offset in offsets {
+            // `calldata_offsets` found a closed read for every offset
+            let start = rest.find(CALLDATALOAD).unwrap_or_default();
+            let (before, after) = rest.split_at(start + CALLDATALOAD.len());
+            let end = after.find(')').unwrap_or_default();
+            let offset = if offset < instances_len {
+                offset + proof_len
+            } else {
+                offset - instances_len
+            };
+            remapped.push_str(before);
+            remapped.push_str(&format!("{offset:#x}"));
+            rest = &after[end..];
+        }
+        remapped.push_str(rest);
+        Ok(remapped)
+    }
+}
+
+/// Offsets of the `calldataload`s of `yul_code`, in order, failing if one isn't a literal
+#[cfg(feature = "prover")]
+fn calldata_offsets(yul_code: &str) -> Result<Vec<usize>, VerifierError> {
+    yul_code
+        .split("calldataload(")
+        .skip(1)
+        .map(|read| {
+            let offset = read.find(')').map(|end| &read[..end]);
+            offset
+                .and_then(|offset| usize::from_str_radix(offset.trim_start_matches("0x"), 16).ok())
+                .ok_or_else(|| {
+                    VerifierError::CalldataRemap(format!(
+                        "calldataload at a non-literal offset: {}",
+                        offset.unwrap_or(read)
+                    ))
+                })
+        })
+        .collect()
+}
+
+impl fmt::Display for CalldataLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use super::CalldataLayout;
+    use crate::snark::error::VerifierError;
+
+    #[test]
+    fn test_proof_first_layout() {
+        // 2 instances, then a proof of a point and a scalar
+        let yul_code = "mstore(0x0, mod(calldataload(0x0), f_q))
+            mstore(0x20, mod(calldataload(0x20), f_q))
+            let x := calldataload(0x40)
+            let y := calldataload(0x60)
+            mstore(0x80, mod(calldataload(0x80), f_q))";
+        let remapped = CalldataLayout::ProofFirst.remap_yul(yul_code, 2).unwrap();
+        assert_eq!(
+            remapped,
+            "mstore(0x0, mod(calldataload(0x60), f_q))
+            mstore(0x20, mod(calldataload(0x80), f_q))
+            let x := calldataload(0x0)
+            let y := calldataload(0x20)
+            mstore(0x80, mod(calldataload(0x40), f_q))"
+        );
+        assert_eq!(
+            CalldataLayout::InstancesFirst
+                .remap_yul(yul_code, 2)
+                .unwrap(),
+            yul_code
+        );
+        assert!(matches!(
+            CalldataLayout::ProofFirst.remap_yul("let x := calldataload(add(0x20, i))", 1),
+            Err(VerifierError::CalldataRemap(_))
+        ));
+        // the instances only
+        assert!(matches!(
+            CalldataLayout::ProofFirst.remap_yul(yul_code, 6),
+            Err(VerifierError::CalldataRemap(_))
+        ));
+
+        let instances = [[1; 32], [2; 32]];
+        let proof = [3; 96];
+        let calldata = CalldataLayout::ProofFirst.encode(&instances, &proof);
+        assert_eq!(calldata[..96], proof);
+        assert_eq!(calldata[96..128], instances[0]);
+        assert_eq!(CalldataLayout::ProofFirst.proof_offset(2), 0);
+        assert_eq!(CalldataLayout::InstancesFirst.proof_offset(2), 64);
+        assert_eq!(
+            &calldata[CalldataLayout::ProofFirst.instance_range(1, proof.len())],
+            &[2; 32]
+        );
+        assert_eq!(
+            CalldataLayout::InstancesFirst.instance_range(1, proof.len()),
+            32..64
+        );
+        assert_eq!(
+            CalldataLayout::from_name(&CalldataLayout::ProofFirst.to_string()),
+            Some(CalldataLayout::ProofFirst)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+//! Dump of the challenges derived by the verifier circuit during witness generation, to be
+//! cross-checked against a run of the Plonky2 verifier on the same proof.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2curves::goldilocks::fp::MODULUS as GOLDILOCKS_MODULUS;
+use halo2wrong_maingate::{fe_to_big, AssignedValue};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use serde::{Deserialize, Serialize};
+
+use super::types::assigned::{AssignedExtensionFieldValue, AssignedProofChallenges};
+
+/// Challenges as canonical Goldilocks elements, extension elements as `[c_0, c_1]`. Their layout
+/// follows `ProofChallenges` of Plonky2.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChallengeDump {
+    pub plonk_betas: Vec<u64>,
+    pub plonk_gammas: Vec<u64>,
+    pub plonk_alphas: Vec<u64>,
+    pub plonk_zeta: [u64; 2],
+    pub fri_alpha: [u64; 2],
+    pub fri_betas: Vec<[u64; 2]>,
+    pub fri_pow_response: u64,
+    /// Query indices as squeezed from the transcript
+    pub fri_query_challenges: Vec<u64>,
+    /// Query indices reduced modulo the LDE size, as Plonky2 reports them
+    pub fri_query_indices: Vec<u64>,
+}
+
+fn known<F: FieldExt>(value: &AssignedValue<F>) -> Option<u64> {
+    let mut known = None;
+    value.value().map(|v| {
+        known = (fe_to_big(*v) % BigUint::from(GOLDILOCKS_MODULUS)).to_u64();
+    });
+    known
+}
+
+fn known_all<F: FieldExt>(values: &[AssignedValue<F>]) -> Option<Vec<u64>> {
+    values.iter().map(known).collect()
+}
+
+fn known_extension<F: FieldExt>(value: &AssignedExtensionFieldValue<F, 2>) -> Option<[u64; 2]> {
+    Some([known(&value.0[0])?, known(&value.0[1])?])
+}
+
+impl ChallengeDump {
+    /// Values of `challenges`, or `None` if they aren't known, as during key generation.
+    /// `lde_bits` is the log of the size of the LDE domain the queries are sampled from.
+    pub fn from_assigned<F: FieldExt>(
+        challenges: &AssignedProofChallenges<F, 2>,
+        lde_bits: usize,
+    ) -> Option<Self> {
+        let fri = &challenges.fri_challenges;
+        let fri_query_challenges = known_all(&fri.fri_query_indices)?;
+        let lde_mask = 1u64
+            .checked_shl(lde_bits as u32)
+            .map_or(u64::MAX, |size| size - 1);
+        Some(Self {
+            plonk_betas: known_all(&challenges.plonk_betas)?,
+            plonk_gammas: known_all(&challenges.plonk_gammas)?,
+            plonk_alphas: known_all(&challenges.plonk_alphas)?,
+            plonk_zeta: known_extension(&challenges.plonk_zeta)?,
+            fri_alpha: known_extension(&fri.fri_alpha)?,
+            fri_betas: fri
+                .fri_betas
+                .iter()
+                .map(known_extension)
+                .collect::<Option<_>>()?,
+            fri_pow_response: known(&fri.fri_pow_response)?,
+            fri_query_indices: fri_query_challenges
+                .iter()
+                .map(|challenge| challenge & lde_mask)
+                .collect(),
+            fri_query_challenges,
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let json = self
+            .to_json()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, json)
+    }
+}
@@ -0,0 +1,65 @@
+//! Deduplication of the Goldilocks constants of a region. Chips assign constants such as the
+//! zero embedding a value into the extension, the `k_is` of the permutation argument or the
+//! powers of the multiplicative generator over and over, each taking a `MainGate` row. With a
+//! table, every distinct constant is fixed once in the constant column of `MainGate`, and every
+//! later use copies its cell with an equality constraint instead of taking a row.
+//!
+//! Cells can only be reused within the synthesis pass they were assigned in, so a table is
+//! created for every pass of a region, see `GoldilocksChipConfig::with_constants_table`.
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use halo2_proofs::plonk::Error;
+use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
+use halo2wrong::RegionCtx;
+use halo2wrong_maingate::{big_to_fe, fe_to_big, AssignedValue, MainGate, MainGateInstructions};
+use num_bigint::BigUint;
+
+/// Cells of the constants assigned in a region, shared by the chips built from the same
+/// `GoldilocksChipConfig`
+#[derive(Clone, Debug, Default)]
+pub struct ConstantsTable<F: FieldExt> {
+    cells: Rc<RefCell<BTreeMap<BigUint, AssignedValue<F>>>>,
+    uses: Rc<Cell<usize>>,
+}
+
+impl<F: FieldExt> ConstantsTable<F> {
+    pub fn new() -> Self {
+        Self {
+            cells: Rc::default(),
+            uses: Rc::default(),
+        }
+    }
+
+    /// Cell holding `constant`, fixed in the current row of `ctx` on its first use
+    pub fn constant(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        main_gate: &MainGate<F>,
+        constant: Goldilocks,
+    ) -> Result<AssignedValue<F>, Error> {
+        self.uses.set(self.uses.get() + 1);
+        let key = fe_to_big::<Goldilocks>(constant);
+        if let Some(cell) = self.cells.borrow().get(&key) {
+            return Ok(cell.clone());
+        }
+        let cell = main_gate.assign_constant(ctx, big_to_fe(key.clone()))?;
+        self.cells.borrow_mut().insert(key, cell.clone());
+        Ok(cell)
+    }
+
+    /// Number of distinct constants of the table, each taking a row
+    pub fn len(&self) -> usize {
+        self.cells.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.borrow().is_empty()
+    }
+
+    /// Rows saved by the table: one per use of a constant but the first
+    pub fn rows_saved(&self) -> usize {
+        self.uses.get() - self.len()
+    }
+}
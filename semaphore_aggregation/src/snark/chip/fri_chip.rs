@@ -1,7 +1,7 @@
 use halo2_proofs::{arithmetic::Field, plonk::Error};
 use halo2curves::{goldilocks::fp::Goldilocks, group::ff::PrimeField, FieldExt};
 use halo2wrong::RegionCtx;
-use halo2wrong_maingate::{power_of_two, AssignedValue, Term};
+use halo2wrong_maingate::{power_of_two, AssignedCondition, AssignedValue, MainGateConfig, Term};
 use itertools::Itertools;
 use plonky2::util::reverse_index_bits_in_place;
 use poseidon::Spec;
@@ -23,6 +23,51 @@ use super::{
     vector_chip::VectorChip,
 };
 
+/// Configuration of `FriVerifierChip`. FRI verification only uses `MainGate` rows through the
+/// Goldilocks chips, so any circuit configuring a `MainGate` can verify FRI openings.
+#[derive(Clone)]
+pub struct FriVerifierChipConfig<F: FieldExt> {
+    pub goldilocks_chip_config: GoldilocksChipConfig<F>,
+    /// Spec of the Poseidon hasher of the Merkle trees
+    pub spec: Spec<Goldilocks, 12, 11>,
+    pub fri_params: FriParams,
+}
+
+impl<F: FieldExt> FriVerifierChipConfig<F> {
+    pub fn configure(
+        main_gate_config: &MainGateConfig,
+        spec: Spec<Goldilocks, 12, 11>,
+        fri_params: FriParams,
+    ) -> Self {
+        Self {
+            goldilocks_chip_config: GoldilocksChip::configure(main_gate_config),
+            spec,
+            fri_params,
+        }
+    }
+}
+
+/// Verification of FRI openings of Goldilocks polynomials
+pub trait FriInstructions<F: FieldExt> {
+    /// Checks that `fri_proof` proves the openings `fri_openings` of the polynomials committed
+    /// in `initial_merkle_caps`, given the challenges replayed from the transcript
+    fn verify_fri_proof(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        initial_merkle_caps: &[AssignedMerkleCapValues<F>],
+        fri_challenges: &AssignedFriChallenges<F, 2>,
+        fri_openings: &AssignedFriOpenings<F, 2>,
+        fri_proof: &AssignedFriProofValues<F, 2>,
+        fri_instance_info: &FriInstanceInfo<F, 2>,
+    ) -> Result<(), Error>;
+}
+
+/// Checks every FRI query on its own. Merkle paths aren't deduplicated across queries: the
+/// queried indices are challenges, so which nodes two queries share is only known when proving,
+/// while the rows of the circuit are fixed at keygen. Within a query, the cap selector is
+/// computed once for all its Merkle proofs. Across queries, the nodes every path shares are the
+/// top `cap_height` levels, which the Merkle caps already leave out, so a larger `cap_height`
+/// in the Plonky2 config is what cuts the hashes of every query.
 pub struct FriVerifierChip<F: FieldExt> {
     goldilocks_chip_config: GoldilocksChipConfig<F>,
     spec: Spec<Goldilocks, 12, 11>,
@@ -33,6 +78,22 @@ pub struct FriVerifierChip<F: FieldExt> {
 }
 
 impl<F: FieldExt> FriVerifierChip<F> {
+    /// Constructs the chip from `config`, assigning the coset offset of Plonky2 (the
+    /// multiplicative generator of Goldilocks)
+    pub fn new(
+        ctx: &mut RegionCtx<'_, F>,
+        config: &FriVerifierChipConfig<F>,
+    ) -> Result<Self, Error> {
+        let offset = GoldilocksChip::new(&config.goldilocks_chip_config)
+            .constant(ctx, Goldilocks::multiplicative_generator())?;
+        Ok(Self::construct(
+            &config.goldilocks_chip_config,
+            config.spec.clone(),
+            &offset,
+            config.fri_params.clone(),
+        ))
+    }
+
     pub fn construct(
         goldilocks_chip_config: &GoldilocksChipConfig<F>,
         spec: Spec<Goldilocks, 12, 11>,
@@ -90,7 +151,7 @@ impl<F: FieldExt> FriVerifierChip<F> {
         &self,
         ctx: &mut RegionCtx<'_, F>,
         x_index_bits: &[AssignedValue<F>],
-        cap_index: &AssignedValue<F>,
+        cap_selector: &[AssignedCondition<F>],
         initial_merkle_caps: &[AssignedMerkleCapValues<F>],
         initial_trees_proof: &AssignedFriInitialTreeProofValues<F>,
     ) -> Result<(), Error> {
@@ -102,11 +163,11 @@ impl<F: FieldExt> FriVerifierChip<F> {
             .zip(initial_merkle_caps)
             .enumerate()
         {
-            merkle_proof_chip.verify_merkle_proof_to_cap_with_cap_index(
+            merkle_proof_chip.verify_merkle_proof_to_cap_with_cap_selector(
                 ctx,
                 evals,
                 x_index_bits,
-                &cap_index,
+                cap_selector,
                 &cap,
                 merkle_proof,
             )?;
@@ -195,7 +256,7 @@ impl<F: FieldExt> FriVerifierChip<F> {
             0,
         ]);
         let g_inv = g.invert().unwrap();
-        let g = goldilocks_chip.assign_constant(ctx, g)?;
+        let g = goldilocks_chip.constant(ctx, g)?;
 
         // The evaluation vector needs to be reordered first.
         let mut evals = evals.to_vec();
@@ -213,7 +274,7 @@ impl<F: FieldExt> FriVerifierChip<F> {
         let coset_start = goldilocks_chip.mul(ctx, &start, x)?;
 
         // The answer is gotten by interpolating {(x*g^i, P(x*g^i))} and evaluating at beta.
-        let mut g_power = goldilocks_chip.assign_constant(ctx, Goldilocks::one())?;
+        let mut g_power = goldilocks_chip.constant(ctx, Goldilocks::one())?;
         let mut points = vec![];
         for (_, eval) in evals.iter().enumerate() {
             let x = goldilocks_chip.mul(ctx, &coset_start, &g_power)?;
@@ -263,11 +324,20 @@ impl<F: FieldExt> FriVerifierChip<F> {
             .collect_vec();
 
         let cap_index = self.calculate_cap_index(ctx, &x_index_bits[..])?;
+        // the Merkle proofs of the query all end in the same position of their cap, whose
+        // selector is shared by the initial trees and the commit phase trees
+        let merkle_proof_chip =
+            MerkleProofChip::new(&self.goldilocks_chip_config, self.spec.clone());
+        let cap_selector = merkle_proof_chip.cap_selector(
+            ctx,
+            &cap_index,
+            1 << self.fri_params.config.cap_height,
+        )?;
         // verify evaluation proofs for initial polynomials at `x_index` point
         self.verify_initial_merkle_proof(
             ctx,
             &x_index_bits,
-            &cap_index,
+            &cap_selector,
             initial_merkle_caps,
             &round_proof.initial_trees_proof,
         )?;
@@ -313,13 +383,11 @@ impl<F: FieldExt> FriVerifierChip<F> {
                 &fri_betas[i],
             )?;
 
-            let merkle_proof_chip =
-                MerkleProofChip::new(&self.goldilocks_chip_config, self.spec.clone());
-            merkle_proof_chip.verify_merkle_proof_to_cap_with_cap_index(
+            merkle_proof_chip.verify_merkle_proof_to_cap_with_cap_selector(
                 ctx,
                 &evals.iter().flat_map(|eval| eval.0.clone()).collect_vec(),
                 &coset_index_bits,
-                &cap_index,
+                &cap_selector,
                 &fri_proof.commit_phase_merkle_cap_values[i],
                 &round_proof.steps[i].merkle_proof,
             )?;
@@ -341,8 +409,10 @@ impl<F: FieldExt> FriVerifierChip<F> {
         goldilocks_extension_chip.assert_equal_extension(ctx, &prev_eval, &final_poly_eval)?;
         Ok(())
     }
+}
 
-    pub fn verify_fri_proof(
+impl<F: FieldExt> FriInstructions<F> for FriVerifierChip<F> {
+    fn verify_fri_proof(
         &self,
         ctx: &mut RegionCtx<'_, F>,
         initial_merkle_caps: &[AssignedMerkleCapValues<F>],
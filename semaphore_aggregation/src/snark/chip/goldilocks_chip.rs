@@ -12,13 +12,32 @@ use num_bigint::BigUint;
 use num_integer::Integer;
 use num_traits::{Num, Zero};
 
+use super::constants_table::ConstantsTable;
+use super::range_table::RangeTableConfig;
+
 // TODO : range check
 #[derive(Clone, Debug)]
 pub struct GoldilocksChipConfig<F: FieldExt> {
     pub main_gate_config: MainGateConfig,
+    /// Table used by range checks, which decompose values into bits when it is `None`
+    pub range_table: Option<RangeTableConfig>,
+    /// Table deduplicating the constants of the region, see `with_constants_table`
+    pub constants: Option<ConstantsTable<F>>,
     _marker: PhantomData<F>,
 }
 
+impl<F: FieldExt> GoldilocksChipConfig<F> {
+    /// Same config, with a fresh table deduplicating the constants assigned by the chips built
+    /// from it. Meant to be called in the closure of a region, so that every synthesis pass of
+    /// the region has its own table.
+    pub fn with_constants_table(&self) -> Self {
+        Self {
+            constants: Some(ConstantsTable::new()),
+            ..self.clone()
+        }
+    }
+}
+
 pub struct GoldilocksChip<F: FieldExt> {
     goldilocks_chip_config: GoldilocksChipConfig<F>,
 }
@@ -27,6 +46,21 @@ impl<F: FieldExt> GoldilocksChip<F> {
     pub fn configure(main_gate_config: &MainGateConfig) -> GoldilocksChipConfig<F> {
         GoldilocksChipConfig {
             main_gate_config: main_gate_config.clone(),
+            range_table: None,
+            constants: None,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Same as `configure`, with range checks looked up in `range_table`
+    pub fn configure_with_range_table(
+        main_gate_config: &MainGateConfig,
+        range_table: &RangeTableConfig,
+    ) -> GoldilocksChipConfig<F> {
+        GoldilocksChipConfig {
+            main_gate_config: main_gate_config.clone(),
+            range_table: Some(range_table.clone()),
+            constants: None,
             _marker: PhantomData,
         }
     }
@@ -73,6 +107,19 @@ impl<F: FieldExt> GoldilocksChip<F> {
         self.assign_value(ctx, Value::known(constant))
     }
 
+    /// Cell fixed to `constant`, shared with its other uses when the config has a constants
+    /// table
+    pub fn constant(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        constant: Goldilocks,
+    ) -> Result<AssignedValue<F>, Error> {
+        match &self.goldilocks_chip_config.constants {
+            Some(constants) => constants.constant(ctx, &self.main_gate(), constant),
+            None => self.assign_constant(ctx, constant),
+        }
+    }
+
     pub fn add(
         &self,
         ctx: &mut RegionCtx<'_, F>,
@@ -265,7 +312,7 @@ impl<F: FieldExt> GoldilocksChip<F> {
         ctx: &mut RegionCtx<'_, F>,
         a: &AssignedValue<F>,
     ) -> Result<(), Error> {
-        let one = self.assign_constant(ctx, Goldilocks::one())?;
+        let one = self.constant(ctx, Goldilocks::one())?;
         self.assert_equal(ctx, a, &one)
     }
 
@@ -274,7 +321,7 @@ impl<F: FieldExt> GoldilocksChip<F> {
         ctx: &mut RegionCtx<'_, F>,
         a: &AssignedValue<F>,
     ) -> Result<(), Error> {
-        let zero = self.assign_constant(ctx, Goldilocks::zero())?;
+        let zero = self.constant(ctx, Goldilocks::zero())?;
         self.assert_equal(ctx, a, &zero)
     }
 
@@ -454,6 +501,74 @@ impl<F: FieldExt> GoldilocksChip<F> {
         Ok(bits)
     }
 
+    /// Constrains `a` to be a canonical Goldilocks element, i.e. less than
+    /// `p = 2^64 - 2^32 + 1`. `a` is decomposed into 64 bits, then `a < p` holds iff the low
+    /// 32 bits are zero whenever the high 32 bits are all ones.
+    pub fn assert_canonical(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        a: &AssignedValue<F>,
+    ) -> Result<(), Error> {
+        if let Some(range_table) = &self.goldilocks_chip_config.range_table {
+            return self.assert_canonical_with_table(ctx, range_table, a);
+        }
+        let main_gate = self.main_gate();
+        let bits = main_gate.to_bits(ctx, a, 64)?;
+        let (low, high) = bits.split_at(32);
+        let mut high_all_ones = high[0].clone();
+        for bit in high[1..].iter() {
+            high_all_ones = main_gate.and(ctx, &high_all_ones, bit)?;
+        }
+        let terms = low
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| Term::Assigned(bit, power_of_two(i)))
+            .collect_vec();
+        let low = main_gate.compose(ctx, &terms[..], F::zero())?;
+        let overflow = main_gate.mul(ctx, &high_all_ones, &low)?;
+        main_gate.assert_zero(ctx, &overflow)
+    }
+
+    /// Same as `assert_canonical`, decomposing `a` into four 16-bit limbs looked up in
+    /// `range_table`
+    fn assert_canonical_with_table(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        range_table: &RangeTableConfig,
+        a: &AssignedValue<F>,
+    ) -> Result<(), Error> {
+        let main_gate = self.main_gate();
+        let limbs = a.value().map(|a| {
+            let a = fe_to_big(*a);
+            (0..4)
+                .map(|i| {
+                    let limb: BigUint = (&a >> (16 * i)) & BigUint::from(0xffffu64);
+                    big_to_fe::<F>(limb)
+                })
+                .collect::<Vec<F>>()
+        });
+        let limbs = (0..4)
+            .map(|i| range_table.assign_limb(ctx, limbs.as_ref().map(|limbs| limbs[i])))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let compose_limbs = |ctx: &mut RegionCtx<'_, F>, limbs: &[AssignedValue<F>]| {
+            let terms = limbs
+                .iter()
+                .enumerate()
+                .map(|(i, limb)| Term::Assigned(limb, power_of_two(16 * i)))
+                .collect_vec();
+            main_gate.compose(ctx, &terms[..], F::zero())
+        };
+        let composed = compose_limbs(ctx, &limbs)?;
+        main_gate.assert_equal(ctx, &composed, a)?;
+
+        let low = compose_limbs(ctx, &limbs[..2])?;
+        let high = compose_limbs(ctx, &limbs[2..])?;
+        let high_all_ones = main_gate.assign_constant(ctx, F::from(u32::MAX as u64))?;
+        let high_all_ones = main_gate.is_equal(ctx, &high, &high_all_ones)?;
+        let overflow = main_gate.mul(ctx, &high_all_ones, &low)?;
+        main_gate.assert_zero(ctx, &overflow)
+    }
+
     pub fn from_bits(
         &self,
         ctx: &mut RegionCtx<'_, F>,
@@ -486,13 +601,13 @@ impl<F: FieldExt> GoldilocksChip<F> {
         base: Goldilocks,
         power_bits: &[AssignedValue<F>],
     ) -> Result<AssignedValue<F>, Error> {
-        let mut x = self.assign_constant(ctx, Goldilocks::one())?;
-        let one = self.assign_constant(ctx, Goldilocks::one())?;
+        let mut x = self.constant(ctx, Goldilocks::one())?;
+        let one = self.constant(ctx, Goldilocks::one())?;
         for (i, bit) in power_bits.iter().enumerate() {
             let is_zero_bit = self.is_zero(ctx, bit)?;
 
             let power = u64::from(1u64 << i).to_le();
-            let base = self.assign_constant(ctx, base.pow(&[power, 0, 0, 0]))?;
+            let base = self.constant(ctx, base.pow(&[power, 0, 0, 0]))?;
             let multiplicand = self.select(ctx, &one, &base, &is_zero_bit)?;
             x = self.mul(ctx, &x, &multiplicand)?;
         }
@@ -509,3 +624,112 @@ impl<F: FieldExt> GoldilocksChip<F> {
         self.is_zero(ctx, &a_mimus_b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use halo2curves::goldilocks::fp::MODULUS;
+    use halo2wrong::RegionCtx;
+    use halo2wrong_maingate::{MainGate, MainGateConfig};
+
+    use super::GoldilocksChip;
+    use crate::snark::chip::range_table::{
+        DecompositionRangeChecks, EmulationConfig, LookupRangeChecks, RangeCheckMode,
+        RangeTableConfig, RANGE_TABLE_BITS,
+    };
+
+    /// Asserts that `value`, assigned as a witness, is a canonical Goldilocks element, range
+    /// checking as `E` says
+    #[derive(Clone)]
+    struct CanonicalCircuit<E: EmulationConfig> {
+        value: u64,
+        _emulation: PhantomData<E>,
+    }
+
+    impl<E: EmulationConfig> Circuit<Fr> for CanonicalCircuit<E> {
+        type Config = (MainGateConfig, Option<RangeTableConfig>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let main_gate_config = MainGate::configure(meta);
+            let range_table = match E::RANGE_CHECK {
+                RangeCheckMode::Decomposition => None,
+                RangeCheckMode::Lookup => Some(RangeTableConfig::configure(meta)),
+            };
+            (main_gate_config, range_table)
+        }
+
+        fn synthesize(
+            &self,
+            (main_gate_config, range_table): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let config = match &range_table {
+                Some(range_table) => {
+                    range_table.load(&mut layouter)?;
+                    GoldilocksChip::configure_with_range_table(&main_gate_config, range_table)
+                }
+                None => GoldilocksChip::configure(&main_gate_config),
+            };
+            let chip = GoldilocksChip::new(&config);
+            layouter.assign_region(
+                || "canonical",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let value = chip.assign_value(ctx, Value::known(Fr::from(self.value)))?;
+                    chip.assert_canonical(ctx, &value)
+                },
+            )
+        }
+    }
+
+    fn is_satisfied<E: EmulationConfig>(value: u64) -> bool {
+        let circuit = CanonicalCircuit::<E> {
+            value,
+            _emulation: PhantomData,
+        };
+        let k = RANGE_TABLE_BITS as u32 + 1;
+        let prover = MockProver::run(k, &circuit, vec![vec![]]).unwrap();
+        prover.verify().is_ok()
+    }
+
+    const CANONICAL: [u64; 5] = [0, 1, 0xffff_ffff, 0xffff_fffe_ffff_ffff, MODULUS - 1];
+
+    // `p + x` encodes the same element as `x` for `x < 2^32 - 1`
+    const NON_CANONICAL: [u64; 3] = [MODULUS, MODULUS + 1, u64::MAX];
+
+    #[test]
+    fn test_canonical_check_by_decomposition() {
+        for value in CANONICAL {
+            assert!(
+                is_satisfied::<DecompositionRangeChecks>(value),
+                "{value:#x}"
+            );
+        }
+        for value in NON_CANONICAL {
+            assert!(
+                !is_satisfied::<DecompositionRangeChecks>(value),
+                "{value:#x}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_canonical_check_by_lookup() {
+        for value in CANONICAL {
+            assert!(is_satisfied::<LookupRangeChecks>(value), "{value:#x}");
+        }
+        for value in NON_CANONICAL {
+            assert!(!is_satisfied::<LookupRangeChecks>(value), "{value:#x}");
+        }
+    }
+}
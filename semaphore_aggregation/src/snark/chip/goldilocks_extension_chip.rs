@@ -1,3 +1,7 @@
+//! Arithmetic over the quadratic extension `Goldilocks[X] / (X^2 - 7)`, emulated in the scalar
+//! field `F` of the circuit. Elements are pairs of assigned Goldilocks values `(a_0, a_1)`
+//! representing `a_0 + a_1 * X`. The chip only uses `MainGate` rows through `GoldilocksChip`,
+//! so it can be used by any circuit configuring a `MainGate`.
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::plonk::Error;
 use halo2curves::goldilocks::fp2::QuadraticExtension;
@@ -11,6 +15,7 @@ use super::goldilocks_chip::{GoldilocksChip, GoldilocksChipConfig};
 
 pub struct AssignedExtensionAlgebra<F: FieldExt>(pub [AssignedExtensionFieldValue<F, 2>; 2]);
 
+/// Goldilocks quadratic extension chip, built from a `GoldilocksChipConfig`
 pub struct GoldilocksExtensionChip<F: FieldExt> {
     goldilocks_chip_config: GoldilocksChipConfig<F>,
 }
@@ -35,6 +40,7 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         big_to_fe::<Goldilocks>(fe_to_big::<F>(fe))
     }
 
+    /// Non-residue `W` of the extension, such that `X^2 = W`
     pub fn w() -> Goldilocks {
         Goldilocks::from(7)
     }
@@ -66,37 +72,59 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         Ok(AssignedExtensionFieldValue(res.try_into().unwrap()))
     }
 
-    // TODO : optimize
-    pub fn div_add_extension(
+    /// Inverse of `x`, witnessed and constrained by `x * x_inv = 1`. Fails to synthesize if
+    /// `x` is zero.
+    pub fn inverse_extension(
         &self,
         ctx: &mut RegionCtx<'_, F>,
         x: &AssignedExtensionFieldValue<F, 2>,
-        y: &AssignedExtensionFieldValue<F, 2>,
-        z: &AssignedExtensionFieldValue<F, 2>,
     ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
         let goldilocks_chip = self.goldilocks_chip();
-        let y_inv = y.0[0]
+        let mut invertible = true;
+        let x_inv = x.0[0]
             .value()
-            .zip(y.0[1].value())
+            .zip(x.0[1].value())
             .map(|(&hi, &lo)| {
-                let y_inv = QuadraticExtension([
+                let x_inv: Option<QuadraticExtension> = QuadraticExtension([
                     self.native_fe_to_goldilocks(hi),
                     self.native_fe_to_goldilocks(lo),
                 ])
                 .invert()
-                .unwrap()
-                .0
-                .map(|v| self.goldilocks_to_native_fe(v));
-                (y_inv[0], y_inv[1])
+                .into();
+                match x_inv {
+                    Some(x_inv) => {
+                        let x_inv = x_inv.0.map(|v| self.goldilocks_to_native_fe(v));
+                        (x_inv[0], x_inv[1])
+                    }
+                    None => {
+                        invertible = false;
+                        (F::zero(), F::zero())
+                    }
+                }
             })
             .unzip();
-        let y_inv0 = goldilocks_chip.assign_value(ctx, y_inv.0)?;
-        let y_inv1 = goldilocks_chip.assign_value(ctx, y_inv.1)?;
-        let y_inv = AssignedExtensionFieldValue([y_inv0, y_inv1]);
-        // y * y_inv = 1
-        let yy_inv = self.mul(ctx, y, &y_inv)?;
-        self.assert_one_extension(ctx, &yy_inv)?;
+        if !invertible {
+            return Err(Error::Synthesis);
+        }
+        let x_inv0 = goldilocks_chip.assign_value(ctx, x_inv.0)?;
+        let x_inv1 = goldilocks_chip.assign_value(ctx, x_inv.1)?;
+        let x_inv = AssignedExtensionFieldValue([x_inv0, x_inv1]);
+        // x * x_inv = 1
+        let xx_inv = self.mul(ctx, x, &x_inv)?;
+        self.assert_one_extension(ctx, &xx_inv)?;
+        Ok(x_inv)
+    }
 
+    /// `x / y + z`
+    // TODO : optimize
+    pub fn div_add_extension(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        x: &AssignedExtensionFieldValue<F, 2>,
+        y: &AssignedExtensionFieldValue<F, 2>,
+        z: &AssignedExtensionFieldValue<F, 2>,
+    ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
+        let y_inv = self.inverse_extension(ctx, y)?;
         let x_div_y = self.mul(ctx, x, &y_inv)?;
         let res = self.add_extension(ctx, &x_div_y, z)?;
         Ok(res)
@@ -135,7 +163,7 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         scalar: Goldilocks,
     ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
         let goldilocks_chip = self.goldilocks_chip();
-        let assigned_scalar = goldilocks_chip.assign_constant(ctx, scalar)?;
+        let assigned_scalar = goldilocks_chip.constant(ctx, scalar)?;
         let multiplied = multiplicand
             .0
             .iter()
@@ -169,7 +197,7 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
     ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
         let goldilocks_chip = self.goldilocks_chip();
         let elements = (0..2)
-            .map(|_| goldilocks_chip.assign_constant(ctx, Goldilocks::zero()))
+            .map(|_| goldilocks_chip.constant(ctx, Goldilocks::zero()))
             .collect::<Result<Vec<AssignedValue<F>>, Error>>()?;
         Ok(AssignedExtensionFieldValue(elements.try_into().unwrap()))
     }
@@ -180,8 +208,8 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
     ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
         let goldilocks_chip = self.goldilocks_chip();
         let elements = [
-            goldilocks_chip.assign_constant(ctx, Goldilocks::one())?,
-            goldilocks_chip.assign_constant(ctx, Goldilocks::zero())?,
+            goldilocks_chip.constant(ctx, Goldilocks::one())?,
+            goldilocks_chip.constant(ctx, Goldilocks::zero())?,
         ];
         Ok(AssignedExtensionFieldValue(elements))
     }
@@ -192,8 +220,8 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
     ) -> Result<AssignedExtensionFieldValue<F, 2>, Error> {
         let goldilocks_chip = self.goldilocks_chip();
         let elements = [
-            goldilocks_chip.assign_constant(ctx, Goldilocks::from(2))?,
-            goldilocks_chip.assign_constant(ctx, Goldilocks::zero())?,
+            goldilocks_chip.constant(ctx, Goldilocks::from(2))?,
+            goldilocks_chip.constant(ctx, Goldilocks::zero())?,
         ];
         Ok(AssignedExtensionFieldValue(elements))
     }
@@ -255,6 +283,7 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         self.mul_extension(ctx, x, x)
     }
 
+    /// `base^(2^power_log)`, by repeated squaring
     pub fn exp_power_of_2_extension(
         &self,
         ctx: &mut RegionCtx<'_, F>,
@@ -317,11 +346,12 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         let goldilocks_chip = self.goldilocks_chip();
         let elements = constant
             .into_iter()
-            .map(|c| goldilocks_chip.assign_constant(ctx, *c))
+            .map(|c| goldilocks_chip.constant(ctx, *c))
             .collect::<Result<Vec<AssignedValue<F>>, Error>>()?;
         Ok(AssignedExtensionFieldValue(elements.try_into().unwrap()))
     }
 
+    /// Embeds a Goldilocks value into the extension
     pub fn convert_to_extension(
         &self,
         ctx: &mut RegionCtx<'_, F>,
@@ -330,10 +360,11 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         let goldilocks_chip = self.goldilocks_chip();
         Ok(AssignedExtensionFieldValue([
             value.clone(),
-            goldilocks_chip.assign_constant(ctx, Goldilocks::zero())?,
+            goldilocks_chip.constant(ctx, Goldilocks::zero())?,
         ]))
     }
 
+    /// `sum_i terms[i] * base^i`, evaluated with Horner's method
     pub fn reduce_extension(
         &self,
         ctx: &mut RegionCtx<'_, F>,
@@ -421,3 +452,124 @@ impl<F: FieldExt> GoldilocksExtensionChip<F> {
         self.arithmetic_extension(ctx, one, one, cond, &a_minus_b, b)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use halo2curves::goldilocks::fp::Goldilocks;
+    use halo2wrong::RegionCtx;
+    use halo2wrong_maingate::{MainGate, MainGateConfig};
+    use plonky2::field::extension::quadratic::QuadraticExtension;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::{Field, Sample};
+
+    use super::GoldilocksExtensionChip;
+    use crate::snark::chip::goldilocks_chip::GoldilocksChip;
+    use crate::snark::types::to_goldilocks;
+
+    type Ext = QuadraticExtension<GoldilocksField>;
+
+    fn to_limbs(e: Ext) -> [Goldilocks; 2] {
+        e.0.map(to_goldilocks)
+    }
+
+    /// Checks the extension arithmetic of the chip against the host arithmetic of Plonky2
+    #[derive(Clone, Default)]
+    struct ExtensionArithmeticCircuit {
+        a: [Goldilocks; 2],
+        b: [Goldilocks; 2],
+        sum: [Goldilocks; 2],
+        product: [Goldilocks; 2],
+        quotient: [Goldilocks; 2],
+        inverse: [Goldilocks; 2],
+    }
+
+    impl ExtensionArithmeticCircuit {
+        fn new(a: Ext, b: Ext) -> Self {
+            Self {
+                a: to_limbs(a),
+                b: to_limbs(b),
+                sum: to_limbs(a + b),
+                product: to_limbs(a * b),
+                quotient: to_limbs(a / b),
+                inverse: to_limbs(b.inverse()),
+            }
+        }
+    }
+
+    impl Circuit<Fr> for ExtensionArithmeticCircuit {
+        type Config = MainGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MainGate::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let chip = GoldilocksExtensionChip::new(&GoldilocksChip::configure(&config));
+            layouter.assign_region(
+                || "extension arithmetic",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let a = chip.constant_extension(ctx, &self.a)?;
+                    let b = chip.constant_extension(ctx, &self.b)?;
+
+                    let sum = chip.add_extension(ctx, &a, &b)?;
+                    let expected = chip.constant_extension(ctx, &self.sum)?;
+                    chip.assert_equal_extension(ctx, &sum, &expected)?;
+
+                    let product = chip.mul_extension(ctx, &a, &b)?;
+                    let expected = chip.constant_extension(ctx, &self.product)?;
+                    chip.assert_equal_extension(ctx, &product, &expected)?;
+
+                    let quotient = chip.div_extension(ctx, &a, &b)?;
+                    let expected = chip.constant_extension(ctx, &self.quotient)?;
+                    chip.assert_equal_extension(ctx, &quotient, &expected)?;
+
+                    let inverse = chip.inverse_extension(ctx, &b)?;
+                    let expected = chip.constant_extension(ctx, &self.inverse)?;
+                    chip.assert_equal_extension(ctx, &inverse, &expected)?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_extension_arithmetic_matches_plonky2() {
+        let circuit = ExtensionArithmeticCircuit::new(Ext::rand(), Ext::rand());
+        let prover = MockProver::run(12, &circuit, vec![vec![]]).unwrap();
+        prover.assert_satisfied();
+    }
+
+    #[test]
+    fn test_zero_has_no_inverse() {
+        // the quotient by zero fails to synthesize rather than panicking
+        let circuit = ExtensionArithmeticCircuit::default();
+        assert!(matches!(
+            MockProver::run(12, &circuit, vec![vec![]]),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    #[test]
+    fn test_wrong_product_is_rejected() {
+        let a = Ext::rand();
+        let b = Ext::rand();
+        let mut circuit = ExtensionArithmeticCircuit::new(a, b);
+        circuit.product = to_limbs(a * b + Ext::ONE);
+        let prover = MockProver::run(12, &circuit, vec![vec![]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
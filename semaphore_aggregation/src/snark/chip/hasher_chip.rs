@@ -38,7 +38,7 @@ impl<F: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         let initial_state = State::<_, T>::default()
             .words()
             .iter()
-            .map(|word| goldilocks_chip.assign_constant(ctx, *word))
+            .map(|word| goldilocks_chip.constant(ctx, *word))
             .collect::<Result<Vec<AssignedValue<F>>, Error>>()?;
 
         Ok(Self {
@@ -310,6 +310,10 @@ impl<F: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         Ok(())
     }
 
+    /// `num_outputs` elements of the Poseidon sponge absorbing `inputs`, as computed by
+    /// `hash_n_to_m_no_pad` of Plonky2 from a fresh chip: the inputs overwrite the first `RATE`
+    /// words of the state before every permutation, the last block being possibly shorter, and no
+    /// permutation is run for empty inputs
     pub fn hash(
         &mut self,
         ctx: &mut RegionCtx<'_, F>,
@@ -361,3 +365,100 @@ impl<F: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use halo2curves::goldilocks::fp::Goldilocks;
+    use halo2wrong::RegionCtx;
+    use halo2wrong_maingate::{AssignedValue, MainGate, MainGateConfig};
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+    use plonky2::hash::poseidon::PoseidonHash;
+    use plonky2::plonk::config::Hasher;
+    use poseidon::Spec;
+
+    use super::HasherChip;
+    use crate::snark::chip::goldilocks_chip::GoldilocksChip;
+    use crate::snark::types::to_goldilocks;
+    use crate::snark::{cost, RATE, R_F, R_P, T, T_MINUS_ONE};
+
+    /// Checks the hash of the chip against `hash_no_pad` of Plonky2, as used for public inputs
+    #[derive(Clone, Default)]
+    struct HashCircuit {
+        inputs: Vec<Goldilocks>,
+        expected: Vec<Goldilocks>,
+    }
+
+    impl HashCircuit {
+        fn new(num_inputs: usize) -> Self {
+            let inputs = (0..num_inputs)
+                .map(GoldilocksField::from_canonical_usize)
+                .collect::<Vec<_>>();
+            let expected = PoseidonHash::hash_no_pad(&inputs).elements;
+            Self {
+                inputs: inputs.into_iter().map(to_goldilocks).collect(),
+                expected: expected.into_iter().map(to_goldilocks).collect(),
+            }
+        }
+    }
+
+    impl Circuit<Fr> for HashCircuit {
+        type Config = MainGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MainGate::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let goldilocks_chip_config = GoldilocksChip::configure(&config);
+            let goldilocks_chip = GoldilocksChip::new(&goldilocks_chip_config);
+            layouter.assign_region(
+                || "hash",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let inputs = self
+                        .inputs
+                        .iter()
+                        .map(|input| goldilocks_chip.assign_constant(ctx, *input))
+                        .collect::<Result<Vec<AssignedValue<Fr>>, Error>>()?;
+                    let mut hasher_chip = HasherChip::<Fr, T, T_MINUS_ONE, RATE>::new(
+                        ctx,
+                        &Spec::new(R_F, R_P),
+                        &goldilocks_chip_config,
+                    )?;
+                    let outputs = hasher_chip.hash(ctx, inputs, self.expected.len())?;
+                    for (output, expected) in outputs.iter().zip(&self.expected) {
+                        let expected = goldilocks_chip.constant(ctx, *expected)?;
+                        goldilocks_chip.assert_equal(ctx, output, &expected)?;
+                    }
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn test_hash_matches_plonky2() {
+        let spec = Spec::<Goldilocks, T, T_MINUS_ONE>::new(R_F, R_P);
+        // empty, a single full block, a block and one element, and many blocks
+        for num_inputs in [0, RATE, RATE + 1, 1000] {
+            let rows = (num_inputs / RATE + 2) * cost::permutation_rows(&spec) + num_inputs + 64;
+            let circuit = HashCircuit::new(num_inputs);
+            let prover = MockProver::run(cost::k_for_rows(rows), &circuit, vec![vec![]]).unwrap();
+            prover.assert_satisfied();
+        }
+    }
+}
@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use halo2_proofs::plonk::Error;
 use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
 use halo2wrong::RegionCtx;
-use halo2wrong_maingate::AssignedValue;
+use halo2wrong_maingate::{AssignedCondition, AssignedValue};
 use itertools::Itertools;
 use poseidon::Spec;
 
@@ -49,6 +49,40 @@ impl<F: FieldExt> MerkleProofChip<F> {
         cap_index: &AssignedValue<F>,
         merkle_cap: &AssignedMerkleCapValues<F>,
         proof: &AssignedMerkleProofValues<F>,
+    ) -> Result<(), Error> {
+        let cap_selector = self.cap_selector(ctx, cap_index, merkle_cap.0.len())?;
+        self.verify_merkle_proof_to_cap_with_cap_selector(
+            ctx,
+            leaf_data,
+            leaf_index_bits,
+            &cap_selector,
+            merkle_cap,
+            proof,
+        )
+    }
+
+    /// Flags of the hashes of a cap of `cap_len` hashes, the one at `cap_index` being set. The
+    /// Merkle proofs of a FRI query all end in the same position of their cap, so the flags are
+    /// computed once per query rather than once per proof and element of the hash.
+    pub fn cap_selector(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        cap_index: &AssignedValue<F>,
+        cap_len: usize,
+    ) -> Result<Vec<AssignedCondition<F>>, Error> {
+        VectorChip::selector(&self.goldilocks_chip_config, ctx, cap_index, cap_len)
+    }
+
+    /// Same as `verify_merkle_proof_to_cap_with_cap_index`, the position of the cap being
+    /// flagged by `cap_selector`, see `cap_selector`
+    pub fn verify_merkle_proof_to_cap_with_cap_selector(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        leaf_data: &Vec<AssignedValue<F>>,
+        leaf_index_bits: &[AssignedValue<F>],
+        cap_selector: &[AssignedCondition<F>],
+        merkle_cap: &AssignedMerkleCapValues<F>,
+        proof: &AssignedMerkleProofValues<F>,
     ) -> Result<(), Error> {
         let mut hasher = self.hasher(ctx)?;
         let goldilocks_chip = self.goldilocks_chip();
@@ -84,7 +118,7 @@ impl<F: FieldExt> MerkleProofChip<F> {
                     .map(|hash| hash.elements[i].clone())
                     .collect_vec(),
             );
-            let cap_i = vector_chip.access(ctx, &cap_index)?;
+            let cap_i = vector_chip.access_with_selector(ctx, cap_selector)?;
             goldilocks_chip.assert_equal(ctx, &cap_i, &state[i])?;
         }
 
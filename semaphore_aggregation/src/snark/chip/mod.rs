@@ -1,3 +1,4 @@
+pub mod constants_table;
 pub mod fri_chip;
 pub mod goldilocks_chip;
 pub mod goldilocks_extension_algebra_chip;
@@ -5,5 +6,6 @@ pub mod goldilocks_extension_chip;
 pub mod hasher_chip;
 pub mod merkle_proof_chip;
 pub mod plonk;
+pub mod range_table;
 pub mod transcript_chip;
 pub mod vector_chip;
@@ -26,6 +26,7 @@ use crate::snark::chip::goldilocks_extension_algebra_chip::{
 };
 use crate::snark::chip::goldilocks_extension_chip::GoldilocksExtensionChip;
 use crate::snark::types::assigned::{AssignedExtensionFieldValue, AssignedHashValues};
+use crate::snark::types::error::ConversionError;
 
 /// Placeholder value to indicate that a gate doesn't use a selector polynomial.
 const UNUSED_SELECTOR: usize = u32::MAX as usize;
@@ -127,9 +128,11 @@ pub trait CustomGateConstrainer<F: FieldExt>: CustomGateConstrainerClone<F> {
 #[derive(Clone)]
 pub struct CustomGateRef<F: FieldExt>(pub Box<dyn CustomGateConstrainer<F>>);
 
-impl<F: FieldExt> From<&GateRef<GoldilocksField, 2>> for CustomGateRef<F> {
-    fn from(value: &GateRef<GoldilocksField, 2>) -> Self {
-        match value.0.id().as_str().trim_end() {
+impl<F: FieldExt> TryFrom<&GateRef<GoldilocksField, 2>> for CustomGateRef<F> {
+    type Error = ConversionError;
+
+    fn try_from(value: &GateRef<GoldilocksField, 2>) -> Result<Self, ConversionError> {
+        let gate = match value.0.id().as_str().trim_end() {
             "ArithmeticGate { num_ops: 20 }" => Self(Box::new(ArithmeticGateConstrainer {
                 num_ops: value.0.num_ops(),
             })),
@@ -186,11 +189,9 @@ impl<F: FieldExt> From<&GateRef<GoldilocksField, 2>> for CustomGateRef<F> {
                     num_limbs: 4
                 }))
             },
-            s => {
-                println!("{s}");
-                unimplemented!()
-            }
-        }
+            s => return Err(ConversionError::UnsupportedGate(s.to_string())),
+        };
+        Ok(gate)
     }
 }
 
@@ -1,6 +1,6 @@
 use crate::snark::{
     chip::{
-        fri_chip::FriVerifierChip,
+        fri_chip::{FriInstructions, FriVerifierChip, FriVerifierChipConfig},
         goldilocks_chip::{GoldilocksChip, GoldilocksChipConfig},
         transcript_chip::TranscriptChip,
     },
@@ -163,6 +163,20 @@ impl<F: FieldExt> PlonkVerifierChip<F> {
         vk: &AssignedVerificationKeyValues<F>,
         common_data: &CommonData<F>,
         spec: &Spec<Goldilocks, 12, 11>,
+    ) -> Result<(), Error> {
+        self.check_vanishing_poly(ctx, proof, public_inputs_hash, challenges, common_data)?;
+        self.verify_openings(ctx, proof, challenges, vk, common_data, spec)
+    }
+
+    /// Evaluates the gate constraints and the permutation argument at `zeta`, and checks them
+    /// against the opened quotient polynomials
+    pub fn check_vanishing_poly(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        proof: &AssignedProofValues<F, 2>,
+        public_inputs_hash: &AssignedHashValues<F>,
+        challenges: &AssignedProofChallenges<F, 2>,
+        common_data: &CommonData<F>,
     ) -> Result<(), Error> {
         let goldilocks_extension_chip = GoldilocksExtensionChip::new(&self.goldilocks_chip_config);
         let one = goldilocks_extension_chip.one_extension(ctx)?;
@@ -211,7 +225,21 @@ impl<F: FieldExt> PlonkVerifierChip<F> {
                 &computed_vanishing_poly,
             )?;
         }
+        Ok(())
+    }
 
+    /// Verifies the FRI proof of the openings at `zeta` and `g * zeta` against the Merkle caps of
+    /// `vk` and `proof`
+    pub fn verify_openings(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        proof: &AssignedProofValues<F, 2>,
+        challenges: &AssignedProofChallenges<F, 2>,
+        vk: &AssignedVerificationKeyValues<F>,
+        common_data: &CommonData<F>,
+        spec: &Spec<Goldilocks, 12, 11>,
+    ) -> Result<(), Error> {
+        let goldilocks_extension_chip = GoldilocksExtensionChip::new(&self.goldilocks_chip_config);
         let merkle_caps = &[
             vk.constants_sigmas_cap.clone(),
             proof.wires_cap.clone(),
@@ -228,15 +256,12 @@ impl<F: FieldExt> PlonkVerifierChip<F> {
         let zeta_next = goldilocks_extension_chip.scalar_mul(ctx, &challenges.plonk_zeta, g)?;
         let fri_instance_info =
             FriInstanceInfo::new(&challenges.plonk_zeta, &zeta_next, common_data);
-        let offset = self
-            .goldilocks_chip()
-            .assign_constant(ctx, Goldilocks::multiplicative_generator())?;
-        let fri_chip = FriVerifierChip::construct(
-            &self.goldilocks_chip_config,
-            spec.clone(),
-            &offset,
-            common_data.fri_params.clone(),
-        );
+        let fri_chip_config = FriVerifierChipConfig {
+            goldilocks_chip_config: self.goldilocks_chip_config.clone(),
+            spec: spec.clone(),
+            fri_params: common_data.fri_params.clone(),
+        };
+        let fri_chip = FriVerifierChip::new(ctx, &fri_chip_config)?;
         fri_chip.verify_fri_proof(
             ctx,
             merkle_caps,
@@ -0,0 +1,104 @@
+//! Range checks of the Goldilocks emulation against a shared table of 16-bit values, as an
+//! alternative to bit decomposition with `MainGate` rows.
+use halo2_proofs::circuit::{Layouter, Value};
+use halo2_proofs::plonk::{Advice, Column, ConstraintSystem, Error, Fixed, Selector};
+use halo2_proofs::poly::Rotation;
+use halo2curves::FieldExt;
+use halo2wrong::RegionCtx;
+use halo2wrong_maingate::AssignedValue;
+
+/// Number of bits of the values of the range table
+pub const RANGE_TABLE_BITS: usize = 16;
+
+/// How the Goldilocks emulation range checks values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RangeCheckMode {
+    /// Decomposes values into bits with `MainGate` rows. Needs no extra column, but costs about
+    /// two rows per bit.
+    #[default]
+    Decomposition,
+    /// Decomposes values into 16-bit limbs looked up in a shared table. Costs a few rows per
+    /// value, but adds a lookup argument and needs `k > 16` to hold the table.
+    Lookup,
+}
+
+/// Configuration of the Goldilocks emulation. `Circuit::configure` takes no parameters, so the
+/// emulation of a circuit is chosen by a type implementing this trait.
+pub trait EmulationConfig: Clone {
+    const RANGE_CHECK: RangeCheckMode;
+}
+
+/// Range checks by bit decomposition, the default
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DecompositionRangeChecks;
+
+impl EmulationConfig for DecompositionRangeChecks {
+    const RANGE_CHECK: RangeCheckMode = RangeCheckMode::Decomposition;
+}
+
+/// Range checks with the shared 16-bit table
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LookupRangeChecks;
+
+impl EmulationConfig for LookupRangeChecks {
+    const RANGE_CHECK: RangeCheckMode = RangeCheckMode::Lookup;
+}
+
+/// Advice column whose cells are looked up, when enabled, in a fixed column holding
+/// `0..2^16`
+#[derive(Clone, Debug)]
+pub struct RangeTableConfig {
+    selector: Selector,
+    value: Column<Advice>,
+    table: Column<Fixed>,
+}
+
+impl RangeTableConfig {
+    pub fn configure<F: FieldExt>(meta: &mut ConstraintSystem<F>) -> Self {
+        let selector = meta.complex_selector();
+        let value = meta.advice_column();
+        let table = meta.fixed_column();
+        meta.enable_equality(value);
+        meta.lookup_any("16-bit range", |meta| {
+            let selector = meta.query_selector(selector);
+            let value = meta.query_advice(value, Rotation::cur());
+            let table = meta.query_fixed(table, Rotation::cur());
+            vec![(selector * value, table)]
+        });
+        Self {
+            selector,
+            value,
+            table,
+        }
+    }
+
+    /// Fills the table, which has to be done once per circuit
+    pub fn load<F: FieldExt>(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        layouter.assign_region(
+            || "16-bit range table",
+            |mut region| {
+                for i in 0..1 << RANGE_TABLE_BITS {
+                    region.assign_fixed(
+                        || "",
+                        self.table,
+                        i,
+                        || Value::known(F::from(i as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+
+    /// Assigns `value` in the current row and constrains it to be less than `2^16`
+    pub fn assign_limb<F: FieldExt>(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        value: Value<F>,
+    ) -> Result<AssignedValue<F>, Error> {
+        ctx.enable(self.selector)?;
+        let limb = ctx.assign_advice(|| "limb", self.value, value)?;
+        ctx.next();
+        Ok(limb)
+    }
+}
@@ -14,6 +14,8 @@ use super::goldilocks_chip::GoldilocksChipConfig;
 pub struct TranscriptChip<N: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
 {
     hasher_chip: HasherChip<N, T, T_MINUS_ONE, RATE>,
+    #[cfg(feature = "transcript-self-test")]
+    shadow: self_test::ShadowTranscript,
 }
 
 impl<N: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
@@ -26,7 +28,11 @@ impl<N: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         goldilocks_chip_config: &GoldilocksChipConfig<N>,
     ) -> Result<Self, Error> {
         let hasher_chip = HasherChip::new(ctx, spec, goldilocks_chip_config)?;
-        Ok(Self { hasher_chip })
+        Ok(Self {
+            hasher_chip,
+            #[cfg(feature = "transcript-self-test")]
+            shadow: self_test::ShadowTranscript::default(),
+        })
     }
 
     /// Write scalar to the transcript
@@ -35,6 +41,8 @@ impl<N: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         ctx: &mut RegionCtx<'_, N>,
         scalar: &AssignedValue<N>,
     ) -> Result<(), Error> {
+        #[cfg(feature = "transcript-self-test")]
+        self.shadow.observe(scalar);
         self.hasher_chip.update(ctx, scalar)
     }
 
@@ -77,6 +85,132 @@ impl<N: FieldExt, const T: usize, const T_MINUS_ONE: usize, const RATE: usize>
         ctx: &mut RegionCtx<'_, N>,
         num_outputs: usize,
     ) -> Result<Vec<AssignedValue<N>>, Error> {
-        self.hasher_chip.squeeze(ctx, num_outputs)
+        let outputs = self.hasher_chip.squeeze(ctx, num_outputs)?;
+        #[cfg(feature = "transcript-self-test")]
+        self.shadow.check_challenges(&outputs);
+        Ok(outputs)
+    }
+}
+
+/// Host-side Plonky2 challenger run alongside the in-circuit transcript during witness
+/// generation. Every squeezed challenge is compared with the one Plonky2 derives from the same
+/// observed elements, so that a divergence panics at the first wrong challenge instead of
+/// showing up as unrelated constraint failures. Only meaningful for the Poseidon transcript of
+/// width 12 used by Plonky2.
+#[cfg(feature = "transcript-self-test")]
+mod self_test {
+    use halo2_proofs::arithmetic::FieldExt;
+    use halo2wrong_maingate::{fe_to_big, AssignedValue};
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+    use plonky2::hash::poseidon::PoseidonHash;
+    use plonky2::iop::challenger::Challenger;
+
+    pub(super) struct ShadowTranscript {
+        /// `None` once an unknown value was observed, as during key generation
+        challenger: Option<Challenger<GoldilocksField, PoseidonHash>>,
+        num_challenges: usize,
+    }
+
+    impl Default for ShadowTranscript {
+        fn default() -> Self {
+            Self {
+                challenger: Some(Challenger::new()),
+                num_challenges: 0,
+            }
+        }
+    }
+
+    fn to_goldilocks_field<N: FieldExt>(value: &AssignedValue<N>) -> Option<GoldilocksField> {
+        let mut element = None;
+        value.value().map(|v| {
+            element = Some(GoldilocksField::from_noncanonical_biguint(fe_to_big(*v)));
+        });
+        element
+    }
+
+    impl ShadowTranscript {
+        pub(super) fn observe<N: FieldExt>(&mut self, value: &AssignedValue<N>) {
+            self.observe_element(to_goldilocks_field(value));
+        }
+
+        pub(super) fn check_challenges<N: FieldExt>(&mut self, outputs: &[AssignedValue<N>]) {
+            for output in outputs {
+                self.check_challenge(to_goldilocks_field(output));
+            }
+        }
+
+        fn observe_element(&mut self, element: Option<GoldilocksField>) {
+            match (self.challenger.as_mut(), element) {
+                (Some(challenger), Some(element)) => challenger.observe_element(element),
+                _ => self.challenger = None,
+            }
+        }
+
+        fn check_challenge(&mut self, element: Option<GoldilocksField>) {
+            let index = self.num_challenges;
+            self.num_challenges += 1;
+            let (challenger, element) = match (self.challenger.as_mut(), element) {
+                (Some(challenger), Some(element)) => (challenger, element),
+                _ => {
+                    self.challenger = None;
+                    return;
+                }
+            };
+            let expected = challenger.get_challenge();
+            assert_eq!(
+                element, expected,
+                "in-circuit transcript diverges from Plonky2 at challenge {index}"
+            );
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use plonky2::field::goldilocks_field::GoldilocksField;
+        use plonky2::field::types::Field;
+        use plonky2::hash::poseidon::PoseidonHash;
+        use plonky2::iop::challenger::Challenger;
+
+        use super::ShadowTranscript;
+
+        fn challenges(observed: &[GoldilocksField], num_challenges: usize) -> Vec<GoldilocksField> {
+            let mut challenger = Challenger::<GoldilocksField, PoseidonHash>::new();
+            challenger.observe_elements(observed);
+            challenger.get_n_challenges(num_challenges)
+        }
+
+        #[test]
+        fn test_matching_challenges() {
+            let observed = [GoldilocksField::ONE, GoldilocksField::TWO];
+            let mut shadow = ShadowTranscript::default();
+            for element in observed {
+                shadow.observe_element(Some(element));
+            }
+            for challenge in challenges(&observed, 3) {
+                shadow.check_challenge(Some(challenge));
+            }
+            assert_eq!(shadow.num_challenges, 3);
+        }
+
+        #[test]
+        #[should_panic(expected = "diverges from Plonky2 at challenge 1")]
+        fn test_diverging_challenge() {
+            let observed = [GoldilocksField::ONE];
+            let expected = challenges(&observed, 2);
+            let mut shadow = ShadowTranscript::default();
+            shadow.observe_element(Some(observed[0]));
+            shadow.check_challenge(Some(expected[0]));
+            shadow.check_challenge(Some(expected[1] + GoldilocksField::ONE));
+        }
+
+        #[test]
+        fn test_unknown_values_disable_the_check() {
+            // during key generation the values are unknown, and nothing can be compared
+            let mut shadow = ShadowTranscript::default();
+            shadow.observe_element(None);
+            shadow.check_challenge(Some(GoldilocksField::ONE));
+            assert!(shadow.challenger.is_none());
+        }
     }
 }
@@ -1,7 +1,7 @@
 use halo2_proofs::{arithmetic::Field, plonk::Error};
 use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
 use halo2wrong::RegionCtx;
-use halo2wrong_maingate::AssignedValue;
+use halo2wrong_maingate::{AssignedCondition, AssignedValue};
 
 use super::goldilocks_chip::{GoldilocksChip, GoldilocksChipConfig};
 
@@ -27,22 +27,180 @@ impl<F: FieldExt> VectorChip<F> {
         ctx: &mut RegionCtx<'_, F>,
         index: &AssignedValue<F>,
     ) -> Result<AssignedValue<F>, Error> {
-        let main_gate = self.main_gate();
+        let selector = Self::selector(&self.main_gate_config, ctx, index, self.vector.len())?;
+        self.access_with_selector(ctx, &selector)
+    }
+
+    /// Flags of the positions of a vector of length `len`, the one at `index` being the only
+    /// one set. Vectors of the same length accessed at the same index share the flags, computing
+    /// them once instead of at every access.
+    pub fn selector(
+        main_gate_config: &GoldilocksChipConfig<F>,
+        ctx: &mut RegionCtx<'_, F>,
+        index: &AssignedValue<F>,
+        len: usize,
+    ) -> Result<Vec<AssignedCondition<F>>, Error> {
+        let main_gate = GoldilocksChip::new(main_gate_config);
         // this value will be used to check whether the index is in the bound
         let mut not_exists = main_gate.assign_constant(ctx, Goldilocks::one())?;
 
         let zero = main_gate.assign_constant(ctx, Goldilocks::zero())?;
-        let mut element = zero.clone();
-        for (i, v) in self.vector.iter().enumerate() {
+        let mut selector = Vec::with_capacity(len);
+        for i in 0..len {
             let assigned_i = main_gate.assign_constant(ctx, Goldilocks(i as u64))?;
             let i_minus_index = main_gate.sub(ctx, &assigned_i, index)?;
             not_exists = main_gate.mul(ctx, &not_exists, &i_minus_index)?;
-
-            let is_same_index = main_gate.is_equal(ctx, &i_minus_index, &zero)?;
-            element = main_gate.select(ctx, v, &element, &is_same_index)?;
+            selector.push(main_gate.is_equal(ctx, &i_minus_index, &zero)?);
         }
         // if this fails, index is out of the bound, and will return error
         main_gate.assert_zero(ctx, &not_exists)?;
+        Ok(selector)
+    }
+
+    /// Element at the position flagged by `selector`, see `selector`. Fails if `selector`
+    /// isn't as long as the vector.
+    pub fn access_with_selector(
+        &self,
+        ctx: &mut RegionCtx<'_, F>,
+        selector: &[AssignedCondition<F>],
+    ) -> Result<AssignedValue<F>, Error> {
+        if selector.len() != self.vector.len() {
+            return Err(Error::Synthesis);
+        }
+        let main_gate = self.main_gate();
+        let mut element = main_gate.assign_constant(ctx, Goldilocks::zero())?;
+        for (v, is_same_index) in self.vector.iter().zip(selector) {
+            element = main_gate.select(ctx, v, &element, is_same_index)?;
+        }
         Ok(element)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+    use halo2curves::goldilocks::fp::Goldilocks;
+    use halo2wrong::RegionCtx;
+    use halo2wrong_maingate::{MainGate, MainGateConfig};
+
+    use super::VectorChip;
+    use crate::snark::chip::goldilocks_chip::GoldilocksChip;
+
+    const VECTORS: [[u64; 4]; 3] = [[10, 11, 12, 13], [20, 21, 22, 23], [30, 31, 32, 33]];
+
+    /// Reads every vector of `VECTORS` at `index`, with a selector shared by the vectors if
+    /// `shared`, recording the rows used in `rows`
+    #[derive(Clone, Default)]
+    struct AccessCircuit {
+        index: u64,
+        shared: bool,
+        /// Length of the shared selector, the length of the vectors by default
+        selector_len: Option<usize>,
+        rows: Rc<Cell<usize>>,
+    }
+
+    impl Circuit<Fr> for AccessCircuit {
+        type Config = MainGateConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            MainGate::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            main_gate_config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let config = GoldilocksChip::configure(&main_gate_config);
+            let chip = GoldilocksChip::new(&config);
+            layouter.assign_region(
+                || "access",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let index = chip.assign_value(ctx, Value::known(Fr::from(self.index)))?;
+                    let vectors = VECTORS
+                        .iter()
+                        .map(|vector| {
+                            let vector = vector
+                                .iter()
+                                .map(|e| chip.assign_constant(ctx, Goldilocks(*e)))
+                                .collect::<Result<Vec<_>, Error>>()?;
+                            Ok(VectorChip::new(&config, vector))
+                        })
+                        .collect::<Result<Vec<_>, Error>>()?;
+                    let start = ctx.offset();
+                    let selector = if self.shared {
+                        let len = self.selector_len.unwrap_or(VECTORS[0].len());
+                        Some(VectorChip::selector(&config, ctx, &index, len)?)
+                    } else {
+                        None
+                    };
+                    for (vector, values) in vectors.iter().zip(VECTORS) {
+                        let element = match &selector {
+                            Some(selector) => vector.access_with_selector(ctx, selector)?,
+                            None => vector.access(ctx, &index)?,
+                        };
+                        let expected = values.get(self.index as usize).copied().unwrap_or(0);
+                        let expected = chip.assign_constant(ctx, Goldilocks(expected))?;
+                        chip.assert_equal(ctx, &element, &expected)?;
+                    }
+                    self.rows.set(ctx.offset() - start);
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn run(circuit: &AccessCircuit) -> Result<bool, Error> {
+        MockProver::run(10, circuit, vec![vec![]]).map(|prover| prover.verify().is_ok())
+    }
+
+    #[test]
+    fn test_shared_selector() {
+        for index in 0..4 {
+            let separate = AccessCircuit {
+                index,
+                ..Default::default()
+            };
+            let shared = AccessCircuit {
+                index,
+                shared: true,
+                ..Default::default()
+            };
+            assert!(run(&separate).unwrap());
+            assert!(run(&shared).unwrap());
+            // the selector is computed once instead of once per vector
+            assert!(shared.rows.get() < separate.rows.get());
+        }
+
+        // out of bounds
+        let shared = AccessCircuit {
+            index: 4,
+            shared: true,
+            ..Default::default()
+        };
+        assert!(!run(&shared).unwrap());
+    }
+
+    #[test]
+    fn test_selector_length_mismatch() {
+        let circuit = AccessCircuit {
+            index: 1,
+            shared: true,
+            selector_len: Some(3),
+            ..Default::default()
+        };
+        assert!(run(&circuit).is_err());
+    }
+}
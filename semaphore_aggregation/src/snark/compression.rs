@@ -0,0 +1,277 @@
+//! Compression of a wrapper proof by proof composition. The wrapper proof, whose verifier
+//! depends on the shape of the Plonky2 circuit, is made with a Poseidon transcript and verified
+//! by a small fixed circuit built on the halo2 loader of snark-verifier. The circuit defers the
+//! final pairing check of the wrapper proof into a KZG accumulator it exposes as its first
+//! instances, followed by the instances of the wrapper proof, so that the EVM verifier of the
+//! compressed proof checks both proofs with a single pairing.
+//!
+//! The wrapper proof is always GWC, the multi-opening scheme of the compressed proof being the
+//! one of the pipeline.
+use std::rc::Rc;
+
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fr, G1Affine};
+use halo2_proofs::plonk::{self, Circuit, ConstraintSystem, VerifyingKey};
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use itertools::Itertools;
+use snark_verifier::loader::halo2::halo2_wrong_ecc::{
+    self,
+    integer::rns::Rns,
+    maingate::{
+        MainGate, MainGateConfig, MainGateInstructions, RangeChip, RangeConfig, RangeInstructions,
+        RegionCtx,
+    },
+    EccConfig,
+};
+use snark_verifier::loader::{self, native::NativeLoader};
+use snark_verifier::pcs::kzg::{
+    Gwc19, KzgAccumulator, KzgAs, KzgDecidingKey, KzgSuccinctVerifyingKey,
+};
+use snark_verifier::pcs::AccumulationDecider;
+use snark_verifier::system::halo2::{compile, Config};
+use snark_verifier::util::arithmetic::fe_to_limbs;
+use snark_verifier::verifier::plonk::{PlonkProtocol, PlonkSuccinctVerifier};
+use snark_verifier::verifier::SnarkVerifier;
+
+use super::error::VerifierError;
+use super::instances::OrderedInstances;
+
+pub use super::verifier::{BITS, LIMBS};
+/// Number of instances holding the accumulator, the coordinates of its two points
+pub const ACCUMULATOR_LEN: usize = 4 * LIMBS;
+/// Smallest `k` of the compression circuit, which verifies a single proof
+pub const MIN_K: u32 = 21;
+
+const TRANSCRIPT_T: usize = 5;
+const TRANSCRIPT_RATE: usize = 4;
+const TRANSCRIPT_R_F: usize = 8;
+const TRANSCRIPT_R_P: usize = 60;
+
+type As = KzgAs<Bn256, Gwc19>;
+type SuccinctVerifier = PlonkSuccinctVerifier<As>;
+type Svk = KzgSuccinctVerifyingKey<G1Affine>;
+type BaseFieldEccChip = halo2_wrong_ecc::BaseFieldEccChip<G1Affine, LIMBS, BITS>;
+type Halo2Loader<'a> = loader::halo2::Halo2Loader<'a, G1Affine, BaseFieldEccChip>;
+
+/// Poseidon transcript of the wrapper proof, cheap to replay in-circuit
+pub type PoseidonTranscript<L, S> =
+    snark_verifier::system::halo2::transcript::halo2::PoseidonTranscript<
+        G1Affine,
+        L,
+        S,
+        TRANSCRIPT_T,
+        TRANSCRIPT_RATE,
+        TRANSCRIPT_R_F,
+        TRANSCRIPT_R_P,
+    >;
+
+/// Rows of the instance column of the compressed proof holding the accumulator
+pub fn accumulator_indices() -> Vec<(usize, usize)> {
+    (0..ACCUMULATOR_LEN).map(|row| (0, row)).collect()
+}
+
+/// Wrapper proof, with the protocol of the circuit it was made for
+#[derive(Clone, Debug)]
+pub struct WrapperSnark {
+    protocol: PlonkProtocol<G1Affine>,
+    instances: Vec<Fr>,
+    proof: Vec<u8>,
+}
+
+impl WrapperSnark {
+    /// Wrapper `proof` of `instances`, made with `PoseidonTranscript` for the circuit with
+    /// verifying key `vk`, generated from `params`
+    pub fn new(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        proof: Vec<u8>,
+    ) -> Self {
+        let protocol = compile(
+            params,
+            vk,
+            Config::kzg().with_num_instance(instances.num_instance()),
+        );
+        Self {
+            protocol,
+            instances: instances.as_slice().to_vec(),
+            proof,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct SnarkWitness {
+    protocol: PlonkProtocol<G1Affine>,
+    instances: Vec<Value<Fr>>,
+    proof: Value<Vec<u8>>,
+}
+
+impl SnarkWitness {
+    fn without_witnesses(&self) -> Self {
+        Self {
+            protocol: self.protocol.clone(),
+            instances: vec![Value::unknown(); self.instances.len()],
+            proof: Value::unknown(),
+        }
+    }
+
+    fn proof(&self) -> Value<&[u8]> {
+        self.proof.as_ref().map(Vec::as_slice)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CompressionConfig {
+    main_gate_config: MainGateConfig,
+    range_config: RangeConfig,
+}
+
+impl CompressionConfig {
+    fn main_gate(&self) -> MainGate<Fr> {
+        MainGate::new(self.main_gate_config.clone())
+    }
+
+    fn ecc_chip(&self) -> BaseFieldEccChip {
+        BaseFieldEccChip::new(EccConfig::new(
+            self.range_config.clone(),
+            self.main_gate_config.clone(),
+        ))
+    }
+}
+
+/// Circuit verifying a wrapper proof up to its final pairing check, whose accumulator and the
+/// instances of the wrapper proof are its instances
+#[derive(Clone, Debug)]
+pub struct CompressionCircuit {
+    svk: Svk,
+    snark: SnarkWitness,
+    instances: Vec<Fr>,
+}
+
+impl CompressionCircuit {
+    /// Circuit compressing `snark`. Fails if the wrapper proof doesn't verify with the SRS of
+    /// `params`.
+    pub fn new(params: &ParamsKZG<Bn256>, snark: WrapperSnark) -> Result<Self, VerifierError> {
+        let svk: Svk = params.get_g()[0].into();
+        let instances = vec![snark.instances.clone()];
+        let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(snark.proof.as_slice());
+        let proof =
+            SuccinctVerifier::read_proof(&svk, &snark.protocol, &instances, &mut transcript)
+                .map_err(|e| VerifierError::Compression(format!("{e:?}")))?;
+        // the wrapper proof exposes no accumulator, so that its verification yields a single one
+        let accumulator = SuccinctVerifier::verify(&svk, &snark.protocol, &instances, &proof)
+            .map_err(|e| VerifierError::Compression(format!("{e:?}")))?
+            .pop()
+            .unwrap();
+        let dk: KzgDecidingKey<Bn256> = (params.get_g()[0], params.g2(), params.s_g2()).into();
+        if !As::decide(&dk, accumulator.clone()) {
+            return Err(VerifierError::Compression(
+                "the wrapper proof doesn't verify".to_string(),
+            ));
+        }
+
+        let KzgAccumulator { lhs, rhs } = accumulator;
+        let mut circuit_instances = [lhs.x, lhs.y, rhs.x, rhs.y]
+            .map(fe_to_limbs::<_, _, LIMBS, BITS>)
+            .concat();
+        circuit_instances.extend_from_slice(&snark.instances);
+        Ok(Self {
+            svk,
+            snark: SnarkWitness {
+                protocol: snark.protocol,
+                instances: snark.instances.into_iter().map(Value::known).collect(),
+                proof: Value::known(snark.proof),
+            },
+            instances: circuit_instances,
+        })
+    }
+
+    /// Instances of the compressed proof: the accumulator, then the instances of the wrapper
+    /// proof
+    pub fn ordered_instances(&self) -> OrderedInstances {
+        OrderedInstances::new(self.instances.clone())
+    }
+}
+
+impl Circuit<Fr> for CompressionCircuit {
+    type Config = CompressionConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            svk: self.svk,
+            snark: self.snark.without_witnesses(),
+            instances: vec![],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let main_gate_config = MainGate::<Fr>::configure(meta);
+        let range_config = RangeChip::<Fr>::configure(
+            meta,
+            &main_gate_config,
+            vec![BITS / LIMBS],
+            Rns::<Fq, Fr, LIMBS, BITS>::construct().overflow_lengths(),
+        );
+        CompressionConfig {
+            main_gate_config,
+            range_config,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), plonk::Error> {
+        let main_gate = config.main_gate();
+        RangeChip::new(config.range_config.clone()).load_table(&mut layouter)?;
+
+        let cells = layouter.assign_region(
+            || "Verify wrapper proof",
+            |region| {
+                let ctx = RegionCtx::new(region, 0);
+                let loader = Halo2Loader::new(config.ecc_chip(), ctx);
+                let protocol = self.snark.protocol.loaded(&loader);
+                let instances = vec![self
+                    .snark
+                    .instances
+                    .iter()
+                    .map(|instance| loader.assign_scalar(*instance))
+                    .collect_vec()];
+                let mut transcript =
+                    PoseidonTranscript::<Rc<Halo2Loader>, _>::new(&loader, self.snark.proof());
+                let proof =
+                    SuccinctVerifier::read_proof(&self.svk, &protocol, &instances, &mut transcript)
+                        .map_err(|_| plonk::Error::Synthesis)?;
+                let accumulator =
+                    SuccinctVerifier::verify(&self.svk, &protocol, &instances, &proof)
+                        .map_err(|_| plonk::Error::Synthesis)?
+                        .pop()
+                        .unwrap();
+
+                let mut cells = vec![];
+                for point in [accumulator.lhs, accumulator.rhs] {
+                    cells.extend(
+                        loader
+                            .ecc_chip()
+                            .assign_ec_point_to_limbs(&mut loader.ctx_mut(), point.assigned())?,
+                    );
+                }
+                cells.extend(
+                    instances
+                        .into_iter()
+                        .flatten()
+                        .map(|instance| instance.into_assigned()),
+                );
+                Ok(cells)
+            },
+        )?;
+        for (row, cell) in cells.into_iter().enumerate() {
+            main_gate.expose_public(layouter.namespace(|| ""), cell, row)?;
+        }
+        Ok(())
+    }
+}
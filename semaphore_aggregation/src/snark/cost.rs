@@ -0,0 +1,256 @@
+//! Static estimate of the number of rows the verifier circuit consumes. Every Goldilocks
+//! operation in the chips is laid out as one or a few `MainGate` rows, so the estimate counts
+//! operations (dominated by Poseidon permutations) derived from `CommonData` and the hasher spec.
+//! The row counts below are read off the chips: a Goldilocks `add`, `sub`, `mul` or
+//! `mul_add_constant` is a single `MainGate::apply`, i.e. one row, so an extension addition is
+//! 2 rows and `GoldilocksExtensionChip::mul` is 6 (4 products, 2 sums). The estimate is an upper
+//! bound, checked by `test_estimated_k_fits_mock`, and `run_mock_prover` still grows `k` if it
+//! falls short.
+use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
+use poseidon::Spec;
+
+use super::{types::common_data::CommonData, RATE, T, T_MINUS_ONE};
+
+/// Rows at the end of the circuit reserved by halo2 for blinding factors. halo2 reserves
+/// `blinding_factors() + 1` rows, 6 for the rotations `MainGate` queries; the rest is margin
+/// for the range table and the permutation argument.
+pub const RESERVED_ROWS: usize = 16;
+
+/// Number of terms `MainGate` can combine in a single row while composing: of its 5 advice
+/// columns, a chained composition spends one on the sum carried from the previous row and one on
+/// the sum carried to the next.
+const COMPOSE_TERMS_PER_ROW: usize = 3;
+
+/// Rows to evaluate one gate constraint over the extension algebra at `zeta`: an extension
+/// multiplication by the filter of the gate and one by the power of `alpha` it is combined
+/// with, 6 rows each.
+const ROWS_PER_GATE_CONSTRAINT: usize = 12;
+
+fn compose_rows(num_terms: usize) -> usize {
+    (num_terms + COMPOSE_TERMS_PER_ROW - 1) / COMPOSE_TERMS_PER_ROW
+}
+
+fn ceil_div(a: usize, b: usize) -> usize {
+    (a + b - 1) / b
+}
+
+/// Rows of a single Poseidon permutation as laid out by `HasherChip::permutation`.
+pub fn permutation_rows(spec: &Spec<Goldilocks, T, T_MINUS_ONE>) -> usize {
+    let r_f = spec.r_f();
+    let r_p = spec.constants().partial().len();
+    // sbox is `x^7` = 3 muls + 1 mul-add per word
+    let full_round = T * 4 + T * compose_rows(T);
+    let partial_round = 4 + compose_rows(T) + (T - 1) * compose_rows(2);
+    T + r_f * full_round + r_p * partial_round
+}
+
+/// Rows spent hashing the Plonky2 public inputs.
+pub fn public_inputs_hash_rows<F: FieldExt>(
+    common_data: &CommonData<F>,
+    spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+) -> usize {
+    T + ceil_div(common_data.num_public_inputs, RATE).max(1) * permutation_rows(spec)
+}
+
+/// Rows spent committing to the verification key, when it is a witness of the verifier circuit.
+pub fn vk_commitment_rows<F: FieldExt>(
+    common_data: &CommonData<F>,
+    spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+) -> usize {
+    let cap_len = 1 << common_data.fri_params.config.cap_height;
+    T + ceil_div(4 * (cap_len + 1), RATE) * permutation_rows(spec)
+}
+
+/// Number of extension elements opened at `zeta` and `g * zeta`.
+fn num_openings<F: FieldExt>(common_data: &CommonData<F>) -> usize {
+    let num_challenges = common_data.config.num_challenges;
+    common_data.num_constants
+        + common_data.config.num_routed_wires
+        + common_data.config.num_wires
+        + num_challenges * (1 + common_data.num_partial_products)
+        + num_challenges * common_data.quotient_degree_factor
+        + num_challenges
+}
+
+/// Rows spent replaying the Fiat-Shamir transcript.
+pub fn transcript_rows<F: FieldExt>(
+    common_data: &CommonData<F>,
+    spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+) -> usize {
+    let cap_len = 1 << common_data.fri_params.config.cap_height;
+    let num_challenges = common_data.config.num_challenges;
+    let num_openings = num_openings(common_data);
+    let num_reductions = common_data.fri_params.reduction_arity_bits.len();
+    let final_poly_len = 1
+        << (common_data.fri_params.degree_bits
+            - common_data
+                .fri_params
+                .reduction_arity_bits
+                .iter()
+                .sum::<usize>());
+
+    let absorbed = 4
+        + 4
+        + 3 * 4 * cap_len
+        + 2 * num_openings
+        + num_reductions * 4 * cap_len
+        + 2 * final_poly_len
+        + 1;
+    // every squeeze call may trigger one permutation
+    let squeezes = 3 + 1 + 1 + num_reductions + 1 + 1;
+    let squeezed = 3 * num_challenges
+        + 2
+        + 2
+        + 2 * num_reductions
+        + 1
+        + common_data.config.fri_config.num_query_rounds;
+    T + (ceil_div(absorbed, RATE) + squeezes + ceil_div(squeezed, RATE)) * permutation_rows(spec)
+}
+
+/// Rows spent evaluating the custom gate constraints at `zeta`.
+pub fn gate_constraint_rows<F: FieldExt>(common_data: &CommonData<F>) -> usize {
+    common_data.gates.len() * common_data.num_gate_constraints * ROWS_PER_GATE_CONSTRAINT
+}
+
+/// Rows spent checking the FRI query rounds.
+pub fn fri_query_rows<F: FieldExt>(
+    common_data: &CommonData<F>,
+    spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+) -> usize {
+    let permutation = permutation_rows(spec);
+    let fri_params = &common_data.fri_params;
+    let cap_height = fri_params.config.cap_height;
+    let cap_len = 1 << cap_height;
+    let lde_bits = fri_params.lde_bits();
+    let salt = if fri_params.hiding { 4 } else { 0 };
+    // per level of a Merkle path : 8 selects and one permutation
+    let merkle_path_rows = |leaf_len: usize, path_len: usize| {
+        let leaf_hash = if leaf_len <= 4 {
+            0
+        } else {
+            T + ceil_div(leaf_len, RATE) * permutation
+        };
+        // the cap selector being shared, one select per hash of the cap and element, and the
+        // comparison with the root of the path
+        let cap_access = 4 * (cap_len + 2);
+        leaf_hash + path_len * (T + 8 + permutation) + cap_access
+    };
+    // the cap selector of a query, computed once for all its Merkle proofs: per hash of the
+    // cap, the equality flag of `VectorChip::selector` and its accumulation, 6 rows, and the
+    // composition of the cap index from its bits, 3 rows
+    let cap_selector = cap_len * 6 + 3;
+
+    let initial_trees = common_data
+        .fri_oracles()
+        .iter()
+        .map(|oracle| {
+            let leaf_len = oracle.num_polys + if oracle.blinding { salt } else { 0 };
+            merkle_path_rows(leaf_len, lde_bits - cap_height)
+        })
+        .sum::<usize>();
+
+    let mut remaining_bits = lde_bits;
+    let steps = fri_params
+        .reduction_arity_bits
+        .iter()
+        .map(|&arity_bits| {
+            remaining_bits -= arity_bits;
+            let arity = 1 << arity_bits;
+            // `FriChip::next_eval`: per point of the coset, its power of `g` and its
+            // conversion to the extension (6 rows); per point, the interpolation takes an
+            // extension multiplication and a division, i.e. an inverse and a multiplication,
+            // 2 * 6 rows each; `exp_from_bits` spends 3 rows per bit of the coset index and the
+            // coset start and its constants 16
+            let folding = arity * 6 + arity * 2 * 6 * 2 + 3 * arity_bits + 16;
+            merkle_path_rows(2 * arity, remaining_bits.saturating_sub(cap_height)) + folding
+        })
+        .sum::<usize>();
+
+    let num_polys = common_data.fri_all_polys().len() + common_data.fri_zs_polys().len();
+    // `reduce_base_field_terms_extension` is an extension multiplication by `alpha` (6 rows)
+    // and an extension addition (2 rows) per polynomial
+    let batching = num_polys * 8;
+    let final_poly_len =
+        1 << (fri_params.degree_bits - fri_params.reduction_arity_bits.iter().sum::<usize>());
+    // Horner evaluation of the final polynomial, an extension multiplication and addition per
+    // coefficient
+    let final_check = final_poly_len * 8;
+    // the query index is decomposed into all the bits of the native field
+    let index = 2 * F::NUM_BITS as usize + 3 * lde_bits;
+
+    common_data.config.fri_config.num_query_rounds
+        * (initial_trees + steps + cap_selector + batching + final_check + index)
+}
+
+/// Rows of `GoldilocksChip::assert_canonical`: 64 bits and their composition, the conjunction
+/// of the high 32 bits and the composition of the low 32 bits.
+pub const CANONICAL_CHECK_ROWS: usize = 64 + 21 + 31 + 11 + 2;
+
+/// Rows of `GoldilocksChip::assert_canonical` with a range table: 4 looked up limbs, the
+/// compositions of `a` and of its halves, and the comparison of the high half with `2^32 - 1`.
+/// The table itself takes `2^16` rows of its own fixed column.
+pub const CANONICAL_CHECK_LOOKUP_ROWS: usize = 4 + 4 + 1 + 4 + 2;
+
+/// Number of Goldilocks elements in the public inputs and the proof.
+pub fn proof_elements<F: FieldExt>(common_data: &CommonData<F>) -> usize {
+    let fri_params = &common_data.fri_params;
+    let cap_height = fri_params.config.cap_height;
+    let cap_len = 1 << cap_height;
+    let lde_bits = fri_params.lde_bits();
+    let salt = if fri_params.hiding { 4 } else { 0 };
+    let num_reductions = fri_params.reduction_arity_bits.len();
+
+    let initial_trees = common_data
+        .fri_oracles()
+        .iter()
+        .map(|oracle| {
+            let leaf_len = oracle.num_polys + if oracle.blinding { salt } else { 0 };
+            leaf_len + 4 * (lde_bits - cap_height)
+        })
+        .sum::<usize>();
+    let mut remaining_bits = lde_bits;
+    let steps = fri_params
+        .reduction_arity_bits
+        .iter()
+        .map(|&arity_bits| {
+            remaining_bits -= arity_bits;
+            2 * (1 << arity_bits) + 4 * remaining_bits.saturating_sub(cap_height)
+        })
+        .sum::<usize>();
+    let final_poly_len =
+        1 << (fri_params.degree_bits - fri_params.reduction_arity_bits.iter().sum::<usize>());
+
+    common_data.num_public_inputs
+        + 3 * 4 * cap_len
+        + 2 * num_openings(common_data)
+        + num_reductions * 4 * cap_len
+        + common_data.config.fri_config.num_query_rounds * (initial_trees + steps)
+        + 2 * final_poly_len
+        + 1
+}
+
+/// Rows spent checking that every element of the public inputs and the proof is canonical,
+/// when the verifier circuit runs with strict canonical checks.
+pub fn canonical_check_rows<F: FieldExt>(common_data: &CommonData<F>) -> usize {
+    proof_elements(common_data) * CANONICAL_CHECK_ROWS
+}
+
+/// Total estimated number of rows consumed by the verifier circuit.
+pub fn estimate_rows<F: FieldExt>(
+    common_data: &CommonData<F>,
+    spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+) -> usize {
+    public_inputs_hash_rows(common_data, spec)
+        + transcript_rows(common_data, spec)
+        + gate_constraint_rows(common_data)
+        + fri_query_rows(common_data, spec)
+}
+
+/// Smallest `k` such that `rows` fit in the usable part of a circuit of `2^k` rows.
+pub fn k_for_rows(rows: usize) -> u32 {
+    let mut k = 1;
+    while (1usize << k) < rows + RESERVED_ROWS {
+        k += 1;
+    }
+    k
+}
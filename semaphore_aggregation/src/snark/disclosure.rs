@@ -0,0 +1,119 @@
+//! Selective disclosure of the Plonky2 public inputs: only some of them are exposed as instances,
+//! the others are hidden behind a Poseidon commitment computed in-circuit.
+use halo2_proofs::arithmetic::FieldExt;
+use halo2curves::goldilocks::fp::Goldilocks;
+use halo2wrong_maingate::fe_to_big;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field, PrimeField64, Sample};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::plonk::config::Hasher;
+
+use super::types::to_goldilocks;
+
+/// Number of instances holding the commitment to the hidden public inputs
+pub const COMMITMENT_LEN: usize = 4;
+
+/// Public inputs exposed as instances, in increasing order of index. The hidden public inputs
+/// are committed to as `Poseidon(hidden || blinding)`, which is exposed as `COMMITMENT_LEN`
+/// instances after the disclosed ones. The blinding stays private, so that hidden public inputs
+/// with little entropy can't be recovered from the commitment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelectiveDisclosure {
+    disclosed: Vec<usize>,
+    blinding: [GoldilocksField; 4],
+}
+
+impl SelectiveDisclosure {
+    pub fn new(mut disclosed: Vec<usize>, blinding: [GoldilocksField; 4]) -> Self {
+        disclosed.sort_unstable();
+        disclosed.dedup();
+        Self {
+            disclosed,
+            blinding,
+        }
+    }
+
+    /// Same as `new`, with a random blinding
+    pub fn random(disclosed: Vec<usize>) -> Self {
+        Self::new(disclosed, GoldilocksField::rand_array())
+    }
+
+    /// Indices of the disclosed public inputs
+    pub fn disclosed(&self) -> &[usize] {
+        &self.disclosed
+    }
+
+    /// Blinding as elements to assign in-circuit
+    pub fn blinding(&self) -> [Goldilocks; 4] {
+        self.blinding.map(to_goldilocks)
+    }
+
+    /// Returns the first disclosed index which is out of range for `num_public_inputs`
+    pub fn check(&self, num_public_inputs: usize) -> Result<(), usize> {
+        match self.disclosed.last() {
+            Some(&index) if index >= num_public_inputs => Err(index),
+            _ => Ok(()),
+        }
+    }
+
+    /// Splits `public_inputs` into the disclosed and the hidden ones
+    pub fn split<T: Clone>(&self, public_inputs: &[T]) -> (Vec<T>, Vec<T>) {
+        let (disclosed, hidden): (Vec<_>, Vec<_>) = public_inputs
+            .iter()
+            .enumerate()
+            .partition(|(i, _)| self.disclosed.binary_search(i).is_ok());
+        let values = |v: Vec<(usize, &T)>| v.into_iter().map(|(_, t)| t.clone()).collect();
+        (values(disclosed), values(hidden))
+    }
+
+    /// Commitment to the hidden public inputs
+    pub fn commitment<F: FieldExt>(&self, public_inputs: &[F]) -> [F; COMMITMENT_LEN] {
+        let (_, hidden) = self.split(public_inputs);
+        let inputs = hidden
+            .iter()
+            .map(|e| GoldilocksField::from_noncanonical_biguint(fe_to_big(*e)))
+            .chain(self.blinding)
+            .collect::<Vec<_>>();
+        PoseidonHash::hash_no_pad(&inputs)
+            .elements
+            .map(|e| F::from(e.to_canonical_u64()))
+    }
+
+    /// Instances exposed in place of `public_inputs`: the disclosed ones, then the commitment to
+    /// the hidden ones
+    pub fn instances<F: FieldExt>(&self, public_inputs: &[F]) -> Vec<F> {
+        let (disclosed, _) = self.split(public_inputs);
+        disclosed
+            .into_iter()
+            .chain(self.commitment(public_inputs))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+
+    use super::{SelectiveDisclosure, COMMITMENT_LEN};
+
+    #[test]
+    fn test_selective_disclosure_hides_inputs() {
+        let public_inputs = [Fr::from(1), Fr::from(2), Fr::from(3)];
+        let disclosure = SelectiveDisclosure::new(vec![2, 0], [GoldilocksField::ONE; 4]);
+        assert_eq!(disclosure.disclosed(), &[0, 2]);
+        assert_eq!(disclosure.check(3), Ok(()));
+        assert_eq!(disclosure.check(2), Err(2));
+
+        let instances = disclosure.instances(&public_inputs);
+        assert_eq!(instances.len(), 2 + COMMITMENT_LEN);
+        assert_eq!(instances[..2], [Fr::from(1), Fr::from(3)]);
+
+        // the commitment binds the hidden inputs and the blinding
+        let other_inputs = [Fr::from(1), Fr::from(4), Fr::from(3)];
+        assert_ne!(disclosure.instances(&other_inputs), instances);
+        let other_blinding = SelectiveDisclosure::new(vec![0, 2], [GoldilocksField::TWO; 4]);
+        assert_ne!(other_blinding.instances(&public_inputs), instances);
+    }
+}
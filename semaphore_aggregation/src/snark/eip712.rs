@@ -0,0 +1,337 @@
+//! EIP-712 typed data of the submission of a wrapped proof through a permissioned relayer. The
+//! submitter signs the hashes of the verifying key, of the instances and of the proof of the
+//! envelope, under a domain binding the chain and the contract accepting submissions, so that the
+//! signature can't be replayed for another proof, chain or contract. The signed message also
+//! holds a nonce of the submitter and a deadline, so that the signature can't be replayed on the
+//! same contract nor used once expired. The contract checks it with the Solidity library of
+//! `submission_library`, exported with the verifier.
+use serde_json::{json, Value};
+
+use super::envelope::WrappedProofEnvelope;
+use super::utils::keccak256;
+
+pub const DOMAIN_TYPE: &str =
+    "EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+
+pub const SUBMISSION_TYPE: &str =
+    "ProofSubmission(bytes32 vkHash,bytes32 instancesHash,bytes32 proofHash,uint256 nonce,uint256 deadline)";
+
+fn hex0x(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// `uint256` word of `value`
+fn uint256(value: u64) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&value.to_be_bytes());
+    word
+}
+
+/// Domain of the signatures, the contract accepting the submissions being `verifying_contract`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Eip712Domain {
+    pub name: String,
+    pub version: String,
+    pub chain_id: u64,
+    pub verifying_contract: [u8; 20],
+}
+
+impl Eip712Domain {
+    /// `hashStruct` of the domain
+    pub fn separator(&self) -> [u8; 32] {
+        let mut verifying_contract = [0u8; 32];
+        verifying_contract[12..].copy_from_slice(&self.verifying_contract);
+        keccak256(
+            &[
+                keccak256(DOMAIN_TYPE.as_bytes()),
+                keccak256(self.name.as_bytes()),
+                keccak256(self.version.as_bytes()),
+                uint256(self.chain_id),
+                verifying_contract,
+            ]
+            .concat(),
+        )
+    }
+}
+
+/// Signed message of a submission
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofSubmission {
+    /// `WrappedProofEnvelope::vk_hash`
+    pub vk_hash: [u8; 32],
+    /// Keccak256 of the instances as packed `bytes32` words
+    pub instances_hash: [u8; 32],
+    /// Keccak256 of the proof bytes
+    pub proof_hash: [u8; 32],
+    /// Nonce of the submitter, which the contract accepts once
+    pub nonce: u64,
+    /// Unix timestamp in seconds past which the contract rejects the submission
+    pub deadline: u64,
+}
+
+impl ProofSubmission {
+    pub fn new(envelope: &WrappedProofEnvelope, nonce: u64, deadline: u64) -> Self {
+        Self {
+            vk_hash: envelope.vk_hash,
+            instances_hash: keccak256(&envelope.instances.concat()),
+            proof_hash: keccak256(&envelope.proof_bytes),
+            nonce,
+            deadline,
+        }
+    }
+
+    /// `hashStruct` of the submission
+    pub fn struct_hash(&self) -> [u8; 32] {
+        keccak256(
+            &[
+                keccak256(SUBMISSION_TYPE.as_bytes()),
+                self.vk_hash,
+                self.instances_hash,
+                self.proof_hash,
+                uint256(self.nonce),
+                uint256(self.deadline),
+            ]
+            .concat(),
+        )
+    }
+
+    /// Digest signed by the submitter, `keccak256(0x1901 || separator || hashStruct)`
+    pub fn digest(&self, domain: &Eip712Domain) -> [u8; 32] {
+        keccak256(&[&[0x19, 0x01][..], &domain.separator(), &self.struct_hash()].concat())
+    }
+
+    /// Typed data of the submission, as taken by `eth_signTypedData_v4`
+    pub fn typed_data(&self, domain: &Eip712Domain) -> Value {
+        json!({
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" },
+                ],
+                "ProofSubmission": [
+                    { "name": "vkHash", "type": "bytes32" },
+                    { "name": "instancesHash", "type": "bytes32" },
+                    { "name": "proofHash", "type": "bytes32" },
+                    { "name": "nonce", "type": "uint256" },
+                    { "name": "deadline", "type": "uint256" },
+                ],
+            },
+            "primaryType": "ProofSubmission",
+            "domain": {
+                "name": domain.name,
+                "version": domain.version,
+                "chainId": domain.chain_id,
+                "verifyingContract": hex0x(&domain.verifying_contract),
+            },
+            "message": {
+                "vkHash": hex0x(&self.vk_hash),
+                "instancesHash": hex0x(&self.instances_hash),
+                "proofHash": hex0x(&self.proof_hash),
+                "nonce": self.nonce,
+                "deadline": self.deadline,
+            },
+        })
+    }
+
+    /// Signature of the digest by the account of `private_key`, with a low `s`. `to_vec` of the
+    /// signature gives the 65 bytes `r || s || v` taken by the `submitter` of
+    /// `submission_library`.
+    #[cfg(feature = "signing")]
+    pub fn sign(
+        &self,
+        domain: &Eip712Domain,
+        private_key: &str,
+    ) -> Result<ethers::types::Signature, ethers::signers::WalletError> {
+        let wallet = private_key
+            .trim_start_matches("0x")
+            .parse::<ethers::signers::LocalWallet>()?;
+        wallet.sign_hash(self.digest(domain).into())
+    }
+}
+
+/// Source of the Solidity library recovering the submitter of a proof from its signature, with
+/// the digest of `ProofSubmission::digest`. `consume` checks the signature, the deadline and the
+/// nonce of the submitter, which it increments so that the signature can't be replayed.
+pub fn submission_library(library_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+library {library_name}Submission {{
+    bytes32 internal constant DOMAIN_TYPEHASH = keccak256("{DOMAIN_TYPE}");
+    bytes32 internal constant SUBMISSION_TYPEHASH = keccak256("{SUBMISSION_TYPE}");
+
+    function domainSeparator(string memory name, string memory version) internal view returns (bytes32) {{
+        return keccak256(
+            abi.encode(DOMAIN_TYPEHASH, keccak256(bytes(name)), keccak256(bytes(version)), block.chainid, address(this))
+        );
+    }}
+
+    /// Half the order of secp256k1, above which the `s` of a signature is rejected, as the
+    /// signature with `n - s` is valid too
+    uint256 internal constant HALF_ORDER = 0x7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0;
+
+    /// Digest of the submission of `proof` with `instances` to the verifier of `vkHash`
+    function digest(
+        bytes32 separator,
+        bytes32 vkHash,
+        bytes32[] memory instances,
+        bytes memory proof,
+        uint256 nonce,
+        uint256 deadline
+    ) internal pure returns (bytes32) {{
+        bytes32 structHash = keccak256(
+            abi.encode(
+                SUBMISSION_TYPEHASH, vkHash, keccak256(abi.encodePacked(instances)), keccak256(proof), nonce, deadline
+            )
+        );
+        return keccak256(abi.encodePacked("\x19\x01", separator, structHash));
+    }}
+
+    /// Signer of `digest`, the zero address if the signature is invalid or malleable
+    function submitter(bytes32 digest, bytes memory signature) internal pure returns (address) {{
+        if (signature.length != 65) {{
+            return address(0);
+        }}
+        bytes32 r;
+        bytes32 s;
+        uint8 v;
+        assembly {{
+            r := mload(add(signature, 0x20))
+            s := mload(add(signature, 0x40))
+            v := byte(0, mload(add(signature, 0x60)))
+        }}
+        if (uint256(s) > HALF_ORDER || (v != 27 && v != 28)) {{
+            return address(0);
+        }}
+        return ecrecover(digest, v, r, s);
+    }}
+
+    /// Signer of the submission of `digest`, signed with `nonce` and `deadline`, reverting if
+    /// the signature is invalid, expired or already used. Increments the nonce of the signer.
+    function consume(
+        mapping(address => uint256) storage nonces,
+        bytes32 digest,
+        bytes memory signature,
+        uint256 nonce,
+        uint256 deadline
+    ) internal returns (address signer) {{
+        require(block.timestamp <= deadline, "submission expired");
+        signer = submitter(digest, signature);
+        require(signer != address(0), "invalid signature");
+        require(nonces[signer] == nonce, "invalid nonce");
+        nonces[signer] = nonce + 1;
+    }}
+}}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{submission_library, Eip712Domain, ProofSubmission};
+    use crate::snark::envelope::{WrappedProofEnvelope, ENVELOPE_VERSION};
+
+    fn domain() -> Eip712Domain {
+        Eip712Domain {
+            name: "StarkVerifier".to_string(),
+            version: "1".to_string(),
+            chain_id: 1,
+            verifying_contract: [0x11; 20],
+        }
+    }
+
+    fn proof_submission(nonce: u64) -> ProofSubmission {
+        let envelope = WrappedProofEnvelope {
+            version: ENVELOPE_VERSION,
+            vk_hash: [1; 32],
+            instances: vec![[2; 32], [3; 32]],
+            proof_bytes: vec![4; 64],
+            metadata: BTreeMap::new(),
+        };
+        ProofSubmission::new(&envelope, nonce, 1_800_000_000)
+    }
+
+    #[test]
+    fn test_submission_digest() {
+        let domain = domain();
+        let submission = proof_submission(7);
+        // computed independently from the EIP-712 encoding of the domain and the message
+        assert_eq!(
+            hex::encode(domain.separator()),
+            "67ac0ac7ac5b44f8b8ebc0fa4fffcb4dcb43e23dc6ea803509b44a3c840c9dc4"
+        );
+        assert_eq!(
+            hex::encode(submission.digest(&domain)),
+            "335d4902cf1b54b4d8bdbccb00dddecf968d4dd532cd535420c5afcec014cbf2"
+        );
+        assert_ne!(
+            submission.digest(&domain),
+            ProofSubmission {
+                nonce: 8,
+                ..submission
+            }
+            .digest(&domain)
+        );
+
+        let other_chain = Eip712Domain {
+            chain_id: 10,
+            ..domain.clone()
+        };
+        assert_ne!(submission.digest(&domain), submission.digest(&other_chain));
+        let typed_data = submission.typed_data(&domain);
+        assert_eq!(typed_data["domain"]["chainId"], 1);
+        assert_eq!(
+            typed_data["message"]["vkHash"],
+            format!("0x{}", "01".repeat(32))
+        );
+        assert_eq!(typed_data["message"]["nonce"], 7);
+        let library = submission_library("Semaphore");
+        assert!(library.contains("library SemaphoreSubmission"));
+        assert!(library.contains("uint256(s) > HALF_ORDER"));
+    }
+
+    #[cfg(feature = "signing")]
+    #[test]
+    fn test_sign_then_recover() {
+        use ethers::signers::{LocalWallet, Signer};
+        use ethers::types::{H256, U256};
+
+        let private_key = "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318";
+        let address = private_key
+            .trim_start_matches("0x")
+            .parse::<LocalWallet>()
+            .unwrap()
+            .address();
+        let domain = domain();
+        let submission = proof_submission(7);
+        let signature = submission.sign(&domain, private_key).unwrap();
+        assert_eq!(signature.to_vec().len(), 65);
+        assert!(signature.v == 27 || signature.v == 28);
+        // the library rejects the malleable signature with a high `s`
+        let half_order = U256::from_str_radix(
+            "7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0",
+            16,
+        )
+        .unwrap();
+        assert!(signature.s <= half_order);
+        assert_eq!(
+            signature
+                .recover(H256::from(submission.digest(&domain)))
+                .unwrap(),
+            address
+        );
+
+        let replayed = proof_submission(8);
+        assert_ne!(
+            signature
+                .recover(H256::from(replayed.digest(&domain)))
+                .unwrap(),
+            address
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::fmt;
+
+use halo2_proofs::halo2curves::{bn256::Fr, group::ff::PrimeField};
+use halo2curves::goldilocks::fp::MODULUS as GOLDILOCKS_MODULUS;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EncodingError {
+    /// The word at `index` is not a canonical encoding of a BN254 scalar
+    NonCanonicalScalar { index: usize },
+    /// The word at `index` is a valid scalar, but not a Goldilocks element
+    NotGoldilocks { index: usize },
+}
+
+impl fmt::Display for EncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodingError::NonCanonicalScalar { index } => {
+                write!(f, "instance {index} is not a canonical BN254 scalar")
+            }
+            EncodingError::NotGoldilocks { index } => {
+                write!(f, "instance {index} is not a canonical Goldilocks element")
+            }
+        }
+    }
+}
+
+impl std::error::Error for EncodingError {}
+
+/// Encodes a scalar as a big-endian word, the layout Solidity uses for `uint256`/`bytes32`
+pub fn fr_to_bytes32(value: &Fr) -> [u8; 32] {
+    let mut word = value.to_repr();
+    word.reverse();
+    word
+}
+
+/// Decodes a big-endian word into a scalar, rejecting values not less than the BN254 modulus
+pub fn fr_from_bytes32(word: &[u8; 32]) -> Option<Fr> {
+    let mut repr = *word;
+    repr.reverse();
+    Option::from(Fr::from_repr(repr))
+}
+
+/// Converts the wrapper instances into the `bytes32[]` expected by the verifier contract
+pub fn instances_to_bytes32(instances: &[Fr]) -> Vec<[u8; 32]> {
+    instances.iter().map(fr_to_bytes32).collect()
+}
+
+/// Converts `bytes32[]` words back into wrapper instances, each word being the canonical
+/// encoding of a BN254 scalar. The instances beside the Plonky2 public inputs, e.g. the nonce or
+/// the commitments, span the whole scalar field.
+pub fn scalars_from_bytes32(words: &[[u8; 32]]) -> Result<Vec<Fr>, EncodingError> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            fr_from_bytes32(word).ok_or(EncodingError::NonCanonicalScalar { index })
+        })
+        .collect()
+}
+
+/// Same as `scalars_from_bytes32`, for instances which are all Plonky2 public inputs, so each
+/// word must also be a canonical Goldilocks element.
+pub fn instances_from_bytes32(words: &[[u8; 32]]) -> Result<Vec<Fr>, EncodingError> {
+    words
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            let value = fr_from_bytes32(word).ok_or(EncodingError::NonCanonicalScalar { index })?;
+            let (high, low) = word.split_at(24);
+            let low = u64::from_be_bytes(low.try_into().unwrap());
+            if high.iter().any(|b| *b != 0) || low >= GOLDILOCKS_MODULUS {
+                return Err(EncodingError::NotGoldilocks { index });
+            }
+            Ok(value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::{arithmetic::Field, halo2curves::bn256::Fr};
+
+    use super::{instances_from_bytes32, instances_to_bytes32, EncodingError};
+    use halo2curves::goldilocks::fp::MODULUS as GOLDILOCKS_MODULUS;
+
+    #[test]
+    fn test_bytes32_round_trip() {
+        let instances = vec![
+            Fr::zero(),
+            Fr::one(),
+            Fr::from(0x0102030405060708),
+            Fr::from(GOLDILOCKS_MODULUS - 1),
+        ];
+        let words = instances_to_bytes32(&instances);
+        assert_eq!(words[1][31], 1);
+        assert_eq!(words[2][24..], [1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(instances_from_bytes32(&words).unwrap(), instances);
+    }
+
+    #[test]
+    fn test_bytes32_rejects_non_goldilocks() {
+        let words = instances_to_bytes32(&[Fr::one(), Fr::from(GOLDILOCKS_MODULUS)]);
+        assert_eq!(
+            instances_from_bytes32(&words),
+            Err(EncodingError::NotGoldilocks { index: 1 })
+        );
+        assert_eq!(
+            instances_from_bytes32(&[[0xff; 32]]),
+            Err(EncodingError::NonCanonicalScalar { index: 0 })
+        );
+    }
+}
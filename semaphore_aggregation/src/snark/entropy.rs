@@ -0,0 +1,111 @@
+//! Sources of the randomness blinding the SNARK proofs, for environments whose entropy must come
+//! from a vetted source, e.g. an HSM on an air-gapped prover. The source is drawn once per proof,
+//! and its 32 bytes are hashed with 32 bytes of the OS into the seed of the RNG given to
+//! `create_proof`, so that the blinding is secret as long as either source is.
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+
+use super::utils::keccak256;
+
+/// Error of an entropy source which can't supply entropy, e.g. an unreachable HSM
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EntropyError(pub String);
+
+impl fmt::Display for EntropyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "entropy source failed: {}", self.0)
+    }
+}
+
+impl std::error::Error for EntropyError {}
+
+/// Source of entropy for the blinding, see `Blinding::External`. Called from the thread proving,
+/// once per proof.
+pub trait EntropySource: fmt::Debug + Send + Sync {
+    /// Fills `dest` with uniformly random bytes, failing rather than supplying weaker entropy
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<(), EntropyError>;
+}
+
+/// Entropy of the OS
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<(), EntropyError> {
+        OsRng
+            .try_fill_bytes(dest)
+            .map_err(|e| EntropyError(e.to_string()))
+    }
+}
+
+/// Test double supplying the same bytes at every draw and counting the draws. Its entropy is
+/// public, only the one of the OS it is mixed with blinds the proofs.
+#[derive(Debug)]
+pub struct FixedEntropy {
+    byte: u8,
+    draws: AtomicUsize,
+}
+
+impl FixedEntropy {
+    pub fn new(byte: u8) -> Self {
+        Self {
+            byte,
+            draws: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn draws(&self) -> usize {
+        self.draws.load(Ordering::Relaxed)
+    }
+}
+
+impl EntropySource for FixedEntropy {
+    fn fill_bytes(&self, dest: &mut [u8]) -> Result<(), EntropyError> {
+        self.draws.fetch_add(1, Ordering::Relaxed);
+        dest.fill(self.byte);
+        Ok(())
+    }
+}
+
+/// RNG seeded with the entropy of `source` mixed with the one of the OS
+pub(crate) fn mixed_rng(source: &dyn EntropySource) -> Result<StdRng, EntropyError> {
+    let mut entropy = [0u8; 64];
+    source.fill_bytes(&mut entropy[..32])?;
+    OsEntropy.fill_bytes(&mut entropy[32..])?;
+    let seed = keccak256(&entropy);
+    entropy.fill(0);
+    Ok(StdRng::from_seed(seed))
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+
+    use super::{mixed_rng, EntropyError, EntropySource, FixedEntropy};
+
+    #[derive(Debug)]
+    struct UnreachableHsm;
+
+    impl EntropySource for UnreachableHsm {
+        fn fill_bytes(&self, _: &mut [u8]) -> Result<(), EntropyError> {
+            Err(EntropyError("HSM unreachable".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_mixed_entropy() {
+        let source = FixedEntropy::new(7);
+        let a = mixed_rng(&source).unwrap().next_u64();
+        let b = mixed_rng(&source).unwrap().next_u64();
+        // the entropy of the OS differs between the draws
+        assert_ne!(a, b);
+        assert_eq!(source.draws(), 2);
+        assert_eq!(
+            mixed_rng(&UnreachableHsm).unwrap_err(),
+            EntropyError("HSM unreachable".to_string())
+        );
+    }
+}
@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+
+use halo2_proofs::halo2curves::bn256::G1Affine;
+use halo2_proofs::plonk::VerifyingKey;
+use serde::{Deserialize, Serialize};
+
+use super::calldata::CalldataLayout;
+use super::encoding::{fr_to_bytes32, scalars_from_bytes32, EncodingError};
+use super::instances::OrderedInstances;
+use super::utils::keccak256;
+
+/// Version of the envelope format produced by this crate
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Stable wire format for a wrapped proof. Byte strings are serialized as `0x`-prefixed hex and
+/// `metadata` is kept sorted, so that the JSON encoding of an envelope is canonical.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedProofEnvelope {
+    pub version: u32,
+    /// Keccak256 of the transcript representation of the Halo2 verifying key
+    #[serde(with = "hex_bytes32")]
+    pub vk_hash: [u8; 32],
+    /// Instances as big-endian `bytes32` words
+    #[serde(with = "hex_bytes32_vec")]
+    pub instances: Vec<[u8; 32]>,
+    #[serde(with = "hex_bytes")]
+    pub proof_bytes: Vec<u8>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl WrappedProofEnvelope {
+    pub fn new(
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        proof_bytes: Vec<u8>,
+    ) -> Self {
+        Self {
+            version: ENVELOPE_VERSION,
+            vk_hash: vk_hash(vk),
+            instances: instances.to_bytes32(),
+            proof_bytes,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.metadata.insert(key.into(), value.to_string());
+        self
+    }
+
+    /// Instances of the wrapper proof, rejecting words which aren't canonical BN254 scalars
+    pub fn instances(&self) -> Result<OrderedInstances, EncodingError> {
+        scalars_from_bytes32(&self.instances).map(OrderedInstances::new)
+    }
+
+    /// Whether the instances start with a commitment to the Plonky2 verification key, as
+    /// recorded in the `vk_commitment` metadata, see `PipelineConfig::with_vk_commitment`
+    pub fn has_vk_commitment(&self) -> bool {
+        self.metadata
+            .get("vk_commitment")
+            .map_or(false, |v| v == "true")
+    }
+
+    /// Layout of the calldata of the EVM verifier, as recorded in the `calldata_layout`
+    /// metadata, the instances first if none is recorded
+    pub fn calldata_layout(&self) -> CalldataLayout {
+        self.metadata
+            .get("calldata_layout")
+            .and_then(|name| CalldataLayout::from_name(name))
+            .unwrap_or_default()
+    }
+
+    /// Calldata of the EVM verifier, the instances followed by the proof. The verifier generated
+    /// for the envelope reads `calldata_with_layout(calldata_layout())`.
+    pub fn calldata(&self) -> Vec<u8> {
+        self.calldata_with_layout(CalldataLayout::InstancesFirst)
+    }
+
+    /// Calldata of an EVM verifier reading the calldata in `layout`
+    pub fn calldata_with_layout(&self, layout: CalldataLayout) -> Vec<u8> {
+        layout.encode(&self.instances, &self.proof_bytes)
+    }
+
+    /// Canonical byte encoding of the envelope. Every variable-length field is prefixed with
+    /// its length as a big-endian `u64`.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&self.version.to_be_bytes());
+        buf.extend_from_slice(&self.vk_hash);
+        buf.extend_from_slice(&(self.instances.len() as u64).to_be_bytes());
+        for instance in self.instances.iter() {
+            buf.extend_from_slice(instance);
+        }
+        buf.extend_from_slice(&(self.proof_bytes.len() as u64).to_be_bytes());
+        buf.extend_from_slice(&self.proof_bytes);
+        buf.extend_from_slice(&(self.metadata.len() as u64).to_be_bytes());
+        for (key, value) in self.metadata.iter() {
+            buf.extend_from_slice(&(key.len() as u64).to_be_bytes());
+            buf.extend_from_slice(key.as_bytes());
+            buf.extend_from_slice(&(value.len() as u64).to_be_bytes());
+            buf.extend_from_slice(value.as_bytes());
+        }
+        buf
+    }
+
+    /// Keccak256 of `to_canonical_bytes`, meant to be signed by downstream systems
+    pub fn digest(&self) -> [u8; 32] {
+        keccak256(&self.to_canonical_bytes())
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+pub fn vk_hash(vk: &VerifyingKey<G1Affine>) -> [u8; 32] {
+    keccak256(&fr_to_bytes32(&vk.transcript_repr()))
+}
+
+pub(crate) mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{}", hex::encode(bytes)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        hex::decode(s.trim_start_matches("0x")).map_err(serde::de::Error::custom)
+    }
+}
+
+pub(crate) mod hex_bytes32 {
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        super::hex_bytes::serialize(bytes, serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        super::hex_bytes::deserialize(deserializer)?
+            .try_into()
+            .map_err(|_| serde::de::Error::custom("expected 32 bytes"))
+    }
+}
+
+pub(crate) mod hex_bytes32_vec {
+    use serde::{ser::SerializeSeq, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(words: &[[u8; 32]], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(words.len()))?;
+        for word in words {
+            seq.serialize_element(&format!("0x{}", hex::encode(word)))?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<[u8; 32]>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| {
+                hex::decode(s.trim_start_matches("0x"))
+                    .map_err(serde::de::Error::custom)?
+                    .try_into()
+                    .map_err(|_| serde::de::Error::custom("expected 32 bytes"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use halo2_proofs::{arithmetic::Field, halo2curves::bn256::Fr};
+
+    use super::{WrappedProofEnvelope, ENVELOPE_VERSION};
+    use crate::snark::encoding::{instances_to_bytes32, EncodingError};
+
+    fn envelope() -> WrappedProofEnvelope {
+        let mut instance = [0u8; 32];
+        instance[31] = 7;
+        WrappedProofEnvelope {
+            version: ENVELOPE_VERSION,
+            vk_hash: [0xab; 32],
+            instances: vec![instance],
+            proof_bytes: vec![1, 2, 3],
+            metadata: BTreeMap::new(),
+        }
+        .with_metadata("k", 22)
+    }
+
+    #[test]
+    fn test_envelope_json_round_trip() {
+        let envelope = envelope();
+        let json = envelope.to_json().unwrap();
+        assert!(json.contains("\"proof_bytes\": \"0x010203\""));
+        let decoded = WrappedProofEnvelope::from_json(&json).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.digest(), envelope.digest());
+    }
+
+    #[test]
+    fn test_envelope_instances() {
+        // a nonce or a commitment spans the whole scalar field, beyond Goldilocks
+        let instances = vec![Fr::one(), -Fr::one()];
+        let mut envelope = envelope();
+        envelope.instances = instances_to_bytes32(&instances);
+        assert_eq!(envelope.instances().unwrap().as_slice(), instances);
+
+        envelope.instances[1] = [0xff; 32];
+        assert_eq!(
+            envelope.instances().unwrap_err(),
+            EncodingError::NonCanonicalScalar { index: 1 }
+        );
+    }
+
+    #[test]
+    fn test_envelope_digest_binds_metadata() {
+        let envelope = envelope();
+        let other = envelope.clone().with_metadata("k", 23);
+        assert_ne!(envelope.digest(), other.digest());
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt;
+
+use super::disclosure::COMMITMENT_LEN;
+use super::entropy::EntropyError;
+use super::pipeline::TranscriptKind;
+use super::proof_encoding::ProofEncodingError;
+use super::types::error::ConversionError;
+
+/// Errors returned by the public API in `verifier_api`
+#[derive(Debug)]
+pub enum VerifierError {
+    /// The Plonky2 proof was rejected by the native Plonky2 verifier
+    InvalidInnerProof(anyhow::Error),
+    /// The verifier circuit only emulates the quadratic extension of Goldilocks
+    UnsupportedExtensionDegree(usize),
+    /// The generated EVM verifier is larger than what can be deployed on mainnet
+    ContractTooLarge { size: usize, limit: usize },
+    /// The snark-verifier protocol compiled from the verifying key, which the EVM verifier is
+    /// generated from, rejects the proof verified by halo2
+    ProtocolMismatch(String),
+    /// The verifier circuit exposes more instances than the EVM verifier supports, see
+    /// `MAX_EVM_INSTANCES`
+    TooManyInstances { instances: usize, limit: usize },
+    /// The verifier circuit doesn't fit in `2^k` rows, and `k` can't be increased further
+    NotEnoughRows { k: u32 },
+    /// Synthesis of the verifier circuit failed
+    Synthesis(halo2_proofs::plonk::Error),
+    /// The witness of the verifier circuit doesn't satisfy its constraints, see
+    /// `MockProver::verify`
+    Unsatisfied(Vec<halo2_proofs::dev::VerifyFailure>),
+    /// The SNARK proof was rejected by the native halo2 verifier
+    NativeVerificationFailed,
+    /// The EVM verifier couldn't be deployed in the simulated EVM
+    DeploymentFailed,
+    /// The EVM verifier rejected the SNARK proof in the simulated EVM
+    EvmVerificationFailed,
+    /// The EVM backend running the verifier failed, e.g. an RPC error of an Anvil node
+    EvmBackend(String),
+    /// The selective disclosure refers to a public input the Plonky2 proof doesn't have
+    InvalidDisclosure {
+        index: usize,
+        num_public_inputs: usize,
+    },
+    /// The verifier circuit exposes more instances than the fixed number they are padded to, see
+    /// `PipelineConfig::with_instance_padding`
+    InstancesExceedPadding {
+        num_instances: usize,
+        padded_len: usize,
+    },
+    /// The conjectured security of the wrapped FRI proof, `None` if unknown, is below
+    /// `PipelineConfig::min_security_bits`
+    InsufficientSoundness {
+        conjectured_bits: Option<usize>,
+        min_bits: usize,
+    },
+    /// The entropy source of `Blinding::External` couldn't supply entropy
+    Entropy(EntropyError),
+    /// The proof at index `proof` of redundant proofs doesn't have the public inputs of the
+    /// first one
+    StatementMismatch { proof: usize },
+    /// The public inputs of the proof at index `proof` of a batch don't start with the shared
+    /// public inputs of the first proof
+    SharedPrefixMismatch { proof: usize },
+    /// The proof at index `proof` of a multi-circuit batch has a verification key or common data
+    /// which isn't registered
+    UnregisteredCircuit { proof: usize },
+    /// The proof is of a verification key which isn't whitelisted by the registry of the
+    /// exported verifier, see `vk_registry`
+    UnregisteredVerificationKey,
+    /// The job was cancelled, and stopped before `phase`
+    Cancelled { phase: &'static str },
+    /// The deadline of the job passed, and it stopped before `phase`
+    TimedOut { phase: &'static str },
+    /// The EVM verifier would be generated from a locally generated SRS, which is only allowed
+    /// with `PipelineConfig::allow_insecure`
+    InsecureSrs,
+    /// The verifier circuit needs `k` rows, more than the `max_k` of the SRS in use
+    SrsTooSmall { k: u32, max_k: u32 },
+    /// The proof uses a transcript the EVM can't replay, so it has no EVM verifier or calldata,
+    /// see `TranscriptKind::is_evm_compatible`
+    NoEvmVerifier { transcript: TranscriptKind },
+    /// The word at `index` of the G2 points wasn't found in the generated EVM verifier, which
+    /// can't take them as constructor arguments
+    G2ConstantNotFound { index: usize },
+    /// The crate was built with the `asm` feature, and the CPU lacks the ADX or BMI2 extensions
+    /// its assembly uses
+    UnsupportedCpu,
+    /// Solc couldn't be run, installed, or failed to compile the verifier
+    Solc(String),
+    /// The solc of the host isn't the pinned version, see `PipelineConfig::with_solc_version`
+    SolcVersionMismatch { expected: String, found: String },
+    /// The wrapper proof couldn't be verified by the compression circuit, see `compression`
+    Compression(String),
+    /// Artifacts couldn't be written
+    Io(std::io::Error),
+    /// The Plonky2 proof, verifier data or common data can't be handled by the verifier circuit
+    MalformedInput(ConversionError),
+    /// The SNARK proof isn't encoded as the EVM verifier expects, see `proof_encoding`
+    MalformedProof(ProofEncodingError),
+    /// The native verifier couldn't read the SNARK proof from its transcript
+    UnreadableProof(std::io::Error),
+    /// The thread pool of the pipeline couldn't be built, see `PipelineConfig::num_threads`
+    ThreadPool(rayon::ThreadPoolBuildError),
+    /// The calldata reads of the generated EVM verifier couldn't be remapped to the calldata
+    /// layout, see `CalldataLayout::remap_yul`
+    CalldataRemap(String),
+}
+
+impl fmt::Display for VerifierError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifierError::InvalidInnerProof(e) => write!(f, "invalid Plonky2 proof: {e}"),
+            VerifierError::UnsupportedExtensionDegree(d) => {
+                write!(
+                    f,
+                    "extension degree D = {d} is not supported, only D = 2 is"
+                )
+            }
+            VerifierError::ContractTooLarge { size, limit } => write!(
+                f,
+                "EVM verifier is {size} bytes, over the {limit} bytes contract size limit; \
+                 consider exposing fewer instances, using SHPLONK instead of GWC, or splitting \
+                 the verifier into several contracts"
+            ),
+            VerifierError::ProtocolMismatch(reason) => write!(
+                f,
+                "the protocol the EVM verifier is generated from rejects the proof: {reason}"
+            ),
+            VerifierError::TooManyInstances { instances, limit } => write!(
+                f,
+                "{instances} instances, over the {limit} the EVM verifier supports; expose fewer \
+                 public inputs with `PipelineConfig::with_disclosure`, which commits to the other \
+                 ones in {COMMITMENT_LEN} instances"
+            ),
+            VerifierError::NotEnoughRows { k } => write!(
+                f,
+                "verifier circuit doesn't fit in k = {k}, consider raising the maximum k"
+            ),
+            VerifierError::Synthesis(e) => {
+                write!(f, "synthesis of the verifier circuit failed: {e:?}")
+            }
+            VerifierError::Unsatisfied(failures) => write!(
+                f,
+                "verifier circuit is not satisfied, {} failures, the first one: {}",
+                failures.len(),
+                failures
+                    .first()
+                    .map_or_else(String::new, ToString::to_string)
+            ),
+            VerifierError::NativeVerificationFailed => {
+                write!(f, "the native verifier rejected the SNARK proof")
+            }
+            VerifierError::DeploymentFailed => write!(f, "failed to deploy the EVM verifier"),
+            VerifierError::EvmVerificationFailed => {
+                write!(f, "the EVM verifier rejected the SNARK proof")
+            }
+            VerifierError::EvmBackend(e) => write!(f, "EVM backend error: {e}"),
+            VerifierError::InvalidDisclosure {
+                index,
+                num_public_inputs,
+            } => write!(
+                f,
+                "cannot disclose public input {index}, the proof has {num_public_inputs}"
+            ),
+            VerifierError::InstancesExceedPadding {
+                num_instances,
+                padded_len,
+            } => write!(
+                f,
+                "verifier circuit exposes {num_instances} instances, more than the \
+                 {padded_len} they are padded to"
+            ),
+            VerifierError::InsufficientSoundness {
+                conjectured_bits: Some(bits),
+                min_bits,
+            } => write!(
+                f,
+                "inner proof has {bits} bits of conjectured security, less than the required \
+                 {min_bits}"
+            ),
+            VerifierError::InsufficientSoundness {
+                conjectured_bits: None,
+                min_bits,
+            } => write!(
+                f,
+                "soundness of the inner proof is unknown, but {min_bits} bits are required"
+            ),
+            VerifierError::Entropy(e) => write!(f, "{e}"),
+            VerifierError::StatementMismatch { proof } => write!(
+                f,
+                "proof {proof} doesn't prove the statement of the first proof"
+            ),
+            VerifierError::SharedPrefixMismatch { proof } => write!(
+                f,
+                "public inputs of proof {proof} don't start with the shared public inputs"
+            ),
+            VerifierError::UnregisteredCircuit { proof } => {
+                write!(f, "proof {proof} is of a circuit which isn't registered")
+            }
+            VerifierError::UnregisteredVerificationKey => write!(
+                f,
+                "the proof is of a verification key which isn't registered"
+            ),
+            VerifierError::Cancelled { phase } => write!(f, "job cancelled before {phase}"),
+            VerifierError::TimedOut { phase } => write!(f, "job timed out before {phase}"),
+            VerifierError::InsecureSrs => write!(
+                f,
+                "refusing to generate an EVM verifier from a locally generated SRS, load a \
+                 ceremony SRS or allow insecure parameters for testing"
+            ),
+            VerifierError::SrsTooSmall { k, max_k } => write!(
+                f,
+                "verifier circuit needs k = {k}, but the SRS only supports k = {max_k}"
+            ),
+            VerifierError::NoEvmVerifier { transcript } => write!(
+                f,
+                "proofs with a {transcript} transcript can only be verified natively, they have \
+                 no EVM verifier"
+            ),
+            VerifierError::G2ConstantNotFound { index } => write!(
+                f,
+                "G2 word {index} not found in the EVM verifier, it can't take the G2 points as \
+                 constructor arguments"
+            ),
+            VerifierError::UnsupportedCpu => write!(
+                f,
+                "the CPU lacks the ADX and BMI2 extensions of the asm field arithmetic, use a \
+                 build without the asm feature"
+            ),
+            VerifierError::Solc(e) => write!(f, "solc error: {e}"),
+            VerifierError::SolcVersionMismatch { expected, found } => {
+                write!(f, "solc {found} found, but solc {expected} is pinned")
+            }
+            VerifierError::Compression(e) => write!(f, "failed to compress the proof: {e}"),
+            VerifierError::Io(e) => write!(f, "failed to write artifacts: {e}"),
+            VerifierError::MalformedInput(e) => write!(f, "malformed Plonky2 input: {e}"),
+            VerifierError::MalformedProof(e) => write!(f, "malformed SNARK proof: {e}"),
+            VerifierError::UnreadableProof(e) => write!(f, "unreadable SNARK proof: {e}"),
+            VerifierError::ThreadPool(e) => {
+                write!(f, "failed to build the thread pool of the pipeline: {e}")
+            }
+            VerifierError::CalldataRemap(e) => {
+                write!(f, "can't remap the calldata reads of the EVM verifier: {e}")
+            }
+        }
+    }
+}
+
+impl From<ConversionError> for VerifierError {
+    fn from(e: ConversionError) -> Self {
+        VerifierError::MalformedInput(e)
+    }
+}
+
+impl From<ProofEncodingError> for VerifierError {
+    fn from(e: ProofEncodingError) -> Self {
+        VerifierError::MalformedProof(e)
+    }
+}
+
+impl From<EntropyError> for VerifierError {
+    fn from(e: EntropyError) -> Self {
+        VerifierError::Entropy(e)
+    }
+}
+
+impl std::error::Error for VerifierError {}
@@ -0,0 +1,337 @@
+//! Events of the wrapping pipeline, emitted to the sink of `PipelineConfig::with_event_sink` as
+//! they happen, so that schedulers orchestrating provers follow the jobs without polling.
+//! `WebhookSink` posts them as JSON to a URL with the `webhooks` feature, `CallbackSink` hands
+//! them to a closure.
+use std::fmt;
+#[cfg(feature = "webhooks")]
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::report::{Stage, VerificationReport};
+use super::utils::keccak256;
+
+fn hex0x(bytes: &[u8]) -> String {
+    format!("0x{}", hex::encode(bytes))
+}
+
+/// Hashes of the artifacts of a wrapped proof, for schedulers to check the artifacts they fetch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ArtifactHashes {
+    /// `WrappedProofEnvelope::digest`
+    pub envelope_digest: [u8; 32],
+    /// `WrappedProofEnvelope::vk_hash`
+    pub vk_hash: [u8; 32],
+    /// Keccak256 of the proof bytes
+    pub proof_hash: [u8; 32],
+    /// Keccak256 of the deployment code followed by the constructor arguments, `None` for a
+    /// proof only verified natively
+    pub deployment_code_hash: Option<[u8; 32]>,
+}
+
+impl ArtifactHashes {
+    pub fn new(report: &VerificationReport) -> Self {
+        Self {
+            envelope_digest: report.envelope.digest(),
+            vk_hash: report.envelope.vk_hash,
+            proof_hash: keccak256(&report.envelope.proof_bytes),
+            deployment_code_hash: report
+                .deployment_code_with_args()
+                .map(|code| keccak256(&code)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PipelineEvent {
+    /// The pipeline started wrapping a proof
+    Started,
+    /// A stage of the pipeline completed in `elapsed`
+    StageCompleted {
+        stage: Stage,
+        elapsed: Duration,
+    },
+    /// The proof is wrapped and its EVM verifier accepts it, or it is verified natively if its
+    /// transcript has no EVM verifier
+    ProofReady(ArtifactHashes),
+    Failed {
+        error: String,
+    },
+}
+
+impl PipelineEvent {
+    /// JSON object of the event, whose `event` field names it
+    pub fn to_json(&self) -> Value {
+        match self {
+            PipelineEvent::Started => json!({ "event": "started" }),
+            PipelineEvent::StageCompleted { stage, elapsed } => json!({
+                "event": "stage_completed",
+                "stage": stage.to_string(),
+                "elapsed_ms": elapsed.as_millis() as u64,
+            }),
+            PipelineEvent::ProofReady(hashes) => json!({
+                "event": "proof_ready",
+                "envelope_digest": hex0x(&hashes.envelope_digest),
+                "vk_hash": hex0x(&hashes.vk_hash),
+                "proof_hash": hex0x(&hashes.proof_hash),
+                "deployment_code_hash": hashes.deployment_code_hash.map(|hash| hex0x(&hash)),
+            }),
+            PipelineEvent::Failed { error } => json!({ "event": "failed", "error": error }),
+        }
+    }
+}
+
+/// Receiver of the events of the pipeline. Emitting runs on the thread of the pipeline, so
+/// sinks should return quickly and must not fail the pipeline.
+pub trait EventSink: fmt::Debug + Send + Sync {
+    fn emit(&self, event: &PipelineEvent);
+}
+
+/// Sink handing every event to a closure
+pub struct CallbackSink<F>(pub F);
+
+impl<F> fmt::Debug for CallbackSink<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CallbackSink")
+    }
+}
+
+impl<F: Fn(&PipelineEvent) + Send + Sync> EventSink for CallbackSink<F> {
+    fn emit(&self, event: &PipelineEvent) {
+        (self.0)(event)
+    }
+}
+
+/// Default timeout of a post of `WebhookSink`, bounding how long a slow receiver blocks the
+/// pipeline
+#[cfg(feature = "webhooks")]
+pub const DEFAULT_WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Failure to post an event with `WebhookSink`
+#[cfg(feature = "webhooks")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebhookError {
+    pub url: String,
+    /// Status of the response, if the receiver answered with an error status
+    pub status: Option<u16>,
+    pub message: String,
+}
+
+#[cfg(feature = "webhooks")]
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to post the pipeline event to {}: ", self.url)?;
+        match self.status {
+            Some(status) => write!(f, "status {status}, {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+type WebhookErrorHandler = Arc<dyn Fn(&PipelineEvent, &WebhookError) + Send + Sync>;
+
+/// Sink posting every event as JSON to `url`, with the id of the job if set. Every post gives up
+/// after the timeout of the sink. As a sink can't fail the pipeline, a failed post is handed to
+/// the handler of `with_error_handler` and the event is dropped.
+#[cfg(feature = "webhooks")]
+#[derive(Clone)]
+pub struct WebhookSink {
+    url: String,
+    job_id: Option<String>,
+    agent: ureq::Agent,
+    timeout: Duration,
+    on_error: Option<WebhookErrorHandler>,
+}
+
+#[cfg(feature = "webhooks")]
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            job_id: None,
+            agent: ureq::AgentBuilder::new()
+                .timeout(DEFAULT_WEBHOOK_TIMEOUT)
+                .build(),
+            timeout: DEFAULT_WEBHOOK_TIMEOUT,
+            on_error: None,
+        }
+    }
+
+    /// Adds `job_id` to every event as its `job` field
+    pub fn with_job_id(mut self, job_id: impl Into<String>) -> Self {
+        self.job_id = Some(job_id.into());
+        self
+    }
+
+    /// Gives up on a post, connection included, after `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.agent = ureq::AgentBuilder::new().timeout(timeout).build();
+        self.timeout = timeout;
+        self
+    }
+
+    /// Hands every failed post to `on_error` with its event
+    pub fn with_error_handler(
+        mut self,
+        on_error: impl Fn(&PipelineEvent, &WebhookError) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_error = Some(Arc::new(on_error));
+        self
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Posts `event` to the URL of the sink
+    pub fn post(&self, event: &PipelineEvent) -> Result<(), WebhookError> {
+        let mut body = event.to_json();
+        if let Some(job_id) = &self.job_id {
+            body["job"] = json!(job_id);
+        }
+        let error = |status, message: String| WebhookError {
+            url: self.url.clone(),
+            status,
+            message,
+        };
+        match self.agent.post(&self.url).send_json(body) {
+            Ok(_) => Ok(()),
+            Err(ureq::Error::Status(status, response)) => {
+                Err(error(Some(status), response.status_text().to_string()))
+            }
+            Err(e) => Err(error(None, e.to_string())),
+        }
+    }
+}
+
+#[cfg(feature = "webhooks")]
+impl fmt::Debug for WebhookSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookSink")
+            .field("url", &self.url)
+            .field("job_id", &self.job_id)
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+#[cfg(feature = "webhooks")]
+impl EventSink for WebhookSink {
+    fn emit(&self, event: &PipelineEvent) {
+        if let (Err(e), Some(on_error)) = (self.post(event), &self.on_error) {
+            on_error(event, &e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::{CallbackSink, EventSink, PipelineEvent};
+    use crate::snark::report::{Stage, StageTimings};
+
+    #[test]
+    fn test_stage_events() {
+        let events = Arc::new(Mutex::new(vec![]));
+        let sink = {
+            let events = events.clone();
+            CallbackSink(move |event: &PipelineEvent| events.lock().unwrap().push(event.clone()))
+        };
+        sink.emit(&PipelineEvent::Started);
+        let mut stages = StageTimings::with_events(Some(Arc::new(sink)));
+        stages.record(Stage::Keygen, Duration::from_millis(1500));
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[1].to_json(),
+            json!({ "event": "stage_completed", "stage": "keygen", "elapsed_ms": 1500 })
+        );
+    }
+
+    /// Answers `connections` requests with `status` after `delay`, returning the URL and the
+    /// bodies of the requests
+    #[cfg(feature = "webhooks")]
+    fn serve(
+        connections: usize,
+        status: &'static str,
+        delay: Duration,
+    ) -> (String, std::sync::mpsc::Receiver<String>) {
+        use std::io::{BufRead, BufReader, Read, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        let (bodies, received) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            for _ in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(len) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = len.trim().parse().unwrap();
+                    }
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                bodies.send(String::from_utf8(body).unwrap()).unwrap();
+                std::thread::sleep(delay);
+                // the client may have given up already
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+            }
+        });
+        (url, received)
+    }
+
+    #[cfg(feature = "webhooks")]
+    #[test]
+    fn test_webhook_sink() {
+        use std::time::Instant;
+
+        use super::{WebhookError, WebhookSink};
+
+        let (url, bodies) = serve(1, "200 OK", Duration::ZERO);
+        let sink = WebhookSink::new(url).with_job_id("job-1");
+        sink.post(&PipelineEvent::Started).unwrap();
+        let body: serde_json::Value = serde_json::from_str(&bodies.recv().unwrap()).unwrap();
+        assert_eq!(body, json!({ "event": "started", "job": "job-1" }));
+
+        let (url, _bodies) = serve(1, "500 Internal Server Error", Duration::ZERO);
+        let error = WebhookSink::new(url)
+            .post(&PipelineEvent::Started)
+            .unwrap_err();
+        assert_eq!(error.status, Some(500));
+
+        // a receiver slower than the timeout doesn't block the pipeline, and the failure goes
+        // to the error handler
+        let (url, _bodies) = serve(1, "200 OK", Duration::from_secs(5));
+        let errors = Arc::new(Mutex::new(Vec::<WebhookError>::new()));
+        let sink = {
+            let errors = errors.clone();
+            WebhookSink::new(url)
+                .with_timeout(Duration::from_millis(200))
+                .with_error_handler(move |_, e| errors.lock().unwrap().push(e.clone()))
+        };
+        let start = Instant::now();
+        sink.emit(&PipelineEvent::Started);
+        assert!(start.elapsed() < Duration::from_secs(2));
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].status, None);
+    }
+}
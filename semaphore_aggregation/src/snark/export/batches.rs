@@ -0,0 +1,125 @@
+//! Artifacts of the verifier circuits of one circuit family at several batch sizes, such as
+//! Semaphore aggregation of 16, 64 and 256 signals.
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use halo2_proofs::halo2curves::bn256::G1Affine;
+use halo2_proofs::plonk::ProvingKey;
+use halo2_proofs::SerdeFormat;
+
+use super::layout::{Artifact, ArtifactsLayout};
+
+/// Proving key and EVM verifier of the verifier circuit for one batch size
+pub struct BatchArtifacts {
+    pub batch_size: usize,
+    pub num_instances: usize,
+    pub k: u32,
+    pub pk: Arc<ProvingKey<G1Affine>>,
+    pub yul_code: String,
+    pub deployment_code: Vec<u8>,
+    /// Time spent generating the keys and the EVM verifier
+    pub keygen_time: Duration,
+}
+
+/// Row of the comparison table of `comparison_table`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchSummary {
+    pub batch_size: usize,
+    pub num_instances: usize,
+    pub k: u32,
+    pub deployment_code_size: usize,
+    pub keygen_time: Duration,
+}
+
+impl BatchArtifacts {
+    pub fn summary(&self) -> BatchSummary {
+        BatchSummary {
+            batch_size: self.batch_size,
+            num_instances: self.num_instances,
+            k: self.k,
+            deployment_code_size: self.deployment_code.len(),
+            keygen_time: self.keygen_time,
+        }
+    }
+
+    /// Writes `pk.bin`, `vk.bin`, `Verifier.yul` and `Verifier.bin` to `dir/batch_<size>`
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        self.write_with_layout(&ArtifactsLayout::new(dir))
+    }
+
+    /// Layout of the artifacts of the batch size, `batch_<size>` of `layout`
+    fn layout(&self, layout: &ArtifactsLayout) -> ArtifactsLayout {
+        layout.nested(format!("batch_{}", self.batch_size))
+    }
+
+    /// Fails with `io::ErrorKind::AlreadyExists` if `layout` never overwrites and any artifact
+    /// `write_with_layout` writes is on disk, see `ArtifactsLayout::check`
+    pub fn check(&self, layout: &ArtifactsLayout) -> io::Result<()> {
+        self.layout(layout).check([
+            Artifact::ProvingKey,
+            Artifact::VerifyingKey,
+            Artifact::Yul,
+            Artifact::DeploymentCode,
+        ])
+    }
+
+    /// Same as `write`, in `batch_<size>` of `layout`
+    pub fn write_with_layout(&self, layout: &ArtifactsLayout) -> io::Result<()> {
+        self.check(layout)?;
+        let layout = self.layout(layout);
+        if let Some(file) = layout.create(Artifact::ProvingKey)? {
+            self.pk
+                .write(&mut BufWriter::new(file), SerdeFormat::RawBytes)?;
+        }
+        if let Some(file) = layout.create(Artifact::VerifyingKey)? {
+            self.pk
+                .get_vk()
+                .write(&mut BufWriter::new(file), SerdeFormat::RawBytes)?;
+        }
+        layout.write(Artifact::Yul, &self.yul_code)?;
+        layout.write(Artifact::DeploymentCode, hex::encode(&self.deployment_code))?;
+        Ok(())
+    }
+}
+
+/// Markdown table comparing the artifacts of every batch size
+pub fn comparison_table(summaries: &[BatchSummary]) -> String {
+    let mut table = String::from(
+        "| batch size | instances | k | verifier size (bytes) | keygen (s) |\n\
+         |---|---|---|---|---|\n",
+    );
+    for summary in summaries {
+        table.push_str(&format!(
+            "| {} | {} | {} | {} | {:.1} |\n",
+            summary.batch_size,
+            summary.num_instances,
+            summary.k,
+            summary.deployment_code_size,
+            summary.keygen_time.as_secs_f64()
+        ));
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{comparison_table, BatchSummary};
+
+    #[test]
+    fn test_comparison_table() {
+        let summary = BatchSummary {
+            batch_size: 16,
+            num_instances: 4,
+            k: 20,
+            deployment_code_size: 20_000,
+            keygen_time: Duration::from_millis(1500),
+        };
+        let table = comparison_table(&[summary]);
+        assert_eq!(table.lines().count(), 3);
+        assert_eq!(table.lines().last(), Some("| 16 | 4 | 20 | 20000 | 1.5 |"));
+    }
+}
@@ -0,0 +1,265 @@
+//! EIP-4844 export of a batch of wrapped proofs: the calldata of every proof is packed into
+//! blobs, which cost much less than calldata, and posted with a small inbox contract checking
+//! that the blobs of the transaction are the expected ones. The EVM can't read the content of a
+//! blob, so the proofs are verified from the blob data off-chain or by a rollup bridge, the
+//! inbox recording which batches were made available under their `batch_id`.
+//!
+//! The KZG commitments of the blobs are computed with `c-kzg` behind the `blobs` feature.
+use std::io;
+use std::path::Path;
+
+use super::layout::{Artifact, ArtifactsLayout};
+use crate::snark::envelope::WrappedProofEnvelope;
+use crate::snark::utils::keccak256;
+
+/// Field elements of a blob
+pub const FIELD_ELEMENTS_PER_BLOB: usize = 4096;
+/// Bytes of a blob
+pub const BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * 32;
+/// Payload bytes stored in a field element. Its first byte is left zero, so that every element
+/// is less than the BLS12-381 scalar modulus.
+pub const PAYLOAD_BYTES_PER_ELEMENT: usize = 31;
+/// Payload bytes stored in a blob
+pub const PAYLOAD_BYTES_PER_BLOB: usize = FIELD_ELEMENTS_PER_BLOB * PAYLOAD_BYTES_PER_ELEMENT;
+
+/// Payload of a batch: the number of proofs as a big-endian `u32`, then the calldata of every
+/// proof prefixed with its length as a big-endian `u32`
+pub fn encode_payload(envelopes: &[WrappedProofEnvelope]) -> Vec<u8> {
+    let mut payload = (envelopes.len() as u32).to_be_bytes().to_vec();
+    for envelope in envelopes {
+        let calldata = envelope.calldata_with_layout(envelope.calldata_layout());
+        payload.extend_from_slice(&(calldata.len() as u32).to_be_bytes());
+        payload.extend_from_slice(&calldata);
+    }
+    payload
+}
+
+/// Calldata of every proof of `payload`, ignoring the padding after the last one
+pub fn decode_payload(payload: &[u8]) -> Option<Vec<Vec<u8>>> {
+    fn read_u32(bytes: &[u8], offset: usize) -> Option<usize> {
+        let word = bytes.get(offset..offset + 4)?;
+        Some(u32::from_be_bytes(word.try_into().unwrap()) as usize)
+    }
+    let num_proofs = read_u32(payload, 0)?;
+    let mut offset = 4;
+    let mut calldata = Vec::new();
+    for _ in 0..num_proofs {
+        let len = read_u32(payload, offset)?;
+        offset += 4;
+        calldata.push(payload.get(offset..offset + len)?.to_vec());
+        offset += len;
+    }
+    Some(calldata)
+}
+
+/// Packs `payload` into blobs, 31 bytes per field element, padding the last blob with zeros
+pub fn pack_blobs(payload: &[u8]) -> Vec<Vec<u8>> {
+    payload
+        .chunks(PAYLOAD_BYTES_PER_BLOB)
+        .map(|chunk| {
+            let mut blob = vec![0u8; BYTES_PER_BLOB];
+            for (element, bytes) in blob
+                .chunks_mut(32)
+                .zip(chunk.chunks(PAYLOAD_BYTES_PER_ELEMENT))
+            {
+                element[1..1 + bytes.len()].copy_from_slice(bytes);
+            }
+            blob
+        })
+        .collect()
+}
+
+/// Payload packed into `blobs` by `pack_blobs`, with the padding of the last blob, or `None` if
+/// a blob has the wrong size or an element with a non-zero first byte
+pub fn unpack_blobs(blobs: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let mut payload = Vec::with_capacity(blobs.len() * PAYLOAD_BYTES_PER_BLOB);
+    for blob in blobs {
+        if blob.len() != BYTES_PER_BLOB {
+            return None;
+        }
+        for element in blob.chunks(32) {
+            if element[0] != 0 {
+                return None;
+            }
+            payload.extend_from_slice(&element[1..]);
+        }
+    }
+    Some(payload)
+}
+
+/// Identifier of the batch posted in the blobs of `versioned_hashes`, in the order of the blobs
+/// of the transaction, as recorded by the inbox contract
+pub fn batch_id(versioned_hashes: &[[u8; 32]]) -> [u8; 32] {
+    keccak256(&versioned_hashes.concat())
+}
+
+/// Source of the inbox contract. `post` takes the versioned hashes of all the blobs of a batch,
+/// which must be exactly the blobs of the transaction in order, and records the batch under
+/// its `batch_id`. The number of proofs is read from the payload, see `encode_payload`.
+pub fn inbox_contract(contract_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.24;
+
+contract {contract_name}BlobInbox {{
+    event BatchPosted(bytes32 indexed batchId, bytes32[] versionedHashes);
+
+    mapping(bytes32 => bool) public posted;
+
+    function post(bytes32[] calldata versionedHashes) external returns (bytes32 batchId) {{
+        require(versionedHashes.length > 0, "no blob");
+        for (uint256 i = 0; i < versionedHashes.length; i++) {{
+            require(blobhash(i) == versionedHashes[i], "unexpected blob");
+        }}
+        require(blobhash(versionedHashes.length) == bytes32(0), "unexpected blob");
+        batchId = keccak256(abi.encodePacked(versionedHashes));
+        require(!posted[batchId], "batch already posted");
+        posted[batchId] = true;
+        emit BatchPosted(batchId, versionedHashes);
+    }}
+}}
+"#
+    )
+}
+
+/// Writes the blobs of `envelopes` to `dir` as `blob_<i>.hex`, with the inbox contract as
+/// `<contract_name>BlobInbox.sol`. Returns the number of blobs.
+pub fn export_blobs(
+    dir: &Path,
+    contract_name: &str,
+    envelopes: &[WrappedProofEnvelope],
+) -> io::Result<usize> {
+    let layout = ArtifactsLayout::new(dir)
+        .with_file_name(Artifact::BlobInbox, format!("{contract_name}BlobInbox.sol"));
+    export_blobs_with_layout(&layout, contract_name, envelopes)
+}
+
+/// Same as `export_blobs`, writing the blobs and the inbox contract where `layout` puts them
+pub fn export_blobs_with_layout(
+    layout: &ArtifactsLayout,
+    contract_name: &str,
+    envelopes: &[WrappedProofEnvelope],
+) -> io::Result<usize> {
+    let blobs = pack_blobs(&encode_payload(envelopes));
+    layout.check(
+        (0..blobs.len())
+            .map(Artifact::Blob)
+            .chain([Artifact::BlobInbox]),
+    )?;
+    for (i, blob) in blobs.iter().enumerate() {
+        layout.write(Artifact::Blob(i), hex::encode(blob))?;
+    }
+    layout.write(Artifact::BlobInbox, inbox_contract(contract_name))?;
+    Ok(blobs.len())
+}
+
+/// KZG commitment of `blob` with the trusted setup of `settings`
+#[cfg(feature = "blobs")]
+pub fn blob_commitment(
+    blob: &[u8],
+    settings: &c_kzg::KzgSettings,
+) -> Result<[u8; 48], c_kzg::Error> {
+    let blob = c_kzg::Blob::from_bytes(blob)?;
+    let commitment = c_kzg::KzgCommitment::blob_to_kzg_commitment(&blob, settings)?;
+    Ok(commitment.to_bytes().into_inner())
+}
+
+/// Versioned hash of a blob commitment, as returned by the `BLOBHASH` opcode
+#[cfg(feature = "blobs")]
+pub fn versioned_hash(commitment: &[u8; 48]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+
+    let mut hash: [u8; 32] = Sha256::digest(commitment).into();
+    hash[0] = 0x01;
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::io;
+
+    use super::{
+        batch_id, decode_payload, encode_payload, export_blobs, export_blobs_with_layout,
+        inbox_contract, pack_blobs, unpack_blobs, BYTES_PER_BLOB,
+    };
+    use crate::snark::envelope::{WrappedProofEnvelope, ENVELOPE_VERSION};
+    use crate::snark::export::layout::{Artifact, ArtifactsLayout, OverwritePolicy};
+    use crate::snark::utils::keccak256;
+
+    fn envelopes() -> Vec<WrappedProofEnvelope> {
+        (0..3u8)
+            .map(|i| WrappedProofEnvelope {
+                version: ENVELOPE_VERSION,
+                vk_hash: [0; 32],
+                instances: vec![[i; 32]],
+                // large enough for the batch to span two blobs
+                proof_bytes: vec![i; 50_000],
+                metadata: BTreeMap::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_blob_round_trip() {
+        let envelopes = envelopes();
+        let blobs = pack_blobs(&encode_payload(&envelopes));
+        assert_eq!(blobs.len(), 2);
+        assert!(blobs.iter().all(|blob| blob.len() == BYTES_PER_BLOB));
+
+        let calldata = decode_payload(&unpack_blobs(&blobs).unwrap()).unwrap();
+        let expected = envelopes
+            .iter()
+            .map(WrappedProofEnvelope::calldata)
+            .collect::<Vec<_>>();
+        assert_eq!(calldata, expected);
+
+        let mut invalid = blobs;
+        invalid[1][0] = 1;
+        assert_eq!(unpack_blobs(&invalid), None);
+    }
+
+    #[test]
+    fn test_batch_id() {
+        let versioned_hashes = [[1; 32], [2; 32]];
+        let mut packed = [1; 64];
+        packed[32..].fill(2);
+        assert_eq!(batch_id(&versioned_hashes), keccak256(&packed));
+        // the order of the blobs is part of the batch
+        assert_ne!(batch_id(&versioned_hashes), batch_id(&[[2; 32], [1; 32]]));
+
+        let inbox = inbox_contract("Rollup");
+        assert!(inbox.contains("function post(bytes32[] calldata versionedHashes)"));
+        assert!(inbox.contains("keccak256(abi.encodePacked(versionedHashes))"));
+    }
+
+    #[test]
+    fn test_export_blobs() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        assert_eq!(export_blobs(dir, "Rollup", &envelopes()).unwrap(), 2);
+        assert!(dir.join("blob_0.hex").exists());
+        assert!(dir.join("blob_1.hex").exists());
+        assert!(dir.join("RollupBlobInbox.sol").exists());
+
+        // with a new inbox file, the blobs on disk fail the export before the inbox is written
+        let layout = ArtifactsLayout::new(dir)
+            .with_file_name(Artifact::BlobInbox, "inbox/Inbox.sol")
+            .with_overwrite(OverwritePolicy::Fail);
+        assert_eq!(
+            export_blobs_with_layout(&layout, "Rollup", &envelopes())
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert!(!layout.path(Artifact::BlobInbox).exists());
+        assert_eq!(
+            export_blobs_with_layout(&layout.nested("next"), "Rollup", &envelopes()).unwrap(),
+            2
+        );
+        assert!(fs::read_to_string(dir.join("next/inbox/Inbox.sol"))
+            .unwrap()
+            .contains("contract RollupBlobInbox"));
+    }
+}
@@ -0,0 +1,76 @@
+//! Deployment of an exported verifier to a live network, signing the transaction with a local
+//! private key. The gas limit is the estimate of the node with a margin, and the address is
+//! returned once the transaction has `CONFIRMATIONS` confirmations.
+use std::path::Path;
+
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Http, Middleware, Provider};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::{Address, TransactionRequest, U256};
+
+use super::read_deployment_code;
+use crate::snark::error::VerifierError;
+
+/// Number of blocks the deployment waits for once mined
+pub const CONFIRMATIONS: usize = 2;
+
+/// Margin added to the gas estimate of the node, in percent, so that the deployment doesn't run
+/// out of gas if the state changes between the estimation and the inclusion
+pub const GAS_MARGIN_PERCENT: u64 = 20;
+
+fn rpc_error(e: impl ToString) -> VerifierError {
+    VerifierError::EvmBackend(e.to_string())
+}
+
+/// Deploys the verifier exported to `artifacts` by `export_artifacts` with a transaction from
+/// the account of `private_key`, hex encoded, to the node at `rpc_url`, returning the address
+/// of the verifier. Blocks until the deployment is confirmed.
+pub fn deploy(
+    rpc_url: &str,
+    private_key: &str,
+    artifacts: &Path,
+) -> Result<Address, VerifierError> {
+    let deployment_code = read_deployment_code(artifacts)?;
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(VerifierError::Io)?
+        .block_on(deploy_code(rpc_url, private_key, deployment_code))
+}
+
+async fn deploy_code(
+    rpc_url: &str,
+    private_key: &str,
+    deployment_code: Vec<u8>,
+) -> Result<Address, VerifierError> {
+    let provider = Provider::<Http>::try_from(rpc_url).map_err(rpc_error)?;
+    let chain_id = provider.get_chainid().await.map_err(rpc_error)?;
+    let wallet = private_key
+        .trim_start_matches("0x")
+        .parse::<LocalWallet>()
+        .map_err(rpc_error)?
+        .with_chain_id(chain_id.as_u64());
+    let client = SignerMiddleware::new(provider, wallet);
+
+    let mut tx: TypedTransaction = TransactionRequest::new()
+        .from(client.address())
+        .data(deployment_code)
+        .into();
+    let gas = client.estimate_gas(&tx, None).await.map_err(rpc_error)?;
+    tx.set_gas(gas * U256::from(100 + GAS_MARGIN_PERCENT) / U256::from(100));
+    let receipt = client
+        .send_transaction(tx, None)
+        .await
+        .map_err(rpc_error)?
+        .confirmations(CONFIRMATIONS)
+        .await
+        .map_err(rpc_error)?
+        .ok_or_else(|| rpc_error("deployment transaction dropped from the mempool"))?;
+    if receipt.status != Some(1.into()) {
+        return Err(VerifierError::DeploymentFailed);
+    }
+    receipt
+        .contract_address
+        .ok_or(VerifierError::DeploymentFailed)
+}
@@ -0,0 +1,73 @@
+//! Foundry test suite deploying the verifier from its bytecode and checking it against embedded
+//! proof fixtures.
+
+/// Returns `calldata` with a bit flipped in the first word of the proof, at `proof_offset`, see
+/// `CalldataLayout::proof_offset`. That word is a coordinate of a commitment, so the tampered
+/// calldata doesn't hold a valid proof.
+pub fn tamper(calldata: &[u8], proof_offset: usize) -> Vec<u8> {
+    let mut tampered = calldata.to_vec();
+    tampered[proof_offset + 31] ^= 1;
+    tampered
+}
+
+/// Source of a Foundry test contract deploying `deployment_code` and asserting that the verifier
+/// accepts `valid_calldata` and rejects `invalid_calldata`
+pub fn test_suite(
+    contract_name: &str,
+    deployment_code: &[u8],
+    valid_calldata: &[u8],
+    invalid_calldata: &[u8],
+) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+import "forge-std/Test.sol";
+
+contract {contract_name}Test is Test {{
+    address verifier;
+
+    function setUp() public {{
+        bytes memory code = hex"{deployment_code}";
+        address deployed;
+        assembly {{
+            deployed := create(0, add(code, 0x20), mload(code))
+        }}
+        require(deployed != address(0), "deployment failed");
+        verifier = deployed;
+    }}
+
+    function testAcceptsValidProof() public {{
+        (bool success, ) = verifier.call(hex"{valid_calldata}");
+        assertTrue(success);
+    }}
+
+    function testRejectsInvalidProof() public {{
+        (bool success, ) = verifier.call(hex"{invalid_calldata}");
+        assertFalse(success);
+    }}
+}}
+"#,
+        deployment_code = hex::encode(deployment_code),
+        valid_calldata = hex::encode(valid_calldata),
+        invalid_calldata = hex::encode(invalid_calldata),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tamper, test_suite};
+
+    #[test]
+    fn test_foundry_suite_embeds_fixtures() {
+        let calldata = vec![0u8; 96];
+        let invalid = tamper(&calldata, 32);
+        assert_eq!(invalid[63], 1);
+        assert_eq!(invalid.iter().filter(|b| **b != 0).count(), 1);
+
+        let suite = test_suite("Verifier", &[0x60, 0x00], &calldata, &invalid);
+        assert!(suite.contains("contract VerifierTest is Test"));
+        assert!(suite.contains("hex\"6000\""));
+        assert!(suite.contains(&format!("hex\"{}\"", hex::encode(&invalid))));
+    }
+}
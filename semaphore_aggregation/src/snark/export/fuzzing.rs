@@ -0,0 +1,127 @@
+//! Property harness fuzzing the verifier with Echidna or Medusa. The harness deploys the verifier
+//! from its bytecode and lets the fuzzer flip bytes of the golden calldata, checking that:
+//! - the verifier accepts the golden calldata
+//! - the verifier never accepts calldata differing from the golden one
+//!
+//! Run it from the exported directory with `echidna fuzz/VerifierFuzz.sol --contract
+//! VerifierFuzz --config fuzz/echidna.yaml`, or `medusa fuzz --config fuzz/medusa.json`.
+
+/// Prefix of the property functions, the default one of Echidna, configured for Medusa
+const PROPERTY_PREFIX: &str = "echidna_";
+
+/// Source of the harness contract `{contract_name}Fuzz` deploying `deployment_code`, with
+/// `golden_calldata` as the only calldata the verifier may accept
+pub fn harness(contract_name: &str, deployment_code: &[u8], golden_calldata: &[u8]) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+contract {contract_name}Fuzz {{
+    address verifier;
+    bytes golden = hex"{golden_calldata}";
+    bytes mutated;
+    bool acceptedMutation;
+
+    constructor() {{
+        bytes memory code = hex"{deployment_code}";
+        address deployed;
+        assembly {{
+            deployed := create(0, add(code, 0x20), mload(code))
+        }}
+        require(deployed != address(0), "deployment failed");
+        verifier = deployed;
+        mutated = golden;
+    }}
+
+    /// XORs the byte of the calldata at `index` with `mask`, mutations accumulating across calls
+    function flipByte(uint256 index, uint8 mask) public {{
+        require(mask != 0);
+        index = index % mutated.length;
+        mutated[index] = mutated[index] ^ bytes1(mask);
+        check();
+    }}
+
+    /// Restores the golden calldata
+    function reset() public {{
+        mutated = golden;
+    }}
+
+    function check() internal {{
+        if (keccak256(mutated) == keccak256(golden)) {{
+            return;
+        }}
+        (bool success, ) = verifier.call(mutated);
+        if (success) {{
+            acceptedMutation = true;
+        }}
+    }}
+
+    function {PROPERTY_PREFIX}accepts_golden_proof() public returns (bool) {{
+        (bool success, ) = verifier.call(golden);
+        return success;
+    }}
+
+    function {PROPERTY_PREFIX}rejects_mutated_proof() public view returns (bool) {{
+        return !acceptedMutation;
+    }}
+}}
+"#,
+        deployment_code = hex::encode(deployment_code),
+        golden_calldata = hex::encode(golden_calldata),
+    )
+}
+
+/// Echidna config of the harness, whose constructor embeds the bytecode of the verifier, so that
+/// the code size limit is lifted
+pub fn echidna_config() -> String {
+    "testMode: property\n\
+     testLimit: 50000\n\
+     seqLen: 10\n\
+     codeSize: 0xffffffff\n\
+     balanceContract: 0\n"
+        .to_string()
+}
+
+/// Medusa config of the harness `{contract_name}Fuzz`
+pub fn medusa_config(contract_name: &str) -> String {
+    let config = serde_json::json!({
+        "fuzzing": {
+            "workers": 4,
+            "testLimit": 50000,
+            "callSequenceLength": 10,
+            "targetContracts": [format!("{contract_name}Fuzz")],
+            "testing": {
+                "propertyTesting": {
+                    "enabled": true,
+                    "testPrefixes": [PROPERTY_PREFIX],
+                },
+                "assertionTesting": { "enabled": false },
+            },
+        },
+        "compilation": {
+            "platform": "crytic-compile",
+            "platformConfig": { "target": format!("fuzz/{contract_name}Fuzz.sol") },
+        },
+    });
+    serde_json::to_string_pretty(&config).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{harness, medusa_config};
+
+    #[test]
+    fn test_fuzzing_harness_embeds_golden_proof() {
+        let harness = harness("Verifier", &[0x60, 0x00], &[0xab; 4]);
+        assert!(harness.contains("contract VerifierFuzz {"));
+        assert!(harness.contains("hex\"6000\""));
+        assert!(harness.contains("bytes golden = hex\"abababab\""));
+        assert!(harness.contains("function echidna_rejects_mutated_proof()"));
+
+        let config: serde_json::Value = serde_json::from_str(&medusa_config("Verifier")).unwrap();
+        assert_eq!(
+            config["fuzzing"]["targetContracts"][0].as_str(),
+            Some("VerifierFuzz")
+        );
+    }
+}
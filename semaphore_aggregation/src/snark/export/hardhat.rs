@@ -0,0 +1,199 @@
+//! Artifact of the verifier in the format of Hardhat, consumable by hardhat-deploy, and a
+//! TypeScript helper encoding its calldata.
+use std::io;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use snark_verifier::loader::evm;
+
+use super::layout::{Artifact, ArtifactsLayout};
+use super::{evm_verifier, CONTRACT_NAME};
+use crate::snark::g2_args::NUM_G2_WORDS;
+use crate::snark::report::VerificationReport;
+
+/// Hardhat artifact of the verifier. The verifier has no ABI functions: its fallback reads the
+/// instances as 32-byte words followed by the proof, and reverts if the proof is invalid. A
+/// verifier taking the G2 points of the SRS as constructor arguments has a constructor taking
+/// them as `bytes32[8]`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HardhatArtifact {
+    #[serde(rename = "_format")]
+    pub format: String,
+    pub contract_name: String,
+    pub source_name: String,
+    pub abi: Value,
+    pub bytecode: String,
+    pub deployed_bytecode: String,
+    pub link_references: Value,
+    pub deployed_link_references: Value,
+    /// JSON describing the circuit the verifier was generated for
+    pub metadata: String,
+}
+
+impl HardhatArtifact {
+    /// Builds the artifact of the verifier of `report`, compiling the runtime object of its Yul
+    /// source for `deployedBytecode`. Fails for a report of a proof only verified natively, or
+    /// if the Yul source has no runtime object or it compiles to no code.
+    pub fn new(contract_name: &str, report: &VerificationReport) -> io::Result<Self> {
+        let (yul_code, deployment_code) = evm_verifier(report)?;
+        let deployed_bytecode = runtime_code(yul_code)?;
+        let metadata = json!({
+            "k": report.k,
+            "numInstances": report.envelope.instances.len(),
+            "vkHash": format!("0x{}", hex::encode(report.envelope.vk_hash)),
+        });
+        let mut abi = vec![json!({ "type": "fallback", "stateMutability": "nonpayable" })];
+        if !report.constructor_args.is_empty() {
+            abi.push(json!({
+                "type": "constructor",
+                "stateMutability": "nonpayable",
+                "inputs": [{
+                    "name": "g2Points",
+                    "type": format!("bytes32[{NUM_G2_WORDS}]"),
+                    "internalType": format!("bytes32[{NUM_G2_WORDS}]"),
+                }],
+            }));
+        }
+        Ok(Self {
+            format: "hh-sol-artifact-1".to_string(),
+            contract_name: contract_name.to_string(),
+            source_name: format!("{contract_name}.yul"),
+            abi: Value::Array(abi),
+            bytecode: format!("0x{}", hex::encode(deployment_code)),
+            deployed_bytecode: format!("0x{}", hex::encode(deployed_bytecode)),
+            link_references: json!({}),
+            deployed_link_references: json!({}),
+            metadata: metadata.to_string(),
+        })
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+/// Runtime code of the verifier compiled from the Yul source `yul_code`
+fn runtime_code(yul_code: &str) -> io::Result<Vec<u8>> {
+    let code = runtime_object(yul_code)
+        .map(evm::compile_yul)
+        .unwrap_or_default();
+    if code.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the verifier has no runtime code for deployedBytecode",
+        ));
+    }
+    Ok(code)
+}
+
+/// Source of the object nested in the Yul object of the verifier, which holds its runtime code
+fn runtime_object(yul_code: &str) -> Option<&str> {
+    let start = yul_code.match_indices("object \"").nth(1)?.0;
+    let mut depth = 0;
+    for (i, c) in yul_code[start..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&yul_code[start..start + i + 1]);
+                }
+            }
+            _ => (),
+        }
+    }
+    None
+}
+
+/// TypeScript helper encoding the calldata of a verifier taking `num_instances` instances
+pub fn encode_instances_helper(num_instances: usize) -> String {
+    format!(
+        r#"// Generated by semaphore_aggregation, encodes the calldata of the verifier
+
+export const NUM_INSTANCES = {num_instances};
+
+export const SCALAR_FIELD_MODULUS =
+  0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001n;
+
+// Encodes every instance as a 32-byte big-endian word, followed by the proof
+export function encodeCalldata(instances: bigint[], proof: string): string {{
+  if (instances.length !== NUM_INSTANCES) {{
+    throw new Error(`expected ${{NUM_INSTANCES}} instances, got ${{instances.length}}`);
+  }}
+  const words = instances.map((instance) => {{
+    if (instance < 0n || instance >= SCALAR_FIELD_MODULUS) {{
+      throw new Error(`instance ${{instance}} is not a scalar field element`);
+    }}
+    return instance.toString(16).padStart(64, "0");
+  }});
+  return "0x" + words.join("") + proof.replace(/^0x/, "");
+}}
+"#
+    )
+}
+
+/// Writes `Verifier.json`, the Hardhat artifact of the verifier of `report`, and
+/// `encodeCalldata.ts` to `dir`. Fails for a report of a proof only verified natively, see
+/// `TranscriptKind`, which has no EVM verifier.
+pub fn export_hardhat(dir: &Path, report: &VerificationReport) -> io::Result<()> {
+    export_hardhat_with_layout(&ArtifactsLayout::new(dir), report)
+}
+
+/// Same as `export_hardhat`, writing the files where `layout` puts them
+pub fn export_hardhat_with_layout(
+    layout: &ArtifactsLayout,
+    report: &VerificationReport,
+) -> io::Result<()> {
+    evm_verifier(report)?;
+    layout.check([Artifact::HardhatArtifact, Artifact::CalldataEncoder])?;
+    let artifact = HardhatArtifact::new(CONTRACT_NAME, report)?
+        .to_json()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    layout.write(Artifact::HardhatArtifact, artifact)?;
+    layout.write(
+        Artifact::CalldataEncoder,
+        encode_instances_helper(report.envelope.instances.len()),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{encode_instances_helper, runtime_code, runtime_object};
+
+    #[test]
+    fn test_runtime_object() {
+        let yul = r#"object "plonk_verifier" {
+    code {
+        let size := datasize("Runtime")
+        datacopy(0, dataoffset("Runtime"), size)
+        return(0, size)
+    }
+    object "Runtime" {
+        code {
+            mstore(0, 1)
+        }
+    }
+}"#;
+        assert_eq!(
+            runtime_object(yul),
+            Some(
+                "object \"Runtime\" {\n        code {\n            mstore(0, 1)\n        }\n    }"
+            )
+        );
+        assert_eq!(runtime_object("object \"a\" { code {} }"), None);
+        assert!(encode_instances_helper(3).contains("NUM_INSTANCES = 3;"));
+    }
+
+    #[test]
+    fn test_missing_runtime_code() {
+        assert_eq!(
+            runtime_code("object \"a\" { code {} }").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
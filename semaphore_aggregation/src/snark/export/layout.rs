@@ -0,0 +1,342 @@
+//! Where the exports write their files: a root directory, the file name of every artifact
+//! relative to it, and what to do with the files already there. The exported verifier, its
+//! `VerifierKey`, the manifest, the proving and verifying keys of the batch sizes, the blobs and
+//! the fixtures are all written through an `ArtifactsLayout`, whose default file names are the
+//! ones the tooling reading them expects, e.g. `Verifier.bin` for `read_deployment_code`.
+//!
+//! With `OverwritePolicy::Fail`, an export checks every file it writes before writing any, so
+//! that it either fails or writes all of them, and creates them only if they don't exist yet.
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use super::CONTRACT_NAME;
+
+/// File written by an export
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Artifact {
+    /// Yul source of the EVM verifier
+    Yul,
+    /// Deployment code of the EVM verifier, as hex
+    DeploymentCode,
+    /// Constructor arguments of the EVM verifier, as hex
+    ConstructorArgs,
+    /// Envelope of the wrapped proof, as JSON
+    Proof,
+    /// `Manifest` of the EVM verifier
+    Manifest,
+    /// Directory of the `VerifierKey`, whose files are the ones of `Artifact::VERIFIER_KEY`
+    VerifierKey,
+    /// snark-verifier protocol of a `VerifierKey`, as JSON
+    Protocol,
+    /// KZG deciding key of a `VerifierKey`
+    DecidingKey,
+    /// Multi-opening scheme of a `VerifierKey`
+    MultiOpen,
+    /// Transcript of a `VerifierKey`
+    Transcript,
+    /// Foundry test of the EVM verifier
+    FoundryTest,
+    /// Fuzzing harness of the EVM verifier and its configs, see `fuzzing`
+    FuzzHarness,
+    EchidnaConfig,
+    MedusaConfig,
+    /// Hardhat artifact of the EVM verifier, see `hardhat`
+    HardhatArtifact,
+    /// TypeScript helper encoding the calldata, see `hardhat`
+    CalldataEncoder,
+    /// Halo2 proving key of the verifier circuit
+    ProvingKey,
+    /// Halo2 verifying key of the verifier circuit
+    VerifyingKey,
+    /// Plonky2 proof of a fixture, see `fixtures`
+    Plonky2Proof,
+    /// Plonky2 verifier data of a fixture
+    Plonky2VerifierData,
+    /// Plonky2 common data of a fixture
+    Plonky2CommonData,
+    /// Registry contract whitelisting verification keys, see `vk_registry`
+    RegistryContract,
+    /// Keys whitelisted by the registry, as JSON
+    RegistryKeys,
+    /// Solidity library reading the registry key of a proof, see `vk_registry::registry_library`
+    RegistryLibrary,
+    /// Solidity library checking signed submissions of a proof, see
+    /// `eip712::submission_library`
+    SubmissionLibrary,
+    /// Blob at an index of the blobs of a batch of proofs, as hex, see `blobs`
+    Blob(usize),
+    /// Contract recording the batches of blobs posted, see `blobs::inbox_contract`
+    BlobInbox,
+}
+
+impl Artifact {
+    /// Files of a `VerifierKey`, relative to its directory
+    pub const VERIFIER_KEY: [Artifact; 4] = [
+        Artifact::Protocol,
+        Artifact::DecidingKey,
+        Artifact::MultiOpen,
+        Artifact::Transcript,
+    ];
+
+    /// File name of the artifact unless overridden, relative to the root of the layout
+    pub fn default_file_name(&self) -> PathBuf {
+        match self {
+            Artifact::Yul => format!("{CONTRACT_NAME}.yul").into(),
+            Artifact::DeploymentCode => format!("{CONTRACT_NAME}.bin").into(),
+            Artifact::ConstructorArgs => format!("{CONTRACT_NAME}.args").into(),
+            Artifact::Proof => "proof.json".into(),
+            Artifact::Manifest => "manifest.json".into(),
+            Artifact::VerifierKey => "verifier_key".into(),
+            Artifact::Protocol => "protocol.json".into(),
+            Artifact::DecidingKey => "deciding_key.bin".into(),
+            Artifact::MultiOpen => "multiopen.txt".into(),
+            Artifact::Transcript => "transcript.txt".into(),
+            Artifact::FoundryTest => Path::new("test").join(format!("{CONTRACT_NAME}.t.sol")),
+            Artifact::FuzzHarness => Path::new("fuzz").join(format!("{CONTRACT_NAME}Fuzz.sol")),
+            Artifact::EchidnaConfig => Path::new("fuzz").join("echidna.yaml"),
+            Artifact::MedusaConfig => Path::new("fuzz").join("medusa.json"),
+            Artifact::HardhatArtifact => format!("{CONTRACT_NAME}.json").into(),
+            Artifact::CalldataEncoder => "encodeCalldata.ts".into(),
+            Artifact::ProvingKey => "pk.bin".into(),
+            Artifact::VerifyingKey => "vk.bin".into(),
+            Artifact::Plonky2Proof => Path::new("plonky2").join("proof.bin"),
+            Artifact::Plonky2VerifierData => Path::new("plonky2").join("verifier_only.bin"),
+            Artifact::Plonky2CommonData => Path::new("plonky2").join("common.bin"),
+            Artifact::RegistryContract => format!("{CONTRACT_NAME}Registry.sol").into(),
+            Artifact::RegistryKeys => "registry_keys.json".into(),
+            Artifact::RegistryLibrary => format!("{CONTRACT_NAME}VkRegistry.sol").into(),
+            Artifact::SubmissionLibrary => format!("{CONTRACT_NAME}Submission.sol").into(),
+            Artifact::Blob(index) => format!("blob_{index}.hex").into(),
+            Artifact::BlobInbox => format!("{CONTRACT_NAME}BlobInbox.sol").into(),
+        }
+    }
+}
+
+/// What to do when an artifact is already on disk
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Replaces the artifact
+    #[default]
+    Overwrite,
+    /// Keeps the artifact on disk and skips writing it
+    Keep,
+    /// Fails with `io::ErrorKind::AlreadyExists` before writing anything, e.g. to never replace
+    /// a deployed verifier
+    Fail,
+}
+
+/// Paths of the artifacts of an export
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArtifactsLayout {
+    root: PathBuf,
+    file_names: BTreeMap<Artifact, PathBuf>,
+    overwrite: OverwritePolicy,
+}
+
+impl ArtifactsLayout {
+    /// Layout writing the artifacts to `root` with their default file names, overwriting them
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            file_names: BTreeMap::new(),
+            overwrite: OverwritePolicy::default(),
+        }
+    }
+
+    /// Writes `artifact` to `file_name`, relative to the root
+    pub fn with_file_name(mut self, artifact: Artifact, file_name: impl Into<PathBuf>) -> Self {
+        self.file_names.insert(artifact, file_name.into());
+        self
+    }
+
+    pub fn with_overwrite(mut self, overwrite: OverwritePolicy) -> Self {
+        self.overwrite = overwrite;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn overwrite(&self) -> OverwritePolicy {
+        self.overwrite
+    }
+
+    /// Same layout rooted at `dir` relative to the root, e.g. for the artifacts of a batch size
+    pub fn nested(&self, dir: impl AsRef<Path>) -> Self {
+        Self {
+            root: self.root.join(dir),
+            ..self.clone()
+        }
+    }
+
+    /// Same layout rooted at the path of `artifact`, a directory of artifacts such as
+    /// `Artifact::VerifierKey`
+    pub fn directory(&self, artifact: Artifact) -> Self {
+        Self {
+            root: self.path(artifact),
+            ..self.clone()
+        }
+    }
+
+    /// Path of `artifact`
+    pub fn path(&self, artifact: Artifact) -> PathBuf {
+        match self.file_names.get(&artifact) {
+            Some(file_name) => self.root.join(file_name),
+            None => self.root.join(artifact.default_file_name()),
+        }
+    }
+
+    /// Fails with `io::ErrorKind::AlreadyExists` if the policy is `OverwritePolicy::Fail` and
+    /// any of `artifacts` is on disk. Exports call it with every artifact they write before
+    /// writing any.
+    pub fn check(&self, artifacts: impl IntoIterator<Item = Artifact>) -> io::Result<()> {
+        if self.overwrite != OverwritePolicy::Fail {
+            return Ok(());
+        }
+        match artifacts
+            .into_iter()
+            .map(|artifact| self.path(artifact))
+            .find(|path| path.exists())
+        {
+            Some(path) => Err(already_exists(&path)),
+            None => Ok(()),
+        }
+    }
+
+    /// Path to write `artifact` to, its parent directory being created, or `None` if it is on
+    /// disk and kept
+    pub fn target(&self, artifact: Artifact) -> io::Result<Option<PathBuf>> {
+        let path = self.path(artifact);
+        if path.exists() {
+            match self.overwrite {
+                OverwritePolicy::Overwrite => {}
+                OverwritePolicy::Keep => return Ok(None),
+                OverwritePolicy::Fail => return Err(already_exists(&path)),
+            }
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(Some(path))
+    }
+
+    /// Creates the file of `artifact`, or returns `None` if it is on disk and kept. With
+    /// `OverwritePolicy::Fail`, the file must not exist when it is created, so that a file
+    /// created since the check isn't replaced either.
+    pub fn create(&self, artifact: Artifact) -> io::Result<Option<File>> {
+        let path = match self.target(artifact)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let mut options = OpenOptions::new();
+        match self.overwrite {
+            OverwritePolicy::Fail => options.write(true).create_new(true),
+            _ => options.write(true).create(true).truncate(true),
+        };
+        match options.open(&path) {
+            Ok(file) => Ok(Some(file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => Err(already_exists(&path)),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes `contents` to the path of `artifact`, returning whether it was written
+    pub fn write(&self, artifact: Artifact, contents: impl AsRef<[u8]>) -> io::Result<bool> {
+        match self.create(artifact)? {
+            Some(mut file) => file.write_all(contents.as_ref()).map(|_| true),
+            None => Ok(false),
+        }
+    }
+}
+
+fn already_exists(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("{} already exists", path.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+
+    use super::{Artifact, ArtifactsLayout, OverwritePolicy};
+
+    #[test]
+    fn test_artifacts_layout() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let layout = ArtifactsLayout::new(dir).with_file_name(Artifact::Proof, "out/wrapped.json");
+        assert_eq!(layout.path(Artifact::Proof), dir.join("out/wrapped.json"));
+        assert_eq!(
+            layout.path(Artifact::DeploymentCode),
+            dir.join("Verifier.bin")
+        );
+        let batch = layout.nested("batch_16");
+        assert_eq!(
+            batch.path(Artifact::Proof),
+            dir.join("batch_16/out/wrapped.json")
+        );
+        let key = layout.directory(Artifact::VerifierKey);
+        assert_eq!(
+            key.path(Artifact::Protocol),
+            dir.join("verifier_key/protocol.json")
+        );
+        assert_eq!(layout.path(Artifact::Blob(2)), dir.join("blob_2.hex"));
+
+        assert!(layout.write(Artifact::Proof, "a").unwrap());
+        let kept = layout.clone().with_overwrite(OverwritePolicy::Keep);
+        assert!(!kept.write(Artifact::Proof, "b").unwrap());
+        assert_eq!(
+            fs::read_to_string(layout.path(Artifact::Proof)).unwrap(),
+            "a"
+        );
+        let failing = layout.clone().with_overwrite(OverwritePolicy::Fail);
+        assert_eq!(
+            failing.write(Artifact::Proof, "b").unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert!(layout.write(Artifact::Proof, "b").unwrap());
+        assert_eq!(
+            fs::read_to_string(layout.path(Artifact::Proof)).unwrap(),
+            "b"
+        );
+    }
+
+    #[test]
+    fn test_fail_policy() {
+        let tmp = tempfile::tempdir().unwrap();
+        let layout = ArtifactsLayout::new(tmp.path()).with_overwrite(OverwritePolicy::Fail);
+        assert!(layout.write(Artifact::Proof, "a").unwrap());
+
+        // an export fails before writing anything if any of its artifacts is on disk
+        assert!(layout.check([Artifact::Yul, Artifact::Manifest]).is_ok());
+        assert_eq!(
+            layout
+                .check([Artifact::Yul, Artifact::Proof])
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert!(!layout.path(Artifact::Yul).exists());
+        // other policies never fail the check
+        assert!(layout
+            .clone()
+            .with_overwrite(OverwritePolicy::Keep)
+            .check([Artifact::Proof])
+            .is_ok());
+
+        // a file created between the check and the write isn't replaced
+        let path = layout.target(Artifact::Yul).unwrap().unwrap();
+        fs::write(&path, "deployed").unwrap();
+        assert_eq!(
+            layout.create(Artifact::Yul).unwrap_err().kind(),
+            io::ErrorKind::AlreadyExists
+        );
+        assert_eq!(fs::read_to_string(path).unwrap(), "deployed");
+    }
+}
@@ -0,0 +1,395 @@
+//! Files describing a wrapped proof and its EVM verifier, for integrators deploying the
+//! verifier with their own tooling.
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use self::layout::{Artifact, ArtifactsLayout};
+use super::eip712::submission_library;
+use super::envelope::{hex_bytes32, WrappedProofEnvelope};
+use super::error::VerifierError;
+use super::protocol::ProtocolMetadata;
+use super::report::VerificationReport;
+use super::simulator::{EvmSimulator, SimulationResult};
+use super::srs::SrsMetadata;
+use super::vk_registry::{registry_contract, registry_library, VkRegistry};
+
+pub mod batches;
+pub mod blobs;
+#[cfg(feature = "deploy")]
+pub mod deploy;
+pub mod foundry;
+pub mod fuzzing;
+pub mod hardhat;
+pub mod layout;
+
+/// Name of the verifier contract in the exported files
+pub const CONTRACT_NAME: &str = "Verifier";
+
+/// Summary of the exported verifier, with the provenance of the SRS it was generated from
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub contract_name: String,
+    pub k: u32,
+    /// Hash of the Halo2 verifying key, see `envelope::vk_hash`
+    #[serde(with = "hex_bytes32")]
+    pub vk_hash: [u8; 32],
+    pub deployment_code_size: usize,
+    /// Version of the solc the deployment code was compiled with, absent from manifests written
+    /// before it was recorded
+    #[serde(default)]
+    pub solc_version: Option<String>,
+    pub gas_used: u64,
+    pub srs: Option<SrsMetadata>,
+    /// snark-verifier protocol the verifier was generated from, absent from manifests written
+    /// before it was recorded
+    #[serde(default)]
+    pub protocol: Option<ProtocolMetadata>,
+    /// Layout of the calldata the verifier reads, see `CalldataLayout`, absent from manifests
+    /// written before it was recorded
+    #[serde(default)]
+    pub calldata_layout: Option<String>,
+}
+
+impl Manifest {
+    /// Fails for a report of a proof only verified natively, which has no EVM verifier
+    pub fn new(contract_name: &str, report: &VerificationReport) -> io::Result<Self> {
+        let (_, deployment_code) = evm_verifier(report)?;
+        let gas_used = report.gas_used.ok_or_else(|| no_evm_verifier(report))?;
+        let protocol = ProtocolMetadata::new(
+            &report.verifier_key,
+            report.envelope.calldata_layout(),
+            !report.constructor_args.is_empty(),
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self {
+            contract_name: contract_name.to_string(),
+            k: report.k,
+            vk_hash: report.envelope.vk_hash,
+            deployment_code_size: deployment_code.len(),
+            solc_version: report.solc_version.clone(),
+            gas_used,
+            srs: report.srs.clone(),
+            protocol: Some(protocol),
+            calldata_layout: Some(report.envelope.calldata_layout().to_string()),
+        })
+    }
+}
+
+fn no_evm_verifier(report: &VerificationReport) -> io::Error {
+    let transcript = report.verifier_key.transcript();
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("proofs with a {transcript} transcript have no EVM verifier to export"),
+    )
+}
+
+/// Yul source and deployment code of the EVM verifier of `report`, failing for a report of a
+/// proof only verified natively, see `TranscriptKind`
+fn evm_verifier(report: &VerificationReport) -> io::Result<(&str, &[u8])> {
+    match (&report.yul_code, &report.deployment_code) {
+        (Some(yul_code), Some(deployment_code))
+            if report.verifier_key.transcript().is_evm_compatible() =>
+        {
+            Ok((yul_code, deployment_code))
+        }
+        _ => Err(no_evm_verifier(report)),
+    }
+}
+
+/// Artifacts `export_artifacts_with_layout` writes for `report`, besides the files of its
+/// `VerifierKey`
+fn exported_artifacts(report: &VerificationReport) -> Vec<Artifact> {
+    let mut artifacts = vec![
+        Artifact::Yul,
+        Artifact::DeploymentCode,
+        Artifact::Proof,
+        Artifact::Manifest,
+        Artifact::SubmissionLibrary,
+        Artifact::FoundryTest,
+        Artifact::FuzzHarness,
+        Artifact::EchidnaConfig,
+        Artifact::MedusaConfig,
+    ];
+    if !report.constructor_args.is_empty() {
+        artifacts.push(Artifact::ConstructorArgs);
+    }
+    if report.envelope.has_vk_commitment() {
+        artifacts.push(Artifact::RegistryLibrary);
+    }
+    artifacts
+}
+
+/// Fails with `io::ErrorKind::AlreadyExists` if `layout` never overwrites and any artifact
+/// `export_artifacts_with_layout` writes for `report` is on disk, see `ArtifactsLayout::check`
+pub fn check_artifacts_with_layout(
+    layout: &ArtifactsLayout,
+    report: &VerificationReport,
+) -> io::Result<()> {
+    layout.check(exported_artifacts(report))?;
+    layout
+        .directory(Artifact::VerifierKey)
+        .check(Artifact::VERIFIER_KEY)
+}
+
+/// Writes the artifacts of `report` to `dir`:
+/// - `Verifier.yul`, the Yul source of the verifier
+/// - `Verifier.bin`, its deployment code as hex
+/// - `Verifier.args`, its constructor arguments as hex, if it takes the G2 points of the SRS as
+///   constructor arguments
+/// - `proof.json`, the envelope of the wrapped proof
+/// - `manifest.json`, the `Manifest` of the verifier
+/// - `verifier_key/`, the `VerifierKey` verifying the proof natively without the SRS
+/// - `VerifierVkRegistry.sol`, the library reading the registry key of a proof, see
+///   `vk_registry::registry_library`, if the proof exposes a commitment to the verification key
+/// - `VerifierSubmission.sol`, the library checking the EIP-712 signatures of submissions of a
+///   proof through a relayer, see `eip712`
+/// - `test/Verifier.t.sol`, a Foundry test checking that the deployed verifier accepts the
+///   proof and rejects a tampered one
+/// - `fuzz/VerifierFuzz.sol`, `fuzz/echidna.yaml` and `fuzz/medusa.json`, a property harness
+///   fuzzing the verifier with mutations of the proof, see `fuzzing`
+///
+/// Fails for a report of a proof only verified natively, see `TranscriptKind`, whose
+/// `VerifierKey` is written on its own.
+pub fn export_artifacts(dir: &Path, report: &VerificationReport) -> io::Result<()> {
+    export_artifacts_with_layout(&ArtifactsLayout::new(dir), report)
+}
+
+/// Same as `export_artifacts`, writing the artifacts where `layout` puts them
+pub fn export_artifacts_with_layout(
+    layout: &ArtifactsLayout,
+    report: &VerificationReport,
+) -> io::Result<()> {
+    let (yul_code, deployment_code) = evm_verifier(report)?;
+    check_artifacts_with_layout(layout, report)?;
+    let manifest = serde_json::to_string_pretty(&Manifest::new(CONTRACT_NAME, report)?)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let deployment_code_with_args = [deployment_code, &report.constructor_args].concat();
+    layout.write(Artifact::Yul, yul_code)?;
+    layout.write(Artifact::DeploymentCode, hex::encode(deployment_code))?;
+    if !report.constructor_args.is_empty() {
+        layout.write(
+            Artifact::ConstructorArgs,
+            hex::encode(&report.constructor_args),
+        )?;
+    }
+    let envelope = report
+        .envelope
+        .to_json()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    layout.write(Artifact::Proof, envelope)?;
+    layout.write(Artifact::Manifest, manifest)?;
+    report
+        .verifier_key
+        .write_with_layout(&layout.directory(Artifact::VerifierKey))?;
+    if report.envelope.has_vk_commitment() {
+        layout.write(Artifact::RegistryLibrary, registry_library(CONTRACT_NAME))?;
+    }
+    layout.write(
+        Artifact::SubmissionLibrary,
+        submission_library(CONTRACT_NAME),
+    )?;
+
+    let calldata_layout = report.envelope.calldata_layout();
+    let calldata = report.envelope.calldata_with_layout(calldata_layout);
+    let invalid_calldata = foundry::tamper(
+        &calldata,
+        calldata_layout.proof_offset(report.envelope.instances.len()),
+    );
+    layout.write(
+        Artifact::FoundryTest,
+        foundry::test_suite(
+            CONTRACT_NAME,
+            &deployment_code_with_args,
+            &calldata,
+            &invalid_calldata,
+        ),
+    )?;
+    layout.write(
+        Artifact::FuzzHarness,
+        fuzzing::harness(CONTRACT_NAME, &deployment_code_with_args, &calldata),
+    )?;
+    layout.write(Artifact::EchidnaConfig, fuzzing::echidna_config())?;
+    layout.write(
+        Artifact::MedusaConfig,
+        fuzzing::medusa_config(CONTRACT_NAME),
+    )?;
+    Ok(())
+}
+
+/// Writes the registry of the verification keys accepted by the verifier exported to `dir`:
+/// - `VerifierRegistry.sol`, the registry contract forwarding proofs of registered keys to the
+///   verifier, see `vk_registry::registry_contract`
+/// - `registry_keys.json`, the keys of `registry`, which `verify_artifacts` then checks the proof
+///   against
+pub fn export_registry(dir: &Path, registry: &VkRegistry) -> io::Result<()> {
+    export_registry_with_layout(&ArtifactsLayout::new(dir), registry)
+}
+
+/// Same as `export_registry`, writing the artifacts where `layout` puts them
+pub fn export_registry_with_layout(
+    layout: &ArtifactsLayout,
+    registry: &VkRegistry,
+) -> io::Result<()> {
+    layout.check([Artifact::RegistryContract, Artifact::RegistryKeys])?;
+    layout.write(Artifact::RegistryContract, registry_contract(CONTRACT_NAME))?;
+    let keys = registry
+        .to_json()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    layout.write(Artifact::RegistryKeys, keys)?;
+    Ok(())
+}
+
+/// Deployment code of the verifier exported to `dir` by `export_artifacts`, followed by its
+/// constructor arguments if any
+pub fn read_deployment_code(dir: &Path) -> Result<Vec<u8>, VerifierError> {
+    read_deployment_code_with_layout(&ArtifactsLayout::new(dir))
+}
+
+/// Same as `read_deployment_code`, for artifacts exported with `layout`
+pub fn read_deployment_code_with_layout(
+    layout: &ArtifactsLayout,
+) -> Result<Vec<u8>, VerifierError> {
+    let invalid_data = |e| VerifierError::Io(io::Error::new(io::ErrorKind::InvalidData, e));
+    let deployment_code =
+        fs::read_to_string(layout.path(Artifact::DeploymentCode)).map_err(VerifierError::Io)?;
+    let mut deployment_code =
+        hex::decode(deployment_code.trim()).map_err(|e| invalid_data(e.to_string()))?;
+    let args_path = layout.path(Artifact::ConstructorArgs);
+    if args_path.exists() {
+        let args = fs::read_to_string(args_path).map_err(VerifierError::Io)?;
+        deployment_code.extend(hex::decode(args.trim()).map_err(|e| invalid_data(e.to_string()))?);
+    }
+    Ok(deployment_code)
+}
+
+/// Deploys the verifier exported to `dir` by `export_artifacts` in the in-memory EVM and runs it
+/// on the exported proof. Needs neither the SRS nor the keys, so that artifacts produced on
+/// another machine can be checked.
+/// If a registry was exported with `export_registry`, fails with
+/// `VerifierError::UnregisteredVerificationKey` unless it whitelists the verification key the
+/// proof commits to.
+pub fn verify_artifacts(dir: &Path) -> Result<SimulationResult, VerifierError> {
+    verify_artifacts_with_layout(&ArtifactsLayout::new(dir))
+}
+
+/// Same as `verify_artifacts`, for artifacts exported with `layout`
+pub fn verify_artifacts_with_layout(
+    layout: &ArtifactsLayout,
+) -> Result<SimulationResult, VerifierError> {
+    let invalid_data = |e| VerifierError::Io(io::Error::new(io::ErrorKind::InvalidData, e));
+    let deployment_code = read_deployment_code_with_layout(layout)?;
+    let envelope = fs::read_to_string(layout.path(Artifact::Proof)).map_err(VerifierError::Io)?;
+    let envelope =
+        WrappedProofEnvelope::from_json(&envelope).map_err(|e| invalid_data(e.to_string()))?;
+    let registry_path = layout.path(Artifact::RegistryKeys);
+    if registry_path.exists() {
+        let registry = fs::read_to_string(registry_path).map_err(VerifierError::Io)?;
+        let registry = VkRegistry::from_json(&registry).map_err(VerifierError::Io)?;
+        if !registry.contains_instances(&envelope.instances) {
+            return Err(VerifierError::UnregisteredVerificationKey);
+        }
+    }
+    EvmSimulator::deploy(deployment_code)?
+        .call(envelope.calldata_with_layout(envelope.calldata_layout()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{export_registry, verify_artifacts, CONTRACT_NAME};
+    use crate::snark::encoding::fr_to_bytes32;
+    use crate::snark::envelope::{WrappedProofEnvelope, ENVELOPE_VERSION};
+    use crate::snark::error::VerifierError;
+    use crate::snark::test_utils;
+    use crate::snark::types::verification_key::VerificationKeyValues;
+    use crate::snark::vk_registry::{vk_commitment, VkRegistry};
+
+    /// Writes the deployment code of a contract reverting when called with empty calldata:
+    /// CALLDATASIZE PUSH1 6 JUMPI INVALID JUMPDEST STOP
+    fn write_calldata_checker(dir: &Path) {
+        let runtime = [0x36, 0x60, 0x06, 0x57, 0xfe, 0x5b, 0x00];
+        let mut deployment_code = vec![
+            0x60, 0x07, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x07, 0x60, 0x00, 0xf3,
+        ];
+        deployment_code.extend_from_slice(&runtime);
+        fs::write(
+            dir.join(format!("{CONTRACT_NAME}.bin")),
+            hex::encode(&deployment_code),
+        )
+        .unwrap();
+    }
+
+    fn envelope(instances: Vec<[u8; 32]>, proof_bytes: Vec<u8>) -> WrappedProofEnvelope {
+        WrappedProofEnvelope {
+            version: ENVELOPE_VERSION,
+            vk_hash: [0; 32],
+            instances,
+            proof_bytes,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_verify_artifacts() {
+        let dir = std::env::temp_dir().join("stark-verifier-verify-artifacts-test");
+        fs::create_dir_all(&dir).unwrap();
+        write_calldata_checker(&dir);
+
+        let envelope_path = dir.join("proof.json");
+        let accepted = envelope(vec![[1; 32]], vec![2; 32]);
+        fs::write(&envelope_path, accepted.to_json().unwrap()).unwrap();
+        assert!(verify_artifacts(&dir).unwrap().success);
+
+        let rejected = envelope(vec![], vec![]);
+        fs::write(&envelope_path, rejected.to_json().unwrap()).unwrap();
+        assert!(!verify_artifacts(&dir).unwrap().success);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_artifacts_with_registry() -> Result<()> {
+        let dir = std::env::temp_dir().join("stark-verifier-verify-registry-test");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir)?;
+        write_calldata_checker(&dir);
+
+        let (_, vd, _) = test_utils::constant_proof()?;
+        let vk = VerificationKeyValues::<Fr>::try_from(vd)?;
+        let mut registry = VkRegistry::new();
+        let key = registry.register(&vk);
+        export_registry(&dir, &registry)?;
+        assert!(dir.join("VerifierRegistry.sol").exists());
+        let exported = fs::read_to_string(dir.join("registry_keys.json"))?;
+        assert_eq!(
+            VkRegistry::from_json(&exported)?.keys().collect::<Vec<_>>(),
+            vec![&key]
+        );
+
+        // a proof of the registered verification key is looked up and verified
+        let instances = vk_commitment(&vk).iter().map(fr_to_bytes32).collect();
+        let proof = envelope(instances, vec![2; 32]);
+        fs::write(dir.join("proof.json"), proof.to_json()?)?;
+        assert!(verify_artifacts(&dir)?.success);
+
+        let (_, other_vd, _) = test_utils::poseidon_proof()?;
+        let other_vk = VerificationKeyValues::<Fr>::try_from(other_vd)?;
+        let instances = vk_commitment(&other_vk).iter().map(fr_to_bytes32).collect();
+        let proof = envelope(instances, vec![2; 32]);
+        fs::write(dir.join("proof.json"), proof.to_json()?)?;
+        assert!(matches!(
+            verify_artifacts(&dir),
+            Err(VerifierError::UnregisteredVerificationKey)
+        ));
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,352 @@
+//! C interface of the wrapping pipeline, so that sequencers written in Go or Node can wrap Plonky2
+//! proofs without spawning a subprocess. Build the library with
+//! `cargo rustc --release --features cffi --crate-type cdylib`; the declarations are in
+//! `include/stark_verifier.h`.
+//!
+//! The Plonky2 proof, verifier data and common data are given in the byte encoding of
+//! `plonky2::util::serialization`, common data being encoded with the default gate serializer.
+//! Every function returns a `StatusCode`, and writes its output to a `ByteBuffer` owned by the
+//! caller until it is released with `stark_verifier_free_buffer`.
+
+use std::panic::{catch_unwind, UnwindSafe};
+use std::{ptr, slice};
+
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::circuit_data::{CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::gate_serialization::default::DefaultGateSerializer;
+
+use super::error::VerifierError;
+use super::pipeline::PipelineConfig;
+use super::report::VerificationReport;
+use super::simulator::EvmSimulator;
+use super::verifier_api::verify_inside_snark_with_config;
+use crate::ProofTuple;
+
+/// Status returned by every function of the C interface
+#[repr(i32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusCode {
+    Ok = 0,
+    /// A pointer was null, or an input buffer couldn't be decoded
+    InvalidArgument = 1,
+    /// The Plonky2 proof was rejected by the native Plonky2 verifier
+    InvalidInnerProof = 2,
+    /// The Plonky2 proof, verifier data or common data can't be handled by the verifier circuit
+    MalformedInput = 3,
+    /// The verifier circuit doesn't fit in the largest supported SRS
+    NotEnoughRows = 4,
+    /// The SRS in use was generated locally, and `allow_insecure_srs` was not set
+    InsecureSrs = 5,
+    /// The EVM verifier couldn't be deployed, or rejected the proof
+    VerificationFailed = 6,
+    /// Any other error of the pipeline
+    PipelineError = 7,
+    /// The pipeline panicked
+    Panic = 8,
+}
+
+impl From<&VerifierError> for StatusCode {
+    fn from(error: &VerifierError) -> Self {
+        match error {
+            VerifierError::InvalidInnerProof(_) => StatusCode::InvalidInnerProof,
+            VerifierError::MalformedInput(_) | VerifierError::UnsupportedExtensionDegree(_) => {
+                StatusCode::MalformedInput
+            }
+            VerifierError::NotEnoughRows { .. } => StatusCode::NotEnoughRows,
+            VerifierError::InsecureSrs => StatusCode::InsecureSrs,
+            VerifierError::DeploymentFailed
+            | VerifierError::EvmVerificationFailed
+            | VerifierError::MalformedProof(_) => StatusCode::VerificationFailed,
+            _ => StatusCode::PipelineError,
+        }
+    }
+}
+
+/// Bytes allocated by the library. `data` is null for an empty buffer.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ByteBuffer {
+    pub data: *mut u8,
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl ByteBuffer {
+    fn empty() -> Self {
+        Self {
+            data: ptr::null_mut(),
+            len: 0,
+            capacity: 0,
+        }
+    }
+
+    fn from_vec(bytes: Vec<u8>) -> Self {
+        let mut bytes = std::mem::ManuallyDrop::new(bytes);
+        Self {
+            data: bytes.as_mut_ptr(),
+            len: bytes.len(),
+            capacity: bytes.capacity(),
+        }
+    }
+}
+
+/// Options of the pipeline, mirroring the fields of `PipelineConfig` a caller may set
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FfiPipelineConfig {
+    /// Largest `k` of the outer circuit, 0 for the largest supported one
+    pub max_k: u32,
+    /// Whether a locally generated SRS may be used
+    pub allow_insecure_srs: bool,
+    /// Whether the G2 points are passed to the EVM verifier as constructor arguments
+    pub g2_constructor_args: bool,
+}
+
+impl FfiPipelineConfig {
+    fn pipeline_config(&self) -> PipelineConfig {
+        let mut config =
+            PipelineConfig::default().with_g2_constructor_args(self.g2_constructor_args);
+        if self.max_k != 0 {
+            config = config.with_max_k(self.max_k);
+        }
+        if self.allow_insecure_srs {
+            config = config.allow_insecure();
+        }
+        config
+    }
+}
+
+/// Slice of `len` bytes at `data`, or `None` if `data` is null
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    if data.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(data, len))
+    }
+}
+
+fn decode_proof_tuple(
+    proof: &[u8],
+    verifier_only: &[u8],
+    common: &[u8],
+) -> Option<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>> {
+    let common = CommonCircuitData::from_bytes(common.to_vec(), &DefaultGateSerializer).ok()?;
+    let verifier_only = VerifierOnlyCircuitData::from_bytes(verifier_only.to_vec()).ok()?;
+    let proof = ProofWithPublicInputs::from_bytes(proof.to_vec(), &common).ok()?;
+    Some((proof, verifier_only, common))
+}
+
+/// Runs `f`, writing its output to `out`. Panics are caught, as they must not unwind into the
+/// caller.
+unsafe fn run(
+    out: *mut ByteBuffer,
+    f: impl FnOnce() -> Result<Vec<u8>, StatusCode> + UnwindSafe,
+) -> StatusCode {
+    if out.is_null() {
+        return StatusCode::InvalidArgument;
+    }
+    *out = ByteBuffer::empty();
+    match catch_unwind(f) {
+        Ok(Ok(bytes)) => {
+            *out = ByteBuffer::from_vec(bytes);
+            StatusCode::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(_) => StatusCode::Panic,
+    }
+}
+
+unsafe fn wrap_proof(
+    proof: *const u8,
+    proof_len: usize,
+    verifier_only: *const u8,
+    verifier_only_len: usize,
+    common: *const u8,
+    common_len: usize,
+    config: *const FfiPipelineConfig,
+) -> Result<VerificationReport, StatusCode> {
+    let (proof, verifier_only, common) = match (
+        input(proof, proof_len),
+        input(verifier_only, verifier_only_len),
+        input(common, common_len),
+    ) {
+        (Some(proof), Some(verifier_only), Some(common)) => (proof, verifier_only, common),
+        _ => return Err(StatusCode::InvalidArgument),
+    };
+    let config = config.as_ref().copied().unwrap_or_default();
+    let proof =
+        decode_proof_tuple(proof, verifier_only, common).ok_or(StatusCode::InvalidArgument)?;
+    verify_inside_snark_with_config(proof, &config.pipeline_config())
+        .map_err(|e| StatusCode::from(&e))
+}
+
+/// Wraps a Plonky2 proof, writing the JSON encoding of its `WrappedProofEnvelope` to `out`.
+/// `config` may be null for the default options.
+///
+/// # Safety
+///
+/// Every input pointer must be null or valid for reads of its length, and `out` must be valid
+/// for writes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn stark_verifier_prove(
+    proof: *const u8,
+    proof_len: usize,
+    verifier_only: *const u8,
+    verifier_only_len: usize,
+    common: *const u8,
+    common_len: usize,
+    config: *const FfiPipelineConfig,
+    out: *mut ByteBuffer,
+) -> StatusCode {
+    run(out, || {
+        let report = wrap_proof(
+            proof,
+            proof_len,
+            verifier_only,
+            verifier_only_len,
+            common,
+            common_len,
+            config,
+        )?;
+        report
+            .envelope
+            .to_json()
+            .map(String::into_bytes)
+            .map_err(|_| StatusCode::PipelineError)
+    })
+}
+
+/// Generates the EVM verifier of the circuit wrapping the given Plonky2 proof, writing its
+/// deployment code, constructor arguments included, to `out`. The whole pipeline is run, so
+/// that the verifier is checked against the proof before being returned.
+///
+/// # Safety
+///
+/// Every input pointer must be null or valid for reads of its length, and `out` must be valid
+/// for writes.
+#[no_mangle]
+#[allow(clippy::too_many_arguments)]
+pub unsafe extern "C" fn stark_verifier_gen_verifier(
+    proof: *const u8,
+    proof_len: usize,
+    verifier_only: *const u8,
+    verifier_only_len: usize,
+    common: *const u8,
+    common_len: usize,
+    config: *const FfiPipelineConfig,
+    out: *mut ByteBuffer,
+) -> StatusCode {
+    run(out, || {
+        let report = wrap_proof(
+            proof,
+            proof_len,
+            verifier_only,
+            verifier_only_len,
+            common,
+            common_len,
+            config,
+        )?;
+        report.deployment_code_with_args().ok_or_else(|| {
+            StatusCode::from(&VerifierError::NoEvmVerifier {
+                transcript: report.verifier_key.transcript(),
+            })
+        })
+    })
+}
+
+/// Deploys `deployment_code` in an in-memory EVM and calls it with `calldata`, writing the gas
+/// used to `gas_used` if it isn't null. Returns `VerificationFailed` if the verifier rejects
+/// the calldata.
+///
+/// # Safety
+///
+/// Every input pointer must be null or valid for reads of its length, and `gas_used` must be
+/// null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn stark_verifier_verify(
+    deployment_code: *const u8,
+    deployment_code_len: usize,
+    calldata: *const u8,
+    calldata_len: usize,
+    gas_used: *mut u64,
+) -> StatusCode {
+    let (deployment_code, calldata) = match (
+        input(deployment_code, deployment_code_len),
+        input(calldata, calldata_len),
+    ) {
+        (Some(deployment_code), Some(calldata)) => (deployment_code.to_vec(), calldata.to_vec()),
+        _ => return StatusCode::InvalidArgument,
+    };
+    let result = catch_unwind(|| EvmSimulator::deploy(deployment_code)?.call(calldata));
+    match result {
+        Ok(Ok(result)) => {
+            if !gas_used.is_null() {
+                *gas_used = result.gas_used;
+            }
+            if result.success {
+                StatusCode::Ok
+            } else {
+                StatusCode::VerificationFailed
+            }
+        }
+        Ok(Err(e)) => StatusCode::from(&e),
+        Err(_) => StatusCode::Panic,
+    }
+}
+
+/// Releases a buffer written by this library
+///
+/// # Safety
+///
+/// `buffer` must have been written by this library and not released yet.
+#[no_mangle]
+pub unsafe extern "C" fn stark_verifier_free_buffer(buffer: ByteBuffer) {
+    if !buffer.data.is_null() {
+        drop(Vec::from_raw_parts(
+            buffer.data,
+            buffer.len,
+            buffer.capacity,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::{stark_verifier_free_buffer, stark_verifier_prove, stark_verifier_verify};
+    use super::{ByteBuffer, StatusCode};
+
+    #[test]
+    fn test_ffi_rejects_invalid_arguments() {
+        let mut out = ByteBuffer {
+            data: ptr::null_mut(),
+            len: 0,
+            capacity: 0,
+        };
+        let bytes = [1u8, 2, 3];
+        let status = unsafe {
+            stark_verifier_prove(
+                bytes.as_ptr(),
+                bytes.len(),
+                ptr::null(),
+                0,
+                bytes.as_ptr(),
+                bytes.len(),
+                ptr::null(),
+                &mut out,
+            )
+        };
+        assert_eq!(status, StatusCode::InvalidArgument);
+        assert!(out.data.is_null());
+        unsafe { stark_verifier_free_buffer(out) };
+
+        let status = unsafe {
+            stark_verifier_verify(ptr::null(), 0, bytes.as_ptr(), bytes.len(), ptr::null_mut())
+        };
+        assert_eq!(status, StatusCode::InvalidArgument);
+    }
+}
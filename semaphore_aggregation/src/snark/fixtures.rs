@@ -0,0 +1,167 @@
+//! Fixtures for hermetic integration tests: the proof of a small Plonky2 circuit, wrapped with an
+//! insecure SRS generated from a fixed seed. Run `cargo run --release --bin gen_fixtures` to
+//! write them to `tests/fixtures`:
+//! - `plonky2/proof.bin`, `plonky2/verifier_only.bin` and `plonky2/common.bin`, the proof tuple
+//!   in the byte encoding of `plonky2::util::serialization`, read back by `load_fixture`
+//! - `artifacts/`, the files of `export::export_artifacts` for the wrapped proof
+//!
+//! The files are written where an `ArtifactsLayout` rooted at the fixtures directory puts them,
+//! see `gen_fixtures_with_layout`. The committed fixtures are checked by `tests/fixtures.rs`.
+//!
+//! Neither the Plonky2 proof nor the wrapper proof are zero-knowledge, the latter being blinded
+//! with `Blinding::Seeded`, so that the fixtures are the same at every generation, as are the
+//! SRS, the keys and the EVM verifier.
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::Field;
+use plonky2::fri::reduction_strategies::FriReductionStrategy;
+use plonky2::fri::FriConfig;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::{CircuitConfig, CommonCircuitData, VerifierOnlyCircuitData};
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+use plonky2::plonk::proof::ProofWithPublicInputs;
+use plonky2::util::serialization::gate_serialization::default::DefaultGateSerializer;
+
+use super::export::layout::{Artifact, ArtifactsLayout};
+use super::export::{
+    check_artifacts_with_layout, export_artifacts_with_layout, Manifest, CONTRACT_NAME,
+};
+use super::pipeline::{Blinding, PipelineConfig};
+use super::verifier_api::{use_insecure_srs, verify_inside_snark_with_config};
+use crate::ProofTuple;
+
+type F = GoldilocksField;
+type C = PoseidonGoldilocksConfig;
+const D: usize = 2;
+
+/// Directory of the fixtures, relative to the crate root
+pub const FIXTURES_DIR: &str = "tests/fixtures";
+/// Seed of the insecure SRS the fixtures are wrapped with, and of the blinding of the wrapper
+/// proof
+pub const FIXTURE_SEED: u64 = 0;
+/// Number of public inputs of the fixture circuit
+pub const FIXTURE_PUBLIC_INPUTS: usize = 2;
+
+/// Circuit config using only 2-arity FRI reductions, which is what the verifier circuit supports
+pub fn wrapper_circuit_config() -> CircuitConfig {
+    CircuitConfig {
+        zero_knowledge: true,
+        num_wires: 135,
+        num_routed_wires: 80,
+        num_constants: 2,
+        use_base_arithmetic_gate: true,
+        security_bits: 100,
+        num_challenges: 2,
+        max_quotient_degree_factor: 8,
+        fri_config: FriConfig {
+            rate_bits: 3,
+            cap_height: 4,
+            proof_of_work_bits: 16,
+            reduction_strategy: FriReductionStrategy::ConstantArityBits(1, 5),
+            num_query_rounds: 28,
+        },
+    }
+}
+
+/// Proof of `x^1024 = y`, with `x = 2` and `y` as public inputs
+pub fn fixture_proof() -> Result<ProofTuple<F, C, D>> {
+    let config = CircuitConfig {
+        zero_knowledge: false,
+        ..wrapper_circuit_config()
+    };
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let x = builder.add_virtual_target();
+    // pads the circuit to a degree large enough for the FRI parameters of the config
+    let mut y = x;
+    for _ in 0..1 << 10 {
+        y = builder.mul(y, x);
+    }
+    builder.register_public_input(x);
+    builder.register_public_input(y);
+
+    let mut pw = PartialWitness::new();
+    pw.set_target(x, F::TWO);
+    let data = builder.build::<C>();
+    let proof = data.prove(pw)?;
+    data.verify(proof.clone())?;
+    Ok((proof, data.verifier_only, data.common))
+}
+
+/// Proves the fixture circuit, wraps its proof and writes both to `dir`, returning the manifest
+/// of the verifier. Replaces the SRS of the pipeline with the seeded one.
+pub fn gen_fixtures(dir: &Path) -> Result<Manifest> {
+    gen_fixtures_with_layout(&ArtifactsLayout::new(dir))
+}
+
+/// Same as `gen_fixtures`, writing the fixtures where `layout` puts them, the artifacts of the
+/// wrapped proof in its `artifacts` directory
+pub fn gen_fixtures_with_layout(layout: &ArtifactsLayout) -> Result<Manifest> {
+    let (proof, verifier_only, common) = fixture_proof()?;
+    let io_error = |e| anyhow!("failed to serialize the fixture: {e:?}");
+    let proof_bytes = proof.to_bytes();
+    let verifier_only_bytes = verifier_only.to_bytes().map_err(io_error)?;
+    let common_bytes = common.to_bytes(&DefaultGateSerializer).map_err(io_error)?;
+
+    use_insecure_srs(FIXTURE_SEED);
+    let config = PipelineConfig::new()
+        .allow_insecure()
+        .with_blinding(Blinding::Seeded(FIXTURE_SEED));
+    let report = verify_inside_snark_with_config((proof, verifier_only, common), &config)?;
+    // every fixture is checked before any is written, see `ArtifactsLayout::check`
+    layout.check([
+        Artifact::Plonky2Proof,
+        Artifact::Plonky2VerifierData,
+        Artifact::Plonky2CommonData,
+    ])?;
+    check_artifacts_with_layout(&layout.nested("artifacts"), &report)?;
+    layout.write(Artifact::Plonky2Proof, proof_bytes)?;
+    layout.write(Artifact::Plonky2VerifierData, verifier_only_bytes)?;
+    layout.write(Artifact::Plonky2CommonData, common_bytes)?;
+    export_artifacts_with_layout(&layout.nested("artifacts"), &report)?;
+    Ok(Manifest::new(CONTRACT_NAME, &report)?)
+}
+
+/// Proof tuple written to `dir` by `gen_fixtures`
+pub fn load_fixture(dir: &Path) -> Result<ProofTuple<F, C, D>> {
+    load_fixture_with_layout(&ArtifactsLayout::new(dir))
+}
+
+/// Proof tuple written with `layout` by `gen_fixtures_with_layout`
+pub fn load_fixture_with_layout(layout: &ArtifactsLayout) -> Result<ProofTuple<F, C, D>> {
+    let common = CommonCircuitData::from_bytes(
+        fs::read(layout.path(Artifact::Plonky2CommonData))?,
+        &DefaultGateSerializer,
+    )
+    .map_err(|_| anyhow!("malformed common data"))?;
+    let verifier_only =
+        VerifierOnlyCircuitData::from_bytes(fs::read(layout.path(Artifact::Plonky2VerifierData))?)
+            .map_err(|_| anyhow!("malformed verifier data"))?;
+    let proof =
+        ProofWithPublicInputs::from_bytes(fs::read(layout.path(Artifact::Plonky2Proof))?, &common)
+            .map_err(|_| anyhow!("malformed proof"))?;
+    Ok((proof, verifier_only, common))
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+
+    use super::{fixture_proof, FIXTURE_PUBLIC_INPUTS};
+
+    #[test]
+    fn test_fixture_proof_is_deterministic() -> Result<()> {
+        let (proof, verifier_only, _) = fixture_proof()?;
+        let (other_proof, other_verifier_only, _) = fixture_proof()?;
+        assert_eq!(proof.public_inputs.len(), FIXTURE_PUBLIC_INPUTS);
+        assert_eq!(proof.to_bytes(), other_proof.to_bytes());
+        assert_eq!(
+            verifier_only.circuit_digest,
+            other_verifier_only.circuit_digest
+        );
+        Ok(())
+    }
+}
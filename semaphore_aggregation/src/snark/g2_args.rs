@@ -0,0 +1,114 @@
+//! Passes the G2 points of the KZG verifying key to the EVM verifier as constructor arguments
+//! instead of baking them in its code, so that one verifier template can be deployed with the
+//! SRS of any ceremony. The constructor stores the points, and the verifier reads them with
+//! `sload`, which costs 8 cold storage reads (about 17k gas) per verification.
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fq2, G2Affine};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use num_bigint::BigUint;
+
+/// Number of words of the constructor arguments: `g2` and `-s_g2`, each as
+/// `(x.c1, x.c0, y.c1, y.c0)` like the inputs of the pairing precompile (EIP-197)
+pub const NUM_G2_WORDS: usize = 8;
+
+pub(crate) fn fq_to_bytes32(value: &Fq) -> [u8; 32] {
+    let mut word = value.to_repr();
+    word.reverse();
+    word
+}
+
+fn fq2_words(value: &Fq2) -> [[u8; 32]; 2] {
+    [fq_to_bytes32(&value.c1), fq_to_bytes32(&value.c0)]
+}
+
+fn g2_words(point: &G2Affine) -> impl Iterator<Item = [u8; 32]> {
+    fq2_words(&point.x).into_iter().chain(fq2_words(&point.y))
+}
+
+/// Words of `g2` and `-s_g2` of `params`, in the order of the constructor arguments
+pub fn g2_constructor_args(params: &ParamsKZG<Bn256>) -> [[u8; 32]; NUM_G2_WORDS] {
+    let minus_s_g2 = -params.s_g2();
+    let mut words = [[0u8; 32]; NUM_G2_WORDS];
+    for (word, value) in words
+        .iter_mut()
+        .zip(g2_words(&params.g2()).chain(g2_words(&minus_s_g2)))
+    {
+        *word = value;
+    }
+    words
+}
+
+/// Offsets of the occurrences of `literal` in `code` which aren't part of a longer token
+fn literal_occurrences(code: &str, literal: &str) -> Vec<usize> {
+    let is_token_char = |c: Option<char>| c.map_or(false, |c| c.is_ascii_alphanumeric());
+    code.match_indices(literal)
+        .map(|(offset, _)| offset)
+        .filter(|&offset| {
+            !is_token_char(code[..offset].chars().next_back())
+                && !is_token_char(code[offset + literal.len()..].chars().next())
+        })
+        .collect()
+}
+
+/// Rewrites the Yul source of a verifier generated with the G2 points `words` so that it reads
+/// them from storage slots `0..NUM_G2_WORDS`, filled by its constructor from the
+/// `NUM_G2_WORDS` words appended to its deployment code. Returns the index of the first word
+/// which can't be found in the source.
+pub fn parametrize_g2(yul_code: &str, words: &[[u8; 32]; NUM_G2_WORDS]) -> Result<String, usize> {
+    let mut code = yul_code.to_string();
+    for (index, word) in words.iter().enumerate() {
+        let value = BigUint::from_bytes_be(word);
+        let literals = [value.to_string(), format!("0x{}", value.to_str_radix(16))];
+        let mut found = false;
+        for literal in literals.iter() {
+            for offset in literal_occurrences(&code, literal).into_iter().rev() {
+                code.replace_range(offset..offset + literal.len(), &format!("sload({index})"));
+                found = true;
+            }
+        }
+        if !found {
+            return Err(index);
+        }
+    }
+    let constructor = code.find("code {").ok_or(NUM_G2_WORDS)? + "code {".len();
+    code.insert_str(
+        constructor,
+        &format!(
+            "
+        codecopy(0, sub(codesize(), {len}), {len})
+        for {{ let i := 0 }} lt(i, {NUM_G2_WORDS}) {{ i := add(i, 1) }} {{
+            sstore(i, mload(mul(i, 0x20)))
+        }}",
+            len = NUM_G2_WORDS * 32
+        ),
+    );
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parametrize_g2, NUM_G2_WORDS};
+
+    #[test]
+    fn test_parametrize_g2() {
+        let mut words = [[0u8; 32]; NUM_G2_WORDS];
+        for (i, word) in words.iter_mut().enumerate() {
+            word[31] = 0x10 + i as u8;
+        }
+        let constants = (0..NUM_G2_WORDS)
+            .map(|i| format!("mstore({:#x}, {})", 0x20 * i, 0x10 + i))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let yul = format!(
+            "object \"verifier\" {{ code {{ }} object \"Runtime\" {{ code {{ {constants} mstore(0, 160) }} }} }}"
+        );
+        let code = parametrize_g2(&yul, &words).unwrap();
+        assert!(code.contains("mstore(0x20, sload(1))"));
+        // 160 = 0xa0 isn't a G2 word, and 16 isn't a token of it
+        assert!(code.contains("mstore(0, 160)"));
+        assert!(code.contains("sstore(i, mload(mul(i, 0x20)))"));
+
+        words[0][0] = 1;
+        assert_eq!(parametrize_g2(&yul, &words), Err(0));
+    }
+}
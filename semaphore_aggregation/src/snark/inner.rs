@@ -0,0 +1,166 @@
+//! Proof systems whose proofs the pipeline wraps. A system turns its proofs into a halo2 circuit
+//! verifying them, with the instances the circuit exposes, and the pipeline proves the circuit
+//! and generates its EVM verifier the same way for every system. Plonky2 is the only one
+//! implemented. Another backend would implement `InnerProofSystem` and be wrapped with
+//! `Pipeline::run_inner` or `verifier_api::verify_inside_snark_with_system`.
+//!
+//! Plonky3 proofs can't be wrapped: `stark_verifier_core::plonky3` only decodes and checks the
+//! shape of its uni-stark proofs, and there is no `InnerProofSystem` for them, as verifying them
+//! in-circuit needs a Poseidon2 chip and the constraints of the AIR of the proven program.
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::Circuit;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+use super::error::VerifierError;
+use super::instances::OrderedInstances;
+use super::pipeline::PipelineConfig;
+use super::stages::Convert;
+use super::types::common_data::{fri_params, FriSoundness};
+use super::verifier_api::{build_verifier, check_inner_proof, extract_instances};
+use super::verifier_circuit::Verifier;
+use crate::ProofTuple;
+
+/// Proof system whose proofs are verified by a halo2 circuit
+pub trait InnerProofSystem {
+    /// Proof with the data needed to verify it, e.g. the verifier data of the circuit
+    type Proof;
+    /// Circuit verifying a proof
+    type Circuit: Circuit<Fr>;
+
+    /// Runs the native verifier of the system on `proof`, see
+    /// `PipelineConfig::with_inner_proof_check`
+    fn check(&self, proof: &Self::Proof) -> Result<(), VerifierError>;
+
+    /// Security of the FRI proof of `proof`, or `None` if the system doesn't estimate it, which
+    /// fails the pipeline when `PipelineConfig::min_security_bits` is set
+    fn fri_soundness(&self, _proof: &Self::Proof) -> Option<FriSoundness> {
+        None
+    }
+
+    /// Rows of `circuit` saved by deduplicating its constants, see
+    /// `Verifier::constant_rows_saved`, or `None` if the circuit doesn't deduplicate them
+    fn constant_rows_saved(
+        &self,
+        _circuit: &Self::Circuit,
+    ) -> Result<Option<usize>, VerifierError> {
+        Ok(None)
+    }
+
+    /// Instances the circuit of `proof` exposes with the options of `config`, in the order of
+    /// the instance column, without building the circuit
+    fn instances(
+        &self,
+        proof: &Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<OrderedInstances, VerifierError>;
+
+    /// Circuit verifying `proof` with the options of `config`, with its instances and the `k`
+    /// to start from
+    fn circuit(
+        &self,
+        proof: Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<(Self::Circuit, OrderedInstances, u32), VerifierError>;
+}
+
+/// Plonky2 proofs over Goldilocks with Poseidon, verified by `Verifier`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Plonky2;
+
+impl InnerProofSystem for Plonky2 {
+    type Proof = ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>;
+    type Circuit = Verifier;
+
+    fn check(&self, proof: &Self::Proof) -> Result<(), VerifierError> {
+        check_inner_proof(proof)
+    }
+
+    fn fri_soundness(&self, proof: &Self::Proof) -> Option<FriSoundness> {
+        Some(fri_params(&proof.2).soundness())
+    }
+
+    fn constant_rows_saved(&self, circuit: &Verifier) -> Result<Option<usize>, VerifierError> {
+        circuit
+            .constant_rows_saved()
+            .map(Some)
+            .map_err(VerifierError::Synthesis)
+    }
+
+    fn instances(
+        &self,
+        proof: &Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<OrderedInstances, VerifierError> {
+        extract_instances(proof, config)
+    }
+
+    fn circuit(
+        &self,
+        proof: Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<(Verifier, OrderedInstances, u32), VerifierError> {
+        let (circuit, k) = build_verifier(proof, config)?;
+        let instances = circuit.ordered_instances();
+        Ok((circuit, instances, k))
+    }
+}
+
+/// Plonky2 converted with the `Convert` stage of a pipeline
+pub(crate) struct ConvertStage<'a, Cv>(pub(crate) &'a Cv);
+
+impl<Cv: Convert> InnerProofSystem for ConvertStage<'_, Cv> {
+    type Proof = ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>;
+    type Circuit = Verifier;
+
+    fn check(&self, proof: &Self::Proof) -> Result<(), VerifierError> {
+        Plonky2.check(proof)
+    }
+
+    fn fri_soundness(&self, proof: &Self::Proof) -> Option<FriSoundness> {
+        Plonky2.fri_soundness(proof)
+    }
+
+    fn constant_rows_saved(&self, circuit: &Verifier) -> Result<Option<usize>, VerifierError> {
+        Plonky2.constant_rows_saved(circuit)
+    }
+
+    fn instances(
+        &self,
+        proof: &Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<OrderedInstances, VerifierError> {
+        Plonky2.instances(proof, config)
+    }
+
+    fn circuit(
+        &self,
+        proof: Self::Proof,
+        config: &PipelineConfig,
+    ) -> Result<(Verifier, OrderedInstances, u32), VerifierError> {
+        let (circuit, k) = self.0.convert(proof, config)?;
+        let instances = circuit.ordered_instances();
+        Ok((circuit, instances, k))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{InnerProofSystem, Plonky2};
+    use crate::snark::pipeline::PipelineConfig;
+    use crate::snark::test_utils;
+
+    #[test]
+    fn test_plonky2_instances() -> Result<()> {
+        let config = PipelineConfig::new().with_nonce(Fr::from(7));
+        let proof = test_utils::public_inputs_proof(2)?;
+        Plonky2.check(&proof)?;
+        let instances = Plonky2.instances(&proof, &config)?;
+        let (_, circuit_instances, _) = Plonky2.circuit(proof, &config)?;
+        assert_eq!(instances, circuit_instances);
+        Ok(())
+    }
+}
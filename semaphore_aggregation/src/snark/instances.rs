@@ -0,0 +1,462 @@
+use std::fmt;
+use std::ops::Range;
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::halo2curves::bn256::Fr;
+#[cfg(feature = "prover")]
+use halo2wrong_maingate::fe_to_big;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::PrimeField64;
+use snark_verifier::loader::evm::encode_calldata;
+
+use super::calldata::CalldataLayout;
+#[cfg(feature = "prover")]
+use super::disclosure::{SelectiveDisclosure, COMMITMENT_LEN};
+use super::encoding::{fr_to_bytes32, instances_to_bytes32};
+#[cfg(feature = "prover")]
+use super::types::error::{ConversionError, ProofComponent};
+#[cfg(feature = "prover")]
+use super::types::{try_big_to_fe, try_to_goldilocks};
+
+/// Number of instances holding the commitment to the verification key, see `vk_registry`
+pub const VK_COMMITMENT_LEN: usize = 4;
+/// Row of the instance column the commitment starts at, whatever the rest of the layout
+pub const VK_COMMITMENT_OFFSET: usize = 0;
+
+/// Instances of the verifier circuit, in the order of its instance column: the Plonky2 public
+/// inputs in their original order, followed by the nonce if any. It can only be built by the
+/// conversion layer, so that the instances can't be permuted or truncated between proving and
+/// calldata encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OrderedInstances(Vec<Fr>);
+
+impl OrderedInstances {
+    pub(crate) fn new(instances: Vec<Fr>) -> Self {
+        Self(instances)
+    }
+
+    /// Converts Plonky2 public inputs into instances, keeping their order. The public inputs
+    /// must be canonical, see `try_from_public_inputs`.
+    pub(crate) fn from_public_inputs(public_inputs: &[GoldilocksField]) -> Self {
+        Self(
+            public_inputs
+                .iter()
+                .map(|e| Fr::from(e.to_canonical_u64()))
+                .collect(),
+        )
+    }
+
+    /// `from_public_inputs`, failing if a public input isn't canonical, as in proofs from
+    /// untrusted submitters
+    #[cfg(feature = "prover")]
+    pub fn try_from_public_inputs(
+        public_inputs: &[GoldilocksField],
+    ) -> Result<Self, ConversionError> {
+        public_inputs
+            .iter()
+            .map(|e| try_to_goldilocks(*e).and_then(|e| try_big_to_fe(&fe_to_big(e))))
+            .collect::<Option<_>>()
+            .map(Self)
+            .ok_or(ConversionError::NonCanonical(ProofComponent::PublicInputs))
+    }
+
+    pub fn as_slice(&self) -> &[Fr] {
+        &self.0
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Instances of every instance column, as expected by the halo2 prover and verifier
+    pub fn columns(&self) -> Vec<Vec<Fr>> {
+        vec![self.0.clone()]
+    }
+
+    /// Number of instances of every instance column. The verifier circuit has a single
+    /// instance column, kept even when there are no instances, so that the generated verifier
+    /// always expects exactly one, possibly empty, instance vector.
+    pub fn num_instance(&self) -> Vec<usize> {
+        vec![self.0.len()]
+    }
+
+    /// Instances as the `bytes32[]` expected by the verifier contract
+    pub fn to_bytes32(&self) -> Vec<[u8; 32]> {
+        instances_to_bytes32(&self.0)
+    }
+
+    /// Calldata of the EVM verifier for `proof`
+    pub fn encode_calldata(&self, proof: &[u8]) -> Vec<u8> {
+        encode_calldata(&self.columns(), proof)
+    }
+
+    /// Same as `encode_calldata`, for an EVM verifier reading the calldata in `layout`
+    pub fn encode_calldata_with_layout(&self, proof: &[u8], layout: CalldataLayout) -> Vec<u8> {
+        match layout {
+            CalldataLayout::InstancesFirst => self.encode_calldata(proof),
+            CalldataLayout::ProofFirst => layout.encode(&self.to_bytes32(), proof),
+        }
+    }
+}
+
+/// Layout of the instance column as seen by the verifier contract: named protocol metadata words
+/// (version, chain id, vk hash, ...) first, then the payload made of the Plonky2 public inputs,
+/// then the nonce if any. A commitment to the verification key, if exposed, precedes the
+/// metadata, see `VK_COMMITMENT_OFFSET`. The same layout is given to the circuit and used to
+/// build the instances for calldata, so that it is declared once. The metadata words are
+/// constants of the circuit, pinned by its verifying key, so a verifier contract only accepts
+/// proofs exposing the metadata it was generated with.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceLayout<F: FieldExt = Fr> {
+    metadata: Vec<(String, F)>,
+    vk_commitment: bool,
+}
+
+impl<F: FieldExt> Default for InstanceLayout<F> {
+    fn default() -> Self {
+        Self {
+            metadata: vec![],
+            vk_commitment: false,
+        }
+    }
+}
+
+impl<F: FieldExt> InstanceLayout<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends a metadata word named `name` to the prefix
+    pub fn with_metadata(mut self, name: impl Into<String>, value: F) -> Self {
+        self.metadata.push((name.into(), value));
+        self
+    }
+
+    pub fn with_version(self, version: u64) -> Self {
+        self.with_metadata("version", F::from(version))
+    }
+
+    pub fn with_chain_id(self, chain_id: u64) -> Self {
+        self.with_metadata("chain_id", F::from(chain_id))
+    }
+
+    /// Appends a 32-byte hash, read as a big-endian integer reduced modulo the scalar field.
+    /// Such a word isn't a Goldilocks element, so envelopes holding it can't be decoded with
+    /// `WrappedProofEnvelope::instances`.
+    pub fn with_vk_hash(self, vk_hash: &[u8; 32]) -> Self {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(vk_hash);
+        bytes[..32].reverse();
+        self.with_metadata("vk_hash", F::from_bytes_wide(&bytes))
+    }
+
+    /// Whether the commitment to the verification key precedes the metadata. Set by
+    /// `PipelineConfig::with_vk_commitment` and `Verifier::with_vk_commitment`.
+    pub fn with_vk_commitment(mut self, vk_commitment: bool) -> Self {
+        self.vk_commitment = vk_commitment;
+        self
+    }
+
+    pub fn has_vk_commitment(&self) -> bool {
+        self.vk_commitment
+    }
+
+    pub fn metadata(&self) -> &[(String, F)] {
+        &self.metadata
+    }
+
+    pub fn metadata_values(&self) -> Vec<F> {
+        self.metadata.iter().map(|(_, value)| *value).collect()
+    }
+
+    /// Number of instances preceding the metadata
+    fn metadata_offset(&self) -> usize {
+        if self.vk_commitment {
+            VK_COMMITMENT_LEN
+        } else {
+            0
+        }
+    }
+
+    /// Row of the metadata word `name` in the instance column
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.metadata
+            .iter()
+            .position(|(n, _)| n == name)
+            .map(|i| self.metadata_offset() + i)
+    }
+
+    /// Row of the first payload instance in the instance column
+    pub fn payload_offset(&self) -> usize {
+        self.metadata_offset() + self.metadata.len()
+    }
+}
+
+impl InstanceLayout<Fr> {
+    /// Lays out `vk_commitment`, the metadata, `public_inputs` and `nonce` in the instance
+    /// column, `vk_commitment` being the commitment to the verification key if the layout
+    /// exposes one
+    pub fn instances(
+        &self,
+        vk_commitment: Option<[Fr; VK_COMMITMENT_LEN]>,
+        public_inputs: &[GoldilocksField],
+        nonce: Option<Fr>,
+    ) -> OrderedInstances {
+        let payload = OrderedInstances::from_public_inputs(public_inputs);
+        OrderedInstances(instance_column(self, vk_commitment, payload.0, nonce, None))
+    }
+
+    /// Instances following the metadata prefix, or `None` if the metadata of `instances`
+    /// doesn't match the one of this layout
+    pub fn payload<'a>(&self, instances: &'a OrderedInstances) -> Option<&'a [Fr]> {
+        let (start, end) = (self.metadata_offset(), self.payload_offset());
+        if instances.len() < end || instances.0[start..end] != self.metadata_values()[..] {
+            return None;
+        }
+        Some(&instances.0[end..])
+    }
+}
+
+/// Values of the instance column of the verifier circuit: the commitment to the verification key
+/// if any, the metadata of `layout`, the exposed Plonky2 public inputs and the nonce if any,
+/// followed by zeros up to `padded_len` if any
+pub(crate) fn instance_column<F: FieldExt>(
+    layout: &InstanceLayout<F>,
+    vk_commitment: Option<[F; VK_COMMITMENT_LEN]>,
+    exposed_public_inputs: Vec<F>,
+    nonce: Option<F>,
+    padded_len: Option<usize>,
+) -> Vec<F> {
+    let mut instances: Vec<F> = vk_commitment
+        .into_iter()
+        .flatten()
+        .chain(layout.metadata_values())
+        .chain(exposed_public_inputs)
+        .chain(nonce)
+        .collect();
+    if let Some(padded_len) = padded_len {
+        instances.resize(padded_len.max(instances.len()), F::zero());
+    }
+    instances
+}
+
+/// What an instance of the verifier circuit holds
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstanceSource {
+    /// Metadata word of the `InstanceLayout`
+    Metadata(String),
+    /// Word of the commitment to the Plonky2 verification key
+    VkCommitment(usize),
+    /// Plonky2 public input of this index
+    PublicInput(usize),
+    /// Word of the commitment to the public inputs hidden by the `SelectiveDisclosure`
+    DisclosureCommitment(usize),
+    Nonce,
+    /// Zero padding the instances to a fixed length, see `PipelineConfig::with_instance_padding`
+    Padding,
+}
+
+impl fmt::Display for InstanceSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceSource::Metadata(name) => write!(f, "metadata {name:?}"),
+            InstanceSource::VkCommitment(word) => write!(f, "vk commitment word {word}"),
+            InstanceSource::PublicInput(index) => write!(f, "Plonky2 public input {index}"),
+            InstanceSource::DisclosureCommitment(word) => {
+                write!(f, "disclosure commitment word {word}")
+            }
+            InstanceSource::Nonce => write!(f, "nonce"),
+            InstanceSource::Padding => write!(f, "padding"),
+        }
+    }
+}
+
+/// Sources of the instances of `instance_column`, in the same order
+#[cfg(feature = "prover")]
+pub(crate) fn instance_sources<F: FieldExt>(
+    layout: &InstanceLayout<F>,
+    vk_commitment: bool,
+    disclosure: Option<&SelectiveDisclosure>,
+    num_public_inputs: usize,
+    nonce: bool,
+    padded_len: Option<usize>,
+) -> Vec<InstanceSource> {
+    let metadata = layout
+        .metadata()
+        .iter()
+        .map(|(name, _)| InstanceSource::Metadata(name.clone()));
+    let vk_commitment = (0..VK_COMMITMENT_LEN)
+        .filter(|_| vk_commitment)
+        .map(InstanceSource::VkCommitment);
+    let public_inputs: Vec<_> = match disclosure {
+        Some(disclosure) => disclosure
+            .disclosed()
+            .iter()
+            .copied()
+            .map(InstanceSource::PublicInput)
+            .chain((0..COMMITMENT_LEN).map(InstanceSource::DisclosureCommitment))
+            .collect(),
+        None => (0..num_public_inputs)
+            .map(InstanceSource::PublicInput)
+            .collect(),
+    };
+    let mut sources: Vec<_> = vk_commitment
+        .chain(metadata)
+        .chain(public_inputs)
+        .chain(nonce.then_some(InstanceSource::Nonce))
+        .collect();
+    if let Some(padded_len) = padded_len {
+        sources.resize(padded_len.max(sources.len()), InstanceSource::Padding);
+    }
+    sources
+}
+
+/// Everything needed to follow an instance from the Plonky2 proof to the calldata of the EVM
+/// verifier, see `verifier_api::explain_instance`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstanceExplanation {
+    /// Row of the instance in the instance column
+    pub index: usize,
+    pub source: InstanceSource,
+    /// Value of the Plonky2 public input, if the instance is one
+    pub goldilocks: Option<u64>,
+    pub value: Fr,
+    /// Big-endian word of the instance in the calldata
+    pub bytes32: [u8; 32],
+    /// Bytes of the calldata holding the instance, see `CalldataLayout::instance_range`
+    pub calldata_range: Range<usize>,
+}
+
+impl InstanceExplanation {
+    pub(crate) fn new(
+        index: usize,
+        source: InstanceSource,
+        goldilocks: Option<u64>,
+        value: Fr,
+        calldata_layout: CalldataLayout,
+        proof_len: usize,
+    ) -> Self {
+        Self {
+            index,
+            source,
+            goldilocks,
+            value,
+            bytes32: fr_to_bytes32(&value),
+            calldata_range: calldata_layout.instance_range(index, proof_len),
+        }
+    }
+}
+
+impl fmt::Display for InstanceExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "instance {}: {}", self.index, self.source)?;
+        if let Some(goldilocks) = self.goldilocks {
+            writeln!(f, "  goldilocks: {goldilocks} (0x{goldilocks:016x})")?;
+        }
+        writeln!(f, "  fr: {:?}", self.value)?;
+        writeln!(f, "  bytes32: 0x{}", hex::encode(self.bytes32))?;
+        write!(
+            f,
+            "  calldata: bytes {}..{}",
+            self.calldata_range.start, self.calldata_range.end
+        )
+    }
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+
+    use super::{
+        instance_sources, InstanceLayout, InstanceSource, OrderedInstances, VK_COMMITMENT_LEN,
+        VK_COMMITMENT_OFFSET,
+    };
+    use crate::snark::disclosure::SelectiveDisclosure;
+    use crate::snark::types::error::{ConversionError, ProofComponent};
+
+    #[test]
+    fn test_instance_layout_prefixes_metadata() {
+        let layout = InstanceLayout::new().with_version(1).with_chain_id(10);
+        let public_inputs = [GoldilocksField::from_canonical_u64(7)];
+        let instances = layout.instances(None, &public_inputs, Some(Fr::from(42)));
+        assert_eq!(
+            instances.as_slice(),
+            &[Fr::from(1), Fr::from(10), Fr::from(7), Fr::from(42)]
+        );
+        assert_eq!(layout.position("chain_id"), Some(1));
+        assert_eq!(layout.payload_offset(), 2);
+        assert_eq!(
+            layout.payload(&instances),
+            Some(&[Fr::from(7), Fr::from(42)][..])
+        );
+
+        let other = InstanceLayout::new().with_version(2).with_chain_id(10);
+        assert_eq!(other.payload(&instances), None);
+    }
+
+    #[test]
+    fn test_instance_layout_with_vk_commitment() {
+        let layout = InstanceLayout::new()
+            .with_version(1)
+            .with_chain_id(10)
+            .with_vk_commitment(true);
+        let commitment = [Fr::from(100), Fr::from(101), Fr::from(102), Fr::from(103)];
+        let public_inputs = [GoldilocksField::from_canonical_u64(7)];
+        let instances = layout.instances(Some(commitment), &public_inputs, None);
+        assert_eq!(
+            &instances.as_slice()[VK_COMMITMENT_OFFSET..VK_COMMITMENT_LEN],
+            &commitment
+        );
+        assert_eq!(layout.position("version"), Some(VK_COMMITMENT_LEN));
+        assert_eq!(layout.position("chain_id"), Some(VK_COMMITMENT_LEN + 1));
+        assert_eq!(layout.payload_offset(), VK_COMMITMENT_LEN + 2);
+        assert_eq!(
+            instances.as_slice()[layout.position("chain_id").unwrap()],
+            Fr::from(10)
+        );
+        assert_eq!(layout.payload(&instances), Some(&[Fr::from(7)][..]));
+
+        // the commitment isn't mistaken for the metadata
+        let without_commitment = layout.clone().with_vk_commitment(false);
+        assert_eq!(without_commitment.payload(&instances), None);
+    }
+
+    #[test]
+    fn test_instance_sources() {
+        let layout = InstanceLayout::<Fr>::new().with_version(1);
+        let disclosure = SelectiveDisclosure::new(vec![2], [GoldilocksField::ZERO; 4]);
+        let sources = instance_sources(&layout, false, Some(&disclosure), 3, true, None);
+        assert_eq!(sources.len(), 1 + 1 + 4 + 1);
+        assert_eq!(sources[0], InstanceSource::Metadata("version".into()));
+        assert_eq!(sources[1], InstanceSource::PublicInput(2));
+        assert_eq!(sources[2], InstanceSource::DisclosureCommitment(0));
+        assert_eq!(sources[6], InstanceSource::Nonce);
+
+        let sources = instance_sources(&layout, true, None, 2, false, None);
+        assert_eq!(sources[0], InstanceSource::VkCommitment(0));
+        assert_eq!(sources[4], InstanceSource::Metadata("version".into()));
+        assert_eq!(sources[5..], [0, 1].map(InstanceSource::PublicInput));
+
+        let sources = instance_sources(&layout, false, None, 2, false, Some(5));
+        assert_eq!(
+            sources[3..],
+            [InstanceSource::Padding, InstanceSource::Padding]
+        );
+    }
+
+    #[test]
+    fn test_non_canonical_public_inputs() {
+        let public_inputs = [GoldilocksField::ONE, GoldilocksField(u64::MAX)];
+        assert_eq!(
+            OrderedInstances::try_from_public_inputs(&public_inputs),
+            Err(ConversionError::NonCanonical(ProofComponent::PublicInputs))
+        );
+        assert!(OrderedInstances::try_from_public_inputs(&public_inputs[..1]).is_ok());
+    }
+}
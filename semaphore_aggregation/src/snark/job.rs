@@ -0,0 +1,172 @@
+//! Handles of wrapping jobs run in the background, which a job scheduler can cancel or give a
+//! deadline. Proving phases can't be interrupted, so a job stops at the end of the phase it is
+//! running when cancelled or when its deadline passes.
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::error::VerifierError;
+use super::report::VerificationReport;
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: AtomicBool,
+    deadline: Mutex<Option<Instant>>,
+    temp_files: Mutex<Vec<PathBuf>>,
+}
+
+/// Shared flag checked by the pipeline between its phases. Clones of a token share its state.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    state: Arc<TokenState>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Aborts the job at the first phase boundary after `timeout` from now
+    pub fn set_timeout(&self, timeout: Duration) {
+        *self.state.deadline.lock().unwrap() = Some(Instant::now() + timeout);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn is_timed_out(&self) -> bool {
+        self.state
+            .deadline
+            .lock()
+            .unwrap()
+            .map_or(false, |deadline| Instant::now() >= deadline)
+    }
+
+    /// Registers a file written by the job, removed if the job is aborted
+    pub fn track_temp_file(&self, path: impl Into<PathBuf>) {
+        self.state.temp_files.lock().unwrap().push(path.into());
+    }
+
+    /// Fails if the job was cancelled or timed out before `phase`
+    pub(crate) fn checkpoint(&self, phase: &'static str) -> Result<(), VerifierError> {
+        if self.is_cancelled() {
+            Err(VerifierError::Cancelled { phase })
+        } else if self.is_timed_out() {
+            Err(VerifierError::TimedOut { phase })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Removes the files registered with `track_temp_file`, ignoring the ones already removed
+    fn remove_temp_files(&self) {
+        for path in self.state.temp_files.lock().unwrap().drain(..) {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Wrapping job running in a background thread, see `verifier_api::spawn_verify_inside_snark`
+pub struct JobHandle {
+    token: CancellationToken,
+    thread: JoinHandle<Result<VerificationReport, VerifierError>>,
+}
+
+impl JobHandle {
+    /// Runs `job` in a background thread with a fresh token. Files registered with the token
+    /// are removed if the job is aborted.
+    pub(crate) fn spawn(
+        job: impl FnOnce(&CancellationToken) -> Result<VerificationReport, VerifierError>
+            + Send
+            + 'static,
+    ) -> Self {
+        let token = CancellationToken::new();
+        let thread = {
+            let token = token.clone();
+            std::thread::spawn(move || {
+                let result = job(&token);
+                if matches!(
+                    result,
+                    Err(VerifierError::Cancelled { .. } | VerifierError::TimedOut { .. })
+                ) {
+                    token.remove_temp_files();
+                }
+                result
+            })
+        };
+        Self { token, thread }
+    }
+
+    /// Aborts the job at the end of its current phase
+    pub fn cancel(&self) {
+        self.token.cancel();
+    }
+
+    /// Aborts the job at the end of the phase running `timeout` from now
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.token.set_timeout(timeout);
+        self
+    }
+
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the job to finish. Panics if the job panicked.
+    pub fn join(self) -> Result<VerificationReport, VerifierError> {
+        self.thread.join().expect("wrapping job panicked")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{CancellationToken, JobHandle};
+    use crate::snark::error::VerifierError;
+
+    #[test]
+    fn test_cancellation_token_checkpoint() {
+        let token = CancellationToken::new();
+        assert!(token.checkpoint("keygen").is_ok());
+        token.set_timeout(Duration::ZERO);
+        assert!(matches!(
+            token.checkpoint("keygen"),
+            Err(VerifierError::TimedOut { phase: "keygen" })
+        ));
+        token.clone().cancel();
+        assert!(matches!(
+            token.checkpoint("prove"),
+            Err(VerifierError::Cancelled { phase: "prove" })
+        ));
+    }
+
+    #[test]
+    fn test_aborted_job_removes_temp_files() {
+        let path = std::env::temp_dir().join("stark-verifier-job-test.tmp");
+        let job_path = path.clone();
+        let job = JobHandle::spawn(move |token| {
+            std::fs::write(&job_path, b"partial").unwrap();
+            token.track_temp_file(&job_path);
+            token.cancel();
+            token.checkpoint("keygen")?;
+            unreachable!()
+        });
+        assert!(matches!(
+            job.join(),
+            Err(VerifierError::Cancelled { phase: "keygen" })
+        ));
+        assert!(!path.exists());
+    }
+}
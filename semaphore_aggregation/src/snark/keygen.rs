@@ -0,0 +1,188 @@
+//! Precomputation shared by the keygens of a process.
+//!
+//! The only precomputation derived from the SRS alone is the Lagrange basis of the parameters
+//! of every degree, computed with an FFT over the points of the SRS when the parameters are
+//! downsized. It is held by the `SrsManager` of the pipeline, so the keygens of all the
+//! variants of a family sharing a `k`, whatever their batch size, downsize once, see
+//! `SrsManager::downsizes` and `verifier_api::gen_batch_artifacts`. The rest of a keygen isn't
+//! shared, so setup time still grows with the number of variants.
+//!
+//! The FFTs of the fixed and permutation polynomials over the extended domain, done at every
+//! `keygen_pk`, depend on the circuit and can't be shared: the pinned halo2 doesn't take a
+//! precomputed domain. Circuits with the same fixed columns and permutation, e.g. the verifier
+//! circuits of proofs of the same Plonky2 circuit, can instead reuse a proving key, keyed by
+//! the verifying key which `keygen_vk` computes without the extended domain. As a proving key
+//! takes a few GB, this cache is opt-in, see `verifier_api::set_keygen_cache_capacity`.
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use halo2_proofs::halo2curves::bn256::G1Affine;
+use halo2_proofs::plonk::{ProvingKey, VerifyingKey};
+
+use super::envelope::vk_hash;
+
+/// Number of proving keys kept by default, none as a proving key of the verifier circuit takes
+/// a few GB at the usual degrees
+pub const DEFAULT_CAPACITY: usize = 0;
+
+struct Entry {
+    pk: Arc<ProvingKey<G1Affine>>,
+    last_used: u64,
+}
+
+/// Proving keys by degree and verifying key hash, evicting the least recently used ones beyond
+/// its capacity
+pub struct KeygenCache {
+    entries: BTreeMap<(u32, [u8; 32]), Entry>,
+    capacity: usize,
+    clock: u64,
+    hits: usize,
+    misses: usize,
+}
+
+impl Default for KeygenCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl KeygenCache {
+    /// Cache of at most `capacity` proving keys, which caches nothing if `capacity` is zero
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            capacity,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of `get` calls which found a proving key
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// Number of `get` calls which didn't
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    /// Proving key of degree `k` whose verifying key is `vk`, if cached
+    pub fn get(
+        &mut self,
+        k: u32,
+        vk: &VerifyingKey<G1Affine>,
+    ) -> Option<Arc<ProvingKey<G1Affine>>> {
+        self.clock += 1;
+        match self.entries.get_mut(&(k, vk_hash(vk))) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                self.hits += 1;
+                Some(entry.pk.clone())
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Caches `pk` of degree `k`
+    pub fn insert(&mut self, k: u32, pk: Arc<ProvingKey<G1Affine>>) {
+        self.clock += 1;
+        let entry = Entry {
+            last_used: self.clock,
+            pk,
+        };
+        self.entries.insert((k, vk_hash(entry.pk.get_vk())), entry);
+        self.evict();
+    }
+
+    /// Evicts least recently used proving keys until the cache fits its capacity
+    fn evict(&mut self) {
+        while self.entries.len() > self.capacity {
+            let lru = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            if let Some(key) = lru {
+                self.entries.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::KeygenCache;
+    use crate::snark::srs::SrsManager;
+    use crate::snark::test_utils;
+
+    #[test]
+    fn test_keygen_cache() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let vk = keygen_vk(&params, &test_utils::FixedCircuit).unwrap();
+        let pk = keygen_pk(&params, vk.clone(), &test_utils::FixedCircuit).unwrap();
+        let mut disabled = KeygenCache::default();
+        disabled.insert(8, Arc::new(pk));
+        assert!(disabled.is_empty());
+        assert!(disabled.get(8, &vk).is_none());
+
+        let mut cache = KeygenCache::new(1);
+        for k in [8, 9] {
+            let params = ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(0));
+            let vk = keygen_vk(&params, &test_utils::FixedCircuit).unwrap();
+            assert!(cache.get(k, &vk).is_none());
+            let pk = Arc::new(keygen_pk(&params, vk.clone(), &test_utils::FixedCircuit).unwrap());
+            cache.insert(k, pk.clone());
+            assert!(Arc::ptr_eq(&cache.get(k, &vk).unwrap(), &pk));
+        }
+        // the key of degree 8 was evicted by the one of degree 9
+        assert_eq!(cache.len(), 1);
+        assert_eq!((cache.hits(), cache.misses()), (2, 2));
+
+        cache.set_capacity(0);
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_srs_precomputation_is_shared() {
+        let mut srs = SrsManager::default();
+        srs.insert(ParamsKZG::<Bn256>::setup(9, StdRng::seed_from_u64(0)));
+        // the Lagrange basis of degree 8 is computed by the first keygen only
+        for _ in 0..3 {
+            let params = srs.get(8).unwrap();
+            keygen_vk(params.as_ref(), &test_utils::FixedCircuit).unwrap();
+        }
+        assert_eq!(srs.downsizes(), 1);
+        srs.get(9).unwrap();
+        assert_eq!(srs.downsizes(), 1);
+    }
+}
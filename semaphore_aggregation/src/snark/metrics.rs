@@ -0,0 +1,97 @@
+//! Pipeline metrics exported in the Prometheus format. Recording is a no-op unless the
+//! `metrics` feature is enabled.
+use std::time::Duration;
+
+#[cfg(feature = "metrics")]
+use lazy_static::lazy_static;
+#[cfg(feature = "metrics")]
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, Registry, TextEncoder,
+};
+
+#[cfg(feature = "metrics")]
+lazy_static! {
+    /// Registry holding every metric recorded by the wrapping pipeline
+    pub static ref REGISTRY: Registry = Registry::new();
+    static ref PHASE_DURATION: HistogramVec = register(
+        HistogramVec::new(
+            HistogramOpts::new(
+                "stark_verifier_phase_duration_seconds",
+                "Duration of each phase of the wrapping pipeline"
+            )
+            .buckets(vec![0.1, 1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0]),
+            &["phase"]
+        )
+        .unwrap()
+    );
+    static ref PROOF_SIZE: Histogram = register(
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "stark_verifier_proof_size_bytes",
+                "Size of the generated SNARK proof"
+            )
+            .buckets(prometheus::exponential_buckets(1024.0, 2.0, 8).unwrap())
+        )
+        .unwrap()
+    );
+    static ref GAS_USED: Histogram = register(
+        Histogram::with_opts(
+            HistogramOpts::new(
+                "stark_verifier_evm_gas_used",
+                "Gas used by the EVM verifier"
+            )
+            .buckets(prometheus::linear_buckets(200_000.0, 100_000.0, 10).unwrap())
+        )
+        .unwrap()
+    );
+    static ref PROOFS_WRAPPED: IntCounter = register(
+        IntCounter::new(
+            "stark_verifier_proofs_wrapped_total",
+            "Number of Plonky2 proofs wrapped into SNARK proofs"
+        )
+        .unwrap()
+    );
+}
+
+#[cfg(feature = "metrics")]
+fn register<M: prometheus::core::Collector + Clone + 'static>(metric: M) -> M {
+    REGISTRY.register(Box::new(metric.clone())).unwrap();
+    metric
+}
+
+#[cfg(feature = "metrics")]
+pub fn observe_phase(phase: &str, elapsed: Duration) {
+    PHASE_DURATION
+        .with_label_values(&[phase])
+        .observe(elapsed.as_secs_f64());
+}
+
+#[cfg(feature = "metrics")]
+pub fn observe_proof_size(size: usize) {
+    PROOF_SIZE.observe(size as f64);
+    PROOFS_WRAPPED.inc();
+}
+
+#[cfg(feature = "metrics")]
+pub fn observe_gas(gas: u64) {
+    GAS_USED.observe(gas as f64);
+}
+
+/// Encodes all recorded metrics in the Prometheus text exposition format
+#[cfg(feature = "metrics")]
+pub fn gather() -> String {
+    let mut buf = Vec::new();
+    TextEncoder::new()
+        .encode(&REGISTRY.gather(), &mut buf)
+        .unwrap();
+    String::from_utf8(buf).unwrap()
+}
+
+#[cfg(not(feature = "metrics"))]
+pub fn observe_phase(_phase: &str, _elapsed: Duration) {}
+
+#[cfg(not(feature = "metrics"))]
+pub fn observe_proof_size(_size: usize) {}
+
+#[cfg(not(feature = "metrics"))]
+pub fn observe_gas(_gas: u64) {}
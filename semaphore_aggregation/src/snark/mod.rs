@@ -1,17 +1,100 @@
+#[cfg(feature = "prover")]
 use plonky2::hash::{
     hashing::SPONGE_WIDTH,
     poseidon::{HALF_N_FULL_ROUNDS, N_PARTIAL_ROUNDS},
 };
+#[cfg(feature = "prover")]
 const T: usize = SPONGE_WIDTH;
+#[cfg(feature = "prover")]
 const T_MINUS_ONE: usize = SPONGE_WIDTH - 1;
+#[cfg(feature = "prover")]
 const RATE: usize = SPONGE_WIDTH - 4;
 
+#[cfg(feature = "prover")]
 const R_F: usize = HALF_N_FULL_ROUNDS * 2;
+#[cfg(feature = "prover")]
 const R_F_HALF: usize = R_F / 2;
+#[cfg(feature = "prover")]
 const R_P: usize = N_PARTIAL_ROUNDS;
 
+#[cfg(feature = "prover")]
+pub mod backend;
+#[cfg(feature = "prover")]
+pub mod batch_verifier_circuit;
+pub mod calldata;
+#[cfg(all(feature = "prover", feature = "debug"))]
+pub mod challenge_dump;
+#[cfg(feature = "prover")]
 pub mod chip;
+#[cfg(feature = "prover")]
+pub mod compression;
+#[cfg(feature = "prover")]
+pub mod cost;
+#[cfg(feature = "prover")]
+pub mod disclosure;
+#[cfg(feature = "prover")]
+pub mod eip712;
+pub mod encoding;
+#[cfg(feature = "prover")]
+pub mod entropy;
+#[cfg(feature = "prover")]
+pub mod envelope;
+#[cfg(feature = "prover")]
+pub mod error;
+#[cfg(feature = "prover")]
+pub mod events;
+#[cfg(feature = "prover")]
+pub mod export;
+#[cfg(all(feature = "prover", feature = "cffi"))]
+pub mod ffi;
+#[cfg(feature = "prover")]
+pub mod fixtures;
+#[cfg(feature = "prover")]
+pub mod g2_args;
+#[cfg(feature = "prover")]
+pub mod inner;
+pub mod instances;
+#[cfg(feature = "prover")]
+pub mod job;
+#[cfg(feature = "prover")]
+pub mod keygen;
+#[cfg(feature = "prover")]
+pub mod metrics;
+#[cfg(all(feature = "prover", feature = "node"))]
+pub mod node;
+#[cfg(feature = "prover")]
+pub mod occupancy;
+#[cfg(feature = "prover")]
+pub mod pipeline;
+pub mod proof_encoding;
+#[cfg(feature = "prover")]
+pub mod protocol;
+#[cfg(feature = "prover")]
+pub mod report;
+#[cfg(feature = "prover")]
+pub mod simulator;
+#[cfg(feature = "prover")]
+pub mod soak;
+#[cfg(feature = "prover")]
+pub mod solc;
+#[cfg(feature = "prover")]
+pub mod srs;
+#[cfg(all(feature = "prover", feature = "srs-download"))]
+pub mod srs_download;
+#[cfg(feature = "prover")]
+pub mod stages;
+#[cfg(all(test, feature = "prover"))]
+pub(crate) mod test_utils;
+#[cfg(feature = "prover")]
 pub mod types;
+#[cfg(feature = "prover")]
 pub mod utils;
+pub mod verifier;
+#[cfg(feature = "prover")]
 pub mod verifier_api;
+#[cfg(feature = "prover")]
 pub mod verifier_circuit;
+#[cfg(feature = "prover")]
+pub mod vk_keccak;
+#[cfg(feature = "prover")]
+pub mod vk_registry;
@@ -0,0 +1,137 @@
+//! Node.js bindings, so that relayers written in TypeScript use the encoding of the verifier
+//! instead of re-implementing it. Build the addon with
+//! `cargo rustc --release --features node --crate-type cdylib` and rename the library to
+//! `stark_verifier.node`.
+
+use napi::bindgen_prelude::{BigInt, Buffer};
+use napi::{Error, Result};
+use napi_derive::napi;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::Field;
+
+use super::encoding::fr_from_bytes32;
+use super::envelope::WrappedProofEnvelope;
+use super::instances::OrderedInstances;
+use super::simulator::EvmSimulator;
+use halo2curves::goldilocks::fp::MODULUS as GOLDILOCKS_MODULUS;
+
+fn word_from_hex(index: usize, word: &str) -> Result<[u8; 32]> {
+    hex::decode(word.trim_start_matches("0x"))
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::from_reason(format!("instance {index} is not a 32-byte hex word")))
+}
+
+/// Converts Plonky2 public inputs into the `bytes32` instances of the verifier, as `0x`-prefixed
+/// hex strings
+#[napi]
+pub fn pack_instances(public_inputs: Vec<BigInt>) -> Result<Vec<String>> {
+    let public_inputs = public_inputs
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let (signed, value, lossless) = value.get_u64();
+            if signed || !lossless || value >= GOLDILOCKS_MODULUS {
+                return Err(Error::from_reason(format!(
+                    "public input {index} is not a canonical Goldilocks element"
+                )));
+            }
+            Ok(GoldilocksField::from_canonical_u64(value))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(OrderedInstances::from_public_inputs(&public_inputs)
+        .to_bytes32()
+        .iter()
+        .map(|word| format!("0x{}", hex::encode(word)))
+        .collect())
+}
+
+/// Calldata of the EVM verifier for `proof`, given the `bytes32` instances as hex strings
+#[napi]
+pub fn encode_calldata(instances: Vec<String>, proof: Buffer) -> Result<Buffer> {
+    let instances = instances
+        .iter()
+        .enumerate()
+        .map(|(index, word)| {
+            fr_from_bytes32(&word_from_hex(index, word)?).ok_or_else(|| {
+                Error::from_reason(format!("instance {index} is not a canonical BN254 scalar"))
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(OrderedInstances::new(instances)
+        .encode_calldata(&proof)
+        .into())
+}
+
+#[napi(object)]
+pub struct NativeVerification {
+    pub success: bool,
+    pub gas_used: i64,
+}
+
+/// Runs the EVM verifier on the calldata of a JSON encoded `WrappedProofEnvelope`, in an
+/// in-memory EVM of the native library
+#[napi]
+pub fn verify_native(deployment_code: Buffer, envelope: String) -> Result<NativeVerification> {
+    let envelope = WrappedProofEnvelope::from_json(&envelope)
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    let result = EvmSimulator::deploy(deployment_code.to_vec())
+        .and_then(|mut simulator| {
+            simulator.call(envelope.calldata_with_layout(envelope.calldata_layout()))
+        })
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+    Ok(NativeVerification {
+        success: result.success,
+        gas_used: result.gas_used as i64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use napi::bindgen_prelude::{BigInt, Buffer};
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::Field;
+
+    use super::{encode_calldata, pack_instances};
+    use crate::snark::instances::OrderedInstances;
+    use halo2curves::goldilocks::fp::MODULUS as GOLDILOCKS_MODULUS;
+
+    #[test]
+    fn test_pack_instances_and_encode_calldata() {
+        let public_inputs = [1u64, 42, GOLDILOCKS_MODULUS - 1];
+        let instances =
+            pack_instances(public_inputs.iter().map(|&v| BigInt::from(v)).collect()).unwrap();
+        let expected = OrderedInstances::from_public_inputs(
+            &public_inputs.map(GoldilocksField::from_canonical_u64),
+        );
+        assert_eq!(instances.len(), expected.to_bytes32().len());
+        assert!(instances.iter().all(|word| word.starts_with("0x")));
+
+        let proof = vec![7u8; 64];
+        let calldata = encode_calldata(instances, Buffer::from(proof.clone())).unwrap();
+        assert_eq!(calldata.to_vec(), expected.encode_calldata(&proof));
+    }
+
+    #[test]
+    fn test_pack_instances_rejects_non_canonical_inputs() {
+        assert!(pack_instances(vec![BigInt::from(GOLDILOCKS_MODULUS)]).is_err());
+        let negative = BigInt {
+            sign_bit: true,
+            words: vec![1],
+        };
+        assert!(pack_instances(vec![negative]).is_err());
+        let wide = BigInt {
+            sign_bit: false,
+            words: vec![1, 1],
+        };
+        assert!(pack_instances(vec![wide]).is_err());
+    }
+
+    #[test]
+    fn test_encode_calldata_rejects_malformed_instances() {
+        let proof = || Buffer::from(vec![0u8; 32]);
+        for instance in ["0x01", "not hex", &format!("0x{}", "ff".repeat(32))] {
+            assert!(encode_calldata(vec![instance.to_string()], proof()).is_err());
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Rows occupied by the regions of a circuit, measured by laying the circuit out with its floor
+//! planner without computing any witness.
+use std::collections::BTreeMap;
+
+use halo2_proofs::arithmetic::Field;
+use halo2_proofs::circuit::Value;
+use halo2_proofs::plonk::{
+    Advice, Any, Assigned, Assignment, Challenge, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+/// Rows spanned and cells assigned by the regions of a name. Regions assigned several times,
+/// e.g. once per FRI query round, are aggregated.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RegionOccupancy {
+    pub name: String,
+    /// Number of regions with this name
+    pub count: usize,
+    pub rows: usize,
+    pub cells: usize,
+}
+
+/// Rows of the phases of the verification of a Plonky2 proof, see `Verifier::region_profile`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RegionProfile {
+    /// Hashing of the Plonky2 public inputs
+    pub public_inputs_hash: usize,
+    /// Derivation of the challenges by the Poseidon transcript
+    pub transcript: usize,
+    /// Evaluation of the gate constraints and of the permutation argument at `zeta`
+    pub gate_constraints: usize,
+    /// Verification of the FRI proof, dominated by its query rounds
+    pub fri: usize,
+    /// Rows of the rest of the circuit: assignment of the proof and of the verification key,
+    /// canonical checks and instances
+    pub other: usize,
+}
+
+impl RegionProfile {
+    pub fn total(&self) -> usize {
+        self.public_inputs_hash + self.transcript + self.gate_constraints + self.fri + self.other
+    }
+}
+
+#[derive(Default)]
+struct RegionSpan {
+    start: Option<usize>,
+    end: usize,
+    cells: usize,
+}
+
+impl RegionSpan {
+    fn touch(&mut self, row: usize) {
+        self.start = Some(self.start.map_or(row, |start| start.min(row)));
+        self.end = self.end.max(row + 1);
+    }
+
+    fn rows(&self) -> usize {
+        self.start.map_or(0, |start| self.end - start)
+    }
+}
+
+/// `Assignment` recording the rows touched by every region and ignoring the values
+#[derive(Default)]
+struct OccupancyTracer {
+    current: Option<(String, RegionSpan)>,
+    regions: BTreeMap<String, RegionOccupancy>,
+    rows_used: usize,
+}
+
+impl OccupancyTracer {
+    fn touch(&mut self, row: usize) {
+        self.rows_used = self.rows_used.max(row + 1);
+        if let Some((_, span)) = self.current.as_mut() {
+            span.touch(row);
+            span.cells += 1;
+        }
+    }
+}
+
+impl<F: Field> Assignment<F> for OccupancyTracer {
+    fn enter_region<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.current = Some((name_fn().into(), RegionSpan::default()));
+    }
+
+    fn exit_region(&mut self) {
+        if let Some((name, span)) = self.current.take() {
+            let region = self.regions.entry(name.clone()).or_default();
+            region.name = name;
+            region.count += 1;
+            region.rows += span.rows();
+            region.cells += span.cells;
+        }
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Value<F>, Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn get_challenge(&self, _: Challenge) -> Value<F> {
+        Value::unknown()
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {}
+}
+
+/// Occupancy of the regions of `circuit` sorted by decreasing number of rows, and the number of
+/// rows used by the whole circuit
+pub fn region_occupancy<F: Field, C: Circuit<F>>(
+    circuit: &C,
+) -> Result<(Vec<RegionOccupancy>, usize), Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = C::configure(&mut cs);
+    let mut tracer = OccupancyTracer::default();
+    C::FloorPlanner::synthesize(&mut tracer, circuit, config, cs.constants().clone())?;
+    let mut regions = tracer.regions.into_values().collect::<Vec<_>>();
+    regions.sort_by(|a, b| b.rows.cmp(&a.rows).then_with(|| a.name.cmp(&b.name)));
+    Ok((regions, tracer.rows_used))
+}
@@ -0,0 +1,314 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use halo2_proofs::halo2curves::bn256::Fr;
+use rand::rngs::{OsRng, StdRng};
+use rand::{RngCore, SeedableRng};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use super::calldata::CalldataLayout;
+use super::disclosure::SelectiveDisclosure;
+use super::entropy::{mixed_rng, EntropyError, EntropySource};
+use super::error::VerifierError;
+use super::events::EventSink;
+use super::instances::InstanceLayout;
+use super::simulator::gas::Chain;
+pub use super::verifier::{MultiOpenScheme, TranscriptKind};
+
+/// Strategy used to check the SNARK proof natively after proving
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NativeVerificationStrategy {
+    /// Accumulates the pairing check of the proof, as an aggregation layer embedding the proof
+    /// would do, and checks the accumulator once the proof is read
+    #[default]
+    Accumulator,
+    /// Checks the proof on its own, as a standalone verifier would do
+    Single,
+}
+
+/// Randomness blinding the witness of the SNARK proof. Halo2 fills the last rows of every advice
+/// column with it and blinds the commitments of the vanishing argument, so that the proof reveals
+/// nothing beyond its instances, in particular nothing of the Plonky2 proof it verifies, as long
+/// as the randomness is secret. The Plonky2 proof then doesn't need to be zero-knowledge itself
+/// to keep private inputs private.
+#[derive(Clone, Debug, Default)]
+pub enum Blinding {
+    /// Fresh randomness of the OS for every proof
+    #[default]
+    Random,
+    /// Randomness derived from a seed, so that the same witness gives the same proof. Whoever
+    /// knows the seed can recover the witness from the proof, which isn't zero-knowledge: only
+    /// meant for reproducible tests and fixtures.
+    Seeded(u64),
+    /// Fresh randomness of `source`, e.g. an HSM, mixed with the one of the OS for every proof,
+    /// see `entropy`
+    External(Arc<dyn EntropySource>),
+}
+
+impl PartialEq for Blinding {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Blinding::Random, Blinding::Random) => true,
+            (Blinding::Seeded(a), Blinding::Seeded(b)) => a == b,
+            (Blinding::External(a), Blinding::External(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Blinding {}
+
+impl Blinding {
+    /// Whether the proof hides its witness
+    pub fn is_zero_knowledge(&self) -> bool {
+        !matches!(self, Blinding::Seeded(_))
+    }
+
+    pub(crate) fn rng(&self) -> Result<Box<dyn RngCore>, EntropyError> {
+        Ok(match self {
+            Blinding::Random => Box::new(OsRng),
+            Blinding::Seeded(seed) => Box::new(StdRng::seed_from_u64(*seed)),
+            Blinding::External(source) => Box::new(mixed_rng(source.as_ref())?),
+        })
+    }
+}
+
+/// Options of the wrapping pipeline run by `verifier_api`
+#[derive(Clone, Debug, Default)]
+pub struct PipelineConfig {
+    /// Size of the verifier circuit. Defaults to the smallest adequate `k` given by
+    /// `Verifier::min_k`.
+    pub k: Option<u32>,
+    /// Largest `k` to retry with when the verifier circuit doesn't fit in `k`. Retries are
+    /// also capped by the size of the SRS. Defaults to no retry.
+    pub max_k: Option<u32>,
+    /// Verifies the Plonky2 proof natively before wrapping it, so that an invalid proof is
+    /// rejected before spending minutes on SNARK proving.
+    pub check_inner_proof: bool,
+    /// Strategy used to check the SNARK proof natively
+    pub strategy: NativeVerificationStrategy,
+    /// Multi-opening argument of the SNARK proof and of its verifiers
+    pub multiopen: MultiOpenScheme,
+    /// Transcript of the SNARK proof. With `TranscriptKind::Blake2b`, the proof is only verified
+    /// natively: the report has no EVM verifier, its Yul source and deployment code being empty
+    /// and no gas being measured.
+    pub transcript: TranscriptKind,
+    /// Order of the instances and of the proof in the calldata of the EVM verifier
+    pub calldata_layout: CalldataLayout,
+    /// Compresses the wrapper proof with a second circuit of fixed shape, whose proof is the one
+    /// given to the EVM verifier, see `compression`. Ignored by `gen_batch_artifacts`, whose
+    /// verifiers are generated without proving.
+    pub compression: bool,
+    /// Randomness blinding the SNARK proof, which is zero-knowledge by default
+    pub blinding: Blinding,
+    /// Caller-provided value exposed as an extra instance after the Plonky2 public inputs, so
+    /// that the verifier contract can reject replayed proofs. Should be less than the Goldilocks
+    /// modulus for `WrappedProofEnvelope::instances` to decode it.
+    pub nonce: Option<Fr>,
+    /// Range checks every Goldilocks element of the proof in-circuit, see
+    /// `Verifier::with_strict_canonical_checks`
+    pub strict_canonical: bool,
+    /// Public inputs exposed as instances, the other ones being committed to. Defaults to
+    /// exposing all of them.
+    pub disclosure: Option<SelectiveDisclosure>,
+    /// Protocol metadata exposed as instances before the Plonky2 public inputs
+    pub layout: InstanceLayout,
+    /// Exposes a commitment to the Plonky2 verification key instead of fixing it in the
+    /// circuit, see `Verifier::with_vk_commitment`
+    pub vk_commitment: bool,
+    /// Fixed number of instances, the instances being padded with zeros constrained in the
+    /// circuit, for contracts whose ABI expects a fixed number of instances. Fails with
+    /// `VerifierError::InstancesExceedPadding` if the circuit exposes more instances.
+    pub padded_instances: Option<usize>,
+    /// Minimum conjectured security of the FRI proofs wrapped, see `FriSoundness`. Fails with
+    /// `VerifierError::InsufficientSoundness` for weaker proofs, e.g. below 100 bits, so that
+    /// they don't reach the chain. Defaults to no minimum.
+    pub min_security_bits: Option<usize>,
+    /// Allows generating the EVM verifier from a locally generated SRS, see `InsecureTestSrs`.
+    /// Only meant for tests.
+    pub allow_insecure_srs: bool,
+    /// Generates an EVM verifier taking the G2 points of the SRS as constructor arguments, see
+    /// `g2_args`
+    pub g2_constructor_args: bool,
+    /// Exact solc version the EVM verifier is compiled with, e.g. `0.8.19`, recorded in the
+    /// manifest so that its deployment code can be reproduced. Installed with svm-rs with the
+    /// `svm` feature, otherwise the solc of the host must be this version. Defaults to the solc of
+    /// the host.
+    pub solc_version: Option<String>,
+    /// Solc the EVM verifier is compiled with, which must be `solc_version` if set. Defaults to
+    /// the solc of the host, see `Solc::host`.
+    pub solc_path: Option<PathBuf>,
+    /// Chain whose gas schedule prices the verification in the report, see `GasSchedule`
+    pub chain: Chain,
+    /// Rayon pool the pipeline runs in, so that proving doesn't use the global pool of the host
+    /// application. Takes precedence over `num_threads`.
+    pub thread_pool: Option<Arc<ThreadPool>>,
+    /// Number of threads of a pool built for every run of the pipeline. Defaults to running in
+    /// the global pool.
+    pub num_threads: Option<usize>,
+    /// Sink of the events of the pipeline, see `events`
+    pub event_sink: Option<Arc<dyn EventSink>>,
+    /// File the verifier circuit writes the challenges it derives to, see `ChallengeDump`
+    #[cfg(feature = "debug")]
+    pub challenge_dump: Option<std::path::PathBuf>,
+}
+
+impl PipelineConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Preset wrapping proofs in the format of the PSE and Axiom aggregation circuits, so that
+    /// they can be aggregated further without conversion: SHPLONK with a Keccak256 transcript,
+    /// and a single instance column holding only the Plonky2 public inputs, with neither
+    /// protocol metadata, nonce nor accumulator
+    pub fn aggregation_compatible() -> Self {
+        Self::new().with_multiopen(MultiOpenScheme::Shplonk)
+    }
+
+    pub fn with_k(mut self, k: u32) -> Self {
+        self.k = Some(k);
+        self
+    }
+
+    pub fn with_max_k(mut self, max_k: u32) -> Self {
+        self.max_k = Some(max_k);
+        self
+    }
+
+    pub fn with_inner_proof_check(mut self, check_inner_proof: bool) -> Self {
+        self.check_inner_proof = check_inner_proof;
+        self
+    }
+
+    pub fn with_nonce(mut self, nonce: Fr) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn with_strict_canonical_checks(mut self, strict_canonical: bool) -> Self {
+        self.strict_canonical = strict_canonical;
+        self
+    }
+
+    pub fn with_disclosure(mut self, disclosure: SelectiveDisclosure) -> Self {
+        self.disclosure = Some(disclosure);
+        self
+    }
+
+    pub fn with_layout(mut self, layout: InstanceLayout) -> Self {
+        self.layout = layout.with_vk_commitment(self.vk_commitment);
+        self
+    }
+
+    pub fn with_vk_commitment(mut self, vk_commitment: bool) -> Self {
+        self.vk_commitment = vk_commitment;
+        self.layout = self.layout.with_vk_commitment(vk_commitment);
+        self
+    }
+
+    pub fn with_instance_padding(mut self, padded_len: usize) -> Self {
+        self.padded_instances = Some(padded_len);
+        self
+    }
+
+    pub fn with_min_security_bits(mut self, min_security_bits: usize) -> Self {
+        self.min_security_bits = Some(min_security_bits);
+        self
+    }
+
+    /// Allows generating the EVM verifier from a locally generated SRS, whose verifier must
+    /// never be deployed in production
+    pub fn allow_insecure(mut self) -> Self {
+        self.allow_insecure_srs = true;
+        self
+    }
+
+    pub fn with_g2_constructor_args(mut self, g2_constructor_args: bool) -> Self {
+        self.g2_constructor_args = g2_constructor_args;
+        self
+    }
+
+    pub fn with_solc_version(mut self, version: impl Into<String>) -> Self {
+        self.solc_version = Some(version.into());
+        self
+    }
+
+    pub fn with_solc_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.solc_path = Some(path.into());
+        self
+    }
+
+    pub fn with_chain(mut self, chain: Chain) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    pub fn with_thread_pool(mut self, thread_pool: ThreadPool) -> Self {
+        self.thread_pool = Some(Arc::new(thread_pool));
+        self
+    }
+
+    pub fn with_num_threads(mut self, num_threads: usize) -> Self {
+        self.num_threads = Some(num_threads);
+        self
+    }
+
+    pub fn with_event_sink(mut self, event_sink: Arc<dyn EventSink>) -> Self {
+        self.event_sink = Some(event_sink);
+        self
+    }
+
+    /// Runs `f` in the thread pool of the pipeline, see `thread_pool` and `num_threads`. Fails
+    /// with `VerifierError::ThreadPool` if the pool of `num_threads` can't be built.
+    pub(crate) fn install<R: Send>(
+        &self,
+        f: impl FnOnce() -> Result<R, VerifierError> + Send,
+    ) -> Result<R, VerifierError> {
+        match (&self.thread_pool, self.num_threads) {
+            (Some(pool), _) => pool.install(f),
+            (None, Some(num_threads)) => ThreadPoolBuilder::new()
+                .num_threads(num_threads)
+                .build()
+                .map_err(VerifierError::ThreadPool)?
+                .install(f),
+            (None, None) => f(),
+        }
+    }
+
+    #[cfg(feature = "debug")]
+    pub fn with_challenge_dump(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.challenge_dump = Some(path.into());
+        self
+    }
+
+    pub fn with_multiopen(mut self, multiopen: MultiOpenScheme) -> Self {
+        self.multiopen = multiopen;
+        self
+    }
+
+    pub fn with_transcript(mut self, transcript: TranscriptKind) -> Self {
+        self.transcript = transcript;
+        self
+    }
+
+    pub fn with_calldata_layout(mut self, calldata_layout: CalldataLayout) -> Self {
+        self.calldata_layout = calldata_layout;
+        self
+    }
+
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    pub fn with_blinding(mut self, blinding: Blinding) -> Self {
+        self.blinding = blinding;
+        self
+    }
+
+    pub fn with_strategy(mut self, strategy: NativeVerificationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+}
@@ -0,0 +1,242 @@
+//! Checks of the encoding of a SNARK proof, mirroring the ones of the EVM verifier, so that
+//! malformed submissions are rejected off-chain before paying for a reverted transaction. The
+//! proof is a sequence of G1 points, as big-endian `(x, y)` words, and of scalars, as big-endian
+//! words, whose order only depends on the protocol of the verifier circuit:
+//! - the witness commitments of every phase, then the commitments of the quotient chunks
+//! - the evaluations of the queried polynomials
+//! - the opening proof: a point per rotation for GWC, two points for SHPLONK
+//!
+//! The EVM verifier rejects points with a coordinate out of the base field or not on the curve,
+//! the identity included, and scalars out of the scalar field. Unlike it, the checks also reject
+//! trailing bytes, which the verifier ignores, so that a proof has a single encoding.
+use std::fmt;
+
+use halo2_proofs::halo2curves::bn256::{Fq, Fr, G1Affine};
+use halo2_proofs::halo2curves::group::ff::{Field, PrimeField};
+use itertools::Itertools;
+use snark_verifier::verifier::plonk::PlonkProtocol;
+
+use super::verifier::MultiOpenScheme;
+
+/// Encoded item of a proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofItem {
+    /// G1 point, 64 bytes
+    Point,
+    /// Scalar, 32 bytes
+    Scalar,
+}
+
+impl ProofItem {
+    pub fn size(&self) -> usize {
+        match self {
+            ProofItem::Point => 64,
+            ProofItem::Scalar => 32,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofEncodingError {
+    /// The proof is shorter than its layout
+    Truncated { expected: usize, found: usize },
+    /// The proof is longer than its layout
+    TrailingBytes { expected: usize, found: usize },
+    /// The point at `offset` has a coordinate out of the base field, or isn't on the curve
+    InvalidPoint { offset: usize },
+    /// The scalar at `offset` isn't in the scalar field
+    ScalarOutOfRange { offset: usize },
+}
+
+impl fmt::Display for ProofEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofEncodingError::Truncated { expected, found } => {
+                write!(f, "proof is {found} bytes, {expected} expected")
+            }
+            ProofEncodingError::TrailingBytes { expected, found } => write!(
+                f,
+                "proof is {found} bytes, {} trailing bytes after the {expected} expected",
+                found - expected
+            ),
+            ProofEncodingError::InvalidPoint { offset } => {
+                write!(f, "invalid point at byte {offset} of the proof")
+            }
+            ProofEncodingError::ScalarOutOfRange { offset } => {
+                write!(f, "scalar out of range at byte {offset} of the proof")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProofEncodingError {}
+
+/// Items of the proofs of a protocol, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofLayout {
+    items: Vec<ProofItem>,
+}
+
+impl ProofLayout {
+    /// Layout of the proofs of `protocol` made with `multiopen`
+    pub fn new(protocol: &PlonkProtocol<G1Affine>, multiopen: MultiOpenScheme) -> Self {
+        let num_commitments =
+            protocol.num_witness.iter().sum::<usize>() + protocol.quotient.num_chunk();
+        let num_opening_points = match multiopen {
+            MultiOpenScheme::Gwc => protocol
+                .queries
+                .iter()
+                .map(|query| query.rotation)
+                .unique()
+                .count(),
+            MultiOpenScheme::Shplonk => 2,
+        };
+        let items = std::iter::repeat(ProofItem::Point)
+            .take(num_commitments)
+            .chain(std::iter::repeat(ProofItem::Scalar).take(protocol.evaluations.len()))
+            .chain(std::iter::repeat(ProofItem::Point).take(num_opening_points))
+            .collect();
+        Self { items }
+    }
+
+    pub fn items(&self) -> &[ProofItem] {
+        &self.items
+    }
+
+    /// Size of the proofs in bytes
+    pub fn len(&self) -> usize {
+        self.items.iter().map(ProofItem::size).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+/// Field element of the big-endian `word`, if canonical
+fn from_be_word<F: PrimeField<Repr = [u8; 32]>>(word: &[u8]) -> Option<F> {
+    let mut repr = [0u8; 32];
+    repr.copy_from_slice(word);
+    repr.reverse();
+    Option::from(F::from_repr(repr))
+}
+
+/// Whether `bytes` encode `(x, y)` of a point of the curve other than the identity, as the EVM
+/// verifier checks it: `y^2 = x^3 + 3` with canonical coordinates
+fn is_valid_point(bytes: &[u8]) -> bool {
+    match (
+        from_be_word::<Fq>(&bytes[..32]),
+        from_be_word::<Fq>(&bytes[32..]),
+    ) {
+        (Some(x), Some(y)) => y.square() == x.square() * x + Fq::from(3),
+        _ => false,
+    }
+}
+
+/// Checks that `proof_bytes` is the encoding of a proof of `layout`: exactly its length, with
+/// valid points and canonical scalars
+pub fn validate_proof_encoding(
+    proof_bytes: &[u8],
+    layout: &ProofLayout,
+) -> Result<(), ProofEncodingError> {
+    let expected = layout.len();
+    let found = proof_bytes.len();
+    if found < expected {
+        return Err(ProofEncodingError::Truncated { expected, found });
+    }
+    if found > expected {
+        return Err(ProofEncodingError::TrailingBytes { expected, found });
+    }
+    let mut offset = 0;
+    for item in layout.items() {
+        let bytes = &proof_bytes[offset..offset + item.size()];
+        match item {
+            ProofItem::Point if !is_valid_point(bytes) => {
+                return Err(ProofEncodingError::InvalidPoint { offset })
+            }
+            ProofItem::Scalar if from_be_word::<Fr>(bytes).is_none() => {
+                return Err(ProofEncodingError::ScalarOutOfRange { offset })
+            }
+            _ => {}
+        }
+        offset += item.size();
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "prover"))]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use halo2_proofs::plonk::{keygen_pk, keygen_vk};
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::{OsRng, StdRng};
+    use rand::SeedableRng;
+
+    use super::{ProofEncodingError, ProofItem};
+    use crate::snark::instances::OrderedInstances;
+    use crate::snark::pipeline::MultiOpenScheme;
+    use crate::snark::test_utils;
+    use crate::snark::verifier::VerifierKey;
+    use crate::snark::verifier_api::EvmVerifier;
+
+    #[test]
+    fn test_validate_proof_encoding() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let vk = keygen_vk(&params, &test_utils::FixedCircuit).unwrap();
+        let pk = keygen_pk(&params, vk, &test_utils::FixedCircuit).unwrap();
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        for multiopen in [MultiOpenScheme::Gwc, MultiOpenScheme::Shplonk] {
+            let key = VerifierKey::new(&params, pk.get_vk(), 1).with_multiopen(multiopen);
+            let proof = EvmVerifier::prove(
+                &params,
+                &pk,
+                test_utils::FixedCircuit,
+                &instances,
+                multiopen,
+                OsRng,
+            );
+            assert_eq!(key.proof_layout().len(), proof.len());
+            assert_eq!(key.validate_proof_encoding(&proof), Ok(()));
+
+            let expected = proof.len();
+            let mut trailing = proof.clone();
+            trailing.push(0);
+            assert_eq!(
+                key.validate_proof_encoding(&trailing),
+                Err(ProofEncodingError::TrailingBytes {
+                    expected,
+                    found: expected + 1
+                })
+            );
+            // the EVM verifier ignores trailing bytes, the native one rejects them
+            assert!(key.verify(&instances, &trailing).is_err());
+            assert!(matches!(
+                key.validate_proof_encoding(&proof[..expected - 1]),
+                Err(ProofEncodingError::Truncated { .. })
+            ));
+
+            // the identity, encoded as (0, 0) like the EVM does
+            let mut identity = proof.clone();
+            identity[..64].fill(0);
+            assert_eq!(
+                key.validate_proof_encoding(&identity),
+                Err(ProofEncodingError::InvalidPoint { offset: 0 })
+            );
+            // the last evaluation, followed by the opening proof
+            let items = key.proof_layout().items().to_vec();
+            let offset = items
+                .iter()
+                .take_while(|item| **item == ProofItem::Point)
+                .chain(items.iter().filter(|item| **item == ProofItem::Scalar))
+                .map(ProofItem::size)
+                .sum::<usize>()
+                - ProofItem::Scalar.size();
+            let mut out_of_range = proof.clone();
+            out_of_range[offset..offset + 32].fill(0xff);
+            assert_eq!(
+                key.validate_proof_encoding(&out_of_range),
+                Err(ProofEncodingError::ScalarOutOfRange { offset })
+            );
+        }
+    }
+}
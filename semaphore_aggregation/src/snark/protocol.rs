@@ -0,0 +1,188 @@
+//! Metadata of the snark-verifier protocol the EVM verifier is generated from, recorded in the
+//! manifest of the artifacts. The generated Yul only depends on the protocol, compiled from the
+//! verifying key with `Config::kzg()`, on the multi-opening scheme, on the SRS, on the layout of
+//! the calldata and on whether the G2 points of the SRS are constructor arguments, so that a
+//! verifier regenerated later, e.g. after upgrading snark-verifier, can be checked against the
+//! deployed one before redeploying.
+use serde::{Deserialize, Serialize};
+use snark_verifier::verifier::plonk::Query;
+
+use super::calldata::CalldataLayout;
+use super::envelope::hex_bytes32;
+use super::utils::keccak256;
+use super::verifier::VerifierKey;
+
+/// Settings of `Config::kzg()` and shape of the compiled protocol
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolMetadata {
+    /// `Config::with_num_instance`: number of instances of every instance column
+    pub num_instance: Vec<usize>,
+    /// `Config::with_accumulator_indices`: column and row of every limb of the accumulators
+    /// checked along the proof, see `compression`
+    pub accumulator_indices: Vec<Vec<(usize, usize)>>,
+    /// Name of the `MultiOpenScheme`
+    pub multiopen: String,
+    /// Number of fixed and permutation commitments
+    pub num_preprocessed: usize,
+    /// Number of witness commitments of every phase
+    pub num_witness: Vec<usize>,
+    /// Number of challenges squeezed after every phase
+    pub num_challenge: Vec<usize>,
+    pub num_quotient_chunks: usize,
+    /// Polynomial and rotation of every evaluation read from the proof
+    pub evaluations: Vec<(usize, i32)>,
+    /// Polynomial and rotation of every opening
+    pub queries: Vec<(usize, i32)>,
+    /// Keccak256 of the protocol as JSON, including the preprocessed commitments
+    #[serde(with = "hex_bytes32")]
+    pub protocol_hash: [u8; 32],
+    /// Name of the `CalldataLayout` the verifier reads, instances first in metadata recorded
+    /// before it was
+    #[serde(default = "default_calldata_layout")]
+    pub calldata_layout: String,
+    /// Whether the verifier takes the G2 points of the SRS as constructor arguments, see
+    /// `PipelineConfig::with_g2_constructor_args`
+    #[serde(default)]
+    pub g2_constructor_args: bool,
+}
+
+fn default_calldata_layout() -> String {
+    CalldataLayout::default().to_string()
+}
+
+impl ProtocolMetadata {
+    /// Metadata of the verifier of `key`, reading the calldata in `calldata_layout` and taking
+    /// the G2 points as constructor arguments if `g2_constructor_args` is set
+    pub fn new(
+        key: &VerifierKey,
+        calldata_layout: CalldataLayout,
+        g2_constructor_args: bool,
+    ) -> serde_json::Result<Self> {
+        let protocol = key.protocol();
+        let protocol_json = key.protocol_json()?;
+        Ok(Self {
+            num_instance: protocol.num_instance.clone(),
+            accumulator_indices: protocol.accumulator_indices.clone(),
+            multiopen: key.multiopen().name().to_string(),
+            num_preprocessed: protocol.preprocessed.len(),
+            num_witness: protocol.num_witness.clone(),
+            num_challenge: protocol.num_challenge.clone(),
+            num_quotient_chunks: protocol.quotient.num_chunk(),
+            evaluations: rotations(&protocol.evaluations),
+            queries: rotations(&protocol.queries),
+            protocol_hash: keccak256(protocol_json.as_bytes()),
+            calldata_layout: calldata_layout.to_string(),
+            g2_constructor_args,
+        })
+    }
+
+    /// Fields differing from `deployed`. A verifier generated with the same SRS is only
+    /// byte-compatible with the deployed one if there are none.
+    pub fn mismatches(&self, deployed: &Self) -> Vec<&'static str> {
+        [
+            ("num_instance", self.num_instance == deployed.num_instance),
+            (
+                "accumulator_indices",
+                self.accumulator_indices == deployed.accumulator_indices,
+            ),
+            ("multiopen", self.multiopen == deployed.multiopen),
+            (
+                "num_preprocessed",
+                self.num_preprocessed == deployed.num_preprocessed,
+            ),
+            ("num_witness", self.num_witness == deployed.num_witness),
+            (
+                "num_challenge",
+                self.num_challenge == deployed.num_challenge,
+            ),
+            (
+                "num_quotient_chunks",
+                self.num_quotient_chunks == deployed.num_quotient_chunks,
+            ),
+            ("evaluations", self.evaluations == deployed.evaluations),
+            ("queries", self.queries == deployed.queries),
+            (
+                "protocol_hash",
+                self.protocol_hash == deployed.protocol_hash,
+            ),
+            (
+                "calldata_layout",
+                self.calldata_layout == deployed.calldata_layout,
+            ),
+            (
+                "g2_constructor_args",
+                self.g2_constructor_args == deployed.g2_constructor_args,
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, equal)| !equal)
+        .map(|(field, _)| field)
+        .collect()
+    }
+}
+
+fn rotations(queries: &[Query]) -> Vec<(usize, i32)> {
+    queries
+        .iter()
+        .map(|query| (query.poly, query.rotation.0))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::plonk::keygen_vk;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::ProtocolMetadata;
+    use crate::snark::calldata::CalldataLayout;
+    use crate::snark::pipeline::MultiOpenScheme;
+    use crate::snark::test_utils;
+    use crate::snark::verifier::VerifierKey;
+
+    #[test]
+    fn test_protocol_mismatches() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let vk = keygen_vk(&params, &test_utils::FixedCircuit).unwrap();
+        let metadata = |key: &VerifierKey| {
+            ProtocolMetadata::new(key, CalldataLayout::InstancesFirst, false).unwrap()
+        };
+        let deployed = metadata(&VerifierKey::new(&params, &vk, 1));
+        assert_eq!(deployed.num_instance, [1]);
+        let json = serde_json::to_string(&deployed).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ProtocolMetadata>(&json).unwrap(),
+            deployed
+        );
+
+        let regenerated = metadata(&VerifierKey::new(&params, &vk, 1));
+        assert!(regenerated.mismatches(&deployed).is_empty());
+        let shplonk =
+            metadata(&VerifierKey::new(&params, &vk, 1).with_multiopen(MultiOpenScheme::Shplonk));
+        assert_eq!(shplonk.mismatches(&deployed), ["multiopen"]);
+        let more_instances = metadata(&VerifierKey::new(&params, &vk, 2));
+        assert!(more_instances
+            .mismatches(&deployed)
+            .contains(&"num_instance"));
+
+        // the same protocol compiles to other bytecode in another layout or with the G2 points
+        // as constructor arguments
+        let key = VerifierKey::new(&params, &vk, 1);
+        let proof_first = ProtocolMetadata::new(&key, CalldataLayout::ProofFirst, false).unwrap();
+        assert_eq!(proof_first.mismatches(&deployed), ["calldata_layout"]);
+        let g2_args = ProtocolMetadata::new(&key, CalldataLayout::InstancesFirst, true).unwrap();
+        assert_eq!(g2_args.mismatches(&deployed), ["g2_constructor_args"]);
+
+        // metadata recorded before the layout and the G2 mode were
+        let mut legacy = serde_json::to_value(&deployed).unwrap();
+        legacy.as_object_mut().unwrap().remove("calldata_layout");
+        legacy
+            .as_object_mut()
+            .unwrap()
+            .remove("g2_constructor_args");
+        let legacy: ProtocolMetadata = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.mismatches(&deployed).is_empty());
+    }
+}
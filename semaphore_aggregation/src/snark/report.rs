@@ -0,0 +1,300 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use halo2_proofs::dev::MockProver;
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2_proofs::plonk::{Circuit, ConstraintSystem, Error};
+
+use super::backend::FieldBackend;
+use super::envelope::WrappedProofEnvelope;
+use super::events::{EventSink, PipelineEvent};
+use super::occupancy::{region_occupancy, RegionOccupancy, RegionProfile};
+use super::simulator::gas::{Chain, GasCost};
+use super::srs::SrsMetadata;
+use super::types::common_data::FriSoundness;
+use super::verifier::VerifierKey;
+
+/// Outcome of a run of the wrapping pipeline
+#[derive(Clone, Debug)]
+pub struct VerificationReport {
+    /// The SNARK proof and its instances
+    pub envelope: WrappedProofEnvelope,
+    /// Size of the verifier circuit
+    pub k: u32,
+    /// Yul source of the EVM verifier, `None` for a proof only verified natively, see
+    /// `TranscriptKind::is_evm_compatible`
+    pub yul_code: Option<String>,
+    /// Deployment bytecode of the EVM verifier, `None` for a proof only verified natively
+    pub deployment_code: Option<Vec<u8>>,
+    /// Version of the solc the deployment code was compiled with, `None` for a proof only
+    /// verified natively
+    pub solc_version: Option<String>,
+    /// Whether the SNARK proof hides its witness, see `Blinding`
+    pub zero_knowledge: bool,
+    /// Key verifying the SNARK proof natively without the SRS
+    pub verifier_key: VerifierKey,
+    /// Arguments to append to the deployment code, empty unless the verifier takes the G2
+    /// points of the SRS as constructor arguments
+    pub constructor_args: Vec<u8>,
+    /// Gas used by the EVM verifier to verify the proof, `None` for a proof only verified
+    /// natively
+    pub gas_used: Option<u64>,
+    /// Chain the verification is priced for, see `PipelineConfig::with_chain`
+    pub chain: Chain,
+    /// Gas of the transaction verifying the proof on `chain`, `None` for a proof only verified
+    /// natively
+    pub gas_cost: Option<GasCost>,
+    /// Provenance of the SRS the verifier was generated from
+    pub srs: Option<SrsMetadata>,
+    /// Total time spent in the pipeline
+    pub elapsed: Duration,
+    /// Time spent in every stage of the pipeline
+    pub stages: StageTimings,
+    /// Field arithmetic the prover ran with
+    pub field_backend: FieldBackend,
+    /// Security of the FRI proof wrapped, the weakest one for a batch, `None` if the inner
+    /// proof system doesn't estimate it
+    pub fri_soundness: Option<FriSoundness>,
+    /// Rows of the verifier circuit saved by deduplicating its constants, `None` if the circuit
+    /// doesn't deduplicate them, e.g. for a batch
+    pub constant_rows_saved: Option<usize>,
+}
+
+impl VerificationReport {
+    pub fn deployment_code_size(&self) -> Option<usize> {
+        self.deployment_code.as_ref().map(Vec::len)
+    }
+
+    /// Deployment code followed by the constructor arguments, as sent to deploy the verifier
+    pub fn deployment_code_with_args(&self) -> Option<Vec<u8>> {
+        self.deployment_code
+            .as_ref()
+            .map(|code| [code.as_slice(), &self.constructor_args].concat())
+    }
+}
+
+/// Stage of the wrapping pipeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Native verification of the Plonky2 proof
+    InnerProofCheck,
+    /// Conversion of the Plonky2 proof, verifier data and common data into circuit values
+    Conversion,
+    /// Runs of the mock prover, including the search for the smallest `k`
+    Mock,
+    Keygen,
+    /// Generation of the Yul source of the EVM verifier
+    Yul,
+    /// Compilation of the Yul source into deployment code
+    Compile,
+    /// Creation of the SNARK proof. Halo2 runs witness generation, commitments and openings in a
+    /// single call, so they are timed together.
+    Prove,
+    /// Native verification of the SNARK proof, by halo2 and with the protocol the EVM verifier is
+    /// generated from
+    NativeVerify,
+    /// Proving of the wrapper proof compressed by the compression circuit, see `compression`
+    Compression,
+    /// Deployment and call of the EVM verifier in the simulated EVM
+    EvmSimulation,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::InnerProofCheck => write!(f, "inner proof check"),
+            Stage::Conversion => write!(f, "conversion"),
+            Stage::Mock => write!(f, "mock prover"),
+            Stage::Keygen => write!(f, "keygen"),
+            Stage::Yul => write!(f, "yul generation"),
+            Stage::Compile => write!(f, "yul compilation"),
+            Stage::Prove => write!(f, "proving"),
+            Stage::NativeVerify => write!(f, "native verification"),
+            Stage::Compression => write!(f, "compression"),
+            Stage::EvmSimulation => write!(f, "evm simulation"),
+        }
+    }
+}
+
+/// Time spent in every stage of a run of the pipeline, in the order the stages ran. Its
+/// `Display` implementation prints a table of the stages with their share of the total.
+#[derive(Clone, Debug, Default)]
+pub struct StageTimings {
+    timings: Vec<(Stage, Duration)>,
+    /// Sink notified of every recorded stage, see `PipelineEvent::StageCompleted`
+    events: Option<Arc<dyn EventSink>>,
+}
+
+impl PartialEq for StageTimings {
+    fn eq(&self, other: &Self) -> bool {
+        self.timings == other.timings
+    }
+}
+
+impl Eq for StageTimings {}
+
+impl StageTimings {
+    pub(crate) fn with_events(events: Option<Arc<dyn EventSink>>) -> Self {
+        Self {
+            timings: vec![],
+            events,
+        }
+    }
+
+    pub(crate) fn record(&mut self, stage: Stage, elapsed: Duration) {
+        self.timings.push((stage, elapsed));
+        if let Some(events) = &self.events {
+            events.emit(&PipelineEvent::StageCompleted { stage, elapsed });
+        }
+    }
+
+    /// Time spent in `stage`, or `None` if it didn't run
+    pub fn get(&self, stage: Stage) -> Option<Duration> {
+        self.timings
+            .iter()
+            .filter(|(s, _)| *s == stage)
+            .map(|(_, elapsed)| *elapsed)
+            .reduce(|a, b| a + b)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(Stage, Duration)> {
+        self.timings.iter()
+    }
+
+    /// Time spent in all the stages
+    pub fn total(&self) -> Duration {
+        self.timings.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+}
+
+impl fmt::Display for StageTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let total = self.total();
+        for (stage, elapsed) in self.timings.iter() {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                100.0 * elapsed.as_secs_f64() / total.as_secs_f64()
+            };
+            writeln!(
+                f,
+                "{:<20} {:>10} ms {:>6.1}%",
+                stage.to_string(),
+                elapsed.as_millis(),
+                share
+            )?;
+        }
+        write!(f, "{:<20} {:>10} ms", "total", total.as_millis())
+    }
+}
+
+/// Constraint of the verifier circuit the mock prover found unsatisfied
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintFailure {
+    /// Description of the failure, with the gate or lookup and the region it occurred in
+    pub description: String,
+}
+
+/// Outcome of a run of the mock prover on the verifier circuit
+#[derive(Clone, Debug)]
+pub struct MockReport {
+    /// Size of the circuit the mock prover ran with
+    pub k: u32,
+    /// Rows assigned by the circuit
+    pub rows_used: usize,
+    /// Rows available to the circuit at `k`, the other ones holding blinding factors
+    pub usable_rows: usize,
+    /// Number of lookup arguments of the circuit
+    pub num_lookups: usize,
+    /// Occupancy of the regions of the circuit, largest first
+    pub regions: Vec<RegionOccupancy>,
+    /// Rows saved by deduplicating the constants of the verifier circuit, see
+    /// `Verifier::constant_rows_saved`
+    pub constant_rows_saved: Option<usize>,
+    /// Rows of the phases of the verification, see `Verifier::region_profile`
+    pub region_profile: Option<RegionProfile>,
+    pub satisfied: bool,
+    pub failures: Vec<ConstraintFailure>,
+}
+
+impl MockReport {
+    /// Collects the report of `prover`, which ran `circuit` at `k`
+    pub fn new<C: Circuit<Fr>>(
+        circuit: &C,
+        k: u32,
+        prover: &MockProver<Fr>,
+    ) -> Result<Self, Error> {
+        let mut cs = ConstraintSystem::<Fr>::default();
+        C::configure(&mut cs);
+        let (regions, rows_used) = region_occupancy(circuit)?;
+        let failures = prover
+            .verify()
+            .err()
+            .unwrap_or_default()
+            .iter()
+            .map(|failure| ConstraintFailure {
+                description: failure.to_string(),
+            })
+            .collect::<Vec<_>>();
+        Ok(Self {
+            k,
+            rows_used,
+            usable_rows: (1 << k) - (cs.blinding_factors() + 1),
+            num_lookups: cs.lookups().len(),
+            regions,
+            constant_rows_saved: None,
+            region_profile: None,
+            satisfied: failures.is_empty(),
+            failures,
+        })
+    }
+
+    /// Share of the usable rows used by the circuit
+    pub fn occupancy(&self) -> f64 {
+        self.rows_used as f64 / self.usable_rows as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use halo2_proofs::dev::MockProver;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{MockReport, Stage, StageTimings};
+    use crate::snark::test_utils::FixedCircuit;
+
+    #[test]
+    fn test_mock_report_failures() {
+        let report = |product: u64| {
+            let prover = MockProver::run(4, &FixedCircuit, vec![vec![Fr::from(product)]]).unwrap();
+            MockReport::new(&FixedCircuit, 4, &prover).unwrap()
+        };
+        let satisfied = report(42);
+        assert!(satisfied.satisfied && satisfied.failures.is_empty());
+        assert!(0 < satisfied.rows_used && satisfied.rows_used <= satisfied.usable_rows);
+
+        let unsatisfied = report(43);
+        assert!(!unsatisfied.satisfied);
+        assert!(!unsatisfied.failures.is_empty());
+        assert_eq!(unsatisfied.rows_used, satisfied.rows_used);
+    }
+
+    #[test]
+    fn test_stage_timings() {
+        let mut stages = StageTimings::default();
+        stages.record(Stage::Mock, Duration::from_millis(30));
+        stages.record(Stage::Prove, Duration::from_millis(60));
+        stages.record(Stage::Mock, Duration::from_millis(10));
+        assert_eq!(stages.get(Stage::Mock), Some(Duration::from_millis(40)));
+        assert_eq!(stages.get(Stage::Keygen), None);
+        assert_eq!(stages.total(), Duration::from_millis(100));
+
+        let table = stages.to_string();
+        assert!(table.contains("proving"));
+        assert!(table.contains("60.0%"));
+        assert!(table.ends_with("total                       100 ms"));
+    }
+}
@@ -0,0 +1,268 @@
+//! `EvmBackend` sending transactions to an Anvil node. Forking a live network lets the gas of
+//! the verifier be measured against its state and EVM version.
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use super::{EvmBackend, SimulationResult};
+use crate::snark::error::VerifierError;
+
+/// Gas limit of the transactions sent to the node, the default block gas limit of Anvil
+const GAS_LIMIT: u64 = 30_000_000;
+
+/// How long `AnvilBackend::spawn` waits for the node to answer
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Anvil node, deploying and calling the verifier with transactions from its first unlocked
+/// account. Anvil mines every transaction right away, so results are read from the receipts.
+/// The reported gas is the gas used by the transaction, which includes the intrinsic gas and
+/// the calldata cost on top of the execution of the verifier.
+pub struct AnvilBackend {
+    endpoint: String,
+    from: String,
+    verifier: Option<String>,
+    node: Option<Child>,
+}
+
+fn rpc_error(e: impl ToString) -> VerifierError {
+    VerifierError::EvmBackend(e.to_string())
+}
+
+fn parse_quantity(value: &Value) -> Result<u64, VerifierError> {
+    let quantity = value
+        .as_str()
+        .ok_or_else(|| rpc_error(format!("expected a quantity, got {value}")))?;
+    u64::from_str_radix(quantity.trim_start_matches("0x"), 16).map_err(rpc_error)
+}
+
+impl AnvilBackend {
+    /// Connects to a running node, e.g. at `http://127.0.0.1:8545`
+    pub fn connect(endpoint: impl Into<String>) -> Result<Self, VerifierError> {
+        let mut backend = Self {
+            endpoint: endpoint.into(),
+            from: String::new(),
+            verifier: None,
+            node: None,
+        };
+        let accounts = backend.request("eth_accounts", json!([]))?;
+        backend.from = accounts[0]
+            .as_str()
+            .ok_or_else(|| rpc_error("node has no unlocked account"))?
+            .to_string();
+        Ok(backend)
+    }
+
+    /// Spawns `anvil` listening on `port`, forking `fork_url` if given, and connects to it.
+    /// The node is killed when the backend is dropped.
+    pub fn spawn(port: u16, fork_url: Option<&str>) -> Result<Self, VerifierError> {
+        let mut command = Command::new("anvil");
+        command
+            .arg("--port")
+            .arg(port.to_string())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(fork_url) = fork_url {
+            command.arg("--fork-url").arg(fork_url);
+        }
+        let mut node = command.spawn().map_err(rpc_error)?;
+
+        let endpoint = format!("http://127.0.0.1:{port}");
+        let start = Instant::now();
+        loop {
+            match Self::connect(endpoint.clone()) {
+                Ok(mut backend) => {
+                    backend.node = Some(node);
+                    return Ok(backend);
+                }
+                Err(_) if start.elapsed() < STARTUP_TIMEOUT => {
+                    thread::sleep(Duration::from_millis(100))
+                }
+                Err(e) => {
+                    let _ = node.kill();
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Address of the deployed verifier
+    pub fn verifier(&self) -> Option<&str> {
+        self.verifier.as_deref()
+    }
+
+    fn request(&self, method: &str, params: Value) -> Result<Value, VerifierError> {
+        let response: Value = ureq::post(&self.endpoint)
+            .send_json(json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": method,
+                "params": params,
+            }))
+            .map_err(rpc_error)?
+            .into_json()
+            .map_err(rpc_error)?;
+        if let Some(error) = response.get("error") {
+            return Err(rpc_error(format!("{method} failed: {error}")));
+        }
+        Ok(response["result"].clone())
+    }
+
+    /// Sends a transaction and returns its receipt
+    fn transact(&self, to: Option<&str>, data: &[u8]) -> Result<Value, VerifierError> {
+        let mut tx = json!({
+            "from": self.from,
+            "data": format!("0x{}", hex::encode(data)),
+            "gas": format!("{GAS_LIMIT:#x}"),
+        });
+        if let Some(to) = to {
+            tx["to"] = json!(to);
+        }
+        let hash = self.request("eth_sendTransaction", json!([tx]))?;
+        self.request("eth_getTransactionReceipt", json!([hash]))
+    }
+}
+
+impl EvmBackend for AnvilBackend {
+    fn deploy(&mut self, deployment_code: Vec<u8>) -> Result<(), VerifierError> {
+        let receipt = self.transact(None, &deployment_code)?;
+        if parse_quantity(&receipt["status"])? != 1 {
+            return Err(VerifierError::DeploymentFailed);
+        }
+        let address = receipt["contractAddress"]
+            .as_str()
+            .ok_or(VerifierError::DeploymentFailed)?;
+        self.verifier = Some(address.to_string());
+        Ok(())
+    }
+
+    fn call(&mut self, calldata: Vec<u8>) -> Result<SimulationResult, VerifierError> {
+        let verifier = self
+            .verifier
+            .as_deref()
+            .ok_or_else(|| rpc_error("no verifier deployed"))?;
+        let receipt = self.transact(Some(verifier), &calldata)?;
+        Ok(SimulationResult {
+            success: parse_quantity(&receipt["status"])? == 1,
+            gas_used: parse_quantity(&receipt["gasUsed"])?,
+        })
+    }
+}
+
+impl Drop for AnvilBackend {
+    fn drop(&mut self) {
+        if let Some(node) = self.node.as_mut() {
+            let _ = node.kill();
+            let _ = node.wait();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use serde_json::{json, Value};
+
+    use super::AnvilBackend;
+    use crate::snark::error::VerifierError;
+    use crate::snark::simulator::EvmSimulator;
+
+    /// Answers the JSON-RPC requests of the backend like a node mining every transaction with
+    /// `status`, recording the transactions sent
+    fn serve(status: &'static str) -> (String, Arc<Mutex<Vec<Value>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let transactions = Arc::new(Mutex::new(vec![]));
+        let sent = transactions.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(value) = line.to_lowercase().strip_prefix("content-length:") {
+                        len = value.trim().parse().unwrap();
+                    }
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                let request: Value = serde_json::from_slice(&body).unwrap();
+                let result = match request["method"].as_str().unwrap() {
+                    "eth_accounts" => json!(["0x00000000000000000000000000000000000000aa"]),
+                    "eth_sendTransaction" => {
+                        sent.lock().unwrap().push(request["params"][0].clone());
+                        json!("0x01")
+                    }
+                    "eth_getTransactionReceipt" => json!({
+                        "status": status,
+                        "contractAddress": "0x00000000000000000000000000000000000000bb",
+                        "gasUsed": "0x5208",
+                    }),
+                    method => panic!("unexpected {method}"),
+                };
+                let response = json!({ "jsonrpc": "2.0", "id": 1, "result": result }).to_string();
+                write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n{response}",
+                    response.len()
+                )
+                .unwrap();
+            }
+        });
+        (endpoint, transactions)
+    }
+
+    #[test]
+    fn test_anvil_backend() {
+        let (endpoint, transactions) = serve("0x1");
+        let backend = AnvilBackend::connect(endpoint).unwrap();
+        let mut simulator = EvmSimulator::with_backend(backend, vec![0x60, 0x00]).unwrap();
+        assert_eq!(
+            simulator.backend().verifier(),
+            Some("0x00000000000000000000000000000000000000bb")
+        );
+        let result = simulator.call(vec![1, 2, 3]).unwrap();
+        assert!(result.success);
+        assert_eq!(result.gas_used, 21_000);
+
+        let transactions = transactions.lock().unwrap();
+        assert_eq!(transactions.len(), 2);
+        assert_eq!(transactions[0]["data"], "0x6000");
+        assert!(transactions[0].get("to").is_none());
+        assert_eq!(transactions[1]["data"], "0x010203");
+        assert_eq!(
+            transactions[1]["to"],
+            "0x00000000000000000000000000000000000000bb"
+        );
+    }
+
+    #[test]
+    fn test_anvil_backend_failures() {
+        let (endpoint, _) = serve("0x0");
+        let backend = AnvilBackend::connect(endpoint).unwrap();
+        assert!(matches!(
+            EvmSimulator::with_backend(backend, vec![0x60, 0x00]),
+            Err(VerifierError::DeploymentFailed)
+        ));
+        // nothing listens on port 1
+        assert!(matches!(
+            AnvilBackend::connect("http://127.0.0.1:1"),
+            Err(VerifierError::EvmBackend(_))
+        ));
+    }
+}
@@ -0,0 +1,165 @@
+//! Gas schedules of the chains the verifier is deployed to. The simulated EVM measures the
+//! execution of the verifier priced as on Ethereum mainnet, and its gas can't be priced with
+//! another schedule. The execution is thus priced the same on every chain listed here, which
+//! all charge the opcodes and precompiles of Ethereum mainnet. A schedule only adds what the
+//! transaction carrying the proof pays on top of the measured execution: the intrinsic gas of
+//! the transaction and its calldata, and on rollups the L1 gas of posting the calldata to
+//! Ethereum. Chains pricing the execution differently aren't supported.
+
+/// Chain whose gas schedule prices the verification of a proof
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Chain {
+    /// Ethereum mainnet after Cancun
+    #[default]
+    Ethereum,
+    /// Arbitrum One
+    Arbitrum,
+    /// OP Mainnet
+    Optimism,
+}
+
+impl Chain {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Chain::Ethereum => "ethereum",
+            Chain::Arbitrum => "arbitrum",
+            Chain::Optimism => "optimism",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ethereum" => Some(Chain::Ethereum),
+            "arbitrum" => Some(Chain::Arbitrum),
+            "optimism" => Some(Chain::Optimism),
+            _ => None,
+        }
+    }
+
+    pub fn schedule(&self) -> GasSchedule {
+        match self {
+            Chain::Ethereum => GasSchedule::ETHEREUM,
+            Chain::Arbitrum => GasSchedule::ARBITRUM,
+            Chain::Optimism => GasSchedule::OPTIMISM,
+        }
+    }
+}
+
+impl std::fmt::Display for Chain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Prices of what the verification of a proof pays on a chain beyond the execution of the
+/// verifier, measured in the simulated EVM
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GasSchedule {
+    /// Intrinsic gas of a transaction
+    pub transaction: u64,
+    /// Gas of a zero byte of calldata
+    pub zero_byte: u64,
+    /// Gas of a nonzero byte of calldata
+    pub nonzero_byte: u64,
+    /// L1 gas per byte of calldata posted to Ethereum by a rollup, zero on other chains
+    pub l1_gas_per_byte: u64,
+    /// Bytes of a transaction posted to Ethereum on top of its calldata
+    pub l1_overhead_bytes: u64,
+}
+
+impl GasSchedule {
+    /// Ethereum mainnet after Cancun
+    pub const ETHEREUM: Self = Self {
+        transaction: 21_000,
+        zero_byte: 4,
+        nonzero_byte: 16,
+        l1_gas_per_byte: 0,
+        l1_overhead_bytes: 0,
+    };
+
+    /// Arbitrum One, charging the calldata posted to Ethereum as if it were incompressible, with
+    /// the fixed padding of the L1 pricer
+    pub const ARBITRUM: Self = Self {
+        l1_gas_per_byte: 16,
+        l1_overhead_bytes: 140,
+        ..Self::ETHEREUM
+    };
+
+    /// OP Mainnet, charging the calldata posted to Ethereum as if it were incompressible, with
+    /// the overhead of the signature and fields of the transaction
+    pub const OPTIMISM: Self = Self {
+        l1_gas_per_byte: 16,
+        l1_overhead_bytes: 68,
+        ..Self::ETHEREUM
+    };
+
+    /// Gas paid on this chain by a transaction sending `calldata` to the verifier, whose
+    /// execution used `execution_gas` in the simulated EVM
+    pub fn cost(&self, execution_gas: u64, calldata: &[u8]) -> GasCost {
+        let zero_bytes = calldata.iter().filter(|byte| **byte == 0).count() as u64;
+        let nonzero_bytes = calldata.len() as u64 - zero_bytes;
+        let intrinsic =
+            self.transaction + zero_bytes * self.zero_byte + nonzero_bytes * self.nonzero_byte;
+        let l1_data = if self.l1_gas_per_byte == 0 {
+            0
+        } else {
+            (calldata.len() as u64 + self.l1_overhead_bytes) * self.l1_gas_per_byte
+        };
+        GasCost {
+            execution: execution_gas,
+            intrinsic,
+            l1_data,
+        }
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        Self::ETHEREUM
+    }
+}
+
+/// Gas of the verification of a proof on a chain
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GasCost {
+    /// Gas of the execution of the verifier, as measured in the simulated EVM
+    pub execution: u64,
+    /// Intrinsic gas of the transaction, its calldata included
+    pub intrinsic: u64,
+    /// L1 gas of posting the calldata to Ethereum, paid by rollup transactions at the price of
+    /// L1 gas on top of `total`
+    pub l1_data: u64,
+}
+
+impl GasCost {
+    /// Gas used by the transaction on the chain
+    pub fn total(&self) -> u64 {
+        self.execution + self.intrinsic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Chain;
+
+    #[test]
+    fn test_gas_schedules() {
+        let calldata = [[0u8; 32], [1u8; 32]].concat();
+        let cost = Chain::Ethereum.schedule().cost(300_000, &calldata);
+        assert_eq!(cost.execution, 300_000);
+        assert_eq!(cost.intrinsic, 21_000 + 32 * 4 + 32 * 16);
+        assert_eq!(cost.l1_data, 0);
+
+        let arbitrum = Chain::Arbitrum.schedule().cost(300_000, &calldata);
+        assert_eq!(arbitrum.total(), cost.total());
+        assert_eq!(arbitrum.l1_data, (64 + 140) * 16);
+        let optimism = Chain::Optimism.schedule().cost(300_000, &calldata);
+        assert_eq!(optimism.total(), cost.total());
+        assert_eq!(optimism.l1_data, (64 + 68) * 16);
+
+        for chain in [Chain::Ethereum, Chain::Arbitrum, Chain::Optimism] {
+            assert_eq!(Chain::from_name(chain.name()), Some(chain));
+        }
+        assert_eq!(Chain::from_name("polygon"), None);
+    }
+}
@@ -0,0 +1,213 @@
+//! Local EVM simulation of a deployed verifier contract, deploying it once and verifying any
+//! number of proofs against it.
+use snark_verifier::loader::evm::{Address, ExecutorBuilder};
+
+use super::calldata::CalldataLayout;
+use super::error::VerifierError;
+use super::instances::OrderedInstances;
+
+#[cfg(feature = "anvil")]
+pub mod anvil;
+pub mod gas;
+
+/// Outcome of a call to the verifier contract
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SimulationResult {
+    pub success: bool,
+    pub gas_used: u64,
+}
+
+/// EVM in which the verifier contract is deployed and called
+pub trait EvmBackend {
+    /// Deploys `deployment_code`, replacing the verifier previously deployed if any
+    fn deploy(&mut self, deployment_code: Vec<u8>) -> Result<(), VerifierError>;
+
+    /// Calls the deployed verifier with raw calldata
+    fn call(&mut self, calldata: Vec<u8>) -> Result<SimulationResult, VerifierError>;
+}
+
+/// In-memory revm executor of `snark_verifier`
+#[derive(Default)]
+pub struct RevmBackend {
+    // the executor type of `snark_verifier` isn't exported, so it is kept inside the closure
+    call: Option<Box<dyn FnMut(Vec<u8>) -> SimulationResult>>,
+}
+
+impl EvmBackend for RevmBackend {
+    /// Deploys `deployment_code` from a fixed caller account
+    fn deploy(&mut self, deployment_code: Vec<u8>) -> Result<(), VerifierError> {
+        let mut evm = ExecutorBuilder::default()
+            .with_gas_limit(u64::MAX.into())
+            .build();
+
+        let caller = Address::from_low_u64_be(0xfe);
+        let verifier = evm
+            .deploy(caller, deployment_code.into(), 0.into())
+            .address
+            .ok_or(VerifierError::DeploymentFailed)?;
+        let call = move |calldata: Vec<u8>| {
+            let result = evm.call_raw(caller, verifier, calldata.into(), 0.into());
+            SimulationResult {
+                success: !result.reverted,
+                gas_used: result.gas_used,
+            }
+        };
+        self.call = Some(Box::new(call));
+        Ok(())
+    }
+
+    fn call(&mut self, calldata: Vec<u8>) -> Result<SimulationResult, VerifierError> {
+        let call = self
+            .call
+            .as_mut()
+            .ok_or_else(|| VerifierError::EvmBackend("no verifier deployed".to_string()))?;
+        Ok(call(calldata))
+    }
+}
+
+/// Verifier contract deployed in an EVM backend, by default the in-memory revm executor
+pub struct EvmSimulator<B: EvmBackend = RevmBackend> {
+    backend: B,
+}
+
+impl EvmSimulator {
+    /// Deploys `deployment_code` in a fresh in-memory EVM
+    pub fn deploy(deployment_code: Vec<u8>) -> Result<Self, VerifierError> {
+        Self::with_backend(RevmBackend::default(), deployment_code)
+    }
+}
+
+impl<B: EvmBackend> EvmSimulator<B> {
+    /// Deploys `deployment_code` in `backend`
+    pub fn with_backend(mut backend: B, deployment_code: Vec<u8>) -> Result<Self, VerifierError> {
+        backend.deploy(deployment_code)?;
+        Ok(Self { backend })
+    }
+
+    pub fn backend(&self) -> &B {
+        &self.backend
+    }
+
+    /// Calls the verifier with raw calldata
+    pub fn call(&mut self, calldata: Vec<u8>) -> Result<SimulationResult, VerifierError> {
+        self.backend.call(calldata)
+    }
+
+    /// Verifies `proof` of `instances` with a verifier reading the instances first
+    pub fn verify(
+        &mut self,
+        instances: &OrderedInstances,
+        proof: &[u8],
+    ) -> Result<SimulationResult, VerifierError> {
+        self.verify_with_layout(instances, proof, CalldataLayout::InstancesFirst)
+    }
+
+    /// Same as `verify`, for a verifier reading the calldata in `layout`
+    pub fn verify_with_layout(
+        &mut self,
+        instances: &OrderedInstances,
+        proof: &[u8],
+        layout: CalldataLayout,
+    ) -> Result<SimulationResult, VerifierError> {
+        self.call(instances.encode_calldata_with_layout(proof, layout))
+    }
+
+    /// Verifies every `(instances, proof)` pair, returning the results in the same order
+    pub fn verify_many<'a>(
+        &mut self,
+        vectors: impl IntoIterator<Item = (&'a OrderedInstances, &'a [u8])>,
+    ) -> Result<Vec<SimulationResult>, VerifierError> {
+        vectors
+            .into_iter()
+            .map(|(instances, proof)| self.verify(instances, proof))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EvmBackend, EvmSimulator, RevmBackend, SimulationResult};
+    use crate::snark::error::VerifierError;
+    use crate::snark::instances::OrderedInstances;
+
+    /// Backend accepting the calldata of even length, recording the calls it receives
+    #[derive(Default)]
+    struct RecordingBackend {
+        deployed: Vec<Vec<u8>>,
+        calls: Vec<Vec<u8>>,
+    }
+
+    impl EvmBackend for RecordingBackend {
+        fn deploy(&mut self, deployment_code: Vec<u8>) -> Result<(), VerifierError> {
+            self.deployed.push(deployment_code);
+            Ok(())
+        }
+
+        fn call(&mut self, calldata: Vec<u8>) -> Result<SimulationResult, VerifierError> {
+            let success = calldata.len() % 2 == 0;
+            self.calls.push(calldata);
+            Ok(SimulationResult {
+                success,
+                gas_used: 1,
+            })
+        }
+    }
+
+    /// Deployment code returning `runtime` as the code of the contract
+    fn deployment_code(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        // CODECOPY(0, 12, len) RETURN(0, len)
+        let mut code = vec![
+            0x60, len, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, len, 0x60, 0x00, 0xf3,
+        ];
+        code.extend_from_slice(runtime);
+        code
+    }
+
+    #[test]
+    fn test_simulator_reuses_deployment() {
+        // STOP
+        let mut simulator = EvmSimulator::deploy(deployment_code(&[0x00])).unwrap();
+        let proofs = vec![vec![1u8; 32], vec![2u8; 64], vec![3u8; 32]];
+        let instances = OrderedInstances::new(vec![]);
+        let results = simulator
+            .verify_many(proofs.iter().map(|proof| (&instances, proof.as_slice())))
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| result.success));
+        assert_eq!(results[0].gas_used, results[2].gas_used);
+
+        // REVERT(0, 0)
+        let mut simulator =
+            EvmSimulator::deploy(deployment_code(&[0x60, 0x00, 0x80, 0xfd])).unwrap();
+        assert!(
+            !simulator
+                .verify(&OrderedInstances::new(vec![]), &[1u8; 32])
+                .unwrap()
+                .success
+        );
+    }
+
+    #[test]
+    fn test_simulator_with_backend() {
+        let mut simulator =
+            EvmSimulator::with_backend(RecordingBackend::default(), vec![0xfe]).unwrap();
+        let instances = OrderedInstances::new(vec![]);
+        assert!(simulator.verify(&instances, &[1, 2]).unwrap().success);
+        assert!(!simulator.call(vec![3]).unwrap().success);
+        let backend = simulator.backend();
+        assert_eq!(backend.deployed, vec![vec![0xfe]]);
+        assert_eq!(
+            backend.calls,
+            vec![instances.encode_calldata(&[1, 2]), vec![3]]
+        );
+    }
+
+    #[test]
+    fn test_revm_backend_call_before_deploy() {
+        assert!(matches!(
+            RevmBackend::default().call(vec![]),
+            Err(VerifierError::EvmBackend(_))
+        ));
+    }
+}
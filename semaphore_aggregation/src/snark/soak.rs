@@ -0,0 +1,100 @@
+//! Memory tracking of the soak test of `src/bin/soak.rs`, which wraps proofs in a loop to find
+//! allocations growing with the number of proofs, e.g. in the circuit, the loaders or the
+//! caches of the pipeline. The first proofs fill the SRS and keygen caches, so the growth is
+//! only estimated past a warmup.
+use std::fs;
+
+/// Resident set size of the process in bytes, read from `/proc/self/status`. `None` on
+/// platforms without procfs.
+pub fn rss_bytes() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim();
+    kb.parse::<u64>().ok().map(|kb| kb * 1024)
+}
+
+/// Resident set sizes sampled after every proof
+#[derive(Clone, Debug, Default)]
+pub struct MemoryTracker {
+    samples: Vec<u64>,
+    warmup: usize,
+}
+
+impl MemoryTracker {
+    /// Tracker ignoring the first `warmup` samples in the growth estimate
+    pub fn new(warmup: usize) -> Self {
+        Self {
+            samples: vec![],
+            warmup,
+        }
+    }
+
+    pub fn record(&mut self, rss: u64) {
+        self.samples.push(rss);
+    }
+
+    pub fn samples(&self) -> &[u64] {
+        &self.samples
+    }
+
+    pub fn peak(&self) -> Option<u64> {
+        self.samples.iter().max().copied()
+    }
+
+    /// Growth of the resident set size per proof past the warmup, as the least squares slope
+    /// of the samples, which tolerates the noise of the allocator. `None` until two samples
+    /// are past the warmup.
+    pub fn growth_per_iteration(&self) -> Option<f64> {
+        let samples = self.samples.get(self.warmup..)?;
+        if samples.len() < 2 {
+            return None;
+        }
+        let n = samples.len() as f64;
+        let mean_x = (n - 1.0) / 2.0;
+        let mean_y = samples.iter().map(|&rss| rss as f64).sum::<f64>() / n;
+        let (covariance, variance) =
+            samples
+                .iter()
+                .enumerate()
+                .fold((0.0, 0.0), |(covariance, variance), (i, &rss)| {
+                    let dx = i as f64 - mean_x;
+                    (covariance + dx * (rss as f64 - mean_y), variance + dx * dx)
+                });
+        Some(covariance / variance)
+    }
+
+    /// Whether the resident set size grows by more than `max_growth` bytes per proof past the
+    /// warmup
+    pub fn leaks(&self, max_growth: u64) -> bool {
+        matches!(self.growth_per_iteration(), Some(growth) if growth > max_growth as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryTracker;
+
+    #[test]
+    fn test_memory_growth() {
+        const MB: u64 = 1 << 20;
+        let mut tracker = MemoryTracker::new(2);
+        // the caches fill during the warmup, then the resident set size only fluctuates
+        for rss in [100, 900, 1000, 1002, 999, 1001, 1000] {
+            tracker.record(rss * MB);
+        }
+        assert!(tracker.growth_per_iteration().unwrap().abs() < MB as f64);
+        assert!(!tracker.leaks(MB));
+        assert_eq!(tracker.peak(), Some(1002 * MB));
+
+        let mut leaking = MemoryTracker::new(2);
+        for i in 0..10 {
+            leaking.record((1000 + 8 * i) * MB);
+        }
+        assert!(leaking.leaks(MB));
+        assert!(MemoryTracker::new(2).growth_per_iteration().is_none());
+    }
+}
@@ -0,0 +1,270 @@
+//! Compilation of the Yul source of the EVM verifier with a pinned solc, so that the deployment
+//! code of a verifier can be reproduced from its manifest. With the `svm` feature the pinned
+//! version is installed with svm-rs, otherwise the solc of the host must be the pinned version.
+//!
+//! The solc of the host is the one at `$SOLC` if set, else the first found in the `PATH` or in
+//! the usual install locations of Linux, macOS (Homebrew) and Windows (the Solidity installer,
+//! Scoop, Chocolatey), or among the versions installed by svm-rs.
+use std::env;
+use std::ffi::OsString;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use super::error::VerifierError;
+
+/// Environment variable holding the path of the solc of the host
+pub const SOLC_ENV: &str = "SOLC";
+
+/// File name of the solc binary
+const SOLC_BINARY: &str = if cfg!(windows) { "solc.exe" } else { "solc" };
+
+/// A solc binary and its version
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solc {
+    path: PathBuf,
+    version: String,
+}
+
+impl Solc {
+    /// The solc at `$SOLC`, or the first one of `candidates` that runs
+    pub fn host() -> Result<Self, VerifierError> {
+        if let Some(path) = env::var_os(SOLC_ENV) {
+            return Self::at(path);
+        }
+        candidates()
+            .into_iter()
+            .find_map(|path| Self::at(path).ok())
+            .ok_or_else(|| {
+                VerifierError::Solc(format!(
+                    "solc not found, install it or set {SOLC_ENV} to its path"
+                ))
+            })
+    }
+
+    /// The solc binary at `path`
+    pub fn at(path: impl Into<PathBuf>) -> Result<Self, VerifierError> {
+        let path = path.into();
+        let version = read_version(&path)?;
+        Ok(Self { path, version })
+    }
+
+    /// Solc of version `version`, e.g. `0.8.19`, installed with svm-rs if needed
+    #[cfg(feature = "svm")]
+    pub fn pinned(version: &str) -> Result<Self, VerifierError> {
+        let parsed = semver::Version::parse(version)
+            .map_err(|e| VerifierError::Solc(format!("invalid solc version {version}: {e}")))?;
+        let path = svm::blocking_install(&parsed)
+            .map_err(|e| VerifierError::Solc(format!("failed to install solc {version}: {e}")))?;
+        Self::at(path)
+    }
+
+    /// The solc of the host, which must be of version `version`. Without `$SOLC`, the first
+    /// of `candidates` of this version is picked, so that it may be installed next to others.
+    #[cfg(not(feature = "svm"))]
+    pub fn pinned(version: &str) -> Result<Self, VerifierError> {
+        if env::var_os(SOLC_ENV).is_none() {
+            if let Some(solc) = candidates()
+                .into_iter()
+                .filter_map(|path| Self::at(path).ok())
+                .find(|solc| solc.version == version)
+            {
+                return Ok(solc);
+            }
+        }
+        Self::host()?.expect_version(version)
+    }
+
+    /// The solc at `path` if set, which must be of `version` if set, else the pinned solc if
+    /// `version` is set, else the one of the host
+    pub fn resolve(version: Option<&str>, path: Option<&Path>) -> Result<Self, VerifierError> {
+        match (version, path) {
+            (Some(version), Some(path)) => Self::at(path)?.expect_version(version),
+            (None, Some(path)) => Self::at(path),
+            (Some(version), None) => Self::pinned(version),
+            (None, None) => Self::host(),
+        }
+    }
+
+    fn expect_version(self, version: &str) -> Result<Self, VerifierError> {
+        if self.version != version {
+            return Err(VerifierError::SolcVersionMismatch {
+                expected: version.to_string(),
+                found: self.version,
+            });
+        }
+        Ok(self)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Version of the compiler, without its commit, e.g. `0.8.19`
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Deployment code of `yul_code`
+    pub fn compile_yul(&self, yul_code: &str) -> Result<Vec<u8>, VerifierError> {
+        let mut child = Command::new(&self.path)
+            .args(["--bin", "--yul", "-"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| VerifierError::Solc(format!("failed to run solc: {e}")))?;
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(yul_code.as_bytes())
+            .map_err(|e| VerifierError::Solc(format!("failed to write to solc: {e}")))?;
+        let output = child
+            .wait_with_output()
+            .map_err(|e| VerifierError::Solc(format!("failed to run solc: {e}")))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let binary = stdout
+            .split("Binary representation:")
+            .nth(1)
+            .map(str::trim)
+            .filter(|binary| output.status.success() && !binary.is_empty())
+            .ok_or_else(|| VerifierError::Solc(String::from_utf8_lossy(&output.stderr).into()))?;
+        hex::decode(binary).map_err(|e| VerifierError::Solc(format!("invalid solc output: {e}")))
+    }
+}
+
+/// Paths solc is looked for at, in order: the directories of the `PATH`, the usual install
+/// locations of the platform, then the versions installed by svm-rs, the latest first
+pub fn candidates() -> Vec<PathBuf> {
+    let mut candidates = env::var_os("PATH")
+        .map(|path| {
+            env::split_paths(&path)
+                .map(|dir| dir.join(SOLC_BINARY))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    candidates.extend(install_locations(
+        home_dir(),
+        env::var_os("ProgramFiles"),
+        env::var_os("ProgramData"),
+    ));
+    candidates.retain(|path| path.is_file());
+    candidates.dedup();
+    candidates
+}
+
+/// Home directory of the user, `%USERPROFILE%` on Windows
+fn home_dir() -> Option<PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    env::var_os(var)
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Usual install locations of solc, whether or not they exist, the svm-rs ones excepted
+fn install_locations(
+    home: Option<PathBuf>,
+    program_files: Option<OsString>,
+    program_data: Option<OsString>,
+) -> Vec<PathBuf> {
+    let mut locations = vec![];
+    if cfg!(windows) {
+        locations.extend(program_files.map(|dir| PathBuf::from(dir).join("solidity")));
+        locations.extend(program_data.map(|dir| PathBuf::from(dir).join("chocolatey").join("bin")));
+        locations.extend(home.iter().map(|home| home.join("scoop").join("shims")));
+    } else {
+        // Homebrew on Apple silicon, then on Intel and Linux
+        locations.push(PathBuf::from("/opt/homebrew/bin"));
+        locations.push(PathBuf::from("/usr/local/bin"));
+        locations.push(PathBuf::from("/usr/bin"));
+        locations.push(PathBuf::from("/snap/bin"));
+    }
+    let mut locations = locations
+        .into_iter()
+        .map(|dir| dir.join(SOLC_BINARY))
+        .collect::<Vec<_>>();
+    // `~/.svm/{version}/solc-{version}`
+    if let Some(svm_dir) = home.map(|home| home.join(".svm")) {
+        let mut versions = std::fs::read_dir(svm_dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| {
+                let version = entry.file_name().into_string().ok()?;
+                let binary = format!("solc-{version}{}", if cfg!(windows) { ".exe" } else { "" });
+                Some((version, entry.path().join(binary)))
+            })
+            .collect::<Vec<_>>();
+        versions.sort_by_key(|(version, _)| version_key(version));
+        locations.extend(versions.into_iter().rev().map(|(_, path)| path));
+    }
+    locations
+}
+
+/// Numeric components of `version`, ordering `0.8.19` after `0.8.9`
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect()
+}
+
+fn read_version(path: &Path) -> Result<String, VerifierError> {
+    let output = Command::new(path)
+        .arg("--version")
+        .output()
+        .map_err(|e| VerifierError::Solc(format!("failed to run {}: {e}", path.display())))?;
+    parse_version(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| VerifierError::Solc(format!("{} didn't report its version", path.display())))
+}
+
+/// Version reported by `solc --version`, e.g. `0.8.19` for
+/// `Version: 0.8.19+commit.7dd6d404.Linux.g++`
+fn parse_version(output: &str) -> Option<String> {
+    let line = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Version: "))?;
+    let version = line.split(['+', '-']).next()?.trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{install_locations, parse_version, version_key, SOLC_BINARY};
+
+    #[test]
+    fn test_parse_solc_version() {
+        let output = "solc, the solidity compiler commandline interface\n\
+                      Version: 0.8.19+commit.7dd6d404.Linux.g++\n";
+        assert_eq!(parse_version(output).as_deref(), Some("0.8.19"));
+        assert_eq!(
+            parse_version("Version: 0.8.24-develop.2024.1.1"),
+            Some("0.8.24".into())
+        );
+        assert_eq!(parse_version("solc"), None);
+        // solc.exe ends its lines with CRLF
+        assert_eq!(
+            parse_version("Version: 0.8.19+commit.7dd6d404.Windows.msvc\r\n").as_deref(),
+            Some("0.8.19")
+        );
+    }
+
+    #[test]
+    fn test_solc_install_locations() {
+        let home = PathBuf::from("home");
+        let locations = install_locations(Some(home.clone()), None, None);
+        assert!(!locations.is_empty());
+        assert!(locations
+            .iter()
+            .all(|path| path.file_name().unwrap() == SOLC_BINARY));
+        if cfg!(windows) {
+            assert!(locations.contains(&home.join("scoop").join("shims").join("solc.exe")));
+        } else {
+            assert!(locations.contains(&PathBuf::from("/opt/homebrew/bin/solc")));
+        }
+        assert!(version_key("0.8.19") > version_key("0.8.9"));
+    }
+}
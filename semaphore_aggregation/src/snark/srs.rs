@@ -0,0 +1,504 @@
+//! Cache of KZG parameters of several degrees, so that a service wrapping proofs of varying
+//! sizes doesn't reload or regenerate multi-GB parameters for every request.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::mem::size_of;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use halo2_kzg_srs::{Srs, SrsFormat};
+use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine};
+use halo2_proofs::poly::commitment::Params;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::SerdeFormat;
+use rand::rngs::{OsRng, StdRng};
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+
+use super::envelope::hex_bytes32;
+
+/// Default memory budget of `SrsManager`, enough for the parameters of `k = 23` and a few
+/// smaller degrees
+pub const DEFAULT_MEMORY_BUDGET: usize = 4 << 30;
+
+/// Approximate memory used by `ParamsKZG` of degree `k`, which holds `2^k` points in monomial
+/// and `2^k` points in Lagrange basis
+pub fn params_size(k: u32) -> usize {
+    2 * (1usize << k) * size_of::<G1Affine>()
+}
+
+/// Source of the SRS of locally generated parameters
+pub const GENERATED_SOURCE: &str = "generated";
+
+/// Source of the SRS of parameters read from the reader of `SrsSource::Custom`
+pub const CUSTOM_SOURCE: &str = "custom";
+
+/// Ceremony of the SRS of `SrsSource::PerpetualPowersOfTau`
+pub const PPOT_CEREMONY: &str = "perpetual-powers-of-tau";
+
+/// Degree of the response files of the Perpetual Powers of Tau ceremony
+pub const PPOT_K: u32 = 23;
+
+/// Provenance of the SRS the parameters were derived from, exported with the verifier so that
+/// auditors can check it was built from a trusted ceremony
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SrsMetadata {
+    /// URL or path the SRS was loaded from, or `GENERATED_SOURCE`
+    pub source: String,
+    /// Name of the ceremony the SRS comes from, if any
+    pub ceremony: Option<String>,
+    /// Degree of the SRS as loaded, before any downsizing
+    pub k: u32,
+    /// Keccak256 of the SRS in the `ParamsKZG` serialization
+    #[serde(with = "hex_bytes32")]
+    pub hash: [u8; 32],
+}
+
+impl SrsMetadata {
+    pub fn new(
+        source: impl Into<String>,
+        ceremony: Option<String>,
+        params: &ParamsKZG<Bn256>,
+    ) -> Self {
+        Self {
+            source: source.into(),
+            ceremony,
+            k: params.k(),
+            hash: params_hash(params),
+        }
+    }
+
+    /// Metadata of parameters generated locally, with randomness whose toxic waste isn't
+    /// destroyed
+    pub fn generated(params: &ParamsKZG<Bn256>) -> Self {
+        Self::new(GENERATED_SOURCE, None, params)
+    }
+
+    pub fn is_generated(&self) -> bool {
+        self.source == GENERATED_SOURCE
+    }
+}
+
+/// Parameters generated in this process. Whoever can read its memory knows the toxic waste and
+/// can forge proofs, so a verifier generated from them must never be deployed in production.
+pub struct InsecureTestSrs {
+    params: ParamsKZG<Bn256>,
+}
+
+impl InsecureTestSrs {
+    pub fn generate(k: u32) -> Self {
+        Self {
+            params: ParamsKZG::<Bn256>::setup(k, OsRng),
+        }
+    }
+
+    /// Parameters generated from `seed`, whose toxic waste anyone can recompute. Only fit for
+    /// fixtures, which need the same verifier at every generation.
+    pub fn from_seed(k: u32, seed: u64) -> Self {
+        Self {
+            params: ParamsKZG::<Bn256>::setup(k, StdRng::seed_from_u64(seed)),
+        }
+    }
+
+    pub fn params(&self) -> &ParamsKZG<Bn256> {
+        &self.params
+    }
+}
+
+/// Parameters obtained from a trusted setup ceremony, the only ones a production verifier may be
+/// generated from
+pub struct CeremonySrs {
+    params: ParamsKZG<Bn256>,
+    metadata: SrsMetadata,
+}
+
+impl CeremonySrs {
+    /// Asserts that `params`, loaded from `source`, come from `ceremony`
+    pub fn new(
+        params: ParamsKZG<Bn256>,
+        source: impl Into<String>,
+        ceremony: impl Into<String>,
+    ) -> Self {
+        let metadata = SrsMetadata::new(source, Some(ceremony.into()), &params);
+        Self { params, metadata }
+    }
+
+    pub fn params(&self) -> &ParamsKZG<Bn256> {
+        &self.params
+    }
+
+    pub fn metadata(&self) -> &SrsMetadata {
+        &self.metadata
+    }
+}
+
+/// Keccak256 of the serialization of `params`, streamed so that the multi-GB encoding of large
+/// parameters isn't buffered
+pub fn params_hash(params: &ParamsKZG<Bn256>) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    params
+        .write(&mut hasher)
+        .expect("writing to a hasher doesn't fail");
+    hasher.finalize().into()
+}
+
+/// Encoding of the points of serialized parameters
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PointEncoding {
+    /// Compressed points, half the size of uncompressed ones. Every point is decompressed and
+    /// checked to be on the curve when read, which is slower.
+    Compressed,
+    /// Uncompressed points, read without any check
+    #[default]
+    Uncompressed,
+}
+
+impl PointEncoding {
+    fn serde_format(self) -> SerdeFormat {
+        match self {
+            PointEncoding::Compressed => SerdeFormat::Processed,
+            PointEncoding::Uncompressed => SerdeFormat::RawBytesUnchecked,
+        }
+    }
+}
+
+/// Reads parameters with points encoded as `encoding`, decoding them as they are read
+pub fn read_params(
+    reader: &mut impl Read,
+    encoding: PointEncoding,
+) -> io::Result<ParamsKZG<Bn256>> {
+    ParamsKZG::read_custom(reader, encoding.serde_format())
+}
+
+pub fn write_params(
+    params: &ParamsKZG<Bn256>,
+    writer: &mut impl Write,
+    encoding: PointEncoding,
+) -> io::Result<()> {
+    params.write_custom(writer, encoding.serde_format())
+}
+
+/// Reads the parameters of the file at `path` through a buffer, without loading the whole
+/// file in memory first
+pub fn read_params_file(path: &Path, encoding: PointEncoding) -> io::Result<ParamsKZG<Bn256>> {
+    read_params(&mut BufReader::new(File::open(path)?), encoding)
+}
+
+pub fn write_params_file(
+    params: &ParamsKZG<Bn256>,
+    path: &Path,
+    encoding: PointEncoding,
+) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_params(params, &mut writer, encoding)?;
+    writer.flush()
+}
+
+/// Reads powers of tau of degree `k` in the format of the Perpetual Powers of Tau ceremony.
+/// `halo2_kzg_srs` panics on truncated or malformed input, which is returned as an
+/// `InvalidData` error instead.
+fn read_powers_of_tau(reader: &mut impl Read, k: u32) -> io::Result<ParamsKZG<Bn256>> {
+    let srs = catch_unwind(AssertUnwindSafe(|| {
+        Srs::<Bn256>::read(reader, SrsFormat::PerpetualPowerOfTau(k))
+    }))
+    .map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("malformed powers of tau of degree {k}"),
+        )
+    })?;
+    let mut buf = Vec::new();
+    srs.write_raw(&mut buf);
+    ParamsKZG::read(&mut io::Cursor::new(buf))
+}
+
+/// Layout of the SRS of a bespoke ceremony
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CustomSrsFormat {
+    /// `ParamsKZG` serialization, as written by `write_params` with points encoded as `encoding`
+    Params(PointEncoding),
+    /// Powers of tau of degree `k` in the format of the Perpetual Powers of Tau ceremony, as
+    /// output by its software run for another ceremony
+    PowersOfTau { k: u32 },
+}
+
+/// SRS of a trusted setup ceremony, see `verifier_api::load_srs`
+pub enum SrsSource {
+    /// Response file of the Perpetual Powers of Tau ceremony at the path, of degree `PPOT_K`
+    PerpetualPowersOfTau(PathBuf),
+    /// SRS of a bespoke ceremony, e.g. an MPC run by the operator, read from `reader` in
+    /// `format`. The SRS is trusted as coming from `ceremony`, which is recorded in the metadata
+    /// exported with the verifier.
+    Custom {
+        reader: Box<dyn Read>,
+        format: CustomSrsFormat,
+        ceremony: String,
+    },
+}
+
+impl SrsSource {
+    pub fn read(self) -> io::Result<CeremonySrs> {
+        match self {
+            SrsSource::PerpetualPowersOfTau(path) => {
+                let mut reader = BufReader::new(File::open(&path)?);
+                let params = read_powers_of_tau(&mut reader, PPOT_K)?;
+                Ok(CeremonySrs::new(
+                    params,
+                    path.to_string_lossy(),
+                    PPOT_CEREMONY,
+                ))
+            }
+            SrsSource::Custom {
+                mut reader,
+                format,
+                ceremony,
+            } => {
+                let params = match format {
+                    CustomSrsFormat::Params(encoding) => read_params(&mut reader, encoding)?,
+                    CustomSrsFormat::PowersOfTau { k } => read_powers_of_tau(&mut reader, k)?,
+                };
+                Ok(CeremonySrs::new(params, CUSTOM_SOURCE, ceremony))
+            }
+        }
+    }
+}
+
+struct Entry {
+    params: Arc<ParamsKZG<Bn256>>,
+    last_used: u64,
+}
+
+/// Holds `ParamsKZG` of several degrees. Parameters of a missing degree are obtained by
+/// downsizing the smallest cached parameters of a larger degree. When the cached parameters
+/// exceed the memory budget, the least recently used ones are evicted.
+pub struct SrsManager {
+    entries: BTreeMap<u32, Entry>,
+    memory_budget: usize,
+    clock: u64,
+    metadata: Option<SrsMetadata>,
+    downsizes: usize,
+}
+
+impl Default for SrsManager {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_BUDGET)
+    }
+}
+
+impl SrsManager {
+    pub fn new(memory_budget: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            memory_budget,
+            clock: 0,
+            metadata: None,
+            downsizes: 0,
+        }
+    }
+
+    /// Provenance of the SRS inserted with `insert_insecure` or `insert_ceremony`, shared by all
+    /// the parameters derived from it
+    pub fn metadata(&self) -> Option<&SrsMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Whether the parameters come from a ceremony. Parameters inserted with `insert` have no
+    /// known provenance and are not.
+    pub fn is_ceremony(&self) -> bool {
+        self.metadata
+            .as_ref()
+            .map_or(false, |metadata| !metadata.is_generated())
+    }
+
+    pub fn insert_insecure(&mut self, srs: InsecureTestSrs) -> Arc<ParamsKZG<Bn256>> {
+        self.metadata = Some(SrsMetadata::generated(&srs.params));
+        self.insert(srs.params)
+    }
+
+    pub fn insert_ceremony(&mut self, srs: CeremonySrs) -> Arc<ParamsKZG<Bn256>> {
+        self.metadata = Some(srs.metadata);
+        self.insert(srs.params)
+    }
+
+    pub fn memory_budget(&self) -> usize {
+        self.memory_budget
+    }
+
+    /// Approximate memory used by the cached parameters
+    pub fn memory_usage(&self) -> usize {
+        self.entries.keys().map(|k| params_size(*k)).sum()
+    }
+
+    /// Degrees of the cached parameters, in increasing order
+    pub fn cached_degrees(&self) -> Vec<u32> {
+        self.entries.keys().copied().collect()
+    }
+
+    /// Number of parameters obtained by downsizing, each computing its Lagrange basis with an
+    /// FFT over the points of the SRS
+    pub fn downsizes(&self) -> usize {
+        self.downsizes
+    }
+
+    /// Largest degree that can be served, either cached or by downsizing
+    pub fn max_k(&self) -> Option<u32> {
+        self.entries.keys().next_back().copied()
+    }
+
+    /// Adds `params` to the cache, replacing parameters of the same degree
+    pub fn insert(&mut self, params: ParamsKZG<Bn256>) -> Arc<ParamsKZG<Bn256>> {
+        let k = params.k();
+        let params = Arc::new(params);
+        self.clock += 1;
+        self.entries.insert(
+            k,
+            Entry {
+                params: params.clone(),
+                last_used: self.clock,
+            },
+        );
+        self.evict(k);
+        params
+    }
+
+    /// Returns the parameters of degree `k`, downsizing cached parameters of a larger degree if
+    /// needed. Returns `None` if no cached parameters have degree at least `k`.
+    pub fn get(&mut self, k: u32) -> Option<Arc<ParamsKZG<Bn256>>> {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(&k) {
+            entry.last_used = self.clock;
+            return Some(entry.params.clone());
+        }
+        let larger = self.entries.range_mut(k..).next()?.1;
+        larger.last_used = self.clock;
+        let mut params = larger.params.as_ref().clone();
+        params.downsize(k);
+        self.downsizes += 1;
+        Some(self.insert(params))
+    }
+
+    /// Evicts least recently used parameters until the cache fits the memory budget, never
+    /// evicting the parameters of degree `keep`
+    fn evict(&mut self, keep: u32) {
+        while self.memory_usage() > self.memory_budget {
+            let lru = self
+                .entries
+                .iter()
+                .filter(|(k, _)| **k != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(k, _)| *k);
+            match lru {
+                Some(k) => {
+                    self.entries.remove(&k);
+                }
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::commitment::Params;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::OsRng;
+
+    use super::{
+        params_hash, params_size, read_params, write_params, CeremonySrs, CustomSrsFormat,
+        InsecureTestSrs, PointEncoding, SrsManager, SrsSource, CUSTOM_SOURCE,
+    };
+
+    #[test]
+    fn test_srs_manager_downsizes() {
+        let mut manager = SrsManager::default();
+        manager.insert(ParamsKZG::<Bn256>::setup(6, OsRng));
+        assert!(manager.get(7).is_none());
+        let params = manager.get(4).unwrap();
+        assert_eq!(params.k(), 4);
+        assert_eq!(manager.cached_degrees(), vec![4, 6]);
+    }
+
+    #[test]
+    fn test_srs_metadata_is_kept_by_downsizing() {
+        let srs = InsecureTestSrs::generate(5);
+        let hash = params_hash(srs.params());
+        let mut manager = SrsManager::default();
+        manager.insert_insecure(srs);
+        manager.get(4).unwrap();
+        let metadata = manager.metadata().unwrap();
+        assert_eq!((metadata.k, metadata.hash), (5, hash));
+        assert!(!manager.is_ceremony());
+
+        let params = ParamsKZG::<Bn256>::setup(5, OsRng);
+        manager.insert_ceremony(CeremonySrs::new(params, "ppot_05.ptau", "ppot"));
+        assert!(manager.is_ceremony());
+    }
+
+    #[test]
+    fn test_compressed_params_round_trip() {
+        let params = ParamsKZG::<Bn256>::setup(4, OsRng);
+        let mut compressed = vec![];
+        write_params(&params, &mut compressed, PointEncoding::Compressed).unwrap();
+        let mut uncompressed = vec![];
+        write_params(&params, &mut uncompressed, PointEncoding::Uncompressed).unwrap();
+        assert!(compressed.len() * 10 < uncompressed.len() * 6);
+
+        let decoded = read_params(&mut compressed.as_slice(), PointEncoding::Compressed).unwrap();
+        assert_eq!(params_hash(&decoded), params_hash(&params));
+    }
+
+    #[test]
+    fn test_custom_srs_source() {
+        let params = ParamsKZG::<Bn256>::setup(4, OsRng);
+        let mut bytes = vec![];
+        write_params(&params, &mut bytes, PointEncoding::Compressed).unwrap();
+        let srs = SrsSource::Custom {
+            reader: Box::new(std::io::Cursor::new(bytes)),
+            format: CustomSrsFormat::Params(PointEncoding::Compressed),
+            ceremony: "in-house-mpc".to_string(),
+        }
+        .read()
+        .unwrap();
+        let metadata = srs.metadata();
+        assert_eq!(metadata.source, CUSTOM_SOURCE);
+        assert_eq!(metadata.ceremony.as_deref(), Some("in-house-mpc"));
+        assert_eq!(metadata.hash, params_hash(&params));
+
+        let mut manager = SrsManager::default();
+        manager.insert_ceremony(srs);
+        assert!(manager.is_ceremony());
+        assert_eq!(manager.max_k(), Some(4));
+    }
+
+    #[test]
+    fn test_truncated_powers_of_tau() {
+        // the 64 bytes hash of the previous contribution, and the first point of a degree 4 SRS
+        let truncated = vec![1u8; 64 + 64];
+        let result = SrsSource::Custom {
+            reader: Box::new(std::io::Cursor::new(truncated)),
+            format: CustomSrsFormat::PowersOfTau { k: 4 },
+            ceremony: "in-house-mpc".to_string(),
+        }
+        .read();
+        assert_eq!(
+            result.err().map(|e| e.kind()),
+            Some(std::io::ErrorKind::InvalidData)
+        );
+    }
+
+    #[test]
+    fn test_srs_manager_evicts_least_recently_used() {
+        let mut manager = SrsManager::new(params_size(6) + params_size(5));
+        manager.insert(ParamsKZG::<Bn256>::setup(6, OsRng));
+        manager.get(5).unwrap();
+        manager.get(6).unwrap();
+        // 4 is derived from 5, which leaves 6 as the least recently used
+        manager.get(4).unwrap();
+        assert_eq!(manager.cached_degrees(), vec![4, 5]);
+        assert!(manager.memory_usage() <= manager.memory_budget());
+    }
+}
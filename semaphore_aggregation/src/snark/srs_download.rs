@@ -0,0 +1,343 @@
+//! Download of the multi-GB SRS files of the ceremonies, e.g. the response files of the Perpetual
+//! Powers of Tau read by `SrsSource::PerpetualPowersOfTau`, with the `srs-download` feature.
+//! Downloads often fail midway on CI runners, so the bytes received are kept in a `.partial`
+//! file next to the destination and the download resumes from them with an HTTP range request,
+//! on the next attempt or on the next mirror. The range is requested with an `If-Range` on the
+//! validator, ETag or Last-Modified, of the response the `.partial` file was started from, so
+//! that a mirror serving another file sends it whole instead of bytes to append. The file is only
+//! renamed to the destination once its SHA-256 is the expected one.
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use sha2::{Digest, Sha256};
+
+/// Attempts per mirror before falling back to the next one
+pub const DEFAULT_ATTEMPTS: usize = 3;
+
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Download of an SRS file from the first mirror serving it
+#[derive(Clone, Debug)]
+pub struct SrsDownload {
+    mirrors: Vec<String>,
+    sha256: [u8; 32],
+    attempts: usize,
+    max_bytes_per_sec: Option<u64>,
+    timeout: Duration,
+}
+
+impl SrsDownload {
+    /// Download of the file with SHA-256 `sha256` from the URLs of `mirrors`, tried in order
+    pub fn new(mirrors: impl IntoIterator<Item = impl Into<String>>, sha256: [u8; 32]) -> Self {
+        Self {
+            mirrors: mirrors.into_iter().map(Into::into).collect(),
+            sha256,
+            attempts: DEFAULT_ATTEMPTS,
+            max_bytes_per_sec: None,
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Attempts per mirror, each resuming from the bytes received so far
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Caps the bandwidth of the download, e.g. to share the link of a CI runner
+    pub fn with_max_bytes_per_sec(mut self, max_bytes_per_sec: u64) -> Self {
+        self.max_bytes_per_sec = Some(max_bytes_per_sec);
+        self
+    }
+
+    /// Time after which a connection or read without progress fails the attempt
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Downloads the file to `dest`, returning its size. Does nothing if `dest` exists. Fails if
+    /// no mirror serves a file with the expected SHA-256.
+    pub fn download(&self, dest: &Path) -> io::Result<u64> {
+        if let Ok(metadata) = fs::metadata(dest) {
+            return Ok(metadata.len());
+        }
+        let partial = partial_path(dest);
+        let validator = validator_path(&partial);
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(self.timeout)
+            .timeout_read(self.timeout)
+            .build();
+        let mut errors = vec![];
+        for url in &self.mirrors {
+            for _ in 0..self.attempts {
+                match self.fetch(&agent, url, &partial) {
+                    Ok(size) => {
+                        let sha256 = sha256_file(&partial)?;
+                        if sha256 != self.sha256 {
+                            // the next attempt starts over rather than resuming from bad bytes
+                            let _ = fs::remove_file(&partial);
+                            let _ = fs::remove_file(&validator);
+                            errors.push(format!(
+                                "{url}: SHA-256 {} instead of {}",
+                                hex::encode(sha256),
+                                hex::encode(self.sha256)
+                            ));
+                            continue;
+                        }
+                        let _ = fs::remove_file(&validator);
+                        fs::rename(&partial, dest)?;
+                        return Ok(size);
+                    }
+                    Err(e) => errors.push(format!("{url}: {e}")),
+                }
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "failed to download the SRS from every mirror: {}",
+                errors.join("; ")
+            ),
+        ))
+    }
+
+    /// Fetches the bytes of `url` missing from `partial`, returning the size of the file
+    fn fetch(&self, agent: &ureq::Agent, url: &str, partial: &Path) -> io::Result<u64> {
+        // without a validator, the bytes received can't be told to be of the same file
+        let validator = fs::read_to_string(validator_path(partial)).ok();
+        let offset = match validator {
+            Some(_) => fs::metadata(partial).map_or(0, |metadata| metadata.len()),
+            None => 0,
+        };
+        let mut request = agent.get(url);
+        if let Some(validator) = validator.filter(|_| offset > 0) {
+            request = request
+                .set("Range", &format!("bytes={offset}-"))
+                .set("If-Range", &validator);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            // the range starts at the end of the file, which was fully received
+            Err(ureq::Error::Status(416, _)) if offset > 0 => return Ok(offset),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::Other, e.to_string())),
+        };
+        // a mirror ignoring the range serves the whole file
+        let resumed = response.status() == 206;
+        let start = if resumed { offset } else { 0 };
+        let remaining = response
+            .header("Content-Length")
+            .and_then(|len| len.parse::<u64>().ok());
+        let mut file = if resumed {
+            OpenOptions::new().append(true).open(partial)?
+        } else {
+            // weak ETags can't be used in an `If-Range`
+            let validator = response
+                .header("ETag")
+                .filter(|etag| !etag.starts_with("W/"))
+                .or_else(|| response.header("Last-Modified"));
+            match validator {
+                Some(validator) => fs::write(validator_path(partial), validator)?,
+                None => {
+                    let _ = fs::remove_file(validator_path(partial));
+                }
+            }
+            File::create(partial)?
+        };
+
+        let mut reader = response.into_reader();
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut received = 0u64;
+        let throttle_start = Instant::now();
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])?;
+            received += n as u64;
+            if let Some(max_bytes_per_sec) = self.max_bytes_per_sec {
+                thread::sleep(throttle_delay(
+                    received,
+                    max_bytes_per_sec,
+                    throttle_start.elapsed(),
+                ));
+            }
+        }
+        file.flush()?;
+        if let Some(remaining) = remaining {
+            if received != remaining {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("received {received} of {remaining} bytes"),
+                ));
+            }
+        }
+        Ok(start + received)
+    }
+}
+
+/// File holding the bytes of `dest` received so far
+pub fn partial_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".partial");
+    dest.with_file_name(name)
+}
+
+/// File holding the validator of the response `partial` was started from
+fn validator_path(partial: &Path) -> PathBuf {
+    let mut name = partial.file_name().unwrap_or_default().to_os_string();
+    name.push(".validator");
+    partial.with_file_name(name)
+}
+
+fn sha256_file(path: &Path) -> io::Result<[u8; 32]> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hasher.finalize().into())
+}
+
+/// Time to wait after receiving `received` bytes in `elapsed` to stay under `max_bytes_per_sec`
+fn throttle_delay(received: u64, max_bytes_per_sec: u64, elapsed: Duration) -> Duration {
+    let target = Duration::from_secs_f64(received as f64 / max_bytes_per_sec.max(1) as f64);
+    target.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    use sha2::{Digest, Sha256};
+
+    use super::{partial_path, throttle_delay, SrsDownload};
+
+    /// Serves `file` with ETag `etag` to `connections` requests, honouring their range if their
+    /// `If-Range` is `etag`. The first response is cut after `cut` bytes of body.
+    fn serve(file: Vec<u8>, etag: &'static str, connections: usize, cut: usize) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for i in 0..connections {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut offset = 0;
+                let mut if_range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    let header = line.to_lowercase();
+                    if let Some(range) = header.strip_prefix("range: bytes=") {
+                        offset = range.trim().trim_end_matches('-').parse().unwrap();
+                    }
+                    if let Some(validator) = header.strip_prefix("if-range: ") {
+                        if_range = Some(validator.trim().to_string());
+                    }
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                if if_range.as_deref() != Some(etag) {
+                    offset = 0;
+                }
+                let body = &file[offset..];
+                let status = if offset > 0 {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nETag: {etag}\r\nContent-Length: {}\r\n\
+                     Connection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                let body = if i == 0 { &body[..cut] } else { body };
+                stream.write_all(body).unwrap();
+            }
+        });
+        format!("http://{addr}/srs")
+    }
+
+    fn sha256(bytes: &[u8]) -> [u8; 32] {
+        Sha256::digest(bytes).into()
+    }
+
+    fn destination(name: &str) -> (PathBuf, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("stark-verifier-srs-download-{name}-test"));
+        fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("response");
+        let _ = fs::remove_file(&dest);
+        let _ = fs::remove_file(partial_path(&dest));
+        (dir, dest)
+    }
+
+    #[test]
+    fn test_resumed_download() {
+        let file = (0..100_000u32).map(|i| i as u8).collect::<Vec<_>>();
+        let url = serve(file.clone(), "\"srs\"", 2, 30_000);
+        let (dir, dest) = destination("resumed");
+
+        // the first mirror refuses connections, the first response of the second is cut
+        let download = SrsDownload::new(["http://127.0.0.1:1/srs".to_string(), url], sha256(&file))
+            .with_attempts(2)
+            .with_timeout(Duration::from_secs(5));
+        assert_eq!(download.download(&dest).unwrap(), file.len() as u64);
+        assert_eq!(fs::read(&dest).unwrap(), file);
+        assert!(!partial_path(&dest).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_from_mirrors_serving_different_files() {
+        let stale = vec![1u8; 100_000];
+        let file = (0..100_000u32).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        // the response of the first mirror is cut, the second one doesn't resume from it
+        let stale_url = serve(stale, "\"stale\"", 1, 30_000);
+        let url = serve(file.clone(), "\"srs\"", 1, file.len());
+        let (dir, dest) = destination("mirrors");
+
+        let download = SrsDownload::new([stale_url, url], sha256(&file))
+            .with_attempts(1)
+            .with_timeout(Duration::from_secs(5));
+        assert_eq!(download.download(&dest).unwrap(), file.len() as u64);
+        assert_eq!(fs::read(&dest).unwrap(), file);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_download_checksum_mismatch() {
+        let file = vec![2u8; 10_000];
+        let url = serve(file.clone(), "\"srs\"", 1, file.len());
+        let (dir, dest) = destination("checksum");
+
+        let download = SrsDownload::new([url], sha256(b"another file"))
+            .with_attempts(1)
+            .with_timeout(Duration::from_secs(5));
+        let err = download.download(&dest).unwrap_err();
+        assert!(err.to_string().contains("SHA-256"));
+        assert!(!dest.exists());
+        assert!(!partial_path(&dest).exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_throttle_delay() {
+        assert_eq!(
+            throttle_delay(2_000, 1_000, Duration::from_millis(500)),
+            Duration::from_millis(1_500)
+        );
+        assert_eq!(
+            throttle_delay(1_000, 1_000, Duration::from_secs(3)),
+            Duration::ZERO
+        );
+    }
+}
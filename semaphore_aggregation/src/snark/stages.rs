@@ -0,0 +1,376 @@
+//! Stages of the wrapping pipeline, each behind a trait so that it can be replaced or tested in
+//! isolation, e.g. the simulation of the EVM verifier by a deployment to a live network:
+//! - `Convert`, the Plonky2 proof into the verifier circuit
+//! - `Synthesize`, the circuit into the smallest `k` it fits in, checking it is satisfied
+//! - `Prove`, the SNARK proof of the circuit
+//! - `CompileVerifier`, the Yul source and the deployment code of the EVM verifier
+//! - `Simulate`, the call of the EVM verifier with the proof
+//!
+//! `Pipeline::run` chains them like `verifier_api::verify_inside_snark_with_config`, which runs
+//! the default stages. Keygen, the native verification of the SNARK proof and the compression
+//! of `PipelineConfig::compression` aren't stages, they are fixed by the SNARK of the pipeline.
+//! The stages are `Send + Sync`, since the pipeline runs in its thread pool, see
+//! `PipelineConfig::thread_pool`.
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
+use halo2_proofs::plonk::{Circuit, ProvingKey, VerifyingKey};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+use super::error::VerifierError;
+use super::inner::InnerProofSystem;
+use super::instances::OrderedInstances;
+use super::job::CancellationToken;
+use super::pipeline::PipelineConfig;
+use super::report::VerificationReport;
+use super::simulator::{EvmSimulator, SimulationResult};
+use super::solc::Solc;
+use super::verifier_api::{build_verifier, run_mock_prover, EvmVerifier};
+use super::verifier_circuit::Verifier;
+use crate::ProofTuple;
+
+/// Conversion of a Plonky2 proof into the verifier circuit
+pub trait Convert: Send + Sync {
+    /// Verifier circuit of `proof` with the options of `config`, with the `k` to start from
+    fn convert(
+        &self,
+        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        config: &PipelineConfig,
+    ) -> Result<(Verifier, u32), VerifierError>;
+}
+
+/// Synthesis of a circuit before keygen
+pub trait Synthesize: Send + Sync {
+    /// Smallest `k` between `k` and `max_k` that `circuit` fits in, failing unless `instances`
+    /// satisfy it
+    fn synthesize<C: Circuit<Fr>>(
+        &self,
+        circuit: &C,
+        instances: &OrderedInstances,
+        k: u32,
+        max_k: u32,
+    ) -> Result<u32, VerifierError>;
+}
+
+/// Proving of the SNARK proof checked by the EVM verifier
+pub trait Prove: Send + Sync {
+    /// Proof of `circuit` with `instances`, with the transcript, multi-opening scheme and
+    /// blinding of `config`
+    fn prove<C: Circuit<Fr>>(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        config: &PipelineConfig,
+    ) -> Result<Vec<u8>, VerifierError>;
+}
+
+/// Generation of the EVM verifier. Both steps have default implementations, generating the Yul
+/// source with snark-verifier and compiling it with the solc of `config`.
+pub trait CompileVerifier: Send + Sync {
+    /// Yul source of the verifier of `vk` with the options of `config`, with the arguments to
+    /// append to its deployment code. The instances at `accumulator_indices`, if any, hold an
+    /// accumulator checked along the proof, see `compression`.
+    fn yul(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        accumulator_indices: Option<Vec<(usize, usize)>>,
+        config: &PipelineConfig,
+    ) -> Result<(String, Vec<u8>), VerifierError> {
+        let num_instance = instances.num_instance();
+        EvmVerifier::check_num_instances(&num_instance)?;
+        let (yul_code, constructor_args) = if config.g2_constructor_args {
+            EvmVerifier::gen_evm_verifier_yul_with_g2_args(
+                params,
+                vk,
+                num_instance,
+                accumulator_indices,
+                config.multiopen,
+            )?
+        } else {
+            let yul_code = EvmVerifier::gen_evm_verifier_yul(
+                params,
+                vk,
+                num_instance,
+                accumulator_indices,
+                config.multiopen,
+            );
+            (yul_code, vec![])
+        };
+        let yul_code = config
+            .calldata_layout
+            .remap_yul(&yul_code, instances.len())?;
+        Ok((yul_code, constructor_args))
+    }
+
+    /// Deployment code of `yul_code`, with the version of the compiler
+    fn compile(
+        &self,
+        yul_code: &str,
+        config: &PipelineConfig,
+    ) -> Result<(Vec<u8>, String), VerifierError> {
+        let solc = Solc::resolve(config.solc_version.as_deref(), config.solc_path.as_deref())?;
+        let deployment_code = EvmVerifier::compile(yul_code, &solc)?;
+        Ok((deployment_code, solc.version().to_string()))
+    }
+}
+
+/// Call of the EVM verifier with a proof
+pub trait Simulate: Send + Sync {
+    /// Deploys `deployment_code`, constructor arguments included, and calls it with `calldata`
+    fn simulate(
+        &self,
+        deployment_code: Vec<u8>,
+        calldata: Vec<u8>,
+    ) -> Result<SimulationResult, VerifierError>;
+}
+
+/// Converts with `verifier_api::build_verifier`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DefaultConvert;
+
+impl Convert for DefaultConvert {
+    fn convert(
+        &self,
+        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        config: &PipelineConfig,
+    ) -> Result<(Verifier, u32), VerifierError> {
+        build_verifier(proof, config)
+    }
+}
+
+/// Synthesizes with the mock prover, retrying with a larger `k` when the circuit doesn't fit
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MockSynthesize;
+
+impl Synthesize for MockSynthesize {
+    fn synthesize<C: Circuit<Fr>>(
+        &self,
+        circuit: &C,
+        instances: &OrderedInstances,
+        k: u32,
+        max_k: u32,
+    ) -> Result<u32, VerifierError> {
+        let (k, mock_prover) = run_mock_prover(circuit, instances, k, max_k)?;
+        mock_prover.verify().map_err(VerifierError::Unsatisfied)?;
+        Ok(k)
+    }
+}
+
+/// Proves with the halo2 KZG prover
+#[derive(Clone, Copy, Debug, Default)]
+pub struct KzgProve;
+
+impl Prove for KzgProve {
+    fn prove<C: Circuit<Fr>>(
+        &self,
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        config: &PipelineConfig,
+    ) -> Result<Vec<u8>, VerifierError> {
+        Ok(EvmVerifier::prove_with_transcript(
+            params,
+            pk,
+            circuit,
+            instances,
+            config.multiopen,
+            config.transcript,
+            config.blinding.rng()?,
+        ))
+    }
+}
+
+/// Generates the verifier with snark-verifier and compiles it with solc
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SolcCompile;
+
+impl CompileVerifier for SolcCompile {}
+
+/// Deploys the verifier in a fresh in-memory revm for every call, see `EvmSimulator`
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RevmSimulate;
+
+impl Simulate for RevmSimulate {
+    fn simulate(
+        &self,
+        deployment_code: Vec<u8>,
+        calldata: Vec<u8>,
+    ) -> Result<SimulationResult, VerifierError> {
+        EvmSimulator::deploy(deployment_code)?.call(calldata)
+    }
+}
+
+/// Wrapping pipeline made of its stages, the default ones unless replaced
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline<
+    Cv = DefaultConvert,
+    Sy = MockSynthesize,
+    P = KzgProve,
+    Co = SolcCompile,
+    Si = RevmSimulate,
+> {
+    pub(crate) convert: Cv,
+    pub(crate) synthesize: Sy,
+    pub(crate) prove: P,
+    pub(crate) compile: Co,
+    pub(crate) simulate: Si,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<Cv, Sy, P, Co, Si> Pipeline<Cv, Sy, P, Co, Si> {
+    pub fn with_convert<T: Convert>(self, convert: T) -> Pipeline<T, Sy, P, Co, Si> {
+        Pipeline {
+            convert,
+            synthesize: self.synthesize,
+            prove: self.prove,
+            compile: self.compile,
+            simulate: self.simulate,
+        }
+    }
+
+    pub fn with_synthesize<T: Synthesize>(self, synthesize: T) -> Pipeline<Cv, T, P, Co, Si> {
+        Pipeline {
+            convert: self.convert,
+            synthesize,
+            prove: self.prove,
+            compile: self.compile,
+            simulate: self.simulate,
+        }
+    }
+
+    pub fn with_prove<T: Prove>(self, prove: T) -> Pipeline<Cv, Sy, T, Co, Si> {
+        Pipeline {
+            convert: self.convert,
+            synthesize: self.synthesize,
+            prove,
+            compile: self.compile,
+            simulate: self.simulate,
+        }
+    }
+
+    pub fn with_compile<T: CompileVerifier>(self, compile: T) -> Pipeline<Cv, Sy, P, T, Si> {
+        Pipeline {
+            convert: self.convert,
+            synthesize: self.synthesize,
+            prove: self.prove,
+            compile,
+            simulate: self.simulate,
+        }
+    }
+
+    pub fn with_simulate<T: Simulate>(self, simulate: T) -> Pipeline<Cv, Sy, P, Co, T> {
+        Pipeline {
+            convert: self.convert,
+            synthesize: self.synthesize,
+            prove: self.prove,
+            compile: self.compile,
+            simulate,
+        }
+    }
+}
+
+impl<Cv: Convert, Sy: Synthesize, P: Prove, Co: CompileVerifier, Si: Simulate>
+    Pipeline<Cv, Sy, P, Co, Si>
+{
+    /// Wraps `proof` with the options of `config`
+    pub fn run(
+        &self,
+        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        config: &PipelineConfig,
+    ) -> Result<VerificationReport, VerifierError> {
+        self.run_cancellable(proof, config, &CancellationToken::new())
+    }
+
+    /// Wraps `proof` of `system` with the options of `config`, the circuit of `system` replacing
+    /// the `Convert` stage
+    pub fn run_inner<S>(
+        &self,
+        system: &S,
+        proof: S::Proof,
+        config: &PipelineConfig,
+    ) -> Result<VerificationReport, VerifierError>
+    where
+        S: InnerProofSystem + Sync,
+        S::Proof: Send,
+    {
+        let token = CancellationToken::new();
+        config.install(|| self.run_inner_in_pool(system, proof, config, &token))
+    }
+
+    /// Same as `run`, stopping between two stages once `token` is cancelled or timed out
+    pub fn run_cancellable(
+        &self,
+        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+    ) -> Result<VerificationReport, VerifierError> {
+        config.install(|| self.run_in_pool(proof, config, token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{MockSynthesize, Pipeline, RevmSimulate, Simulate, Synthesize};
+    use crate::snark::error::VerifierError;
+    use crate::snark::instances::OrderedInstances;
+    use crate::snark::pipeline::PipelineConfig;
+    use crate::snark::simulator::SimulationResult;
+    use crate::snark::test_utils;
+
+    /// Records the calldata it is called with, standing for a deployment to a live network
+    #[derive(Default)]
+    struct RecordingSimulate {
+        calldata: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl Simulate for RecordingSimulate {
+        fn simulate(
+            &self,
+            deployment_code: Vec<u8>,
+            calldata: Vec<u8>,
+        ) -> Result<SimulationResult, VerifierError> {
+            self.calldata.lock().unwrap().push(calldata.clone());
+            RevmSimulate.simulate(deployment_code, calldata)
+        }
+    }
+
+    #[test]
+    fn test_pipeline_with_custom_simulation() -> Result<()> {
+        let proof = test_utils::zero_public_inputs_proof()?;
+        let pipeline = Pipeline::new().with_simulate(RecordingSimulate::default());
+        let report = pipeline.run(proof, &PipelineConfig::new().allow_insecure())?;
+        let calldata = pipeline.simulate.calldata.lock().unwrap();
+        assert_eq!(calldata.len(), 1);
+        let instances = report.envelope.instances()?;
+        assert_eq!(
+            calldata[0],
+            instances.encode_calldata(&report.envelope.proof_bytes)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mock_synthesize_unsatisfied() {
+        let synthesize = |product: u64| {
+            let instances = OrderedInstances::new(vec![Fr::from(product)]);
+            MockSynthesize.synthesize(&test_utils::FixedCircuit, &instances, 4, 4)
+        };
+        assert_eq!(synthesize(42).unwrap(), 4);
+        assert!(matches!(synthesize(43), Err(VerifierError::Unsatisfied(_))));
+    }
+}
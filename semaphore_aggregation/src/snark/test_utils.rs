@@ -0,0 +1,382 @@
+//! Builds small Plonky2 circuits and proves them, so that the wrapper can be tested without
+//! external proof files.
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use halo2_proofs::circuit::{Layouter, SimpleFloorPlanner, Value};
+use halo2_proofs::halo2curves::bn256::{Fq, Fr};
+use halo2_proofs::halo2curves::group::ff::PrimeField;
+use halo2_proofs::plonk::{
+    Advice, Challenge, Circuit, Column, ConstraintSystem, Error, FirstPhase, Instance, SecondPhase,
+    Selector,
+};
+use halo2_proofs::poly::Rotation;
+use halo2wrong::RegionCtx;
+use halo2wrong_maingate::{MainGate, MainGateConfig, MainGateInstructions};
+use plonky2::field::extension::Extendable;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::Field;
+use plonky2::gates::noop::NoopGate;
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::iop::witness::{PartialWitness, WitnessWrite};
+use plonky2::plonk::circuit_builder::CircuitBuilder;
+use plonky2::plonk::circuit_data::CircuitConfig;
+use plonky2::plonk::config::PoseidonGoldilocksConfig;
+
+pub use crate::snark::fixtures::wrapper_circuit_config;
+use crate::snark::instances::OrderedInstances;
+use crate::snark::proof_encoding::{ProofItem, ProofLayout};
+use crate::snark::report::VerificationReport;
+use crate::snark::simulator::EvmSimulator;
+use crate::{PoseidonGoldilocksQuarticConfig, ProofTuple};
+
+pub type F = GoldilocksField;
+pub type C = PoseidonGoldilocksConfig;
+pub const D: usize = 2;
+
+/// Minimum number of arithmetic operations added to every test circuit, so that the degree of
+/// the circuit is large enough for the FRI parameters of `wrapper_circuit_config`
+const PADDING_OPS: usize = 1 << 10;
+
+/// `wrapper_circuit_config` with the FRI rate `2^{-rate_bits}`. Plonky2 evaluates the
+/// constraints on the LDE, so their degree is bounded by `2^rate_bits`: Poseidon gates, of degree
+/// 7, need `rate_bits >= 3`.
+pub fn rate_bits_circuit_config(rate_bits: usize) -> CircuitConfig {
+    let mut config = wrapper_circuit_config();
+    config.fri_config.rate_bits = rate_bits;
+    config.max_quotient_degree_factor = (1 << rate_bits).min(8);
+    config
+}
+
+/// Proof of a circuit made of `NoopGate`s with the FRI rate `2^{-rate_bits}`. It has no public
+/// inputs, hence no Poseidon gate, and its constraints are of degree 1, so that it can be proven
+/// with any rate.
+pub fn rate_bits_proof(rate_bits: usize) -> Result<ProofTuple<F, C, D>> {
+    let mut builder = CircuitBuilder::<F, D>::new(rate_bits_circuit_config(rate_bits));
+    for _ in 0..PADDING_OPS {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let data = builder.build::<C>();
+    let proof = data.prove(PartialWitness::new())?;
+    data.verify(proof.clone())?;
+    Ok((proof, data.verifier_only, data.common))
+}
+
+/// Proof of a circuit made of `NoopGate`s over the quartic extension of Goldilocks, which the
+/// verifier circuit doesn't support
+pub fn quartic_proof() -> Result<ProofTuple<F, PoseidonGoldilocksQuarticConfig, 4>> {
+    let mut builder = CircuitBuilder::<F, 4>::new(wrapper_circuit_config());
+    for _ in 0..PADDING_OPS {
+        builder.add_gate(NoopGate, vec![]);
+    }
+    let data = builder.build::<PoseidonGoldilocksQuarticConfig>();
+    let proof = data.prove(PartialWitness::new())?;
+    data.verify(proof.clone())?;
+    Ok((proof, data.verifier_only, data.common))
+}
+
+/// Builds a circuit with `build`, pads it and proves it with the witness filled in by `build`
+pub fn prove_circuit(
+    config: CircuitConfig,
+    build: impl FnOnce(&mut CircuitBuilder<F, D>, &mut PartialWitness<F>),
+) -> Result<ProofTuple<F, C, D>> {
+    let mut builder = CircuitBuilder::<F, D>::new(config);
+    let mut pw = PartialWitness::new();
+    build(&mut builder, &mut pw);
+
+    let x = builder.add_virtual_target();
+    let mut acc = x;
+    for _ in 0..PADDING_OPS {
+        acc = builder.mul(acc, x);
+    }
+    pw.set_target(x, F::TWO);
+
+    let data = builder.build::<C>();
+    let proof = data.prove(pw)?;
+    data.verify(proof.clone())?;
+    Ok((proof, data.verifier_only, data.common))
+}
+
+/// Proof of a circuit without public inputs
+pub fn zero_public_inputs_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |_, _| {})
+}
+
+/// Proof of a circuit exposing `num_public_inputs` public inputs
+pub fn public_inputs_proof(num_public_inputs: usize) -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let targets = builder.add_virtual_targets(num_public_inputs);
+        builder.register_public_inputs(&targets);
+        for (i, target) in targets.into_iter().enumerate() {
+            pw.set_target(target, F::from_canonical_usize(i));
+        }
+    })
+}
+
+/// Proof of a circuit using `ConstantGate`
+pub fn constant_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let x = builder.add_virtual_target();
+        let c = builder.constant(F::from_canonical_u64(0xdeadbeef));
+        let y = builder.add(x, c);
+        builder.register_public_input(y);
+        pw.set_target(x, F::ONE);
+    })
+}
+
+/// Proof of a circuit using `PoseidonGate`
+pub fn poseidon_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let inputs = builder.add_virtual_targets(12);
+        let hash = builder.hash_n_to_hash_no_pad::<PoseidonHash>(inputs.clone());
+        builder.register_public_inputs(&hash.elements);
+        for (i, target) in inputs.into_iter().enumerate() {
+            pw.set_target(target, F::from_canonical_usize(i));
+        }
+    })
+}
+
+/// Proof of a circuit using `BaseSumGate`
+pub fn base_sum_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let x = builder.add_virtual_target();
+        let bits = builder.split_le(x, 64);
+        builder.register_public_input(bits[0].target);
+        pw.set_target(x, F::from_canonical_u64(0x1234_5678_9abc_def1));
+    })
+}
+
+/// Proof of a circuit using `RandomAccessGate`
+pub fn random_access_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let index = builder.add_virtual_target();
+        let vector = builder.add_virtual_targets(2);
+        let element = builder.random_access(index, vector.clone());
+        builder.register_public_input(element);
+        pw.set_target(index, F::ONE);
+        pw.set_target(vector[0], F::from_canonical_u64(3));
+        pw.set_target(vector[1], F::from_canonical_u64(5));
+    })
+}
+
+/// Proof of a circuit using `ArithmeticExtensionGate` and `MulExtensionGate`
+pub fn extension_arithmetic_proof() -> Result<ProofTuple<F, C, D>> {
+    prove_circuit(wrapper_circuit_config(), |builder, pw| {
+        let a = builder.add_virtual_extension_target();
+        let b = builder.add_virtual_extension_target();
+        let ab = builder.mul_extension(a, b);
+        let abb = builder.mul_add_extension(ab, b, a);
+        let product = builder.mul_extension(ab, abb);
+        builder.register_public_inputs(&product.0);
+        pw.set_extension_target(a, <F as Extendable<D>>::Extension::from_canonical_u64(3));
+        pw.set_extension_target(b, <F as Extendable<D>>::Extension::from_canonical_u64(7));
+    })
+}
+
+/// Environment variable which, when set, makes `assert_golden` overwrite the golden files
+pub const UPDATE_GOLDEN: &str = "UPDATE_GOLDEN";
+
+/// Compares `actual` with the golden file `tests/golden/<name>`, failing if the file is missing
+/// or differs. The file is only written when `UPDATE_GOLDEN` is set, so that an intended change
+/// is blessed with `UPDATE_GOLDEN=1 cargo test` and reviewed in the diff of the golden file.
+pub fn assert_golden(name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(name);
+    if std::env::var_os(UPDATE_GOLDEN).is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|e| {
+        panic!(
+            "failed to read {}: {e}, run with {UPDATE_GOLDEN}=1 to create it",
+            path.display()
+        )
+    });
+    assert!(
+        expected == actual,
+        "{} differs from the generated output, rerun with {UPDATE_GOLDEN}=1 if the change is \
+         intended",
+        path.display()
+    );
+}
+
+/// Asserts that both the native verifier and the EVM verifier of `report` reject `proof` with
+/// `instances`, the report being the one of an accepted proof
+pub fn assert_rejects(report: &VerificationReport, instances: &OrderedInstances, proof: &[u8]) {
+    assert!(
+        !matches!(report.verifier_key.verify(instances, proof), Ok(true)),
+        "the native verifier accepts the mutated proof"
+    );
+    let mut simulator = EvmSimulator::deploy(report.deployment_code_with_args().unwrap()).unwrap();
+    assert!(
+        !simulator.verify(instances, proof).unwrap().success,
+        "the EVM verifier accepts the mutated proof"
+    );
+}
+
+/// Copies of `instances` with a single instance incremented, one per instance
+pub fn mutated_instances(instances: &OrderedInstances) -> Vec<OrderedInstances> {
+    (0..instances.len())
+        .map(|i| {
+            let mut mutated = instances.as_slice().to_vec();
+            mutated[i] += Fr::from(1);
+            OrderedInstances::new(mutated)
+        })
+        .collect()
+}
+
+/// Copies of `proof`, a proof of `layout`, with a single item changed while keeping a valid
+/// encoding, one per item: points are negated and scalars incremented, so that the proofs are
+/// rejected by the verification itself rather than by the checks of the encoding
+pub fn mutated_proofs(proof: &[u8], layout: &ProofLayout) -> Vec<Vec<u8>> {
+    let mut offset = 0;
+    layout
+        .items()
+        .iter()
+        .map(|item| {
+            let mut mutated = proof.to_vec();
+            match item {
+                ProofItem::Point => {
+                    let y = &mut mutated[offset + 32..offset + 64];
+                    write_be_word(y, -read_be_word::<Fq>(y));
+                }
+                ProofItem::Scalar => {
+                    let scalar = &mut mutated[offset..offset + 32];
+                    write_be_word(scalar, read_be_word::<Fr>(scalar) + Fr::from(1));
+                }
+            }
+            offset += item.size();
+            mutated
+        })
+        .collect()
+}
+
+fn read_be_word<P: PrimeField<Repr = [u8; 32]>>(word: &[u8]) -> P {
+    let mut repr = [0u8; 32];
+    repr.copy_from_slice(word);
+    repr.reverse();
+    P::from_repr(repr).unwrap()
+}
+
+fn write_be_word<P: PrimeField<Repr = [u8; 32]>>(word: &mut [u8], value: P) {
+    let mut repr = value.to_repr();
+    repr.reverse();
+    word.copy_from_slice(&repr);
+}
+
+/// Circuit exposing the product of two constants, whose verifying key only depends on the
+/// parameters, for golden tests of the generated EVM verifier
+#[derive(Clone, Default)]
+pub struct FixedCircuit;
+
+impl Circuit<Fr> for FixedCircuit {
+    type Config = MainGateConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        MainGate::configure(meta)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let main_gate = MainGate::new(config);
+        let product = layouter.assign_region(
+            || "product",
+            |region| {
+                let ctx = &mut RegionCtx::new(region, 0);
+                let a = main_gate.assign_value(ctx, Value::known(Fr::from(6)))?;
+                let b = main_gate.assign_value(ctx, Value::known(Fr::from(7)))?;
+                main_gate.mul(ctx, &a, &b)
+            },
+        )?;
+        main_gate.expose_public(layouter.namespace(|| "product"), product, 0)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChallengeConfig {
+    value: Column<Advice>,
+    rlc: Column<Advice>,
+    instance: Column<Instance>,
+    selector: Selector,
+    challenge: Challenge,
+}
+
+/// Circuit of two phases, whose second phase column holds the random linear combination of two
+/// values of the first phase with a challenge, the first value being its instance. Stands for
+/// the RLC lookups a verifier circuit could adopt, for tests of multi-phase proving.
+#[derive(Clone, Default)]
+pub struct ChallengeCircuit {
+    pub values: [Fr; 2],
+}
+
+impl Circuit<Fr> for ChallengeCircuit {
+    type Config = ChallengeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        let value = meta.advice_column_in(FirstPhase);
+        let rlc = meta.advice_column_in(SecondPhase);
+        let instance = meta.instance_column();
+        meta.enable_equality(value);
+        meta.enable_equality(instance);
+        let selector = meta.selector();
+        let challenge = meta.challenge_usable_after(FirstPhase);
+        meta.create_gate("rlc", |meta| {
+            let selector = meta.query_selector(selector);
+            let first = meta.query_advice(value, Rotation::cur());
+            let second = meta.query_advice(value, Rotation::next());
+            let rlc = meta.query_advice(rlc, Rotation::cur());
+            let challenge = meta.query_challenge(challenge);
+            vec![selector * (rlc - (first + challenge * second))]
+        });
+        ChallengeConfig {
+            value,
+            rlc,
+            instance,
+            selector,
+            challenge,
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let challenge = layouter.get_challenge(config.challenge);
+        let [first, second] = self.values;
+        let cell = layouter.assign_region(
+            || "rlc",
+            |mut region| {
+                config.selector.enable(&mut region, 0)?;
+                let cell =
+                    region.assign_advice(|| "first", config.value, 0, || Value::known(first))?;
+                region.assign_advice(|| "second", config.value, 1, || Value::known(second))?;
+                region.assign_advice(
+                    || "rlc",
+                    config.rlc,
+                    0,
+                    || challenge.map(|challenge| first + challenge * second),
+                )?;
+                Ok(cell.cell())
+            },
+        )?;
+        layouter.constrain_instance(cell, config.instance, 0)
+    }
+}
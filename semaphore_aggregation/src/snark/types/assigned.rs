@@ -110,11 +110,79 @@ pub struct AssignedProofWithPisValues<F: FieldExt, const D: usize> {
     pub public_inputs: Vec<AssignedValue<F>>,
 }
 
+fn cap_elements<F: FieldExt>(cap: &AssignedMerkleCapValues<F>) -> Vec<&AssignedValue<F>> {
+    cap.0.iter().flat_map(|hash| hash.elements.iter()).collect()
+}
+
+fn extension_elements<F: FieldExt, const D: usize>(
+    values: &[AssignedExtensionFieldValue<F, D>],
+) -> Vec<&AssignedValue<F>> {
+    values.iter().flat_map(|value| value.0.iter()).collect()
+}
+
+impl<F: FieldExt, const D: usize> AssignedProofWithPisValues<F, D> {
+    /// Every Goldilocks element of the public inputs and the proof
+    pub(crate) fn elements(&self) -> Vec<&AssignedValue<F>> {
+        let proof = &self.proof;
+        let openings = &proof.openings;
+        let fri_proof = &proof.opening_proof;
+
+        let mut elements: Vec<&AssignedValue<F>> = self.public_inputs.iter().collect();
+        elements.extend(cap_elements(&proof.wires_cap));
+        elements.extend(cap_elements(&proof.plonk_zs_partial_products_cap));
+        elements.extend(cap_elements(&proof.quotient_polys_cap));
+        for values in [
+            &openings.constants,
+            &openings.plonk_sigmas,
+            &openings.wires,
+            &openings.plonk_zs,
+            &openings.plonk_zs_next,
+            &openings.partial_products,
+            &openings.quotient_polys,
+        ] {
+            elements.extend(extension_elements(values));
+        }
+        for cap in fri_proof.commit_phase_merkle_cap_values.iter() {
+            elements.extend(cap_elements(cap));
+        }
+        for round in fri_proof.query_round_proofs.iter() {
+            for (evals, merkle_proof) in round.initial_trees_proof.evals_proofs.iter() {
+                elements.extend(evals.iter());
+                elements.extend(merkle_proof.siblings.iter().flat_map(|h| h.elements.iter()));
+            }
+            for step in round.steps.iter() {
+                elements.extend(extension_elements(&step.evals));
+                elements.extend(
+                    step.merkle_proof
+                        .siblings
+                        .iter()
+                        .flat_map(|h| h.elements.iter()),
+                );
+            }
+        }
+        elements.extend(extension_elements(&fri_proof.final_poly.0));
+        elements.push(&fri_proof.pow_witness);
+        elements
+    }
+}
+
 pub struct AssignedVerificationKeyValues<F: FieldExt> {
     pub constants_sigmas_cap: AssignedMerkleCapValues<F>,
     pub circuit_digest: AssignedHashValues<F>,
 }
 
+impl<F: FieldExt> AssignedVerificationKeyValues<F> {
+    /// Elements in the order of `VerificationKeyValues::elements`
+    pub fn elements(&self) -> Vec<&AssignedValue<F>> {
+        self.constants_sigmas_cap
+            .0
+            .iter()
+            .chain(std::iter::once(&self.circuit_digest))
+            .flat_map(|hash| hash.elements.iter())
+            .collect()
+    }
+}
+
 #[derive(Clone)]
 pub struct AssignedFriChallenges<F: FieldExt, const D: usize> {
     pub fri_alpha: AssignedExtensionFieldValue<F, D>,
@@ -1,69 +1,59 @@
 use std::ops::{Range, RangeFrom};
 
-use crate::snark::{chip::plonk::gates::CustomGateRef, types::fri::FriOracleInfo};
-
-use super::{fri::FriPolynomialInfo, to_goldilocks};
+use crate::snark::{
+    chip::plonk::gates::CustomGateRef, types::fri::FriOracleInfo, R_F, R_P, T, T_MINUS_ONE,
+};
+
+use super::{
+    error::{ConversionError, ProofComponent},
+    fri::FriPolynomialInfo,
+    try_to_goldilocks,
+};
 use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
 use itertools::Itertools;
-use plonky2::{field::goldilocks_field::GoldilocksField, plonk::circuit_data::CommonCircuitData};
-
-#[derive(Clone, Debug, Default)]
-pub struct FriConfig {
-    /// `rate = 2^{-rate_bits}`.
-    pub rate_bits: usize,
-
-    /// Height of Merkle tree caps.
-    pub cap_height: usize,
-
-    pub proof_of_work_bits: u32,
-
-    /// Number of query rounds to perform.
-    pub num_query_rounds: usize,
-}
-
-#[derive(Clone, Debug, Default)]
-pub struct CircuitConfig {
-    pub num_wires: usize,
-    pub num_routed_wires: usize,
-    pub num_constants: usize,
-    /// Whether to use a dedicated gate for base field arithmetic, rather than using a single gate
-    /// for both base field and extension field arithmetic.
-    pub use_base_arithmetic_gate: bool,
-    pub security_bits: usize,
-    /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
-    /// `degree / |F|`.
-    pub num_challenges: usize,
-    pub zero_knowledge: bool,
-    /// A cap on the quotient polynomial's degree factor. The actual degree factor is derived
-    /// systematically, but will never exceed this value.
-    pub max_quotient_degree_factor: usize,
-    pub fri_config: FriConfig,
-}
-
-#[derive(Clone, Debug, Default)]
-pub struct FriParams {
-    pub config: FriConfig,
-    pub hiding: bool,
-    pub degree_bits: usize,
-    pub reduction_arity_bits: Vec<usize>,
-}
-
-impl FriParams {
-    pub fn lde_bits(&self) -> usize {
-        self.degree_bits + self.config.rate_bits
+use plonky2::{
+    field::{goldilocks_field::GoldilocksField, types::Field},
+    hash::hashing::SPONGE_WIDTH,
+    plonk::circuit_data::CommonCircuitData,
+};
+use poseidon::Spec;
+use stark_verifier_core::error::check_consistent;
+
+pub use stark_verifier_core::common_data::{
+    CircuitConfig, FriConfig, FriParams, FriSoundness, SelectorsInfo,
+};
+
+/// FRI parameters of the Plonky2 circuit of `common_data`
+pub fn fri_params(common_data: &CommonCircuitData<GoldilocksField, 2>) -> FriParams {
+    let fri_config = &common_data.config.fri_config;
+    FriParams {
+        config: FriConfig {
+            rate_bits: fri_config.rate_bits,
+            cap_height: fri_config.cap_height,
+            proof_of_work_bits: fri_config.proof_of_work_bits,
+            num_query_rounds: fri_config.num_query_rounds,
+        },
+        hiding: common_data.fri_params.hiding,
+        degree_bits: common_data.fri_params.degree_bits,
+        reduction_arity_bits: common_data.fri_params.reduction_arity_bits.clone(),
     }
 }
 
-#[derive(Clone, Default, Debug)]
-pub struct SelectorsInfo {
-    pub selector_indices: Vec<usize>,
-    pub groups: Vec<Range<usize>>,
+/// Parameters of the Poseidon permutation used by the Plonky2 hasher
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HasherConfig {
+    pub width: usize,
+    pub full_rounds: usize,
+    pub partial_rounds: usize,
 }
 
-impl SelectorsInfo {
-    pub fn num_selectors(&self) -> usize {
-        self.groups.len()
-    }
+impl HasherConfig {
+    /// Parameters of `PoseidonHash`, the only hasher supported by the verifier circuit
+    pub const POSEIDON: HasherConfig = HasherConfig {
+        width: SPONGE_WIDTH,
+        full_rounds: R_F,
+        partial_rounds: R_P,
+    };
 }
 
 #[derive(Clone, Default)]
@@ -94,6 +84,9 @@ pub struct CommonData<F: FieldExt> {
 
     /// The number of partial products needed to compute the `Z` polynomials.
     pub num_partial_products: usize,
+
+    /// Parameters of the hasher used for Fiat-Shamir and Merkle trees.
+    pub hasher: HasherConfig,
 }
 
 /// Holds the Merkle tree index and blinding flag of a set of polynomials used in FRI.
@@ -131,6 +124,15 @@ impl<F: FieldExt> CommonData<F> {
         1 << self.degree_bits()
     }
 
+    /// Poseidon spec matching the hasher of the Plonky2 proof.
+    pub fn spec(&self) -> Spec<Goldilocks, T, T_MINUS_ONE> {
+        assert_eq!(
+            self.hasher.width, T,
+            "the verifier circuit only supports Poseidon with width {T}"
+        );
+        Spec::new(self.hasher.full_rounds, self.hasher.partial_rounds)
+    }
+
     /// Range of the constants polynomials in the `constants_sigmas_commitment`.
     pub fn constants_range(&self) -> Range<usize> {
         0..self.num_constants
@@ -220,11 +222,53 @@ impl<F: FieldExt> CommonData<F> {
             },
         ]
     }
+
+    /// Checks the quantities Plonky2 derives from the circuit config against the ones held by
+    /// `self`, so that a change of their derivation in Plonky2 is caught at conversion instead
+    /// of making the verifier circuit unsatisfiable
+    pub fn validate(&self) -> Result<(), ConversionError> {
+        let config = &self.config;
+        if self.quotient_degree_factor > config.max_quotient_degree_factor {
+            return Err(ConversionError::InconsistentCommonData {
+                quantity: "quotient degree factor",
+                expected: config.max_quotient_degree_factor,
+                found: self.quotient_degree_factor,
+            });
+        }
+        check_consistent(
+            "number of partial products",
+            config.num_partial_products(self.quotient_degree_factor),
+            self.num_partial_products,
+        )?;
+        check_consistent("number of k_is", config.num_routed_wires, self.k_is.len())?;
+        check_consistent(
+            "number of constants",
+            self.selectors_info.num_selectors() + config.num_constants,
+            self.num_constants,
+        )?;
+        self.selectors_info.check(self.gates.len())?;
+        self.fri_params.check(config)
+    }
 }
 
-impl<F: FieldExt> From<CommonCircuitData<GoldilocksField, 2>> for CommonData<F> {
-    fn from(value: CommonCircuitData<GoldilocksField, 2>) -> Self {
-        Self {
+impl<F: FieldExt> TryFrom<CommonCircuitData<GoldilocksField, 2>> for CommonData<F> {
+    type Error = ConversionError;
+
+    fn try_from(value: CommonCircuitData<GoldilocksField, 2>) -> Result<Self, ConversionError> {
+        if let Some(arity_bits) = value
+            .fri_params
+            .reduction_arity_bits
+            .iter()
+            .find(|arity_bits| **arity_bits != 1)
+        {
+            return Err(ConversionError::UnsupportedReductionArity(*arity_bits));
+        }
+        // the FRI chip derives the generator of the domain from its size, for any rate
+        let lde_bits = value.fri_params.degree_bits + value.config.fri_config.rate_bits;
+        if lde_bits > GoldilocksField::TWO_ADICITY {
+            return Err(ConversionError::DomainTooLarge { lde_bits });
+        }
+        let common_data = Self {
             config: CircuitConfig {
                 num_wires: value.config.num_wires,
                 num_routed_wires: value.config.num_routed_wires,
@@ -244,19 +288,9 @@ impl<F: FieldExt> From<CommonCircuitData<GoldilocksField, 2>> for CommonData<F>
             gates: value
                 .gates
                 .iter()
-                .map(|gate| CustomGateRef::from(gate))
-                .collect(),
-            fri_params: FriParams {
-                config: FriConfig {
-                    rate_bits: value.config.fri_config.rate_bits,
-                    cap_height: value.config.fri_config.cap_height,
-                    proof_of_work_bits: value.config.fri_config.proof_of_work_bits,
-                    num_query_rounds: value.config.fri_config.num_query_rounds,
-                },
-                hiding: value.fri_params.hiding,
-                degree_bits: value.fri_params.degree_bits,
-                reduction_arity_bits: value.fri_params.reduction_arity_bits,
-            },
+                .map(CustomGateRef::try_from)
+                .collect::<Result<_, _>>()?,
+            fri_params: fri_params(&value),
             selectors_info: SelectorsInfo {
                 selector_indices: value.selectors_info.selector_indices,
                 groups: value.selectors_info.groups,
@@ -265,8 +299,100 @@ impl<F: FieldExt> From<CommonCircuitData<GoldilocksField, 2>> for CommonData<F>
             num_gate_constraints: value.num_gate_constraints,
             num_constants: value.num_constants,
             num_public_inputs: value.num_public_inputs,
-            k_is: value.k_is.iter().map(|e| to_goldilocks(*e)).collect(),
+            k_is: value
+                .k_is
+                .iter()
+                .map(|e| try_to_goldilocks(*e))
+                .collect::<Option<_>>()
+                .ok_or(ConversionError::NonCanonical(ProofComponent::KIs))?,
             num_partial_products: value.num_partial_products,
+            // `CommonCircuitData` doesn't record the hasher, which is fixed to Poseidon by the
+            // `GenericConfig` the wrapper accepts
+            hasher: HasherConfig::POSEIDON,
+        };
+        common_data.validate()?;
+        Ok(common_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use plonky2::field::goldilocks_field::GoldilocksField;
+
+    use super::{CommonData, HasherConfig};
+    use crate::snark::test_utils;
+    use crate::snark::types::error::{ConversionError, ProofComponent};
+
+    #[test]
+    fn test_rate_bits_conversion() -> Result<()> {
+        for rate_bits in 1..=3 {
+            let (_, _, common) = test_utils::rate_bits_proof(rate_bits)?;
+            let degree_bits = common.fri_params.degree_bits;
+            let common_data = CommonData::<Fr>::try_from(common.clone())?;
+            assert_eq!(common_data.fri_params.lde_bits(), degree_bits + rate_bits);
+
+            let mut too_large = common;
+            too_large.fri_params.degree_bits = 33 - rate_bits;
+            assert_eq!(
+                CommonData::<Fr>::try_from(too_large).unwrap_err(),
+                ConversionError::DomainTooLarge { lde_bits: 33 }
+            );
         }
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_canonical_k_is() -> Result<()> {
+        let (_, _, mut common) = test_utils::poseidon_proof()?;
+        common.k_is[1] = GoldilocksField(u64::MAX);
+        assert_eq!(
+            CommonData::<Fr>::try_from(common).unwrap_err(),
+            ConversionError::NonCanonical(ProofComponent::KIs)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_spec() -> Result<()> {
+        let (_, _, common) = test_utils::poseidon_proof()?;
+        let common_data = CommonData::<Fr>::try_from(common)?;
+        // Poseidon of Plonky2 over a width of 12 with 8 full and 22 partial rounds
+        assert_eq!(
+            common_data.hasher,
+            HasherConfig {
+                width: 12,
+                full_rounds: 8,
+                partial_rounds: 22,
+            }
+        );
+        let spec = common_data.spec();
+        assert_eq!(spec.r_f(), 8);
+        assert_eq!(spec.constants().partial().len(), 22);
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let (_, _, common) = test_utils::poseidon_proof()?;
+        let common_data = CommonData::<Fr>::try_from(common)?;
+        common_data.validate()?;
+
+        let mut inconsistent = common_data.clone();
+        inconsistent.num_partial_products += 1;
+        assert!(matches!(
+            inconsistent.validate(),
+            Err(ConversionError::InconsistentCommonData {
+                quantity: "number of partial products",
+                ..
+            })
+        ));
+
+        let mut inconsistent = common_data;
+        inconsistent.fri_params.hiding = !inconsistent.config.zero_knowledge;
+        assert!(inconsistent.validate().is_err());
+        Ok(())
     }
 }
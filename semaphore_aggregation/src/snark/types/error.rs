@@ -0,0 +1,2 @@
+//! Errors of the conversions, defined in `stark_verifier_core` along the checks of the data model
+pub use stark_verifier_core::error::{ConversionError, ProofComponent};
@@ -3,14 +3,15 @@ use std::marker::PhantomData;
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::circuit::Layouter;
 use halo2_proofs::plonk::Error;
-use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
+use halo2curves::goldilocks::fp::Goldilocks;
+use halo2curves::{group::ff::PrimeField, FieldExt};
 use halo2wrong::RegionCtx;
-use halo2wrong_maingate::AssignedValue;
-use plonky2::field::extension::Extendable;
-use plonky2::{
-    field::goldilocks_field::GoldilocksField,
-    hash::{hash_types::HashOut, merkle_tree::MerkleCap, poseidon::PoseidonHash},
-};
+use halo2wrong_maingate::{big_to_fe, fe_to_big, AssignedValue};
+use num_bigint::BigUint;
+use plonky2::field::extension::quadratic::QuadraticExtension;
+use plonky2::{field::goldilocks_field::GoldilocksField, hash::hash_types::HashOut};
+use stark_verifier_core::field::{is_canonical, ExtensionElement, HashOut as CoreHashOut};
+use stark_verifier_core::proof::MerkleCap;
 
 use self::assigned::{AssignedExtensionFieldValue, AssignedHashValues, AssignedMerkleCapValues};
 
@@ -18,14 +19,42 @@ use super::chip::goldilocks_chip::{GoldilocksChip, GoldilocksChipConfig};
 
 pub mod assigned;
 pub mod common_data;
+pub mod error;
 pub mod fri;
 pub mod proof;
 pub mod verification_key;
+#[cfg(feature = "zeroize")]
+pub mod zeroizing;
 
+/// Converts `e`, which must be canonical: the representation of a non-canonical element isn't
+/// reduced. Use `try_to_goldilocks` on values from untrusted submitters.
 pub fn to_goldilocks(e: GoldilocksField) -> Goldilocks {
     Goldilocks::from(e.0)
 }
 
+/// `to_goldilocks`, or `None` if `e` isn't canonical
+pub fn try_to_goldilocks(e: GoldilocksField) -> Option<Goldilocks> {
+    is_canonical(e.0).then(|| to_goldilocks(e))
+}
+
+/// Elements of `hash` as `u64`, as held by `stark_verifier_core`
+pub fn to_core_hash(hash: &HashOut<GoldilocksField>) -> CoreHashOut {
+    hash.elements.map(|e| e.0)
+}
+
+/// Elements of the quadratic extension in `values` as `u64` coefficients, as held by
+/// `stark_verifier_core`
+pub fn to_core_extensions(values: &[QuadraticExtension<GoldilocksField>]) -> Vec<ExtensionElement> {
+    values.iter().map(|e| e.0.map(|c| c.0)).collect()
+}
+
+/// `big_to_fe`, or `None` if `big` isn't less than the modulus of `F`, for which `big_to_fe`
+/// panics
+pub fn try_big_to_fe<F: PrimeField>(big: &BigUint) -> Option<F> {
+    let modulus_minus_one = fe_to_big(-F::one());
+    (*big <= modulus_minus_one).then(|| big_to_fe(big.clone()))
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct HashValues<F: FieldExt> {
     pub elements: [Goldilocks; 4],
@@ -57,14 +86,10 @@ impl<F: FieldExt> HashValues<F> {
     }
 }
 
-impl<F: FieldExt> From<HashOut<GoldilocksField>> for HashValues<F> {
-    fn from(value: HashOut<GoldilocksField>) -> Self {
-        let mut elements = [Goldilocks::zero(); 4];
-        for (to, from) in elements.iter_mut().zip(value.elements.iter()) {
-            *to = to_goldilocks(*from);
-        }
+impl<F: FieldExt> From<CoreHashOut> for HashValues<F> {
+    fn from(value: CoreHashOut) -> Self {
         HashValues {
-            elements,
+            elements: value.map(Goldilocks::from),
             _marker: PhantomData,
         }
     }
@@ -88,9 +113,9 @@ impl<F: FieldExt> MerkleCapValues<F> {
     }
 }
 
-impl<F: FieldExt> From<MerkleCap<GoldilocksField, PoseidonHash>> for MerkleCapValues<F> {
-    fn from(value: MerkleCap<GoldilocksField, PoseidonHash>) -> Self {
-        let cap_values = value.0.iter().map(|h| HashValues::from(*h)).collect();
+impl<F: FieldExt> From<MerkleCap> for MerkleCapValues<F> {
+    fn from(value: MerkleCap) -> Self {
+        let cap_values = value.0.into_iter().map(HashValues::from).collect();
         MerkleCapValues(cap_values)
     }
 }
@@ -135,28 +160,58 @@ impl<F: FieldExt, const D: usize> ExtensionFieldValue<F, D> {
     }
 }
 
-// impl From<<GoldilocksField as Extendable<2>>::Extension> for ExtensionFieldValue<Goldilocks, 2> {
-
-// }
-
-impl<F: FieldExt> From<[GoldilocksField; 2]> for ExtensionFieldValue<F, 2> {
-    fn from(value: [GoldilocksField; 2]) -> Self {
-        let mut elements = vec![];
-        for from in value.iter() {
-            elements.push(to_goldilocks(*from));
-        }
+impl<F: FieldExt> From<ExtensionElement> for ExtensionFieldValue<F, 2> {
+    fn from(value: ExtensionElement) -> Self {
         ExtensionFieldValue {
-            elements: elements.try_into().unwrap(),
+            elements: value.map(Goldilocks::from),
             _marker: PhantomData,
         }
     }
 }
 
 pub fn to_extension_field_values<F: FieldExt>(
-    extension_fields: Vec<<GoldilocksField as Extendable<2>>::Extension>,
+    extension_fields: Vec<ExtensionElement>,
 ) -> Vec<ExtensionFieldValue<F, 2>> {
     extension_fields
-        .iter()
-        .map(|e| ExtensionFieldValue::from(e.0))
+        .into_iter()
+        .map(ExtensionFieldValue::from)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::Field;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2_proofs::halo2curves::group::ff::PrimeField;
+    use halo2curves::goldilocks::fp::Goldilocks;
+    use halo2wrong_maingate::fe_to_big;
+    use num_bigint::BigUint;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+
+    use super::{try_big_to_fe, try_to_goldilocks};
+
+    const GOLDILOCKS_MODULUS: u64 = 0xffff_ffff_0000_0001;
+
+    #[test]
+    fn test_checked_conversions() {
+        let max = GOLDILOCKS_MODULUS - 1;
+        assert_eq!(
+            try_to_goldilocks(GoldilocksField(max)),
+            Some(Goldilocks::from(max))
+        );
+        assert_eq!(try_to_goldilocks(GoldilocksField(GOLDILOCKS_MODULUS)), None);
+        assert_eq!(try_to_goldilocks(GoldilocksField(u64::MAX)), None);
+
+        let modulus = fe_to_big(-Fr::one()) + 1u32;
+        assert_eq!(
+            try_big_to_fe::<Fr>(&(modulus.clone() - 1u32)),
+            Some(-Fr::one())
+        );
+        assert_eq!(try_big_to_fe::<Fr>(&modulus), None);
+        assert_eq!(
+            try_big_to_fe::<Fr>(&(BigUint::from(1u32) << Fr::NUM_BITS)),
+            None
+        );
+        assert_eq!(try_big_to_fe::<Fr>(&BigUint::from(7u32)), Some(Fr::from(7)));
+    }
+}
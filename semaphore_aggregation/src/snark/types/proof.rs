@@ -6,8 +6,10 @@ use super::assigned::{
     AssignedMerkleCapValues, AssignedMerkleProofValues, AssignedOpeningSetValues,
     AssignedPolynomialCoeffsExtValues,
 };
+use super::error::ConversionError;
 use super::{
-    to_extension_field_values, to_goldilocks, ExtensionFieldValue, HashValues, MerkleCapValues,
+    to_core_extensions, to_core_hash, to_extension_field_values, ExtensionFieldValue, HashValues,
+    MerkleCapValues,
 };
 use halo2_proofs::circuit::Layouter;
 use halo2_proofs::plonk::Error;
@@ -15,10 +17,9 @@ use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
 use halo2wrong::RegionCtx;
 use halo2wrong_maingate::AssignedValue;
 use itertools::Itertools;
-use plonky2::field::extension::quadratic::QuadraticExtension;
-use plonky2::field::polynomial::PolynomialCoeffs;
 use plonky2::fri::proof::{FriProof, FriQueryRound};
 use plonky2::hash::merkle_proofs::MerkleProof;
+use plonky2::hash::merkle_tree::MerkleCap;
 use plonky2::plonk::config::PoseidonGoldilocksConfig;
 use plonky2::plonk::proof::{OpeningSet, Proof};
 use plonky2::{
@@ -26,6 +27,8 @@ use plonky2::{
     fri::proof::{FriInitialTreeProof, FriQueryStep},
     hash::poseidon::PoseidonHash,
 };
+use stark_verifier_core::field::ExtensionElement;
+use stark_verifier_core::proof as core_proof;
 
 #[derive(Clone, Debug, Default)]
 pub struct OpeningSetValues<F: FieldExt, const D: usize> {
@@ -38,8 +41,8 @@ pub struct OpeningSetValues<F: FieldExt, const D: usize> {
     pub quotient_polys: Vec<ExtensionFieldValue<F, D>>,
 }
 
-impl<F: FieldExt> From<OpeningSet<GoldilocksField, 2>> for OpeningSetValues<F, 2> {
-    fn from(value: OpeningSet<GoldilocksField, 2>) -> Self {
+impl<F: FieldExt> From<core_proof::OpeningSet> for OpeningSetValues<F, 2> {
+    fn from(value: core_proof::OpeningSet) -> Self {
         Self {
             constants: to_extension_field_values(value.constants),
             plonk_sigmas: to_extension_field_values(value.plonk_sigmas),
@@ -125,13 +128,9 @@ impl<F: FieldExt> MerkleProofValues<F> {
     }
 }
 
-impl<F: FieldExt> From<MerkleProof<GoldilocksField, PoseidonHash>> for MerkleProofValues<F> {
-    fn from(value: MerkleProof<GoldilocksField, PoseidonHash>) -> Self {
-        let siblings = value
-            .siblings
-            .iter()
-            .map(|value| HashValues::from(*value))
-            .collect();
+impl<F: FieldExt> From<core_proof::MerkleProof> for MerkleProofValues<F> {
+    fn from(value: core_proof::MerkleProof) -> Self {
+        let siblings = value.siblings.into_iter().map(HashValues::from).collect();
         MerkleProofValues { siblings }
     }
 }
@@ -141,17 +140,15 @@ pub struct FriInitialTreeProofValues<F: FieldExt> {
     pub evals_proofs: Vec<(Vec<Goldilocks>, MerkleProofValues<F>)>,
 }
 
-impl<F: FieldExt> From<FriInitialTreeProof<GoldilocksField, PoseidonHash>>
-    for FriInitialTreeProofValues<F>
-{
-    fn from(value: FriInitialTreeProof<GoldilocksField, PoseidonHash>) -> Self {
+impl<F: FieldExt> From<core_proof::FriInitialTreeProof> for FriInitialTreeProofValues<F> {
+    fn from(value: core_proof::FriInitialTreeProof) -> Self {
         let evals_proofs = value
             .evals_proofs
-            .iter()
+            .into_iter()
             .map(|(evals, proofs)| {
                 let evals_values: Vec<Goldilocks> =
-                    evals.iter().map(|f| Goldilocks::from(f.0)).collect();
-                let proofs_values = MerkleProofValues::from(proofs.clone());
+                    evals.into_iter().map(Goldilocks::from).collect();
+                let proofs_values = MerkleProofValues::from(proofs);
                 (evals_values, proofs_values)
             })
             .collect();
@@ -191,16 +188,10 @@ impl<F: FieldExt, const D: usize> FriQueryStepValues<F, D> {
     }
 }
 
-impl<F: FieldExt> From<FriQueryStep<GoldilocksField, PoseidonHash, 2>>
-    for FriQueryStepValues<F, 2>
-{
-    fn from(value: FriQueryStep<GoldilocksField, PoseidonHash, 2>) -> Self {
-        let evals_values = value
-            .evals
-            .iter()
-            .map(|e| ExtensionFieldValue::from(e.0))
-            .collect();
-        let merkle_proof_values = MerkleProofValues::from(value.merkle_proof.clone());
+impl<F: FieldExt> From<core_proof::FriQueryStep> for FriQueryStepValues<F, 2> {
+    fn from(value: core_proof::FriQueryStep) -> Self {
+        let evals_values = to_extension_field_values(value.evals);
+        let merkle_proof_values = MerkleProofValues::from(value.merkle_proof);
         FriQueryStepValues {
             evals: evals_values,
             merkle_proof: merkle_proof_values,
@@ -214,16 +205,14 @@ pub struct FriQueryRoundValues<F: FieldExt, const D: usize> {
     pub steps: Vec<FriQueryStepValues<F, D>>,
 }
 
-impl<F: FieldExt> From<FriQueryRound<GoldilocksField, PoseidonHash, 2>>
-    for FriQueryRoundValues<F, 2>
-{
-    fn from(value: FriQueryRound<GoldilocksField, PoseidonHash, 2>) -> Self {
+impl<F: FieldExt> From<core_proof::FriQueryRound> for FriQueryRoundValues<F, 2> {
+    fn from(value: core_proof::FriQueryRound) -> Self {
         Self {
             initial_trees_proof: FriInitialTreeProofValues::from(value.initial_trees_proof),
             steps: value
                 .steps
-                .iter()
-                .map(|step| FriQueryStepValues::from(step.clone()))
+                .into_iter()
+                .map(FriQueryStepValues::from)
                 .collect_vec(),
         }
     }
@@ -286,17 +275,9 @@ pub struct PolynomialCoeffsExtValues<F: FieldExt, const D: usize>(
     pub Vec<ExtensionFieldValue<F, D>>,
 );
 
-impl<F: FieldExt> From<PolynomialCoeffs<QuadraticExtension<GoldilocksField>>>
-    for PolynomialCoeffsExtValues<F, 2>
-{
-    fn from(value: PolynomialCoeffs<QuadraticExtension<GoldilocksField>>) -> Self {
-        Self(
-            value
-                .coeffs
-                .iter()
-                .map(|coeff| ExtensionFieldValue::from(coeff.0))
-                .collect_vec(),
-        )
+impl<F: FieldExt> From<Vec<ExtensionElement>> for PolynomialCoeffsExtValues<F, 2> {
+    fn from(coeffs: Vec<ExtensionElement>) -> Self {
+        Self(to_extension_field_values(coeffs))
     }
 }
 
@@ -324,21 +305,21 @@ pub struct FriProofValues<F: FieldExt, const D: usize> {
     pub pow_witness: Goldilocks,
 }
 
-impl<F: FieldExt> From<FriProof<GoldilocksField, PoseidonHash, 2>> for FriProofValues<F, 2> {
-    fn from(value: FriProof<GoldilocksField, PoseidonHash, 2>) -> Self {
+impl<F: FieldExt> From<core_proof::FriProof> for FriProofValues<F, 2> {
+    fn from(value: core_proof::FriProof) -> Self {
         Self {
             commit_phase_merkle_cap_values: value
                 .commit_phase_merkle_caps
-                .iter()
-                .map(|cap| MerkleCapValues::from(cap.clone()))
+                .into_iter()
+                .map(MerkleCapValues::from)
                 .collect_vec(),
             query_round_proofs: value
                 .query_round_proofs
-                .iter()
-                .map(|proof| FriQueryRoundValues::from(proof.clone()))
+                .into_iter()
+                .map(FriQueryRoundValues::from)
                 .collect_vec(),
             final_poly: PolynomialCoeffsExtValues::from(value.final_poly),
-            pow_witness: to_goldilocks(value.pow_witness),
+            pow_witness: Goldilocks::from(value.pow_witness),
         }
     }
 }
@@ -399,9 +380,12 @@ pub struct ProofValues<F: FieldExt, const D: usize> {
     pub opening_proof: FriProofValues<F, D>,
 }
 
-impl<F: FieldExt> From<Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>> for ProofValues<F, 2> {
-    fn from(value: Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>) -> Self {
-        Self {
+impl<F: FieldExt> TryFrom<core_proof::Proof> for ProofValues<F, 2> {
+    type Error = ConversionError;
+
+    fn try_from(value: core_proof::Proof) -> Result<Self, ConversionError> {
+        value.check()?;
+        Ok(Self {
             wires_cap: MerkleCapValues::from(value.wires_cap),
             plonk_zs_partial_products_cap: MerkleCapValues::from(
                 value.plonk_zs_partial_products_cap,
@@ -409,6 +393,145 @@ impl<F: FieldExt> From<Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>> for
             quotient_polys_cap: MerkleCapValues::from(value.quotient_polys_cap),
             openings: OpeningSetValues::from(value.openings),
             opening_proof: FriProofValues::from(value.opening_proof),
-        }
+        })
+    }
+}
+
+impl<F: FieldExt> TryFrom<Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>>
+    for ProofValues<F, 2>
+{
+    type Error = ConversionError;
+
+    fn try_from(
+        value: Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    ) -> Result<Self, ConversionError> {
+        Self::try_from(to_core_proof(&value))
+    }
+}
+
+pub fn to_core_cap(cap: &MerkleCap<GoldilocksField, PoseidonHash>) -> core_proof::MerkleCap {
+    core_proof::MerkleCap(cap.0.iter().map(to_core_hash).collect())
+}
+
+fn to_core_merkle_proof(
+    merkle_proof: &MerkleProof<GoldilocksField, PoseidonHash>,
+) -> core_proof::MerkleProof {
+    core_proof::MerkleProof {
+        siblings: merkle_proof.siblings.iter().map(to_core_hash).collect(),
+    }
+}
+
+fn to_core_opening_set(openings: &OpeningSet<GoldilocksField, 2>) -> core_proof::OpeningSet {
+    core_proof::OpeningSet {
+        constants: to_core_extensions(&openings.constants),
+        plonk_sigmas: to_core_extensions(&openings.plonk_sigmas),
+        wires: to_core_extensions(&openings.wires),
+        plonk_zs: to_core_extensions(&openings.plonk_zs),
+        plonk_zs_next: to_core_extensions(&openings.plonk_zs_next),
+        partial_products: to_core_extensions(&openings.partial_products),
+        quotient_polys: to_core_extensions(&openings.quotient_polys),
+    }
+}
+
+fn to_core_initial_tree_proof(
+    proof: &FriInitialTreeProof<GoldilocksField, PoseidonHash>,
+) -> core_proof::FriInitialTreeProof {
+    core_proof::FriInitialTreeProof {
+        evals_proofs: proof
+            .evals_proofs
+            .iter()
+            .map(|(evals, merkle_proof)| {
+                (
+                    evals.iter().map(|e| e.0).collect(),
+                    to_core_merkle_proof(merkle_proof),
+                )
+            })
+            .collect(),
+    }
+}
+
+fn to_core_query_step(
+    step: &FriQueryStep<GoldilocksField, PoseidonHash, 2>,
+) -> core_proof::FriQueryStep {
+    core_proof::FriQueryStep {
+        evals: to_core_extensions(&step.evals),
+        merkle_proof: to_core_merkle_proof(&step.merkle_proof),
+    }
+}
+
+fn to_core_query_round(
+    round: &FriQueryRound<GoldilocksField, PoseidonHash, 2>,
+) -> core_proof::FriQueryRound {
+    core_proof::FriQueryRound {
+        initial_trees_proof: to_core_initial_tree_proof(&round.initial_trees_proof),
+        steps: round.steps.iter().map(to_core_query_step).collect(),
+    }
+}
+
+fn to_core_fri_proof(proof: &FriProof<GoldilocksField, PoseidonHash, 2>) -> core_proof::FriProof {
+    core_proof::FriProof {
+        commit_phase_merkle_caps: proof
+            .commit_phase_merkle_caps
+            .iter()
+            .map(to_core_cap)
+            .collect(),
+        query_round_proofs: proof
+            .query_round_proofs
+            .iter()
+            .map(to_core_query_round)
+            .collect(),
+        final_poly: to_core_extensions(&proof.final_poly.coeffs),
+        pow_witness: proof.pow_witness.0,
+    }
+}
+
+/// `proof` as the data model of `stark_verifier_core`, shared with `no_std` light clients. The
+/// elements are copied as they are, `core_proof::Proof::check` rejects the non-canonical ones.
+pub fn to_core_proof(
+    proof: &Proof<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> core_proof::Proof {
+    core_proof::Proof {
+        wires_cap: to_core_cap(&proof.wires_cap),
+        plonk_zs_partial_products_cap: to_core_cap(&proof.plonk_zs_partial_products_cap),
+        quotient_polys_cap: to_core_cap(&proof.quotient_polys_cap),
+        openings: to_core_opening_set(&proof.openings),
+        opening_proof: to_core_fri_proof(&proof.opening_proof),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+
+    use super::ProofValues;
+    use crate::snark::test_utils;
+    use crate::snark::types::error::{ConversionError, ProofComponent};
+
+    #[test]
+    fn test_malformed_proof_is_rejected() -> Result<()> {
+        let (proof_with_public_inputs, _, _) = test_utils::constant_proof()?;
+        let mut proof = proof_with_public_inputs.proof;
+        assert!(ProofValues::<Fr, 2>::try_from(proof.clone()).is_ok());
+
+        let mut non_canonical = proof.clone();
+        non_canonical.opening_proof.pow_witness = GoldilocksField(u64::MAX);
+        assert_eq!(
+            ProofValues::<Fr, 2>::try_from(non_canonical).unwrap_err(),
+            ConversionError::NonCanonical(ProofComponent::PowWitness)
+        );
+
+        let num_steps = proof.opening_proof.commit_phase_merkle_caps.len();
+        proof.opening_proof.query_round_proofs[1].steps.pop();
+        assert_eq!(
+            ProofValues::<Fr, 2>::try_from(proof).unwrap_err(),
+            ConversionError::LengthMismatch {
+                component: ProofComponent::QueryRound(1),
+                expected: num_steps,
+                found: num_steps - 1,
+            }
+        );
+        Ok(())
     }
 }
@@ -1,6 +1,11 @@
 use crate::snark::types::{HashValues, MerkleCapValues};
-use halo2curves::FieldExt;
+use halo2curves::{goldilocks::fp::Goldilocks, FieldExt};
 use plonky2::plonk::{circuit_data::VerifierOnlyCircuitData, config::PoseidonGoldilocksConfig};
+use stark_verifier_core::verification_key::VerificationKey;
+
+use super::error::ConversionError;
+use super::proof::to_core_cap;
+use super::to_core_hash;
 
 #[derive(Clone, Debug, Default)]
 pub struct VerificationKeyValues<F: FieldExt> {
@@ -8,13 +13,48 @@ pub struct VerificationKeyValues<F: FieldExt> {
     pub circuit_digest: HashValues<F>,
 }
 
-impl<F: FieldExt> From<VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>>
-    for VerificationKeyValues<F>
-{
-    fn from(value: VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>) -> Self {
-        VerificationKeyValues {
+impl<F: FieldExt> VerificationKeyValues<F> {
+    /// Elements of the constants and sigmas cap, then of the circuit digest
+    pub fn elements(&self) -> Vec<Goldilocks> {
+        self.constants_sigmas_cap
+            .0
+            .iter()
+            .chain(std::iter::once(&self.circuit_digest))
+            .flat_map(|hash| hash.elements)
+            .collect()
+    }
+}
+
+impl<F: FieldExt> TryFrom<VerificationKey> for VerificationKeyValues<F> {
+    type Error = ConversionError;
+
+    fn try_from(value: VerificationKey) -> Result<Self, ConversionError> {
+        value.check()?;
+        Ok(VerificationKeyValues {
             constants_sigmas_cap: MerkleCapValues::from(value.constants_sigmas_cap),
             circuit_digest: HashValues::from(value.circuit_digest),
-        }
+        })
+    }
+}
+
+impl<F: FieldExt> TryFrom<VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>>
+    for VerificationKeyValues<F>
+{
+    type Error = ConversionError;
+
+    fn try_from(
+        value: VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>,
+    ) -> Result<Self, ConversionError> {
+        Self::try_from(to_core_verification_key(&value))
+    }
+}
+
+/// `vk` as the data model of `stark_verifier_core`
+pub fn to_core_verification_key(
+    vk: &VerifierOnlyCircuitData<PoseidonGoldilocksConfig, 2>,
+) -> VerificationKey {
+    VerificationKey {
+        constants_sigmas_cap: to_core_cap(&vk.constants_sigmas_cap),
+        circuit_digest: to_core_hash(&vk.circuit_digest),
     }
 }
@@ -0,0 +1,176 @@
+//! Wiping of the proof values converted for the verifier circuit, with the `zeroize` feature.
+//! The Plonky2 proof is a witness of the verifier circuit, and may leak the private inputs of the
+//! inner circuit when it isn't zero-knowledge. `ProofValues` is wiped when dropped, so that every
+//! copy of the circuit, e.g. the ones of the mock runs and of the keygen, is wiped once the proof
+//! is finalized.
+//!
+//! Only the proof values are wiped, in place with the full capacity of their vectors. Of the
+//! other buffers holding the witness:
+//! - the witness vectors and the assigned advice columns, with their polynomials, are private
+//!   to `create_proof` and to `MockProver` of the pinned halo2, and freed without being wiped
+//! - the transcript buffer holds the wrapper proof, which is public like its instances
+//! - the Plonky2 proof tuple is owned by the caller, who wipes it if needed
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{compiler_fence, Ordering};
+
+use halo2_proofs::arithmetic::Field;
+use halo2curves::goldilocks::fp::Goldilocks;
+use halo2curves::FieldExt;
+use zeroize::Zeroize;
+
+use super::proof::{
+    FriInitialTreeProofValues, FriProofValues, FriQueryRoundValues, FriQueryStepValues,
+    MerkleProofValues, OpeningSetValues, PolynomialCoeffsExtValues, ProofValues,
+};
+use super::{ExtensionFieldValue, HashValues, MerkleCapValues};
+
+/// Overwrites `values` with zeros, with volatile writes the compiler can't elide, as `zeroize`
+/// does for the primitive types
+fn wipe(values: &mut [Goldilocks]) {
+    for value in values.iter_mut() {
+        // SAFETY: `value` is a valid and aligned reference, and `Goldilocks` has no drop glue
+        unsafe { ptr::write_volatile(value, Goldilocks::zero()) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+impl<F: FieldExt> Zeroize for HashValues<F> {
+    fn zeroize(&mut self) {
+        wipe(&mut self.elements);
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for ExtensionFieldValue<F, D> {
+    fn zeroize(&mut self) {
+        wipe(&mut self.elements);
+    }
+}
+
+impl<F: FieldExt> Zeroize for MerkleCapValues<F> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for OpeningSetValues<F, D> {
+    fn zeroize(&mut self) {
+        self.constants.zeroize();
+        self.plonk_sigmas.zeroize();
+        self.wires.zeroize();
+        self.plonk_zs.zeroize();
+        self.plonk_zs_next.zeroize();
+        self.partial_products.zeroize();
+        self.quotient_polys.zeroize();
+    }
+}
+
+impl<F: FieldExt> Zeroize for MerkleProofValues<F> {
+    fn zeroize(&mut self) {
+        self.siblings.zeroize();
+    }
+}
+
+impl<F: FieldExt> Zeroize for FriInitialTreeProofValues<F> {
+    fn zeroize(&mut self) {
+        for (evals, merkle_proof) in self.evals_proofs.iter_mut() {
+            wipe(evals);
+            merkle_proof.zeroize();
+        }
+        self.evals_proofs.clear();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for FriQueryStepValues<F, D> {
+    fn zeroize(&mut self) {
+        self.evals.zeroize();
+        self.merkle_proof.zeroize();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for FriQueryRoundValues<F, D> {
+    fn zeroize(&mut self) {
+        self.initial_trees_proof.zeroize();
+        self.steps.zeroize();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for PolynomialCoeffsExtValues<F, D> {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for FriProofValues<F, D> {
+    fn zeroize(&mut self) {
+        self.commit_phase_merkle_cap_values.zeroize();
+        self.query_round_proofs.zeroize();
+        self.final_poly.zeroize();
+        wipe(slice::from_mut(&mut self.pow_witness));
+    }
+}
+
+impl<F: FieldExt, const D: usize> Zeroize for ProofValues<F, D> {
+    fn zeroize(&mut self) {
+        self.wires_cap.zeroize();
+        self.plonk_zs_partial_products_cap.zeroize();
+        self.quotient_polys_cap.zeroize();
+        self.openings.zeroize();
+        self.opening_proof.zeroize();
+    }
+}
+
+impl<F: FieldExt, const D: usize> Drop for ProofValues<F, D> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::arithmetic::Field;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use halo2curves::goldilocks::fp::Goldilocks;
+    use zeroize::Zeroize;
+
+    use crate::snark::test_utils;
+    use crate::snark::types::proof::ProofValues;
+
+    /// Address of the buffer of `values`, with the bytes of its elements and of its capacity
+    fn allocation<T>(values: &Vec<T>) -> (*const u8, usize, usize) {
+        let size = std::mem::size_of::<T>();
+        (
+            values.as_ptr() as *const u8,
+            values.len() * size,
+            values.capacity() * size,
+        )
+    }
+
+    #[test]
+    fn test_zeroize_proof_values() {
+        let (proof, _, _) = test_utils::public_inputs_proof(2).unwrap();
+        let mut proof = ProofValues::<Fr, 2>::try_from(proof.proof).unwrap();
+        assert!(!proof.openings.wires.is_empty());
+        let allocations = [
+            allocation(&proof.openings.wires),
+            allocation(&proof.wires_cap.0),
+            allocation(&proof.opening_proof.final_poly.0),
+        ];
+        // SAFETY: the buffers are alive, as the vectors keep them once zeroized, and the bytes
+        // read are initialized
+        let read = |ptr: *const u8, len: usize| unsafe { std::slice::from_raw_parts(ptr, len) };
+        for (ptr, len, _) in allocations {
+            assert!(read(ptr, len).iter().any(|byte| *byte != 0));
+        }
+        proof.zeroize();
+        assert_eq!(allocation(&proof.openings.wires).0, allocations[0].0);
+        // the elements and the spare capacity were overwritten
+        for (ptr, _, capacity) in allocations {
+            assert!(read(ptr, capacity).iter().all(|byte| *byte == 0));
+        }
+        assert!(proof.openings.wires.is_empty());
+        assert!(proof.opening_proof.query_round_proofs.is_empty());
+        assert!(proof.wires_cap.0.is_empty());
+        assert_eq!(proof.opening_proof.pow_witness, Goldilocks::zero());
+    }
+}
@@ -1 +1,7 @@
+use sha3::{Digest, Keccak256};
+
 pub mod fft;
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
@@ -0,0 +1,461 @@
+//! Native verification of wrapped proofs, for services checking proofs without an EVM. Only
+//! needs the protocol of the verifier circuit, as JSON, and the deciding key of the SRS, as
+//! bytes, both exported with `VerifierKey`: neither the proving key nor the SRS is needed.
+//! The multi-opening scheme and the transcript of the proofs are written along them. The points of the SRS the
+//! deciding key is made of can be extracted on their own as `VerifierParams`.
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+use halo2_proofs::halo2curves::group::GroupEncoding;
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::transcript::{Blake2bRead, Challenge255, TranscriptReadBuffer};
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::pcs::kzg::{Bdfg21, Gwc19, KzgAs, KzgDecidingKey, LimbsEncoding};
+use snark_verifier::pcs::AccumulationDecider;
+use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
+use snark_verifier::system::halo2::{compile, Config};
+use snark_verifier::util::transcript::TranscriptRead;
+use snark_verifier::verifier::plonk::{PlonkProtocol, PlonkVerifier};
+use snark_verifier::verifier::SnarkVerifier;
+
+#[cfg(feature = "prover")]
+use super::export::layout::{Artifact, ArtifactsLayout};
+use super::instances::OrderedInstances;
+use super::proof_encoding::{validate_proof_encoding, ProofEncodingError, ProofLayout};
+
+/// Number of limbs of a coordinate of the accumulator of a compressed proof, see `compression`
+pub const LIMBS: usize = 4;
+/// Bits of a limb of the accumulator
+pub const BITS: usize = 68;
+
+type Verifier<MOS> = PlonkVerifier<KzgAs<Bn256, MOS>, LimbsEncoding<LIMBS, BITS>>;
+
+/// KZG multi-opening argument of the SNARK proof, verified in the EVM and natively
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MultiOpenScheme {
+    /// GWC19, whose EVM verifier is the cheapest
+    #[default]
+    Gwc,
+    /// SHPLONK (BDFG21), the scheme of the PSE and Axiom aggregation circuits
+    Shplonk,
+}
+
+impl MultiOpenScheme {
+    pub fn name(&self) -> &'static str {
+        match self {
+            MultiOpenScheme::Gwc => "gwc",
+            MultiOpenScheme::Shplonk => "shplonk",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "gwc" => Some(MultiOpenScheme::Gwc),
+            "shplonk" => Some(MultiOpenScheme::Shplonk),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MultiOpenScheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Transcript the challenges of the SNARK proof are derived with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TranscriptKind {
+    /// Keccak256 with points encoded as `(x, y)` words, the only one the EVM verifier reads
+    #[default]
+    Keccak256,
+    /// Blake2b of halo2 with compressed points, cheaper to prove with but only verified
+    /// natively, see `VerifierKey`. No EVM verifier is generated for its proofs.
+    Blake2b,
+}
+
+impl TranscriptKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TranscriptKind::Keccak256 => "keccak256",
+            TranscriptKind::Blake2b => "blake2b",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "keccak256" => Some(TranscriptKind::Keccak256),
+            "blake2b" => Some(TranscriptKind::Blake2b),
+            _ => None,
+        }
+    }
+
+    /// Whether the EVM verifier can verify the proofs
+    pub fn is_evm_compatible(&self) -> bool {
+        *self == TranscriptKind::Keccak256
+    }
+}
+
+impl std::fmt::Display for TranscriptKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Size of the encoding of a deciding key: the compressed G1 generator, G2 generator and
+/// `s * G2`
+pub const DECIDING_KEY_BYTES: usize = 32 + 64 + 64;
+/// Size of the encoding of `VerifierParams`: the degree as a little-endian `u32`, then the
+/// deciding key
+pub const VERIFIER_PARAMS_BYTES: usize = 4 + DECIDING_KEY_BYTES;
+
+#[derive(Debug)]
+pub enum NativeVerificationError {
+    /// The protocol isn't valid JSON of a `PlonkProtocol`
+    MalformedProtocol(serde_json::Error),
+    /// The deciding key doesn't hold `DECIDING_KEY_BYTES` bytes of valid points
+    MalformedDecidingKey,
+    /// The proof can't be read, e.g. it is truncated or holds points not on the curve
+    MalformedProof(String),
+}
+
+impl fmt::Display for NativeVerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NativeVerificationError::MalformedProtocol(e) => write!(f, "malformed protocol: {e}"),
+            NativeVerificationError::MalformedDecidingKey => write!(f, "malformed deciding key"),
+            NativeVerificationError::MalformedProof(e) => write!(f, "malformed proof: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for NativeVerificationError {}
+
+/// Points of an SRS needed to verify proofs natively, with its degree: a few hundred bytes
+/// instead of the gigabytes of the SRS, so that services only verifying proofs don't load it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifierParams {
+    k: u32,
+    g: G1Affine,
+    g2: G2Affine,
+    s_g2: G2Affine,
+}
+
+impl VerifierParams {
+    pub fn from_params(params: &ParamsKZG<Bn256>) -> Self {
+        Self {
+            k: params.k(),
+            g: params.get_g()[0],
+            g2: params.g2(),
+            s_g2: params.s_g2(),
+        }
+    }
+
+    fn from_deciding_key(k: u32, deciding_key: &[u8]) -> Result<Self, NativeVerificationError> {
+        if deciding_key.len() != DECIDING_KEY_BYTES {
+            return Err(NativeVerificationError::MalformedDecidingKey);
+        }
+        Ok(Self {
+            k,
+            g: read_point::<G1Affine>(&deciding_key[..32])?,
+            g2: read_point::<G2Affine>(&deciding_key[32..96])?,
+            s_g2: read_point::<G2Affine>(&deciding_key[96..])?,
+        })
+    }
+
+    /// Reads the encoding of `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, NativeVerificationError> {
+        if bytes.len() != VERIFIER_PARAMS_BYTES {
+            return Err(NativeVerificationError::MalformedDecidingKey);
+        }
+        let k = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        Self::from_deciding_key(k, &bytes[4..])
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.k.to_le_bytes().as_ref(), &self.deciding_key_bytes()].concat()
+    }
+
+    /// Degree of the SRS the points were extracted from
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    pub fn deciding_key(&self) -> KzgDecidingKey<Bn256> {
+        (self.g, self.g2, self.s_g2).into()
+    }
+
+    pub fn deciding_key_bytes(&self) -> Vec<u8> {
+        [
+            self.g.to_bytes().as_ref(),
+            self.g2.to_bytes().as_ref(),
+            self.s_g2.to_bytes().as_ref(),
+        ]
+        .concat()
+    }
+}
+
+/// Everything needed to verify proofs of a verifier circuit natively
+#[derive(Clone, Debug)]
+pub struct VerifierKey {
+    protocol: PlonkProtocol<G1Affine>,
+    params: VerifierParams,
+    multiopen: MultiOpenScheme,
+    transcript: TranscriptKind,
+}
+
+impl VerifierKey {
+    /// Key of the circuit with verifying key `vk`, generated from `params`, whose instance
+    /// column holds `num_instance` instances
+    pub fn new(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: usize,
+    ) -> Self {
+        Self::new_with_accumulator(params, vk, num_instance, None)
+    }
+
+    /// `new` for a circuit whose instances at `accumulator_indices`, if any, hold an
+    /// accumulator checked along the proof, see `compression`
+    pub fn new_with_accumulator(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: usize,
+        accumulator_indices: Option<Vec<(usize, usize)>>,
+    ) -> Self {
+        Self {
+            protocol: compile(
+                params,
+                vk,
+                Config::kzg()
+                    .with_num_instance(vec![num_instance])
+                    .with_accumulator_indices(accumulator_indices),
+            ),
+            params: VerifierParams::from_params(params),
+            multiopen: MultiOpenScheme::default(),
+            transcript: TranscriptKind::default(),
+        }
+    }
+
+    /// Verifies proofs made with `multiopen` instead of GWC
+    pub fn with_multiopen(mut self, multiopen: MultiOpenScheme) -> Self {
+        self.multiopen = multiopen;
+        self
+    }
+
+    pub fn multiopen(&self) -> MultiOpenScheme {
+        self.multiopen
+    }
+
+    /// Verifies proofs made with `transcript` instead of Keccak256
+    pub fn with_transcript(mut self, transcript: TranscriptKind) -> Self {
+        self.transcript = transcript;
+        self
+    }
+
+    pub fn transcript(&self) -> TranscriptKind {
+        self.transcript
+    }
+
+    pub fn from_parts(
+        protocol_json: &str,
+        deciding_key: &[u8],
+    ) -> Result<Self, NativeVerificationError> {
+        let protocol: PlonkProtocol<G1Affine> = serde_json::from_str(protocol_json)
+            .map_err(NativeVerificationError::MalformedProtocol)?;
+        let params = VerifierParams::from_deciding_key(protocol.domain.k as u32, deciding_key)?;
+        Ok(Self {
+            protocol,
+            params,
+            multiopen: MultiOpenScheme::default(),
+            transcript: TranscriptKind::default(),
+        })
+    }
+
+    pub fn protocol(&self) -> &PlonkProtocol<G1Affine> {
+        &self.protocol
+    }
+
+    pub fn verifier_params(&self) -> &VerifierParams {
+        &self.params
+    }
+
+    pub fn protocol_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string(&self.protocol)
+    }
+
+    pub fn deciding_key_bytes(&self) -> Vec<u8> {
+        self.params.deciding_key_bytes()
+    }
+
+    /// Writes `protocol.json`, `deciding_key.bin`, `multiopen.txt` and `transcript.txt` to `dir`
+    #[cfg(feature = "prover")]
+    pub fn write(&self, dir: &Path) -> io::Result<()> {
+        self.write_with_layout(&ArtifactsLayout::new(dir))
+    }
+
+    /// Same as `write`, writing the files of `Artifact::VERIFIER_KEY` where `layout` puts them
+    #[cfg(feature = "prover")]
+    pub fn write_with_layout(&self, layout: &ArtifactsLayout) -> io::Result<()> {
+        layout.check(Artifact::VERIFIER_KEY)?;
+        let protocol = self
+            .protocol_json()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        layout.write(Artifact::Protocol, protocol)?;
+        layout.write(Artifact::DecidingKey, self.deciding_key_bytes())?;
+        layout.write(Artifact::MultiOpen, self.multiopen.name())?;
+        layout.write(Artifact::Transcript, self.transcript.name())?;
+        Ok(())
+    }
+
+    /// Reads the files written by `write`, `multiopen.txt` defaulting to GWC and
+    /// `transcript.txt` to Keccak256 when missing
+    pub fn read(dir: &Path) -> io::Result<Self> {
+        Self::read_files(
+            &dir.join("protocol.json"),
+            &dir.join("deciding_key.bin"),
+            &dir.join("multiopen.txt"),
+            &dir.join("transcript.txt"),
+        )
+    }
+
+    /// Same as `read`, for files written with `layout`
+    #[cfg(feature = "prover")]
+    pub fn read_with_layout(layout: &ArtifactsLayout) -> io::Result<Self> {
+        Self::read_files(
+            &layout.path(Artifact::Protocol),
+            &layout.path(Artifact::DecidingKey),
+            &layout.path(Artifact::MultiOpen),
+            &layout.path(Artifact::Transcript),
+        )
+    }
+
+    fn read_files(
+        protocol: &Path,
+        deciding_key: &Path,
+        multiopen: &Path,
+        transcript: &Path,
+    ) -> io::Result<Self> {
+        let protocol = fs::read_to_string(protocol)?;
+        let deciding_key = fs::read(deciding_key)?;
+        let key = Self::from_parts(&protocol, &deciding_key)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let multiopen = match fs::read_to_string(multiopen) {
+            Ok(name) => MultiOpenScheme::from_name(name.trim()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown multi-opening scheme {name}"),
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => MultiOpenScheme::default(),
+            Err(e) => return Err(e),
+        };
+        let transcript = match fs::read_to_string(transcript) {
+            Ok(name) => TranscriptKind::from_name(name.trim()).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("unknown transcript {name}"),
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => TranscriptKind::default(),
+            Err(e) => return Err(e),
+        };
+        Ok(key.with_multiopen(multiopen).with_transcript(transcript))
+    }
+
+    /// Layout of the proofs of the circuit
+    pub fn proof_layout(&self) -> ProofLayout {
+        ProofLayout::new(&self.protocol, self.multiopen)
+    }
+
+    /// Checks the encoding of `proof` as the EVM verifier does, see `proof_encoding`. Only
+    /// meaningful for the Keccak256 transcript, the Blake2b one compressing the points.
+    pub fn validate_proof_encoding(&self, proof: &[u8]) -> Result<(), ProofEncodingError> {
+        validate_proof_encoding(proof, &self.proof_layout())
+    }
+
+    /// Whether `proof` is a valid proof for `instances`, rejecting malformed encodings first
+    pub fn verify(
+        &self,
+        instances: &OrderedInstances,
+        proof: &[u8],
+    ) -> Result<bool, NativeVerificationError> {
+        let instances = instances.columns();
+        match self.transcript {
+            TranscriptKind::Keccak256 => {
+                self.validate_proof_encoding(proof)
+                    .map_err(|e| NativeVerificationError::MalformedProof(e.to_string()))?;
+                self.verify_from(
+                    &instances,
+                    &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(proof),
+                )
+            }
+            TranscriptKind::Blake2b => self.verify_from(
+                &instances,
+                &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof),
+            ),
+        }
+    }
+
+    /// Reads the proof from `transcript` and checks it
+    fn verify_from<T: TranscriptRead<G1Affine, NativeLoader>>(
+        &self,
+        instances: &[Vec<Fr>],
+        transcript: &mut T,
+    ) -> Result<bool, NativeVerificationError> {
+        let dk = self.params.deciding_key();
+        let malformed = |e| NativeVerificationError::MalformedProof(format!("{e:?}"));
+        match self.multiopen {
+            MultiOpenScheme::Gwc => {
+                let proof =
+                    Verifier::<Gwc19>::read_proof(&dk, &self.protocol, instances, transcript)
+                        .map_err(malformed)?;
+                let accumulators =
+                    Verifier::<Gwc19>::verify(&dk, &self.protocol, instances, &proof)
+                        .map_err(malformed)?;
+                Ok(KzgAs::<Bn256, Gwc19>::decide_all(&dk, accumulators))
+            }
+            MultiOpenScheme::Shplonk => {
+                let proof =
+                    Verifier::<Bdfg21>::read_proof(&dk, &self.protocol, instances, transcript)
+                        .map_err(malformed)?;
+                let accumulators =
+                    Verifier::<Bdfg21>::verify(&dk, &self.protocol, instances, &proof)
+                        .map_err(malformed)?;
+                Ok(KzgAs::<Bn256, Bdfg21>::decide_all(&dk, accumulators))
+            }
+        }
+    }
+}
+
+fn read_point<C: GroupEncoding>(bytes: &[u8]) -> Result<C, NativeVerificationError> {
+    let mut repr = C::Repr::default();
+    repr.as_mut().copy_from_slice(bytes);
+    Option::from(C::from_bytes(&repr)).ok_or(NativeVerificationError::MalformedDecidingKey)
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{VerifierParams, VERIFIER_PARAMS_BYTES};
+
+    #[test]
+    fn test_verifier_params_round_trip() {
+        let params = ParamsKZG::<Bn256>::setup(4, StdRng::seed_from_u64(0));
+        let verifier_params = VerifierParams::from_params(&params);
+        let bytes = verifier_params.to_bytes();
+        assert_eq!(bytes.len(), VERIFIER_PARAMS_BYTES);
+        assert_eq!(VerifierParams::from_bytes(&bytes).unwrap(), verifier_params);
+        assert_eq!(verifier_params.k(), 4);
+        assert!(VerifierParams::from_bytes(&bytes[1..]).is_err());
+    }
+}
@@ -1,137 +1,469 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-use crate::ProofTuple;
-use anyhow::Context;
+use crate::{AnyProofTuple, ProofTuple};
 use colored::Colorize;
-use halo2_kzg_srs::{Srs, SrsFormat};
 use halo2_proofs::dev::MockProver;
 use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fr, G1Affine};
 use halo2_proofs::plonk::{
-    create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, ProvingKey, VerifyingKey,
+    create_proof, keygen_pk, keygen_vk, verify_proof, Circuit, Error, ProvingKey, VerifyingKey,
 };
-use halo2_proofs::poly::commitment::{Params, ParamsProver};
+use halo2_proofs::poly::commitment::{Params, ParamsProver, Prover};
 use halo2_proofs::poly::kzg::commitment::{KZGCommitmentScheme, ParamsKZG};
-use halo2_proofs::poly::kzg::multiopen::{ProverGWC, VerifierGWC};
-use halo2_proofs::poly::kzg::strategy::AccumulatorStrategy;
+use halo2_proofs::poly::kzg::multiopen::{ProverGWC, ProverSHPLONK, VerifierGWC, VerifierSHPLONK};
+use halo2_proofs::poly::kzg::strategy::{AccumulatorStrategy, SingleStrategy};
 use halo2_proofs::poly::VerificationStrategy;
-use halo2_proofs::transcript::{TranscriptReadBuffer, TranscriptWriterBuffer};
-use halo2curves::goldilocks::fp::Goldilocks;
-use halo2wrong_maingate::{big_to_fe, fe_to_big};
-use itertools::Itertools;
+use halo2_proofs::transcript::{
+    Blake2bRead, Blake2bWrite, Challenge255, EncodedChallenge, TranscriptRead,
+    TranscriptReadBuffer, TranscriptWriterBuffer,
+};
 use lazy_static::lazy_static;
-use plonky2::{field::goldilocks_field::GoldilocksField, plonk::config::PoseidonGoldilocksConfig};
-use poseidon::Spec;
+use plonky2::{
+    field::{extension::Extendable, goldilocks_field::GoldilocksField, types::PrimeField64},
+    plonk::{
+        circuit_data::VerifierCircuitData,
+        config::{GenericConfig, PoseidonGoldilocksConfig},
+    },
+};
 use rand::rngs::OsRng;
-use snark_verifier::loader::evm::{self, encode_calldata, EvmLoader, ExecutorBuilder};
-use snark_verifier::pcs::kzg::{Gwc19, KzgAs};
+use rand::RngCore;
+use snark_verifier::loader::evm::EvmLoader;
+use snark_verifier::loader::native::NativeLoader;
+use snark_verifier::pcs::kzg::{Bdfg21, Gwc19, KzgAs, LimbsEncoding};
 use snark_verifier::system::halo2::transcript::evm::EvmTranscript;
 use snark_verifier::system::halo2::{compile, Config};
 use snark_verifier::verifier::{self, SnarkVerifier};
 
+use super::backend::FieldBackend;
+use super::batch_verifier_circuit::{BatchVerifier, CircuitRegistry};
+use super::compression::{self, CompressionCircuit, PoseidonTranscript, WrapperSnark};
+use super::cost;
+use super::disclosure::SelectiveDisclosure;
+use super::envelope::WrappedProofEnvelope;
+use super::error::VerifierError;
+use super::events::{ArtifactHashes, PipelineEvent};
+use super::export::batches::{comparison_table, BatchArtifacts, BatchSummary};
+use super::export::layout::ArtifactsLayout;
+use super::g2_args;
+use super::inner::{ConvertStage, InnerProofSystem, Plonky2};
+use super::instances::{
+    instance_column, instance_sources, InstanceExplanation, InstanceSource, OrderedInstances,
+};
+use super::job::{CancellationToken, JobHandle};
+use super::keygen::KeygenCache;
+use super::metrics;
+use super::pipeline::{
+    MultiOpenScheme, NativeVerificationStrategy, PipelineConfig, TranscriptKind,
+};
+use super::report::{MockReport, Stage, StageTimings, VerificationReport};
+use super::solc::Solc;
+use super::srs::{
+    read_params_file, CeremonySrs, InsecureTestSrs, PointEncoding, SrsManager, SrsMetadata,
+    SrsSource,
+};
+use super::stages::{CompileVerifier, Convert, Pipeline, Prove, Simulate, Synthesize};
 use super::types::{
-    self, common_data::CommonData, proof::ProofValues, verification_key::VerificationKeyValues,
+    common_data::{CommonData, FriSoundness},
+    proof::ProofValues,
+    verification_key::VerificationKeyValues,
 };
+use super::verifier::VerifierKey;
 use super::verifier_circuit::Verifier;
+use super::vk_registry;
+
+type PlonkVerifier<MOS> = verifier::plonk::PlonkVerifier<
+    KzgAs<Bn256, MOS>,
+    LimbsEncoding<{ compression::LIMBS }, { compression::BITS }>,
+>;
 
-type PlonkVerifier = verifier::plonk::PlonkVerifier<KzgAs<Bn256, Gwc19>>;
+/// Maximum size of deployed contract code, see EIP-170
+pub const EVM_CONTRACT_SIZE_LIMIT: usize = 24_576;
+
+/// Most instances the EVM verifier is generated for. snark-verifier unrolls the loading of every
+/// instance from the calldata and its Lagrange term in the evaluation of the instance polynomial,
+/// so that the Yul of more instances takes long to generate and compiles to a verifier far over
+/// `EVM_CONTRACT_SIZE_LIMIT`, when solc doesn't fail with "stack too deep" first.
+pub const MAX_EVM_INSTANCES: usize = 128;
+
+/// Degree of the SRS generated when no ceremony SRS is loaded
+const INSECURE_SRS_K: u32 = 23;
 
 lazy_static! {
-    static ref SRS: ParamsKZG<Bn256> = EvmVerifier::gen_srs(23);
+    static ref SRS: Mutex<SrsManager> = Mutex::new(SrsManager::default());
+    static ref KEYGEN_CACHE: Mutex<KeygenCache> = Mutex::new(KeygenCache::default());
+}
+
+/// Sets the number of proving keys kept across the keygens of the process, see `KeygenCache`.
+/// Zero, the default, disables the cache and frees the proving keys it holds.
+pub fn set_keygen_cache_capacity(capacity: usize) {
+    KEYGEN_CACHE.lock().unwrap().set_capacity(capacity);
 }
 
-struct EvmVerifier {}
+/// Replaces the SRS used by the pipeline with `srs`
+pub fn use_ceremony_srs(srs: CeremonySrs) {
+    let mut manager = SrsManager::default();
+    manager.insert_ceremony(srs);
+    *SRS.lock().unwrap() = manager;
+}
+
+/// Replaces the SRS used by the pipeline with an insecure SRS of degree `INSECURE_SRS_K`
+/// generated from `seed`, see `InsecureTestSrs::from_seed`
+pub fn use_insecure_srs(seed: u64) {
+    let mut manager = SrsManager::default();
+    manager.insert_insecure(InsecureTestSrs::from_seed(INSECURE_SRS_K, seed));
+    *SRS.lock().unwrap() = manager;
+}
+
+/// Loads the SRS of the Perpetual Powers of Tau ceremony at `path` and uses it in the pipeline
+pub fn load_ceremony_srs(path: PathBuf) -> std::io::Result<()> {
+    load_srs(SrsSource::PerpetualPowersOfTau(path.clone())).map_err(|e| {
+        std::io::Error::new(
+            e.kind(),
+            format!("Failed to read .srs file {}: {e}", path.display()),
+        )
+    })
+}
+
+/// Loads the SRS of `source`, from the Perpetual Powers of Tau or a bespoke ceremony, and uses
+/// it in the pipeline
+pub fn load_srs(source: SrsSource) -> std::io::Result<()> {
+    use_ceremony_srs(source.read()?);
+    Ok(())
+}
+
+/// Loads parameters of `ceremony` serialized in the `ParamsKZG` format with points encoded as
+/// `encoding`, and uses them in the pipeline
+pub fn load_ceremony_params(
+    path: &Path,
+    encoding: PointEncoding,
+    ceremony: &str,
+) -> std::io::Result<()> {
+    let params = read_params_file(path, encoding)?;
+    use_ceremony_srs(CeremonySrs::new(params, path.to_string_lossy(), ceremony));
+    Ok(())
+}
+
+pub(crate) struct EvmVerifier {}
 
 impl EvmVerifier {
-    pub fn gen_srs(k: u32) -> ParamsKZG<Bn256> {
-        ParamsKZG::<Bn256>::setup(k, OsRng)
+    /// Runs `f` on `SRS`, generating an insecure SRS of degree `INSECURE_SRS_K` if no SRS was
+    /// loaded
+    fn with_srs<R>(f: impl FnOnce(&mut SrsManager) -> R) -> R {
+        let mut srs = SRS.lock().unwrap();
+        if srs.max_k().is_none() {
+            srs.insert_insecure(InsecureTestSrs::generate(INSECURE_SRS_K));
+        }
+        f(&mut srs)
     }
 
-    fn prepare_params(path: PathBuf) -> ParamsKZG<Bn256> {
-        let srs = Srs::<Bn256>::read(
-            &mut std::fs::File::open(path.clone())
-                .with_context(|| format!("Failed to read .srs file {}", path.to_str().unwrap()))
-                .unwrap(),
-            SrsFormat::PerpetualPowerOfTau(23),
-        );
+    /// Largest `k` the parameters held by `SRS` support
+    fn max_k() -> u32 {
+        Self::with_srs(|srs| srs.max_k().unwrap_or(0))
+    }
 
-        let mut buf = Vec::new();
-        srs.write_raw(&mut buf);
-        let params = ParamsKZG::<Bn256>::read(&mut std::io::Cursor::new(buf))
-            .with_context(|| "Malformed params file")
-            .unwrap();
-        params
+    /// Fails with `SrsTooSmall` if the SRS held by `SRS` doesn't support `k`
+    fn check_k(k: u32) -> Result<(), VerifierError> {
+        let max_k = Self::max_k();
+        if k > max_k {
+            return Err(VerifierError::SrsTooSmall { k, max_k });
+        }
+        Ok(())
     }
 
-    fn gen_pk<C: Circuit<Fr>>(params: &ParamsKZG<Bn256>, circuit: &C) -> ProvingKey<G1Affine> {
+    /// Returns the parameters of degree `k` held by `SRS`, failing with `SrsTooSmall` if it
+    /// doesn't support `k`
+    fn params_for_k(k: u32) -> Result<Arc<ParamsKZG<Bn256>>, VerifierError> {
+        Self::with_srs(|srs| {
+            let max_k = srs.max_k().unwrap_or(0);
+            srs.get(k).ok_or(VerifierError::SrsTooSmall { k, max_k })
+        })
+    }
+
+    /// Metadata of the SRS held by `SRS`
+    fn srs_metadata() -> Option<SrsMetadata> {
+        Self::with_srs(|srs| srs.metadata().cloned())
+    }
+
+    /// Fails unless the SRS comes from a ceremony or `allow_insecure` is set, so that no
+    /// deployment code is emitted from a locally generated SRS by mistake. Checked before
+    /// any synthesis, so that an insecure SRS fails fast
+    fn check_srs(allow_insecure: bool) -> Result<(), VerifierError> {
+        if allow_insecure || Self::with_srs(|srs| srs.is_ceremony()) {
+            Ok(())
+        } else {
+            Err(VerifierError::InsecureSrs)
+        }
+    }
+
+    /// Generates the proving key of `circuit`, or reuses the one of `KEYGEN_CACHE` with the
+    /// same verifying key. The lock isn't held during `keygen_pk`.
+    fn gen_pk<C: Circuit<Fr>>(params: &ParamsKZG<Bn256>, circuit: &C) -> Arc<ProvingKey<G1Affine>> {
         let vk = keygen_vk(params, circuit).unwrap();
-        keygen_pk(params, vk, circuit).unwrap()
+        if let Some(pk) = KEYGEN_CACHE.lock().unwrap().get(params.k(), &vk) {
+            return pk;
+        }
+        let pk = Arc::new(keygen_pk(params, vk, circuit).unwrap());
+        KEYGEN_CACHE.lock().unwrap().insert(params.k(), pk.clone());
+        pk
     }
 
-    fn gen_proof<C: Circuit<Fr>>(
+    /// Verifies `proof`, made with `multiopen` and a Keccak256 transcript, natively with
+    /// `strategy`
+    fn verify_native(
         params: &ParamsKZG<Bn256>,
-        pk: &ProvingKey<G1Affine>,
-        circuit: C,
-        instances: Vec<Vec<Fr>>,
-    ) -> Vec<u8> {
-        MockProver::run(params.k(), &circuit, instances.clone())
-            .unwrap()
-            .assert_satisfied();
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        proof: &[u8],
+        strategy: NativeVerificationStrategy,
+        multiopen: MultiOpenScheme,
+    ) -> Result<bool, VerifierError> {
+        Self::verify_native_with_transcript(
+            params,
+            vk,
+            instances,
+            proof,
+            strategy,
+            multiopen,
+            TranscriptKind::Keccak256,
+        )
+    }
 
-        let instances = instances
-            .iter()
-            .map(|instances| instances.as_slice())
-            .collect_vec();
-        let proof = {
-            let mut transcript = TranscriptWriterBuffer::<_, G1Affine, _>::init(Vec::new());
-            create_proof::<
-                KZGCommitmentScheme<Bn256>,
-                ProverGWC<_>,
-                _,
-                _,
-                EvmTranscript<_, _, _, _>,
-                _,
-            >(
+    /// Same as `verify_native`, for a proof made with `transcript`
+    fn verify_native_with_transcript(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        proof: &[u8],
+        strategy: NativeVerificationStrategy,
+        multiopen: MultiOpenScheme,
+        transcript: TranscriptKind,
+    ) -> Result<bool, VerifierError> {
+        match multiopen {
+            MultiOpenScheme::Gwc => Self::verify_native_with::<VerifierGWC<_>>(
+                params, vk, instances, proof, strategy, transcript,
+            ),
+            MultiOpenScheme::Shplonk => Self::verify_native_with::<VerifierSHPLONK<_>>(
+                params, vk, instances, proof, strategy, transcript,
+            ),
+        }
+    }
+
+    fn verify_native_with<'params, V>(
+        params: &'params ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        proof: &[u8],
+        strategy: NativeVerificationStrategy,
+        transcript: TranscriptKind,
+    ) -> Result<bool, VerifierError>
+    where
+        V: halo2_proofs::poly::commitment::Verifier<'params, KZGCommitmentScheme<Bn256>>,
+        AccumulatorStrategy<'params, Bn256>: VerificationStrategy<
+            'params,
+            KZGCommitmentScheme<Bn256>,
+            V,
+            Output = AccumulatorStrategy<'params, Bn256>,
+        >,
+        SingleStrategy<'params, Bn256>:
+            VerificationStrategy<'params, KZGCommitmentScheme<Bn256>, V, Output = ()>,
+    {
+        match transcript {
+            TranscriptKind::Keccak256 => Self::verify_native_from::<V, _, _>(
                 params,
-                pk,
-                &[circuit],
-                &[instances.as_slice()],
-                OsRng,
-                &mut transcript,
-            )
-            .unwrap();
-            transcript.finalize()
-        };
+                vk,
+                instances,
+                strategy,
+                &mut EvmTranscript::<G1Affine, NativeLoader, _, _>::new(proof),
+            ),
+            TranscriptKind::Blake2b => Self::verify_native_from::<V, _, _>(
+                params,
+                vk,
+                instances,
+                strategy,
+                &mut Blake2bRead::<_, G1Affine, Challenge255<_>>::init(proof),
+            ),
+        }
+    }
 
-        let accept = {
-            let mut transcript = TranscriptReadBuffer::<_, G1Affine, _>::init(proof.as_slice());
-            VerificationStrategy::<_, VerifierGWC<_>>::finalize(
-                verify_proof::<_, VerifierGWC<_>, _, EvmTranscript<_, _, _, _>, _>(
+    fn verify_native_from<'params, V, E, T>(
+        params: &'params ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &OrderedInstances,
+        strategy: NativeVerificationStrategy,
+        transcript: &mut T,
+    ) -> Result<bool, VerifierError>
+    where
+        E: EncodedChallenge<G1Affine>,
+        T: TranscriptRead<G1Affine, E>,
+        V: halo2_proofs::poly::commitment::Verifier<'params, KZGCommitmentScheme<Bn256>>,
+        AccumulatorStrategy<'params, Bn256>: VerificationStrategy<
+            'params,
+            KZGCommitmentScheme<Bn256>,
+            V,
+            Output = AccumulatorStrategy<'params, Bn256>,
+        >,
+        SingleStrategy<'params, Bn256>:
+            VerificationStrategy<'params, KZGCommitmentScheme<Bn256>, V, Output = ()>,
+    {
+        let instances = [instances.as_slice()];
+
+        match strategy {
+            NativeVerificationStrategy::Accumulator => {
+                let result = verify_proof::<_, V, _, T, _>(
                     params.verifier_params(),
-                    pk.get_vk(),
+                    vk,
                     AccumulatorStrategy::new(params.verifier_params()),
                     &[instances.as_slice()],
-                    &mut transcript,
+                    transcript,
+                );
+                match result {
+                    Ok(strategy) => Ok(VerificationStrategy::<_, V>::finalize(strategy)),
+                    Err(e) => rejection(e),
+                }
+            }
+            // `SingleStrategy` checks the proof while processing it and can't be finalized
+            NativeVerificationStrategy::Single => {
+                let result = verify_proof::<_, V, _, T, _>(
+                    params.verifier_params(),
+                    vk,
+                    SingleStrategy::new(params.verifier_params()),
+                    &[instances.as_slice()],
+                    transcript,
+                );
+                match result {
+                    Ok(()) => Ok(true),
+                    Err(e) => rejection(e),
+                }
+            }
+        }
+    }
+
+    /// Creates the proof with `multiopen` only, without checking it. Circuits with challenges,
+    /// e.g. for RLC lookups, are proven phase by phase, the challenges of a phase being squeezed
+    /// from the transcript once the columns of the previous phases are committed, which the
+    /// protocol compiled by snark-verifier replays from the phases of the verifying key.
+    pub(crate) fn prove<C: Circuit<Fr>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        multiopen: MultiOpenScheme,
+        rng: impl RngCore,
+    ) -> Vec<u8> {
+        Self::prove_with_transcript(
+            params,
+            pk,
+            circuit,
+            instances,
+            multiopen,
+            TranscriptKind::Keccak256,
+            rng,
+        )
+    }
+
+    /// Same as `prove`, with `transcript` instead of the Keccak256 transcript of the EVM verifier
+    pub(crate) fn prove_with_transcript<C: Circuit<Fr>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        multiopen: MultiOpenScheme,
+        transcript: TranscriptKind,
+        rng: impl RngCore,
+    ) -> Vec<u8> {
+        type Keccak256<W> = EvmTranscript<G1Affine, NativeLoader, W, Vec<u8>>;
+        type Blake2b<W> = Blake2bWrite<W, G1Affine, Challenge255<G1Affine>>;
+        match (multiopen, transcript) {
+            (MultiOpenScheme::Gwc, TranscriptKind::Keccak256) => {
+                Self::prove_with::<ProverGWC<_>, _, _, Keccak256<_>>(
+                    params, pk, circuit, instances, rng,
                 )
-                .unwrap(),
-            )
-        };
-        assert!(accept);
+            }
+            (MultiOpenScheme::Gwc, TranscriptKind::Blake2b) => {
+                Self::prove_with::<ProverGWC<_>, _, _, Blake2b<_>>(
+                    params, pk, circuit, instances, rng,
+                )
+            }
+            (MultiOpenScheme::Shplonk, TranscriptKind::Keccak256) => {
+                Self::prove_with::<ProverSHPLONK<_>, _, _, Keccak256<_>>(
+                    params, pk, circuit, instances, rng,
+                )
+            }
+            (MultiOpenScheme::Shplonk, TranscriptKind::Blake2b) => {
+                Self::prove_with::<ProverSHPLONK<_>, _, _, Blake2b<_>>(
+                    params, pk, circuit, instances, rng,
+                )
+            }
+        }
+    }
 
-        proof
+    fn prove_with<'params, P, C, E, T>(
+        params: &'params ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        rng: impl RngCore,
+    ) -> Vec<u8>
+    where
+        P: Prover<'params, KZGCommitmentScheme<Bn256>>,
+        C: Circuit<Fr>,
+        E: EncodedChallenge<G1Affine>,
+        T: TranscriptWriterBuffer<Vec<u8>, G1Affine, E>,
+    {
+        let instances = [instances.as_slice()];
+        let mut transcript = T::init(Vec::new());
+        create_proof::<KZGCommitmentScheme<Bn256>, P, _, _, T, _>(
+            params,
+            pk,
+            &[circuit],
+            &[instances.as_slice()],
+            rng,
+            &mut transcript,
+        )
+        .unwrap();
+        transcript.finalize()
     }
 
-    /// Generates EVM verifier for the proof generated by circuit `stark_verifier`
-    fn gen_evm_verifier(
+    /// Creates a GWC proof with the Poseidon transcript verified by `CompressionCircuit`
+    fn prove_for_compression<C: Circuit<Fr>>(
+        params: &ParamsKZG<Bn256>,
+        pk: &ProvingKey<G1Affine>,
+        circuit: C,
+        instances: &OrderedInstances,
+        rng: impl RngCore,
+    ) -> Vec<u8> {
+        let instances = [instances.as_slice()];
+        let mut transcript = PoseidonTranscript::<NativeLoader, _>::new(Vec::new());
+        create_proof::<KZGCommitmentScheme<Bn256>, ProverGWC<_>, _, _, _, _>(
+            params,
+            pk,
+            &[circuit],
+            &[instances.as_slice()],
+            rng,
+            &mut transcript,
+        )
+        .unwrap();
+        transcript.finalize()
+    }
+
+    /// Generates the Yul source of the EVM verifier for the proof generated by circuit
+    /// `stark_verifier` with `multiopen`. The instances at `accumulator_indices`, if any, hold
+    /// an accumulator checked along the proof, see `compression`.
+    pub(crate) fn gen_evm_verifier_yul(
         params: &ParamsKZG<Bn256>,
         vk: &VerifyingKey<G1Affine>,
         num_instance: Vec<usize>,
-    ) -> Vec<u8> {
+        accumulator_indices: Option<Vec<(usize, usize)>>,
+        multiopen: MultiOpenScheme,
+    ) -> String {
         let protocol = compile(
             params,
             vk,
-            Config::kzg().with_num_instance(num_instance.clone()),
+            Config::kzg()
+                .with_num_instance(num_instance.clone())
+                .with_accumulator_indices(accumulator_indices),
         );
         let vk = (params.get_g()[0], params.g2(), params.s_g2()).into();
 
@@ -140,31 +472,344 @@ impl EvmVerifier {
         let mut transcript = EvmTranscript::<_, Rc<EvmLoader>, _, _>::new(&loader);
 
         let instances = transcript.load_instances(num_instance);
-        let proof = PlonkVerifier::read_proof(&vk, &protocol, &instances, &mut transcript).unwrap();
-        PlonkVerifier::verify(&vk, &protocol, &instances, &proof).unwrap();
+        match multiopen {
+            MultiOpenScheme::Gwc => {
+                let proof =
+                    PlonkVerifier::<Gwc19>::read_proof(&vk, &protocol, &instances, &mut transcript)
+                        .unwrap();
+                PlonkVerifier::<Gwc19>::verify(&vk, &protocol, &instances, &proof).unwrap();
+            }
+            MultiOpenScheme::Shplonk => {
+                let proof = PlonkVerifier::<Bdfg21>::read_proof(
+                    &vk,
+                    &protocol,
+                    &instances,
+                    &mut transcript,
+                )
+                .unwrap();
+                PlonkVerifier::<Bdfg21>::verify(&vk, &protocol, &instances, &proof).unwrap();
+            }
+        }
+
+        loader.yul_code()
+    }
+
+    /// Generates EVM verifier for the proof generated by circuit `stark_verifier`, returning its
+    /// Yul source and deployment code
+    /// Fails if the verifier exceeds `EVM_CONTRACT_SIZE_LIMIT`. The constructor of the generated
+    /// contract only copies the runtime code, so the size of the deployment code is used as a
+    /// (slightly pessimistic) estimate of the deployed code size.
+    fn gen_evm_verifier(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: Vec<usize>,
+        multiopen: MultiOpenScheme,
+        solc: &Solc,
+    ) -> Result<(String, Vec<u8>), VerifierError> {
+        Self::check_num_instances(&num_instance)?;
+        let yul_code = Self::gen_evm_verifier_yul(params, vk, num_instance, None, multiopen);
+        let deployment_code = Self::compile(&yul_code, solc)?;
+        Ok((yul_code, deployment_code))
+    }
 
-        evm::compile_yul(&loader.yul_code())
+    /// Same as `gen_evm_verifier_yul`, for a verifier taking the G2 points of `params` as
+    /// constructor arguments, which are returned with the Yul source
+    pub(crate) fn gen_evm_verifier_yul_with_g2_args(
+        params: &ParamsKZG<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        num_instance: Vec<usize>,
+        accumulator_indices: Option<Vec<(usize, usize)>>,
+        multiopen: MultiOpenScheme,
+    ) -> Result<(String, Vec<u8>), VerifierError> {
+        let words = g2_args::g2_constructor_args(params);
+        let yul_code =
+            Self::gen_evm_verifier_yul(params, vk, num_instance, accumulator_indices, multiopen);
+        let yul_code = g2_args::parametrize_g2(&yul_code, &words)
+            .map_err(|index| VerifierError::G2ConstantNotFound { index })?;
+        Ok((yul_code, words.concat()))
     }
 
-    fn evm_verify(deployment_code: Vec<u8>, instances: Vec<Vec<Fr>>, proof: Vec<u8>) {
-        let calldata = encode_calldata(&instances, &proof);
-        let success = {
-            let mut evm = ExecutorBuilder::default()
-                .with_gas_limit(u64::MAX.into())
-                .build();
+    /// Checks `proof` with the snark-verifier protocol of `verifier_key`, verified with the native
+    /// loader, which the EVM verifier is generated from with the EVM loader
+    pub(crate) fn check_protocol(
+        verifier_key: &VerifierKey,
+        instances: &OrderedInstances,
+        proof: &[u8],
+    ) -> Result<(), VerifierError> {
+        match verifier_key.verify(instances, proof) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(VerifierError::ProtocolMismatch(
+                "the pairing check fails".to_string(),
+            )),
+            Err(e) => Err(VerifierError::ProtocolMismatch(e.to_string())),
+        }
+    }
 
-            let caller = evm::Address::from_low_u64_be(0xfe);
-            let verifier = evm
-                .deploy(caller, deployment_code.into(), 0.into())
-                .address
-                .unwrap();
-            let result = evm.call_raw(caller, verifier, calldata.into(), 0.into());
+    /// Fails if the instance columns of `num_instance` hold more than `MAX_EVM_INSTANCES`
+    /// instances, before generating their verifier
+    pub(crate) fn check_num_instances(num_instance: &[usize]) -> Result<(), VerifierError> {
+        let instances = num_instance.iter().sum::<usize>();
+        if instances > MAX_EVM_INSTANCES {
+            return Err(VerifierError::TooManyInstances {
+                instances,
+                limit: MAX_EVM_INSTANCES,
+            });
+        }
+        Ok(())
+    }
 
-            dbg!(result.gas_used);
+    /// Compiles `yul_code` with `solc`, failing if it exceeds `EVM_CONTRACT_SIZE_LIMIT`
+    pub(crate) fn compile(yul_code: &str, solc: &Solc) -> Result<Vec<u8>, VerifierError> {
+        let deployment_code = solc.compile_yul(yul_code)?;
+        if deployment_code.len() > EVM_CONTRACT_SIZE_LIMIT {
+            return Err(VerifierError::ContractTooLarge {
+                size: deployment_code.len(),
+                limit: EVM_CONTRACT_SIZE_LIMIT,
+            });
+        }
+        Ok(deployment_code)
+    }
+}
 
-            !result.reverted
-        };
-        assert!(success);
+/// Outcome of the native verification of a proof failing with `error`: a proof which can't be
+/// read from the transcript is malformed, any other error rejects it
+fn rejection(error: Error) -> Result<bool, VerifierError> {
+    match error {
+        Error::Transcript(e) => Err(VerifierError::UnreadableProof(e)),
+        _ => Ok(false),
+    }
+}
+
+/// Runs the native Plonky2 verifier on `proof`
+pub(crate) fn check_inner_proof<C: GenericConfig<D, F = GoldilocksField>, const D: usize>(
+    proof: &ProofTuple<GoldilocksField, C, D>,
+) -> Result<(), VerifierError>
+where
+    GoldilocksField: Extendable<D>,
+{
+    let (proof_with_public_inputs, vd, cd) = proof;
+    let verifier_data = VerifierCircuitData {
+        verifier_only: vd.clone(),
+        common: cd.clone(),
+    };
+    verifier_data
+        .verify(proof_with_public_inputs.clone())
+        .map_err(VerifierError::InvalidInnerProof)
+}
+
+/// Builds the verifier circuit of `proof` with the options of `config`, returning it with the
+/// `k` to start from
+pub(crate) fn build_verifier(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<(Verifier, u32), VerifierError> {
+    let (proof_with_public_inputs, vd, cd) = proof;
+    let proof = ProofValues::<Fr, 2>::try_from(proof_with_public_inputs.proof)?;
+    let instances =
+        OrderedInstances::try_from_public_inputs(&proof_with_public_inputs.public_inputs)?;
+    let vk = VerificationKeyValues::try_from(vd)?;
+    let common_data = CommonData::try_from(cd)?;
+    let spec = common_data.spec();
+    if config.strict_canonical && config.k.is_none() {
+        println!(
+            "{}",
+            format!(
+                "Strict canonical checks cost about {} rows",
+                cost::canonical_check_rows(&common_data)
+            )
+            .yellow()
+        );
+    }
+
+    let mut circuit = Verifier::new(proof, instances.as_slice().to_vec(), vk, common_data, spec)
+        .with_layout(config.layout.clone())
+        .with_strict_canonical_checks(config.strict_canonical)
+        .with_vk_commitment(config.vk_commitment);
+    if let Some(nonce) = config.nonce {
+        circuit = circuit.with_nonce(nonce);
+    }
+    if let Some(disclosure) = &config.disclosure {
+        check_disclosure(disclosure, instances.len())?;
+        circuit = circuit.with_disclosure(disclosure.clone());
+    }
+    if let Some(padded_len) = config.padded_instances {
+        check_padding(circuit.instances().len(), padded_len)?;
+        circuit = circuit.with_instance_padding(padded_len);
+    }
+    #[cfg(feature = "debug")]
+    if let Some(path) = &config.challenge_dump {
+        circuit = circuit.with_challenge_dump(path);
+    }
+    // the estimate covers the options of the circuit, e.g. the strict canonical checks
+    let k = config
+        .k
+        .unwrap_or_else(|| cost::k_for_rows(circuit.estimate_rows()));
+    Ok((circuit, k))
+}
+
+fn check_disclosure(
+    disclosure: &SelectiveDisclosure,
+    num_public_inputs: usize,
+) -> Result<(), VerifierError> {
+    disclosure
+        .check(num_public_inputs)
+        .map_err(|index| VerifierError::InvalidDisclosure {
+            index,
+            num_public_inputs,
+        })
+}
+
+fn check_padding(num_instances: usize, padded_len: usize) -> Result<(), VerifierError> {
+    if num_instances > padded_len {
+        return Err(VerifierError::InstancesExceedPadding {
+            num_instances,
+            padded_len,
+        });
+    }
+    Ok(())
+}
+
+/// Fails if `soundness` is below `PipelineConfig::min_security_bits`, or unknown while a minimum
+/// is set
+fn check_soundness(
+    soundness: Option<FriSoundness>,
+    config: &PipelineConfig,
+) -> Result<(), VerifierError> {
+    let conjectured_bits = soundness.map(|soundness| soundness.conjectured_bits);
+    match config.min_security_bits {
+        Some(min_bits) if !matches!(conjectured_bits, Some(bits) if bits >= min_bits) => {
+            Err(VerifierError::InsufficientSoundness {
+                conjectured_bits,
+                min_bits,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Runs `pipeline` between the `Started` event of `config` and its `ProofReady` or `Failed` one
+fn with_events(
+    config: &PipelineConfig,
+    pipeline: impl FnOnce() -> Result<VerificationReport, VerifierError>,
+) -> Result<VerificationReport, VerifierError> {
+    let emit = |event: PipelineEvent| {
+        if let Some(events) = &config.event_sink {
+            events.emit(&event);
+        }
+    };
+    emit(PipelineEvent::Started);
+    let report = pipeline();
+    emit(match &report {
+        Ok(report) => PipelineEvent::ProofReady(ArtifactHashes::new(report)),
+        Err(e) => PipelineEvent::Failed {
+            error: e.to_string(),
+        },
+    });
+    report
+}
+
+/// Instances the verifier circuit of `proof` exposes with the options of `config`, in the order
+/// of the instance column, so that relayers can predict the on-chain instances without building
+/// the circuit. With `PipelineConfig::compression`, the compressed proof exposes them after an
+/// accumulator which depends on the proof, see `compression`.
+pub fn extract_instances(
+    proof: &ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<OrderedInstances, VerifierError> {
+    let (proof_with_public_inputs, vd, _) = proof;
+    let public_inputs =
+        OrderedInstances::try_from_public_inputs(&proof_with_public_inputs.public_inputs)?;
+    let vk_commitment = if config.vk_commitment {
+        let vk = VerificationKeyValues::try_from(vd.clone())?;
+        Some(vk_registry::vk_commitment(&vk))
+    } else {
+        None
+    };
+    let exposed_public_inputs = match &config.disclosure {
+        Some(disclosure) => {
+            check_disclosure(disclosure, public_inputs.len())?;
+            disclosure.instances(public_inputs.as_slice())
+        }
+        None => public_inputs.as_slice().to_vec(),
+    };
+    let mut instances = instance_column(
+        &config.layout,
+        vk_commitment,
+        exposed_public_inputs,
+        config.nonce,
+        None,
+    );
+    if let Some(padded_len) = config.padded_instances {
+        check_padding(instances.len(), padded_len)?;
+        instances.resize(padded_len, Fr::from(0));
+    }
+    Ok(OrderedInstances::new(instances))
+}
+
+/// Explains instance `index` of the instances `extract_instances` computes for `proof` with
+/// `config`: the Plonky2 public input or metadata it holds, its value and where it lies in the
+/// calldata laid out as `config.calldata_layout`, next to a wrapper proof of `proof_len` bytes.
+/// Returns `None` if there is no such instance.
+pub fn explain_instance(
+    proof: &ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+    proof_len: usize,
+    index: usize,
+) -> Result<Option<InstanceExplanation>, VerifierError> {
+    let instances = extract_instances(proof, config)?;
+    let public_inputs = &proof.0.public_inputs;
+    let sources = instance_sources(
+        &config.layout,
+        config.vk_commitment,
+        config.disclosure.as_ref(),
+        public_inputs.len(),
+        config.nonce.is_some(),
+        config.padded_instances,
+    );
+    debug_assert_eq!(sources.len(), instances.len());
+    Ok(sources
+        .into_iter()
+        .zip(instances.as_slice())
+        .nth(index)
+        .map(|(source, value)| {
+            let goldilocks = match source {
+                InstanceSource::PublicInput(i) => Some(public_inputs[i].to_canonical_u64()),
+                _ => None,
+            };
+            InstanceExplanation::new(
+                index,
+                source,
+                goldilocks,
+                *value,
+                config.calldata_layout,
+                proof_len,
+            )
+        }))
+}
+
+/// Runs the mock prover at `k`, retrying with `k + 1` up to `max_k` while the circuit doesn't
+/// fit. Returns the `k` the circuit fits in.
+pub(crate) fn run_mock_prover(
+    circuit: &impl Circuit<Fr>,
+    instances: &OrderedInstances,
+    mut k: u32,
+    max_k: u32,
+) -> Result<(u32, MockProver<Fr>), VerifierError> {
+    loop {
+        match MockProver::run(k, circuit, instances.columns()) {
+            Ok(prover) => return Ok((k, prover)),
+            Err(Error::NotEnoughRowsAvailable { .. }) if k < max_k => {
+                println!(
+                    "{}",
+                    format!("Not enough rows at k = {k}, retrying with k = {}", k + 1).yellow()
+                );
+                k += 1;
+            }
+            Err(Error::NotEnoughRowsAvailable { .. }) => {
+                return Err(VerifierError::NotEnoughRows { k })
+            }
+            Err(e) => return Err(VerifierError::Synthesis(e)),
+        }
     }
 }
 
@@ -180,57 +825,1569 @@ fn report_elapsed(now: Instant) {
 /// Public API for generating Halo2 proof for Plonky2 verifier circuit
 /// feed Plonky2 proof, `VerifierOnlyCircuitData`, `CommonCircuitData`
 /// This runs only mock prover for constraint check
-pub fn verify_inside_snark_mock(proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>) {
-    let (proof_with_public_inputs, vd, cd) = proof;
-
-    // proof_with_public_inputs -> ProofValues type
-    let proof = ProofValues::<Fr, 2>::from(proof_with_public_inputs.proof);
-
-    let instances = proof_with_public_inputs
-        .public_inputs
-        .iter()
-        .map(|e| big_to_fe(fe_to_big::<Goldilocks>(types::to_goldilocks(*e))))
-        .collect::<Vec<Fr>>();
-    let vk = VerificationKeyValues::from(vd.clone());
-    let common_data = CommonData::from(cd);
+pub fn verify_inside_snark_mock(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> Result<MockReport, VerifierError> {
+    verify_inside_snark_mock_with_config(proof, &PipelineConfig::default())
+}
 
-    let spec = Spec::<Goldilocks, 12, 11>::new(8, 22);
+/// Same as `verify_inside_snark_mock`, with the pipeline options given by `config`
+pub fn verify_inside_snark_mock_with_config(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<MockReport, VerifierError> {
+    config.install(|| {
+        check_soundness(Plonky2.fri_soundness(&proof), config)?;
+        if config.check_inner_proof {
+            check_inner_proof(&proof)?;
+        }
+        let (verifier_circuit, k) = build_verifier(proof, config)?;
+        let instances = verifier_circuit.ordered_instances();
+        let max_k = config.max_k.unwrap_or(k);
+        let mut report = mock_report(&verifier_circuit, &instances, k, max_k)?;
+        report.constant_rows_saved = Some(
+            verifier_circuit
+                .constant_rows_saved()
+                .map_err(VerifierError::Synthesis)?,
+        );
+        report.region_profile = Some(
+            verifier_circuit
+                .region_profile()
+                .map_err(VerifierError::Synthesis)?,
+        );
+        Ok(report)
+    })
+}
 
-    let verifier_circuit = Verifier::new(proof, instances.clone(), vk, common_data, spec);
-    let _prover = MockProver::run(23, &verifier_circuit, vec![instances]).unwrap();
-    _prover.assert_satisfied()
+/// Runs the mock prover like `run_mock_prover` and reports on the run
+fn mock_report(
+    circuit: &impl Circuit<Fr>,
+    instances: &OrderedInstances,
+    k: u32,
+    max_k: u32,
+) -> Result<MockReport, VerifierError> {
+    let (k, prover) = run_mock_prover(circuit, instances, k, max_k)?;
+    let report = MockReport::new(circuit, k, &prover).map_err(VerifierError::Synthesis)?;
+    if report.satisfied {
+        println!(
+            "{}",
+            format!("Mock prover passes with k = {k}").white().bold()
+        );
+    } else {
+        println!(
+            "{}",
+            format!(
+                "Mock prover fails with k = {k}, {} unsatisfied constraints",
+                report.failures.len()
+            )
+            .red()
+            .bold()
+        );
+    }
+    Ok(report)
 }
 
 /// Public API for generating Halo2 proof for Plonky2 verifier circuit
 /// feed Plonky2 proof, `VerifierOnlyCircuitData`, `CommonCircuitData`
 /// This runs real prover and generates valid SNARK proof, generates EVM verifier and runs the verifier
-pub fn verify_inside_snark(proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>) {
-    let (proof_with_public_inputs, vd, cd) = proof;
-    let proof = ProofValues::<Fr, 2>::from(proof_with_public_inputs.proof);
-    let instances = proof_with_public_inputs
-        .public_inputs
+/// Plonky2 proofs without public inputs are supported, the generated verifier then takes the
+/// proof bytes only as calldata
+/// Returns a `VerificationReport` holding the SNARK proof and the EVM verifier
+/// Fails with `VerifierError::InsecureSrs` unless a ceremony SRS was loaded with
+/// `load_ceremony_srs`, `load_srs` or `use_ceremony_srs`
+pub fn verify_inside_snark(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+) -> Result<VerificationReport, VerifierError> {
+    verify_inside_snark_with_config(proof, &PipelineConfig::default())
+}
+
+/// Same as `verify_inside_snark`, with the pipeline options given by `config`
+pub fn verify_inside_snark_with_config(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    verify_inside_snark_cancellable(proof, config, &CancellationToken::new())
+}
+
+/// Same as `verify_inside_snark_with_config`, stopping between two phases of the pipeline once
+/// `token` is cancelled or timed out
+pub fn verify_inside_snark_cancellable(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+    token: &CancellationToken,
+) -> Result<VerificationReport, VerifierError> {
+    Pipeline::new().run_cancellable(proof, config, token)
+}
+
+/// Same as `verify_inside_snark_with_config`, for a proof of another proof system than Plonky2,
+/// see `inner`
+pub fn verify_inside_snark_with_system<S>(
+    system: &S,
+    proof: S::Proof,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError>
+where
+    S: InnerProofSystem + Sync,
+    S::Proof: Send,
+{
+    Pipeline::new().run_inner(system, proof, config)
+}
+
+/// Runs `verify_inside_snark_with_config` in a background thread, returning a handle to cancel
+/// the job, give it a deadline and wait for its report
+pub fn spawn_verify_inside_snark(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: PipelineConfig,
+) -> JobHandle {
+    JobHandle::spawn(move |token| verify_inside_snark_cancellable(proof, &config, token))
+}
+
+impl<Cv: Convert, Sy: Synthesize, P: Prove, Co: CompileVerifier, Si: Simulate>
+    Pipeline<Cv, Sy, P, Co, Si>
+{
+    /// Runs the stages on `proof` in the pool of `config`, see `Pipeline::run_cancellable`
+    pub(crate) fn run_in_pool(
+        &self,
+        proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+    ) -> Result<VerificationReport, VerifierError> {
+        self.run_inner_in_pool(&ConvertStage(&self.convert), proof, config, token)
+    }
+
+    /// Runs the stages after the conversion on the circuit `system` builds for `proof`
+    pub(crate) fn run_inner_in_pool<S: InnerProofSystem>(
+        &self,
+        system: &S,
+        proof: S::Proof,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+    ) -> Result<VerificationReport, VerifierError> {
+        with_events(config, || {
+            let start = Instant::now();
+            let mut stages = StageTimings::with_events(config.event_sink.clone());
+            let fri_soundness = system.fri_soundness(&proof);
+            check_soundness(fri_soundness, config)?;
+            if config.check_inner_proof {
+                token.checkpoint("inner proof check")?;
+                system.check(&proof)?;
+                stages.record(Stage::InnerProofCheck, start.elapsed());
+            }
+            token.checkpoint("witness generation")?;
+            let now = Instant::now();
+            let (circuit, instances, k) = system.circuit(proof, config)?;
+            let constant_rows_saved = system.constant_rows_saved(&circuit)?;
+            stages.record(Stage::Conversion, now.elapsed());
+            let report = self.wrap(circuit, &instances, k, config, token, start, stages)?;
+            Ok(VerificationReport {
+                fri_soundness,
+                constant_rows_saved,
+                ..report
+            })
+        })
+    }
+
+    /// Runs the mock prover, generates the EVM verifier and a SNARK proof of `circuit`, and
+    /// runs the verifier on the proof, `start` being the start of the whole pipeline. Checks
+    /// `token` between phases. With `PipelineConfig::compression`, the proof of `circuit` is
+    /// compressed first, and the EVM verifier is the one of the compressed proof. Proofs with a
+    /// transcript the EVM verifier can't read are only verified natively.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn wrap<C: Circuit<Fr>>(
+        &self,
+        circuit: C,
+        instances: &OrderedInstances,
+        k: u32,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+        start: Instant,
+        mut stages: StageTimings,
+    ) -> Result<VerificationReport, VerifierError> {
+        if !config.compression {
+            return self.wrap_stage(circuit, instances, k, None, config, token, start, stages);
+        }
+        let circuit = self.compress(circuit, instances, k, config, token, &mut stages)?;
+        let config = config
+            .clone()
+            .with_compression(false)
+            .with_max_k(EvmVerifier::max_k());
+        self.wrap_stage(
+            circuit.clone(),
+            &circuit.ordered_instances(),
+            compression::MIN_K,
+            Some(compression::accumulator_indices()),
+            &config,
+            token,
+            start,
+            stages,
+        )
+    }
+
+    /// Proves `circuit` with the transcript of the compression circuit, and builds the
+    /// compression circuit verifying the proof
+    fn compress<C: Circuit<Fr>>(
+        &self,
+        circuit: C,
+        instances: &OrderedInstances,
+        k: u32,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+        stages: &mut StageTimings,
+    ) -> Result<CompressionCircuit, VerifierError> {
+        EvmVerifier::check_srs(config.allow_insecure_srs)?;
+        EvmVerifier::check_k(k)?;
+        let now = Instant::now();
+        let max_k = config.max_k.unwrap_or(k).min(EvmVerifier::max_k());
+        let k = self.synthesize.synthesize(&circuit, instances, k, max_k)?;
+        stages.record(Stage::Mock, now.elapsed());
+
+        token.checkpoint("keygen")?;
+        let now = Instant::now();
+        let params = EvmVerifier::params_for_k(k)?;
+        let pk = EvmVerifier::gen_pk(&params, &circuit);
+        stages.record(Stage::Keygen, now.elapsed());
+
+        token.checkpoint("compression")?;
+        println!("{}", "Proving the proof to compress".red().bold());
+        let now = Instant::now();
+        let proof = EvmVerifier::prove_for_compression(
+            &params,
+            &pk,
+            circuit,
+            instances,
+            config.blinding.rng()?,
+        );
+        let circuit = CompressionCircuit::new(
+            &params,
+            WrapperSnark::new(&params, pk.get_vk(), instances, proof),
+        )?;
+        metrics::observe_phase("compression", now.elapsed());
+        stages.record(Stage::Compression, now.elapsed());
+        Ok(circuit)
+    }
+
+    /// Runs the stages of `wrap` after the compression, the instances of `circuit` at
+    /// `accumulator_indices` holding an accumulator checked by the EVM verifier
+    #[allow(clippy::too_many_arguments)]
+    fn wrap_stage<C: Circuit<Fr>>(
+        &self,
+        circuit: C,
+        instances: &OrderedInstances,
+        k: u32,
+        accumulator_indices: Option<Vec<(usize, usize)>>,
+        config: &PipelineConfig,
+        token: &CancellationToken,
+        start: Instant,
+        mut stages: StageTimings,
+    ) -> Result<VerificationReport, VerifierError> {
+        let field_backend = FieldBackend::detect()?;
+        EvmVerifier::check_srs(config.allow_insecure_srs)?;
+        EvmVerifier::check_k(k)?;
+        // runs mock prover
+        let now = Instant::now();
+        let max_k = config.max_k.unwrap_or(k).min(EvmVerifier::max_k());
+        let k = self.synthesize.synthesize(&circuit, instances, k, max_k)?;
+        println!(
+            "{}",
+            format!("Mock prover passes with k = {k}").white().bold()
+        );
+        metrics::observe_phase("mock", now.elapsed());
+        stages.record(Stage::Mock, now.elapsed());
+        let params = EvmVerifier::params_for_k(k)?;
+
+        // generates the keys
+        token.checkpoint("keygen")?;
+        let now = Instant::now();
+        let pk = EvmVerifier::gen_pk(&params, &circuit);
+        metrics::observe_phase("keygen", now.elapsed());
+        stages.record(Stage::Keygen, now.elapsed());
+        if config.transcript.is_evm_compatible() {
+            EvmVerifier::check_num_instances(&instances.num_instance())?;
+        }
+
+        // generates SNARK proof, and checks it with the protocol the EVM verifier is generated
+        // from, so that a protocol not matching the proofs fails here rather than in the EVM
+        token.checkpoint("proving")?;
+        println!("{}", "Starting finalization phase".red().bold());
+        let now = Instant::now();
+        let proof = self.prove.prove(&params, &pk, circuit, instances, config)?;
+        stages.record(Stage::Prove, now.elapsed());
+        let verify_start = Instant::now();
+        if !EvmVerifier::verify_native_with_transcript(
+            &params,
+            pk.get_vk(),
+            instances,
+            &proof,
+            config.strategy,
+            config.multiopen,
+            config.transcript,
+        )? {
+            return Err(VerifierError::NativeVerificationFailed);
+        }
+        let verifier_key = VerifierKey::new_with_accumulator(
+            &params,
+            pk.get_vk(),
+            instances.len(),
+            accumulator_indices.clone(),
+        )
+        .with_multiopen(config.multiopen)
+        .with_transcript(config.transcript);
+        EvmVerifier::check_protocol(&verifier_key, instances, &proof)?;
+        stages.record(Stage::NativeVerify, verify_start.elapsed());
+        println!("{}", "SNARK proof generated successfully!".white().bold());
+        metrics::observe_phase("prove", now.elapsed());
+        metrics::observe_proof_size(proof.len());
+        report_elapsed(now);
+
+        let envelope = WrappedProofEnvelope::new(pk.get_vk(), instances, proof.clone())
+            .with_metadata("k", k)
+            .with_metadata("multiopen", config.multiopen)
+            .with_metadata("transcript", config.transcript)
+            .with_metadata("zero_knowledge", config.blinding.is_zero_knowledge())
+            .with_metadata("vk_commitment", config.vk_commitment)
+            .with_metadata("calldata_layout", config.calldata_layout);
+        if !config.transcript.is_evm_compatible() {
+            return Ok(VerificationReport {
+                envelope,
+                k,
+                yul_code: None,
+                deployment_code: None,
+                solc_version: None,
+                zero_knowledge: config.blinding.is_zero_knowledge(),
+                verifier_key,
+                constructor_args: vec![],
+                gas_used: None,
+                chain: config.chain,
+                gas_cost: None,
+                srs: EvmVerifier::srs_metadata(),
+                elapsed: start.elapsed(),
+                stages,
+                field_backend,
+                fri_soundness: None,
+                constant_rows_saved: None,
+            });
+        }
+
+        // generates EVM verifier and runs it
+        token.checkpoint("EVM verifier generation")?;
+        let evm_verifier_start = Instant::now();
+        let (yul_code, constructor_args) =
+            self.compile
+                .yul(&params, pk.get_vk(), instances, accumulator_indices, config)?;
+        stages.record(Stage::Yul, evm_verifier_start.elapsed());
+        let now = Instant::now();
+        let (deployment_code, solc_version) = self.compile.compile(&yul_code, config)?;
+        stages.record(Stage::Compile, now.elapsed());
+        metrics::observe_phase("evm_verifier", evm_verifier_start.elapsed());
+        token.checkpoint("EVM verification")?;
+        let now = Instant::now();
+        verifier_key.validate_proof_encoding(&proof)?;
+        let calldata = instances.encode_calldata_with_layout(&proof, config.calldata_layout);
+        let result = self.simulate.simulate(
+            [deployment_code.as_slice(), &constructor_args].concat(),
+            calldata.clone(),
+        )?;
+        if !result.success {
+            return Err(VerifierError::EvmVerificationFailed);
+        }
+        let gas_used = result.gas_used;
+        let gas_cost = config.chain.schedule().cost(gas_used, &calldata);
+        metrics::observe_phase("evm_verify", now.elapsed());
+        stages.record(Stage::EvmSimulation, now.elapsed());
+        metrics::observe_gas(gas_used);
+        Ok(VerificationReport {
+            envelope: envelope
+                .with_metadata("gas_used", gas_used)
+                .with_metadata("chain", config.chain)
+                .with_metadata("gas_total", gas_cost.total())
+                .with_metadata("deployment_code_size", deployment_code.len()),
+            k,
+            yul_code: Some(yul_code),
+            deployment_code: Some(deployment_code),
+            solc_version: Some(solc_version),
+            zero_knowledge: config.blinding.is_zero_knowledge(),
+            verifier_key,
+            constructor_args,
+            gas_used: Some(gas_used),
+            chain: config.chain,
+            gas_cost: Some(gas_cost),
+            srs: EvmVerifier::srs_metadata(),
+            elapsed: start.elapsed(),
+            stages,
+            field_backend,
+            fri_soundness: None,
+            constant_rows_saved: None,
+        })
+    }
+}
+
+/// Wraps `proof` and returns exactly the calldata to send to the deployed EVM verifier, for
+/// relayers which don't need to simulate the verifier locally
+/// `pk`, `params` and `multiopen` must be the ones the deployed verifier was generated with
+pub fn prove_to_calldata(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    pk: &ProvingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    multiopen: MultiOpenScheme,
+) -> Result<Vec<u8>, VerifierError> {
+    prove_to_calldata_with_config(
+        proof,
+        pk,
+        params,
+        &PipelineConfig::new().with_multiopen(multiopen),
+    )
+}
+
+/// Same as `prove_to_calldata`, the verifier circuit, its proof and the calldata following the
+/// options of `config`, which must be the ones the deployed verifier was generated with
+pub fn prove_to_calldata_with_config(
+    proof: ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    pk: &ProvingKey<G1Affine>,
+    params: &ParamsKZG<Bn256>,
+    config: &PipelineConfig,
+) -> Result<Vec<u8>, VerifierError> {
+    if !config.transcript.is_evm_compatible() {
+        return Err(VerifierError::NoEvmVerifier {
+            transcript: config.transcript,
+        });
+    }
+    if config.check_inner_proof {
+        check_inner_proof(&proof)?;
+    }
+    let (circuit, _) = build_verifier(proof, config)?;
+    let instances = circuit.ordered_instances();
+    let proof = EvmVerifier::prove_with_transcript(
+        params,
+        pk,
+        circuit,
+        &instances,
+        config.multiopen,
+        config.transcript,
+        config.blinding.rng()?,
+    );
+    Ok(instances.encode_calldata_with_layout(&proof, config.calldata_layout))
+}
+
+/// Builds the circuit verifying every proof of `proofs` at once, whose first `shared_prefix`
+/// public inputs are exposed only once, returning it with the `k` to start from. With a
+/// `registry`, the verification key of every proof is selected in-circuit among the ones
+/// registered for its common data.
+fn build_batch_verifier(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    shared_prefix: usize,
+    registry: Option<&CircuitRegistry>,
+    config: &PipelineConfig,
+) -> Result<(BatchVerifier, u32), VerifierError> {
+    let mut vk_candidates = vec![];
+    let verifiers = proofs
+        .into_iter()
+        .enumerate()
+        .map(|(index, proof)| {
+            if config.check_inner_proof {
+                check_inner_proof(&proof)?;
+            }
+            let (proof_with_public_inputs, vd, cd) = proof;
+            if let Some(registry) = registry {
+                let candidates = registry.verification_keys(&cd);
+                if candidates.is_empty() {
+                    return Err(VerifierError::UnregisteredCircuit { proof: index });
+                }
+                vk_candidates.push(candidates.to_vec());
+            }
+            let proof = ProofValues::<Fr, 2>::try_from(proof_with_public_inputs.proof)?;
+            let instances =
+                OrderedInstances::try_from_public_inputs(&proof_with_public_inputs.public_inputs)?;
+            let vk = VerificationKeyValues::try_from(vd)?;
+            let common_data = CommonData::try_from(cd)?;
+            Ok(
+                Verifier::with_derived_spec(proof, instances.as_slice().to_vec(), vk, common_data)
+                    .with_strict_canonical_checks(config.strict_canonical),
+            )
+        })
+        .collect::<Result<Vec<_>, VerifierError>>()?;
+    let mut circuit = BatchVerifier::new(verifiers, shared_prefix)
+        .map_err(|proof| VerifierError::SharedPrefixMismatch { proof })?;
+    if registry.is_some() {
+        circuit = circuit
+            .with_vk_candidates(vk_candidates)
+            .map_err(|proof| VerifierError::UnregisteredCircuit { proof })?;
+    }
+    let k = config.k.unwrap_or_else(|| circuit.min_k());
+    Ok((circuit, k))
+}
+
+/// Same as `verify_inside_snark_mock_with_config`, for a batch of proofs whose first
+/// `shared_prefix` public inputs are the same, see `BatchVerifier`
+pub fn verify_batch_inside_snark_mock(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    shared_prefix: usize,
+    config: &PipelineConfig,
+) -> Result<MockReport, VerifierError> {
+    let (circuit, k) = build_batch_verifier(proofs, shared_prefix, None, config)?;
+    let instances = circuit.ordered_instances();
+    let max_k = config.max_k.unwrap_or(k);
+    mock_report(&circuit, &instances, k, max_k)
+}
+
+/// Same as `verify_inside_snark_mock_with_config`, for proofs of the same statement by
+/// redundant provers, whose public inputs are all shared
+pub fn verify_redundant_inside_snark_mock(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    config: &PipelineConfig,
+) -> Result<MockReport, VerifierError> {
+    let shared_prefix = redundant_public_inputs(&proofs)?;
+    verify_batch_inside_snark_mock(proofs, shared_prefix, config)
+}
+
+/// Number of public inputs of redundant `proofs`, failing with `StatementMismatch` unless every
+/// proof has the public inputs of the first one
+fn redundant_public_inputs(
+    proofs: &[ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>],
+) -> Result<usize, VerifierError> {
+    let public_inputs = |index: usize| &proofs[index].0.public_inputs;
+    match (0..proofs.len()).position(|index| public_inputs(index) != public_inputs(0)) {
+        Some(proof) => Err(VerifierError::StatementMismatch { proof }),
+        None => Ok(proofs.first().map_or(0, |_| public_inputs(0).len())),
+    }
+}
+
+/// Same as `verify_batch_inside_snark_mock`, for proofs of the different upstream circuits of
+/// `registry`, see `CircuitRegistry`
+pub fn verify_multi_circuit_batch_inside_snark_mock(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    registry: &CircuitRegistry,
+    shared_prefix: usize,
+    config: &PipelineConfig,
+) -> Result<MockReport, VerifierError> {
+    let (circuit, k) = build_batch_verifier(proofs, shared_prefix, Some(registry), config)?;
+    let instances = circuit.ordered_instances();
+    let max_k = config.max_k.unwrap_or(k);
+    mock_report(&circuit, &instances, k, max_k)
+}
+
+/// Same as `verify_inside_snark_with_config`, for a batch of proofs whose first `shared_prefix`
+/// public inputs are the same. The EVM verifier takes the shared public inputs once, followed
+/// by the other public inputs of every proof.
+pub fn verify_batch_inside_snark(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    shared_prefix: usize,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    wrap_batch(proofs, shared_prefix, None, config)
+}
+
+/// Same as `verify_inside_snark_with_config`, for proofs of the same statement by redundant
+/// provers, whose public inputs are all shared. The EVM verifier takes the public inputs once.
+pub fn verify_redundant_inside_snark(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    let shared_prefix = redundant_public_inputs(&proofs)?;
+    wrap_batch(proofs, shared_prefix, None, config)
+}
+
+/// Same as `verify_batch_inside_snark`, for proofs of the different upstream circuits of
+/// `registry`. The EVM verifier accepts batches with the same sequence of common data, each proof
+/// having any of the verification keys registered for its common data.
+pub fn verify_multi_circuit_batch_inside_snark(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    registry: &CircuitRegistry,
+    shared_prefix: usize,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    wrap_batch(proofs, shared_prefix, Some(registry), config)
+}
+
+fn wrap_batch(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    shared_prefix: usize,
+    registry: Option<&CircuitRegistry>,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    config.install(|| {
+        with_events(config, || {
+            wrap_batch_in_pool(proofs, shared_prefix, registry, config)
+        })
+    })
+}
+
+fn wrap_batch_in_pool(
+    proofs: Vec<ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>>,
+    shared_prefix: usize,
+    registry: Option<&CircuitRegistry>,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    let start = Instant::now();
+    let mut stages = StageTimings::with_events(config.event_sink.clone());
+    // the batch is as sound as its weakest proof
+    let fri_soundness = proofs
         .iter()
-        .map(|e| big_to_fe(fe_to_big::<Goldilocks>(types::to_goldilocks(*e))))
-        .collect::<Vec<Fr>>();
-    let vk = VerificationKeyValues::from(vd.clone());
-    let common_data = CommonData::from(cd);
-    let spec = Spec::<Goldilocks, 12, 11>::new(8, 22);
-
-    // runs mock prover
-    let circuit = Verifier::new(proof, instances.clone(), vk, common_data, spec);
-    let mock_prover = MockProver::run(22, &circuit, vec![instances.clone()]).unwrap();
-    mock_prover.assert_satisfied();
-    println!("{}", "Mock prover passes".white().bold());
-
-    // generates EVM verifier
-    let pk = EvmVerifier::gen_pk(&SRS, &circuit);
-    let deployment_code = EvmVerifier::gen_evm_verifier(&SRS, pk.get_vk(), vec![instances.len()]);
-
-    // generates SNARK proof and runs EVM verifier
-    println!("{}", "Starting finalization phase".red().bold());
-    let now = Instant::now();
-    let proof = EvmVerifier::gen_proof(&SRS, &pk, circuit.clone(), vec![instances.clone()]);
-    println!("{}", "SNARK proof generated successfully!".white().bold());
-    report_elapsed(now);
-    EvmVerifier::evm_verify(deployment_code, vec![instances], proof);
+        .filter_map(|proof| Plonky2.fri_soundness(proof))
+        .reduce(|a, b| FriSoundness {
+            conjectured_bits: a.conjectured_bits.min(b.conjectured_bits),
+            provable_bits: a.provable_bits.min(b.provable_bits),
+        });
+    check_soundness(fri_soundness, config)?;
+    let (circuit, k) = build_batch_verifier(proofs, shared_prefix, registry, config)?;
+    stages.record(Stage::Conversion, start.elapsed());
+    let instances = circuit.ordered_instances();
+    let report = Pipeline::new().wrap(
+        circuit,
+        &instances,
+        k,
+        config,
+        &CancellationToken::new(),
+        start,
+        stages,
+    )?;
+    Ok(VerificationReport {
+        fri_soundness,
+        ..report
+    })
+}
+
+/// Generates the proving key and the EVM verifier of the verifier circuit for every batch size
+/// of a circuit family, `build` returning a Plonky2 proof of the family for a batch size. The
+/// parameters of every batch size are derived from the shared SRS, which is only loaded once,
+/// and the parameters of a degree, with their Lagrange basis, are computed once for all the
+/// batch sizes sharing it, see `keygen`. Fails for a transcript the EVM can't replay, see
+/// `TranscriptKind::is_evm_compatible`.
+pub fn gen_batch_artifacts(
+    batch_sizes: &[usize],
+    mut build: impl FnMut(usize) -> ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<Vec<BatchArtifacts>, VerifierError> {
+    if !config.transcript.is_evm_compatible() {
+        return Err(VerifierError::NoEvmVerifier {
+            transcript: config.transcript,
+        });
+    }
+    EvmVerifier::check_srs(config.allow_insecure_srs)?;
+    let solc = Solc::resolve(config.solc_version.as_deref(), config.solc_path.as_deref())?;
+    batch_sizes
+        .iter()
+        .map(|&batch_size| {
+            let (circuit, k) = build_verifier(build(batch_size), config)?;
+            let instances = circuit.ordered_instances();
+            EvmVerifier::check_k(k)?;
+            let max_k = config.max_k.unwrap_or(k).min(EvmVerifier::max_k());
+            let (k, _) = run_mock_prover(&circuit, &instances, k, max_k)?;
+
+            let now = Instant::now();
+            let params = EvmVerifier::params_for_k(k)?;
+            let pk = EvmVerifier::gen_pk(&params, &circuit);
+            let (yul_code, deployment_code) = EvmVerifier::gen_evm_verifier(
+                &params,
+                pk.get_vk(),
+                instances.num_instance(),
+                config.multiopen,
+                &solc,
+            )?;
+            Ok(BatchArtifacts {
+                batch_size,
+                num_instances: instances.len(),
+                k,
+                pk,
+                yul_code,
+                deployment_code,
+                keygen_time: now.elapsed(),
+            })
+        })
+        .collect()
+}
+
+/// Runs `gen_batch_artifacts`, writes the artifacts of every batch size to `dir` and prints a
+/// table comparing them
+pub fn export_batch_artifacts(
+    dir: &Path,
+    batch_sizes: &[usize],
+    build: impl FnMut(usize) -> ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<Vec<BatchSummary>, VerifierError> {
+    export_batch_artifacts_with_layout(&ArtifactsLayout::new(dir), batch_sizes, build, config)
+}
+
+/// Same as `export_batch_artifacts`, writing the artifacts where `layout` puts them
+pub fn export_batch_artifacts_with_layout(
+    layout: &ArtifactsLayout,
+    batch_sizes: &[usize],
+    build: impl FnMut(usize) -> ProofTuple<GoldilocksField, PoseidonGoldilocksConfig, 2>,
+    config: &PipelineConfig,
+) -> Result<Vec<BatchSummary>, VerifierError> {
+    let artifacts = gen_batch_artifacts(batch_sizes, build, config)?;
+    for artifacts in artifacts.iter() {
+        artifacts.check(layout).map_err(VerifierError::Io)?;
+    }
+    for artifacts in artifacts.iter() {
+        artifacts
+            .write_with_layout(layout)
+            .map_err(VerifierError::Io)?;
+    }
+    let summaries = artifacts
+        .iter()
+        .map(BatchArtifacts::summary)
+        .collect::<Vec<_>>();
+    println!("{}", comparison_table(&summaries));
+    Ok(summaries)
+}
+
+/// Same as `verify_inside_snark_mock_with_config`, for a proof whose extension degree is
+/// only known at runtime. Fails with `UnsupportedExtensionDegree` for a proof with `D = 4`,
+/// after checking it natively if `config.check_inner_proof` is set.
+pub fn verify_any_inside_snark_mock(
+    proof: AnyProofTuple,
+    config: &PipelineConfig,
+) -> Result<MockReport, VerifierError> {
+    match proof {
+        AnyProofTuple::D2(proof) => verify_inside_snark_mock_with_config(proof, config),
+        AnyProofTuple::D4(proof) => Err(unsupported_extension_degree(&proof, config)),
+    }
+}
+
+/// Same as `verify_inside_snark_with_config`, for a proof whose extension degree is only
+/// known at runtime, see `verify_any_inside_snark_mock`
+pub fn verify_any_inside_snark(
+    proof: AnyProofTuple,
+    config: &PipelineConfig,
+) -> Result<VerificationReport, VerifierError> {
+    match proof {
+        AnyProofTuple::D2(proof) => verify_inside_snark_with_config(proof, config),
+        AnyProofTuple::D4(proof) => Err(unsupported_extension_degree(&proof, config)),
+    }
+}
+
+/// Error for a proof the verifier circuit can't verify, which is `InvalidInnerProof` if the
+/// proof is checked and rejected natively
+fn unsupported_extension_degree<C: GenericConfig<D, F = GoldilocksField>, const D: usize>(
+    proof: &ProofTuple<GoldilocksField, C, D>,
+    config: &PipelineConfig,
+) -> VerifierError
+where
+    GoldilocksField: Extendable<D>,
+{
+    if config.check_inner_proof {
+        if let Err(e) = check_inner_proof(proof) {
+            return e;
+        }
+    }
+    VerifierError::UnsupportedExtensionDegree(D)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::Arc;
+
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use halo2_proofs::plonk::keygen_vk;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use plonky2::field::goldilocks_field::GoldilocksField;
+    use plonky2::field::types::{Field, PrimeField64};
+    use rand::rngs::{OsRng, StdRng};
+    use rand::SeedableRng;
+
+    use super::{
+        build_batch_verifier, build_verifier, explain_instance, extract_instances,
+        gen_batch_artifacts, run_mock_prover, verify_any_inside_snark_mock,
+        verify_batch_inside_snark_mock, verify_inside_snark_mock,
+        verify_inside_snark_mock_with_config, verify_inside_snark_with_config,
+        verify_multi_circuit_batch_inside_snark_mock, verify_redundant_inside_snark_mock,
+        EvmVerifier, EVM_CONTRACT_SIZE_LIMIT, MAX_EVM_INSTANCES,
+    };
+    use crate::snark::batch_verifier_circuit::CircuitRegistry;
+    use crate::snark::calldata::CalldataLayout;
+    use crate::snark::chip::range_table::{LookupRangeChecks, RANGE_TABLE_BITS};
+    use crate::snark::compression;
+    use crate::snark::disclosure::SelectiveDisclosure;
+    use crate::snark::entropy::FixedEntropy;
+    use crate::snark::envelope::{vk_hash, WrappedProofEnvelope, ENVELOPE_VERSION};
+    use crate::snark::error::VerifierError;
+    use crate::snark::export::hardhat::export_hardhat;
+    use crate::snark::export::{export_artifacts, verify_artifacts, Manifest};
+    use crate::snark::g2_args::NUM_G2_WORDS;
+    use crate::snark::inner::{InnerProofSystem, Plonky2};
+    use crate::snark::instances::{InstanceLayout, InstanceSource, OrderedInstances};
+    use crate::snark::pipeline::{
+        Blinding, MultiOpenScheme, NativeVerificationStrategy, PipelineConfig, TranscriptKind,
+    };
+    use crate::snark::simulator::EvmSimulator;
+    use crate::snark::solc::Solc;
+    use crate::snark::test_utils;
+    use crate::snark::verifier::VerifierKey;
+    use crate::snark::vk_registry::{VK_COMMITMENT_LEN, VK_COMMITMENT_OFFSET};
+    use crate::AnyProofTuple;
+
+    #[test]
+    fn test_zero_instances_calldata() {
+        let instances = OrderedInstances::new(vec![]);
+        assert_eq!(instances.num_instance(), vec![0]);
+        let proof = vec![1u8, 2, 3, 4];
+        assert_eq!(instances.encode_calldata(&proof), proof);
+    }
+
+    /// Fails when snark-verifier or solc change the verifier generated for a fixed verifying key,
+    /// which would change the code of verifiers deployed from the same keys
+    #[test]
+    fn test_golden_evm_verifier() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let (yul_code, deployment_code) = EvmVerifier::gen_evm_verifier(
+            &params,
+            pk.get_vk(),
+            vec![1],
+            MultiOpenScheme::Gwc,
+            &Solc::host().unwrap(),
+        )
+        .unwrap();
+        test_utils::assert_golden("verifier.yul", &yul_code);
+        test_utils::assert_golden("verifier.bin", &hex::encode(deployment_code));
+    }
+
+    #[test]
+    fn test_native_verifier_key() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        for multiopen in [MultiOpenScheme::Gwc, MultiOpenScheme::Shplonk] {
+            let proof = EvmVerifier::prove(
+                &params,
+                &pk,
+                test_utils::FixedCircuit,
+                &instances,
+                multiopen,
+                OsRng,
+            );
+
+            let key = VerifierKey::new(&params, pk.get_vk(), 1);
+            let key =
+                VerifierKey::from_parts(&key.protocol_json().unwrap(), &key.deciding_key_bytes())
+                    .unwrap()
+                    .with_multiopen(multiopen);
+            assert!(key.verify(&instances, &proof).unwrap());
+            let other_instances = OrderedInstances::new(vec![Fr::from(43)]);
+            assert!(!key.verify(&other_instances, &proof).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_blake2b_transcript() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        for multiopen in [MultiOpenScheme::Gwc, MultiOpenScheme::Shplonk] {
+            let proof = EvmVerifier::prove_with_transcript(
+                &params,
+                &pk,
+                test_utils::FixedCircuit,
+                &instances,
+                multiopen,
+                TranscriptKind::Blake2b,
+                OsRng,
+            );
+            assert!(EvmVerifier::verify_native_with_transcript(
+                &params,
+                pk.get_vk(),
+                &instances,
+                &proof,
+                NativeVerificationStrategy::Accumulator,
+                multiopen,
+                TranscriptKind::Blake2b,
+            )
+            .unwrap());
+
+            let key = VerifierKey::new(&params, pk.get_vk(), 1).with_multiopen(multiopen);
+            assert!(key
+                .clone()
+                .with_transcript(TranscriptKind::Blake2b)
+                .verify(&instances, &proof)
+                .unwrap());
+            // the challenges of the Keccak256 transcript differ
+            assert!(!matches!(key.verify(&instances, &proof), Ok(true)));
+        }
+    }
+
+    #[test]
+    fn test_blake2b_pipeline() -> Result<()> {
+        let config = PipelineConfig::new()
+            .allow_insecure()
+            .with_transcript(TranscriptKind::Blake2b);
+        // the proof is only verified natively, so the report has no EVM verifier
+        let report = verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config)?;
+        assert_eq!(report.verifier_key.transcript(), TranscriptKind::Blake2b);
+        assert!(report.yul_code.is_none());
+        assert!(report.deployment_code.is_none());
+        assert!(report.solc_version.is_none());
+        assert!(report.gas_used.is_none());
+        assert!(report.gas_cost.is_none());
+
+        // and no EVM verifier is exported or generated
+        let dir = std::env::temp_dir().join("stark-verifier-blake2b-test");
+        assert_eq!(
+            export_artifacts(&dir, &report).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert_eq!(
+            export_hardhat(&dir, &report).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        assert!(Manifest::new("Verifier", &report).is_err());
+        assert!(matches!(
+            gen_batch_artifacts(&[1], |_| unreachable!(), &config),
+            Err(VerifierError::NoEvmVerifier {
+                transcript: TranscriptKind::Blake2b
+            })
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    /// Circuits with challenges are proven phase by phase, and their verifiers squeeze the
+    /// challenges of every phase from the transcript
+    #[test]
+    fn test_multi_phase_circuit() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let circuit = test_utils::ChallengeCircuit {
+            values: [Fr::from(42), Fr::from(7)],
+        };
+        let pk = EvmVerifier::gen_pk(&params, &circuit);
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        for multiopen in [MultiOpenScheme::Gwc, MultiOpenScheme::Shplonk] {
+            let proof =
+                EvmVerifier::prove(&params, &pk, circuit.clone(), &instances, multiopen, OsRng);
+            assert!(EvmVerifier::verify_native(
+                &params,
+                pk.get_vk(),
+                &instances,
+                &proof,
+                NativeVerificationStrategy::Single,
+                multiopen,
+            )
+            .unwrap());
+            let key = VerifierKey::new(&params, pk.get_vk(), 1).with_multiopen(multiopen);
+            assert!(key.verify(&instances, &proof).unwrap());
+
+            let (_, deployment_code) = EvmVerifier::gen_evm_verifier(
+                &params,
+                pk.get_vk(),
+                instances.num_instance(),
+                multiopen,
+                &Solc::host().unwrap(),
+            )
+            .unwrap();
+            let mut simulator = EvmSimulator::deploy(deployment_code).unwrap();
+            assert!(simulator.verify(&instances, &proof).unwrap().success);
+        }
+    }
+
+    #[test]
+    fn test_protocol_mismatch() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        let proof = EvmVerifier::prove(
+            &params,
+            &pk,
+            test_utils::FixedCircuit,
+            &instances,
+            MultiOpenScheme::Gwc,
+            OsRng,
+        );
+        let key = VerifierKey::new(&params, pk.get_vk(), 1);
+        assert!(EvmVerifier::check_protocol(&key, &instances, &proof).is_ok());
+        // a protocol compiled for another multi-opening scheme doesn't match the proof
+        let key = key.with_multiopen(MultiOpenScheme::Shplonk);
+        assert!(matches!(
+            EvmVerifier::check_protocol(&key, &instances, &proof),
+            Err(VerifierError::ProtocolMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_max_evm_instances() {
+        // the Yul of a verifier loading `MAX_EVM_INSTANCES` instances compiles within the
+        // contract size limit
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let solc = Solc::host().unwrap();
+        let (yul_code, deployment_code) = EvmVerifier::gen_evm_verifier(
+            &params,
+            pk.get_vk(),
+            vec![MAX_EVM_INSTANCES],
+            MultiOpenScheme::Gwc,
+            &solc,
+        )
+        .unwrap();
+        assert!(!yul_code.is_empty());
+        assert!(deployment_code.len() <= EVM_CONTRACT_SIZE_LIMIT);
+        assert!(matches!(
+            EvmVerifier::gen_evm_verifier(
+                &params,
+                pk.get_vk(),
+                vec![MAX_EVM_INSTANCES + 1],
+                MultiOpenScheme::Gwc,
+                &solc,
+            ),
+            Err(VerifierError::TooManyInstances { .. })
+        ));
+
+        assert!(EvmVerifier::check_num_instances(&[MAX_EVM_INSTANCES]).is_ok());
+        assert!(matches!(
+            EvmVerifier::check_num_instances(&[MAX_EVM_INSTANCES - 1, 2]),
+            Err(VerifierError::TooManyInstances {
+                instances,
+                limit: MAX_EVM_INSTANCES,
+            }) if instances == MAX_EVM_INSTANCES + 1
+        ));
+    }
+
+    #[test]
+    fn test_seeded_blinding() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let pk = EvmVerifier::gen_pk(&params, &test_utils::FixedCircuit);
+        let instances = OrderedInstances::new(vec![Fr::from(42)]);
+        let prove = |blinding: Blinding| {
+            EvmVerifier::prove(
+                &params,
+                &pk,
+                test_utils::FixedCircuit,
+                &instances,
+                MultiOpenScheme::Gwc,
+                blinding.rng().unwrap(),
+            )
+        };
+        assert_eq!(prove(Blinding::Seeded(1)), prove(Blinding::Seeded(1)));
+        assert_ne!(prove(Blinding::Seeded(1)), prove(Blinding::Seeded(2)));
+        assert_ne!(prove(Blinding::Random), prove(Blinding::Random));
+        let hsm = Arc::new(FixedEntropy::new(7));
+        let external = Blinding::External(hsm.clone());
+        assert!(external.is_zero_knowledge());
+        assert_ne!(prove(external.clone()), prove(external));
+        assert_eq!(hsm.draws(), 2);
+    }
+
+    #[test]
+    fn test_zero_public_inputs() -> Result<()> {
+        let config = PipelineConfig::new().allow_insecure();
+        let report =
+            verify_inside_snark_with_config(test_utils::zero_public_inputs_proof()?, &config)?;
+        assert!(report.deployment_code_size().unwrap() <= EVM_CONTRACT_SIZE_LIMIT);
+        // the exported key verifies the proof with a few points of the SRS only
+        let instances = report.envelope.instances()?;
+        assert!(report
+            .verifier_key
+            .verify(&instances, &report.envelope.proof_bytes)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rejects_mutated_instances_and_proofs() -> Result<()> {
+        let config = PipelineConfig::new().allow_insecure();
+        let report = verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config)?;
+        assert!(report.constant_rows_saved.unwrap() > 0);
+        let instances = report.envelope.instances()?;
+        let proof = &report.envelope.proof_bytes;
+        assert!(report.verifier_key.verify(&instances, proof)?);
+
+        for mutated in test_utils::mutated_instances(&instances) {
+            test_utils::assert_rejects(&report, &mutated, proof);
+        }
+        let layout = report.verifier_key.proof_layout();
+        for mutated in test_utils::mutated_proofs(proof, &layout) {
+            // still a valid encoding, rejected by the verification itself
+            assert_eq!(
+                report.verifier_key.validate_proof_encoding(&mutated),
+                Ok(())
+            );
+            test_utils::assert_rejects(&report, &instances, &mutated);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_first_calldata() -> Result<()> {
+        let config = PipelineConfig::new()
+            .allow_insecure()
+            .with_calldata_layout(CalldataLayout::ProofFirst);
+        // the pipeline checks the proof in the EVM with the proof-first calldata
+        let report = verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config)?;
+        let envelope = &report.envelope;
+        assert_eq!(envelope.calldata_layout(), CalldataLayout::ProofFirst);
+        let mut simulator = EvmSimulator::deploy(report.deployment_code_with_args().unwrap())?;
+        assert!(!simulator.call(envelope.calldata())?.success);
+        assert!(
+            simulator
+                .call(envelope.calldata_with_layout(envelope.calldata_layout()))?
+                .success
+        );
+
+        // the exported artifacts are checked in the recorded layout
+        let dir = std::env::temp_dir().join("stark-verifier-proof-first-test");
+        export_artifacts(&dir, &report)?;
+        assert!(verify_artifacts(&dir)?.success);
+        let manifest: Manifest =
+            serde_json::from_str(&std::fs::read_to_string(dir.join("manifest.json"))?)?;
+        assert_eq!(manifest.calldata_layout.as_deref(), Some("proof-first"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_g2_constructor_args() -> Result<()> {
+        let config = PipelineConfig::new()
+            .allow_insecure()
+            .with_g2_constructor_args(true);
+        // the pipeline deploys the verifier with the G2 points and checks the proof in the EVM
+        let report = verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config)?;
+        assert_eq!(report.constructor_args.len(), NUM_G2_WORDS * 32);
+        let calldata = report.envelope.calldata();
+        let mut simulator = EvmSimulator::deploy(report.deployment_code_with_args().unwrap())?;
+        assert!(simulator.call(calldata.clone())?.success);
+
+        // the verifier reads the points from its storage, so other points fail the pairing
+        let mut constructor_args = report.constructor_args.clone();
+        constructor_args[31] ^= 1;
+        let mut simulator = EvmSimulator::deploy(
+            [
+                report.deployment_code.as_deref().unwrap(),
+                &constructor_args,
+            ]
+            .concat(),
+        )?;
+        assert!(!simulator.call(calldata)?.success);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compression() -> Result<()> {
+        let config = PipelineConfig::new()
+            .allow_insecure()
+            .with_compression(true);
+        let report = verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config)?;
+        // the accumulator precedes the instances of the wrapper proof
+        let instances = report.envelope.instances()?;
+        assert_eq!(instances.len(), compression::ACCUMULATOR_LEN + 2);
+        assert!(report
+            .verifier_key
+            .verify(&instances, &report.envelope.proof_bytes)?);
+        let mut simulator = EvmSimulator::deploy(report.deployment_code_with_args().unwrap())?;
+        assert!(simulator.call(report.envelope.calldata())?.success);
+
+        // the compression circuit is proven with the SRS too
+        let config = PipelineConfig::new().with_compression(true);
+        assert!(matches!(
+            verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config),
+            Err(VerifierError::InsecureSrs)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_input_gate_mock() -> Result<()> {
+        let report = verify_inside_snark_mock(test_utils::public_inputs_proof(2)?)?;
+        assert!(report.satisfied && report.failures.is_empty());
+        assert!(0 < report.rows_used && report.rows_used <= report.usable_rows);
+        // zero alone is used for every embedding of a value into the extension
+        assert!(report.constant_rows_saved.unwrap() > 0);
+        assert!(report
+            .regions
+            .iter()
+            .any(|region| region.name == "Verify proof"));
+        let profile = report.region_profile.unwrap();
+        assert_eq!(profile.total(), report.rows_used);
+        // the query rounds dominate the verification
+        assert!(profile.fri > profile.gate_constraints && profile.fri > profile.transcript);
+        Ok(())
+    }
+
+    #[test]
+    fn test_srs_too_small() -> Result<()> {
+        let max_k = EvmVerifier::max_k();
+        assert!(EvmVerifier::check_k(max_k).is_ok());
+        assert!(matches!(
+            EvmVerifier::params_for_k(max_k + 1),
+            Err(VerifierError::SrsTooSmall { k, max_k: srs_k }) if k == max_k + 1 && srs_k == max_k
+        ));
+        // a circuit too large for the SRS fails before the mock prover instead of panicking
+        let config = PipelineConfig::new().allow_insecure().with_k(max_k + 1);
+        assert!(matches!(
+            verify_inside_snark_with_config(test_utils::public_inputs_proof(2)?, &config),
+            Err(VerifierError::SrsTooSmall { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_tampered_public_inputs_mock() -> Result<()> {
+        let mut proof = test_utils::public_inputs_proof(2)?;
+        proof.0.public_inputs[0] += GoldilocksField::ONE;
+        let config = PipelineConfig::new().with_inner_proof_check(true);
+        assert!(matches!(
+            verify_inside_snark_mock_with_config(proof.clone(), &config),
+            Err(VerifierError::InvalidInnerProof(_))
+        ));
+        // without the native check, the failures are reported rather than panicking
+        let config = PipelineConfig::new().with_inner_proof_check(false);
+        let report = verify_inside_snark_mock_with_config(proof, &config)?;
+        assert!(!report.satisfied);
+        assert!(!report.failures.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nonce_mock() -> Result<()> {
+        let config = PipelineConfig::new()
+            .with_nonce(Fr::from(42))
+            .with_num_threads(2);
+        assert!(
+            verify_inside_snark_mock_with_config(test_utils::public_inputs_proof(2)?, &config)?
+                .satisfied
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance_padding_mock() -> Result<()> {
+        let proof = test_utils::public_inputs_proof(2)?;
+        let config = PipelineConfig::new()
+            .with_nonce(Fr::from(42))
+            .with_instance_padding(6);
+        let instances = extract_instances(&proof, &config)?;
+        assert_eq!(instances.as_slice()[3..], [Fr::from(0); 3]);
+        let (circuit, k) = build_verifier(proof.clone(), &config)?;
+        assert_eq!(circuit.ordered_instances(), instances);
+        let (k, mock_prover) = run_mock_prover(&circuit, &instances, k, k + 1)?;
+        mock_prover.assert_satisfied();
+
+        // the padding can't hold anything else than zero
+        let mut forged = instances.as_slice().to_vec();
+        forged[5] = Fr::from(1);
+        let (_, mock_prover) = run_mock_prover(&circuit, &OrderedInstances::new(forged), k, k)?;
+        assert!(mock_prover.verify().is_err());
+
+        assert!(matches!(
+            extract_instances(&proof, &config.with_instance_padding(2)),
+            Err(VerifierError::InstancesExceedPadding {
+                num_instances: 3,
+                padded_len: 2
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_min_security_bits() -> Result<()> {
+        let proof = test_utils::public_inputs_proof(2)?;
+        assert_eq!(
+            Plonky2
+                .fri_soundness(&proof)
+                .map(|soundness| soundness.conjectured_bits),
+            Some(100)
+        );
+        assert!(matches!(
+            verify_inside_snark_mock_with_config(
+                proof,
+                &PipelineConfig::new().with_min_security_bits(128)
+            ),
+            Err(VerifierError::InsufficientSoundness {
+                conjectured_bits: Some(100),
+                min_bits: 128
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance_layout_mock() -> Result<()> {
+        let layout = InstanceLayout::new().with_version(1).with_chain_id(1);
+        let config = PipelineConfig::new()
+            .with_layout(layout)
+            .with_nonce(Fr::from(42));
+        assert!(
+            verify_inside_snark_mock_with_config(test_utils::public_inputs_proof(2)?, &config)?
+                .satisfied
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_instance_metadata_is_pinned_by_vk() -> Result<()> {
+        let proof = test_utils::public_inputs_proof(2)?;
+        let vk_hash_of_version = |version| -> Result<[u8; 32]> {
+            let layout = InstanceLayout::new().with_version(version);
+            let config = PipelineConfig::new().with_layout(layout);
+            let (verifier, k) = build_verifier(proof.clone(), &config)?;
+            let instances = verifier.ordered_instances();
+            let (k, _) = run_mock_prover(&verifier, &instances, k, EvmVerifier::max_k())?;
+            let params = EvmVerifier::params_for_k(k)?;
+            Ok(vk_hash(&keygen_vk(params.as_ref(), &verifier)?))
+        };
+        // a verifier of version 1 rejects proofs exposing version 2
+        assert_ne!(vk_hash_of_version(1)?, vk_hash_of_version(2)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_any_proof_tuple() -> Result<()> {
+        let config = PipelineConfig::new().with_inner_proof_check(true);
+        let proof = AnyProofTuple::from(test_utils::public_inputs_proof(2)?);
+        assert_eq!(proof.extension_degree(), 2);
+        assert!(verify_any_inside_snark_mock(proof, &config)?.satisfied);
+
+        let proof = AnyProofTuple::from(test_utils::quartic_proof()?);
+        assert_eq!(proof.extension_degree(), 4);
+        assert!(matches!(
+            verify_any_inside_snark_mock(proof, &config),
+            Err(VerifierError::UnsupportedExtensionDegree(4))
+        ));
+
+        // a quartic proof is checked natively before being rejected
+        let (mut proof, vd, cd) = test_utils::quartic_proof()?;
+        proof.public_inputs.push(GoldilocksField::ONE);
+        assert!(matches!(
+            verify_any_inside_snark_mock(AnyProofTuple::D4((proof, vd, cd)), &config),
+            Err(VerifierError::InvalidInnerProof(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_instances() -> Result<()> {
+        let proof = test_utils::poseidon_proof()?;
+        let config = PipelineConfig::new()
+            .with_layout(InstanceLayout::new().with_version(1))
+            .with_vk_commitment(true)
+            .with_disclosure(SelectiveDisclosure::random(vec![0]))
+            .with_nonce(Fr::from(42));
+        let instances = extract_instances(&proof, &config)?;
+
+        // vk commitment, version, then the disclosed public input 0
+        let explanation = explain_instance(&proof, &config, 100, 5)?.unwrap();
+        assert_eq!(explanation.source, InstanceSource::PublicInput(0));
+        assert_eq!(
+            explanation.goldilocks,
+            Some(proof.0.public_inputs[0].to_canonical_u64())
+        );
+        assert_eq!(explanation.value, instances.as_slice()[5]);
+        assert_eq!(explanation.calldata_range, 160..192);
+        assert!(explain_instance(&proof, &config, 100, instances.len())?.is_none());
+
+        // the instance is read back from the calldata at the reported range
+        let envelope = WrappedProofEnvelope {
+            version: ENVELOPE_VERSION,
+            vk_hash: [0; 32],
+            instances: instances.to_bytes32(),
+            proof_bytes: vec![0xff; 100],
+            metadata: Default::default(),
+        };
+        let proof_first = config
+            .clone()
+            .with_calldata_layout(CalldataLayout::ProofFirst);
+        let explanation = explain_instance(&proof, &proof_first, 100, 5)?.unwrap();
+        assert_eq!(explanation.calldata_range, 260..292);
+        let calldata = envelope.calldata_with_layout(CalldataLayout::ProofFirst);
+        assert_eq!(
+            calldata[explanation.calldata_range.clone()],
+            explanation.bytes32
+        );
+
+        let (circuit, _) = build_verifier(proof, &config)?;
+        assert_eq!(instances, circuit.ordered_instances());
+        Ok(())
+    }
+
+    #[test]
+    fn test_vk_commitment_mock() -> Result<()> {
+        let layout = InstanceLayout::new().with_version(1);
+        let config = PipelineConfig::new()
+            .with_layout(layout)
+            .with_vk_commitment(true);
+        let (verifier, k) = build_verifier(test_utils::public_inputs_proof(2)?, &config)?;
+        let instances = verifier.ordered_instances();
+        let commitment = verifier.vk_commitment().unwrap();
+        // the commitment is at a fixed row, before the metadata
+        let start = VK_COMMITMENT_OFFSET;
+        assert_eq!(
+            instances.as_slice()[start..start + VK_COMMITMENT_LEN],
+            commitment
+        );
+        assert_eq!(instances.as_slice()[VK_COMMITMENT_LEN], Fr::from(1));
+        assert_eq!(instances.len(), 1 + commitment.len() + 2);
+        // the layout of the config accounts for the commitment
+        assert_eq!(config.layout.position("version"), Some(VK_COMMITMENT_LEN));
+        assert_eq!(config.layout.payload_offset(), VK_COMMITMENT_LEN + 1);
+        assert_eq!(config.layout.payload(&instances).map(<[Fr]>::len), Some(2));
+        let (_, prover) = run_mock_prover(&verifier, &instances, k, k)?;
+        prover.assert_satisfied();
+        Ok(())
+    }
+
+    #[test]
+    fn test_strict_canonical_checks_mock() -> Result<()> {
+        let config = PipelineConfig::new().with_strict_canonical_checks(true);
+        assert!(
+            verify_inside_snark_mock_with_config(test_utils::public_inputs_proof(2)?, &config)?
+                .satisfied
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimated_k_fits_mock() -> Result<()> {
+        // without a `k` in the config, the verifier is sized by `cost::estimate_rows`, which
+        // must not fall short of the rows the circuit uses
+        for config in [
+            PipelineConfig::new(),
+            PipelineConfig::new().with_strict_canonical_checks(true),
+        ] {
+            let (verifier, k) = build_verifier(test_utils::public_inputs_proof(2)?, &config)?;
+            let instances = verifier.ordered_instances();
+            let (_, prover) = run_mock_prover(&verifier, &instances, k, k)?;
+            prover.assert_satisfied();
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_check_modes_mock() -> Result<()> {
+        let config = PipelineConfig::new().with_strict_canonical_checks(true);
+        let (verifier, k) = build_verifier(test_utils::public_inputs_proof(2)?, &config)?;
+        let instances = verifier.ordered_instances();
+        let (_, prover) = run_mock_prover(&verifier, &instances, k, k + 2)?;
+        prover.assert_satisfied();
+
+        let verifier = verifier.with_emulation::<LookupRangeChecks>();
+        let k = k.max(RANGE_TABLE_BITS as u32 + 1);
+        let (_, prover) = run_mock_prover(&verifier, &instances, k, k + 2)?;
+        prover.assert_satisfied();
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_prefix_batch_mock() -> Result<()> {
+        let proofs = vec![
+            test_utils::public_inputs_proof(2)?,
+            test_utils::public_inputs_proof(3)?,
+        ];
+        assert!(verify_batch_inside_snark_mock(proofs, 2, &PipelineConfig::new())?.satisfied);
+        Ok(())
+    }
+
+    #[test]
+    fn test_redundant_proofs_mock() -> Result<()> {
+        let proofs = vec![
+            test_utils::public_inputs_proof(2)?,
+            test_utils::public_inputs_proof(2)?,
+        ];
+        let report = verify_redundant_inside_snark_mock(proofs.clone(), &PipelineConfig::new())?;
+        assert!(report.satisfied);
+
+        let (circuit, _) = build_batch_verifier(proofs, 2, None, &PipelineConfig::new())?;
+        assert_eq!(circuit.instances(), [Fr::from(0), Fr::from(1)]);
+
+        let other_statement = vec![
+            test_utils::public_inputs_proof(2)?,
+            test_utils::public_inputs_proof(3)?,
+        ];
+        assert!(matches!(
+            verify_redundant_inside_snark_mock(other_statement, &PipelineConfig::new()),
+            Err(VerifierError::StatementMismatch { proof: 1 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_circuit_batch_mock() -> Result<()> {
+        let proofs = vec![test_utils::constant_proof()?, test_utils::poseidon_proof()?];
+        let mut registry = CircuitRegistry::new();
+        for (_, vd, cd) in &proofs {
+            registry.register(vd.clone(), cd.clone())?;
+        }
+        assert_eq!(registry.num_circuits(), 2);
+        let report = verify_multi_circuit_batch_inside_snark_mock(
+            proofs.clone(),
+            &registry,
+            0,
+            &PipelineConfig::new(),
+        )?;
+        assert!(report.satisfied);
+
+        let mut unregistered = proofs;
+        unregistered.push(test_utils::base_sum_proof()?);
+        let result = verify_multi_circuit_batch_inside_snark_mock(
+            unregistered,
+            &registry,
+            0,
+            &PipelineConfig::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(VerifierError::UnregisteredCircuit { proof: 2 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_shared_prefix_mismatch() -> Result<()> {
+        let proofs = vec![
+            test_utils::public_inputs_proof(2)?,
+            test_utils::constant_proof()?,
+        ];
+        let result = verify_batch_inside_snark_mock(proofs, 1, &PipelineConfig::new());
+        assert!(matches!(
+            result,
+            Err(VerifierError::SharedPrefixMismatch { proof: 1 })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_selective_disclosure_mock() -> Result<()> {
+        let config = PipelineConfig::new().with_disclosure(SelectiveDisclosure::random(vec![1]));
+        assert!(
+            verify_inside_snark_mock_with_config(test_utils::public_inputs_proof(2)?, &config)?
+                .satisfied
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_bits_mock() -> Result<()> {
+        for rate_bits in 1..=3 {
+            let proof = test_utils::rate_bits_proof(rate_bits)?;
+            assert_eq!(proof.2.config.fri_config.rate_bits, rate_bits);
+            assert!(verify_inside_snark_mock(proof)?.satisfied);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_constant_gate_mock() -> Result<()> {
+        assert!(verify_inside_snark_mock(test_utils::constant_proof()?)?.satisfied);
+        Ok(())
+    }
+
+    #[test]
+    fn test_poseidon_gate_mock() -> Result<()> {
+        assert!(verify_inside_snark_mock(test_utils::poseidon_proof()?)?.satisfied);
+        Ok(())
+    }
+
+    #[test]
+    fn test_base_sum_gate_mock() -> Result<()> {
+        assert!(verify_inside_snark_mock(test_utils::base_sum_proof()?)?.satisfied);
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_access_gate_mock() -> Result<()> {
+        assert!(verify_inside_snark_mock(test_utils::random_access_proof()?)?.satisfied);
+        Ok(())
+    }
+
+    #[test]
+    fn test_extension_arithmetic_gates_mock() -> Result<()> {
+        assert!(verify_inside_snark_mock(test_utils::extension_arithmetic_proof()?)?.satisfied);
+        Ok(())
+    }
 }
@@ -1,6 +1,6 @@
 use crate::snark::types::proof::ProofValues;
 use halo2_proofs::{
-    arithmetic::FieldExt,
+    arithmetic::{Field, FieldExt},
     circuit::{floor_planner::V1, *},
     halo2curves::bn256::Fr,
     plonk::*,
@@ -11,12 +11,23 @@ use halo2wrong_maingate::{AssignedValue, MainGate, MainGateConfig, MainGateInstr
 use itertools::Itertools;
 use poseidon::Spec;
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "debug")]
+use super::challenge_dump::ChallengeDump;
 use super::{
     chip::{
         goldilocks_chip::{GoldilocksChip, GoldilocksChipConfig},
+        hasher_chip::HasherChip,
         plonk::plonk_verifier_chip::PlonkVerifierChip,
+        range_table::{
+            DecompositionRangeChecks, EmulationConfig, RangeCheckMode, RangeTableConfig,
+        },
     },
+    cost,
+    disclosure::{SelectiveDisclosure, COMMITMENT_LEN},
+    instances::{instance_column, InstanceLayout, OrderedInstances},
+    occupancy::{region_occupancy, RegionProfile},
     types::{
         assigned::{
             AssignedProofValues, AssignedProofWithPisValues, AssignedVerificationKeyValues,
@@ -26,12 +37,14 @@ use super::{
         verification_key::VerificationKeyValues,
         HashValues, MerkleCapValues,
     },
-    R_F, R_P, T, T_MINUS_ONE,
+    vk_registry::{self, VK_COMMITMENT_LEN, VK_COMMITMENT_OFFSET},
+    RATE, T, T_MINUS_ONE,
 };
 
 #[derive(Clone)]
 pub struct MainGateWithRangeConfig<F: FieldExt> {
     main_gate_config: MainGateConfig,
+    range_table: Option<RangeTableConfig>,
     _marker: PhantomData<F>,
 }
 
@@ -40,18 +53,67 @@ impl<F: FieldExt> MainGateWithRangeConfig<F> {
         let main_gate_config = MainGate::<F>::configure(meta);
         MainGateWithRangeConfig {
             main_gate_config,
+            range_table: None,
             _marker: PhantomData,
         }
     }
+
+    /// Same as `new`, with a 16-bit range table used by the range checks of the Goldilocks
+    /// emulation
+    pub fn with_range_table(meta: &mut ConstraintSystem<F>) -> Self {
+        let main_gate_config = MainGate::<F>::configure(meta);
+        let range_table = RangeTableConfig::configure(meta);
+        MainGateWithRangeConfig {
+            main_gate_config,
+            range_table: Some(range_table),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn main_gate(&self) -> MainGate<F> {
+        MainGate::new(self.main_gate_config.clone())
+    }
+
+    /// Config of the Goldilocks chip range checking values as `mode` says. Fails with
+    /// `Error::Synthesis` for `RangeCheckMode::Lookup` if the config has no range table.
+    pub(crate) fn goldilocks_chip_config(
+        &self,
+        mode: RangeCheckMode,
+    ) -> Result<GoldilocksChipConfig<F>, Error> {
+        match (mode, &self.range_table) {
+            (RangeCheckMode::Decomposition, _) => {
+                Ok(GoldilocksChip::configure(&self.main_gate_config))
+            }
+            (RangeCheckMode::Lookup, Some(range_table)) => Ok(
+                GoldilocksChip::configure_with_range_table(&self.main_gate_config, range_table),
+            ),
+            (RangeCheckMode::Lookup, None) => Err(Error::Synthesis),
+        }
+    }
 }
 
+/// Plonky2 verifier circuit over the BN254 scalar field, the field of the EVM verifier, which
+/// emulates Goldilocks arithmetic. Defaults to range checks by bit decomposition, see
+/// `EmulationConfig`.
 #[derive(Clone)]
-pub struct Verifier {
+pub struct Verifier<E: EmulationConfig = DecompositionRangeChecks> {
     proof: ProofValues<Fr, 2>,
     instances: Vec<Fr>,
     vk: VerificationKeyValues<Fr>,
     common_data: CommonData<Fr>,
     spec: Spec<Goldilocks, T, T_MINUS_ONE>,
+    nonce: Option<Fr>,
+    layout: InstanceLayout<Fr>,
+    strict_canonical: bool,
+    disclosure: Option<SelectiveDisclosure>,
+    vk_commitment: bool,
+    padded_len: Option<usize>,
+    constants_table: bool,
+    /// Rows of the phases of the verification recorded during synthesis, see `region_profile`
+    profile: Option<Arc<Mutex<RegionProfile>>>,
+    #[cfg(feature = "debug")]
+    challenge_dump: Option<std::path::PathBuf>,
+    _emulation: PhantomData<E>,
 }
 
 impl Verifier {
@@ -68,9 +130,202 @@ impl Verifier {
             vk,
             common_data,
             spec,
+            nonce: None,
+            layout: InstanceLayout::default(),
+            strict_canonical: false,
+            disclosure: None,
+            vk_commitment: false,
+            padded_len: None,
+            constants_table: true,
+            profile: None,
+            #[cfg(feature = "debug")]
+            challenge_dump: None,
+            _emulation: PhantomData,
         }
     }
 
+    /// Same as `new`, with the Poseidon spec derived from `common_data`
+    pub fn with_derived_spec(
+        proof: ProofValues<Fr, 2>,
+        instances: Vec<Fr>,
+        vk: VerificationKeyValues<Fr>,
+        common_data: CommonData<Fr>,
+    ) -> Self {
+        let spec = common_data.spec();
+        Self::new(proof, instances, vk, common_data, spec)
+    }
+
+    /// Smallest circuit size in which the verifier for proofs of `common_data` fits
+    pub fn min_k(common_data: &CommonData<Fr>, spec: &Spec<Goldilocks, T, T_MINUS_ONE>) -> u32 {
+        cost::k_for_rows(cost::estimate_rows(common_data, spec))
+    }
+
+    /// Same as `min_k`, for a verifier with strict canonical checks
+    pub fn min_k_strict(
+        common_data: &CommonData<Fr>,
+        spec: &Spec<Goldilocks, T, T_MINUS_ONE>,
+    ) -> u32 {
+        cost::k_for_rows(
+            cost::estimate_rows(common_data, spec) + cost::canonical_check_rows(common_data),
+        )
+    }
+}
+
+impl<E: EmulationConfig> Verifier<E> {
+    /// Switches the emulation of Goldilocks arithmetic to `E2`. With `LookupRangeChecks` the
+    /// circuit needs `k > 16` to hold the range table.
+    pub fn with_emulation<E2: EmulationConfig>(self) -> Verifier<E2> {
+        Verifier {
+            proof: self.proof,
+            instances: self.instances,
+            vk: self.vk,
+            common_data: self.common_data,
+            spec: self.spec,
+            nonce: self.nonce,
+            layout: self.layout,
+            strict_canonical: self.strict_canonical,
+            disclosure: self.disclosure,
+            vk_commitment: self.vk_commitment,
+            padded_len: self.padded_len,
+            constants_table: self.constants_table,
+            profile: self.profile,
+            #[cfg(feature = "debug")]
+            challenge_dump: self.challenge_dump,
+            _emulation: PhantomData,
+        }
+    }
+
+    /// Exposes `nonce` as an extra instance after the Plonky2 public inputs. The nonce isn't
+    /// used by the verifier itself, exposing it binds it to the proof so that a contract can
+    /// refuse to accept the same proof twice.
+    pub fn with_nonce(mut self, nonce: Fr) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    /// Exposes the metadata of `layout` as instances before the Plonky2 public inputs
+    pub fn with_layout(mut self, layout: InstanceLayout<Fr>) -> Self {
+        self.layout = layout.with_vk_commitment(self.vk_commitment);
+        self
+    }
+
+    /// Fixes every distinct constant of the verification once and copies it at every use, see
+    /// `ConstantsTable`. Enabled by default.
+    pub fn with_constants_table(mut self, constants_table: bool) -> Self {
+        self.constants_table = constants_table;
+        self
+    }
+
+    /// Rows saved by the constants table, measured by laying the circuit out with and without it
+    pub fn constant_rows_saved(&self) -> Result<usize, Error> {
+        let (_, rows) = region_occupancy(&self.clone().with_constants_table(true))?;
+        let (_, rows_without) = region_occupancy(&self.clone().with_constants_table(false))?;
+        Ok(rows_without.saturating_sub(rows))
+    }
+
+    /// Rows consumed by the phases of the verification, measured by laying the circuit out
+    /// without computing any witness, so that optimizations target the dominant phase
+    pub fn region_profile(&self) -> Result<RegionProfile, Error> {
+        let profile = Arc::new(Mutex::new(RegionProfile::default()));
+        let circuit = Self {
+            profile: Some(profile.clone()),
+            ..self.clone()
+        };
+        let (_, rows_used) = region_occupancy(&circuit)?;
+        let mut profile = *profile.lock().unwrap();
+        profile.other = rows_used.saturating_sub(profile.total());
+        Ok(profile)
+    }
+
+    /// Writes the challenges derived during witness generation to `path` as JSON, see
+    /// `ChallengeDump`. Synthesis fails with `Error::Synthesis` if the dump can't be written
+    #[cfg(feature = "debug")]
+    pub fn with_challenge_dump(mut self, path: impl Into<std::path::PathBuf>) -> Self {
+        self.challenge_dump = Some(path.into());
+        self
+    }
+
+    /// Constrains every element of the public inputs and the proof to be a canonical Goldilocks
+    /// element, so that no other encoding of the same proof satisfies the circuit. Costs about
+    /// `cost::canonical_check_rows` rows.
+    pub fn with_strict_canonical_checks(mut self, strict_canonical: bool) -> Self {
+        self.strict_canonical = strict_canonical;
+        self
+    }
+
+    /// Only exposes the public inputs selected by `disclosure`, followed by a commitment to the
+    /// other ones
+    pub fn with_disclosure(mut self, disclosure: SelectiveDisclosure) -> Self {
+        self.disclosure = Some(disclosure);
+        self
+    }
+
+    /// Exposes a commitment to the verification key, see `vk_registry`, as the first
+    /// `VK_COMMITMENT_LEN` instances, before the metadata of the layout. The verification key
+    /// is then a witness of the circuit, so that the same EVM verifier accepts proofs of every
+    /// circuit with the same common data.
+    pub fn with_vk_commitment(mut self, vk_commitment: bool) -> Self {
+        self.vk_commitment = vk_commitment;
+        self.layout = self.layout.with_vk_commitment(vk_commitment);
+        self
+    }
+
+    /// Pads the instances with zeros up to `padded_len`, for contracts expecting a fixed number
+    /// of instances. The padding is fixed in the circuit, so that it can only be zero. Ignored if
+    /// the circuit exposes more instances.
+    pub fn with_instance_padding(mut self, padded_len: usize) -> Self {
+        self.padded_len = Some(padded_len);
+        self
+    }
+
+    /// Plonky2 public inputs of the proof
+    pub(crate) fn public_inputs(&self) -> &[Fr] {
+        &self.instances
+    }
+
+    pub(crate) fn vk(&self) -> &VerificationKeyValues<Fr> {
+        &self.vk
+    }
+
+    /// Estimated number of rows of the verification of the proof, without the instances
+    pub(crate) fn estimate_rows(&self) -> usize {
+        let mut rows = cost::estimate_rows(&self.common_data, &self.spec);
+        if self.strict_canonical {
+            rows += cost::canonical_check_rows(&self.common_data);
+        }
+        if self.vk_commitment {
+            rows += cost::vk_commitment_rows(&self.common_data, &self.spec);
+        }
+        rows
+    }
+
+    /// Public inputs exposed as instances, all of them unless some are hidden by the disclosure
+    fn exposed_public_inputs(&self) -> Vec<Fr> {
+        match &self.disclosure {
+            Some(disclosure) => disclosure.instances(&self.instances),
+            None => self.instances.clone(),
+        }
+    }
+
+    /// Commitment to the verification key exposed by the circuit, if any
+    pub fn vk_commitment(&self) -> Option<[Fr; VK_COMMITMENT_LEN]> {
+        self.vk_commitment
+            .then(|| vk_registry::vk_commitment(&self.vk))
+    }
+
+    /// Values of the instance column: the commitment to the verification key if any, the
+    /// metadata of the layout, the exposed Plonky2 public inputs and the nonce if any, padded
+    /// with zeros if the padding is set
+    pub fn instances(&self) -> Vec<Fr> {
+        instance_column(
+            &self.layout,
+            self.vk_commitment(),
+            self.exposed_public_inputs(),
+            self.nonce,
+            self.padded_len,
+        )
+    }
+
     fn assign_proof_with_pis(
         &self,
         config: &GoldilocksChipConfig<Fr>,
@@ -136,41 +391,41 @@ impl Verifier {
             )?,
         })
     }
-}
-
-impl Circuit<Fr> for Verifier {
-    type Config = MainGateWithRangeConfig<Fr>;
-    type FloorPlanner = V1;
-
-    fn without_witnesses(&self) -> Self {
-        Self {
-            proof: self.proof.clone(),
-            instances: self.instances.clone(),
-            vk: self.vk.clone(),
-            common_data: self.common_data.clone(),
-            spec: Spec::new(R_F, R_P),
-        }
-    }
-
-    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
-        MainGateWithRangeConfig::new(meta)
-    }
-
-    fn synthesize(
+    /// Assigns the proof, its public inputs and the verification key, and constrains the proof
+    /// to be valid. Returns the assigned proof and public inputs with the verification key,
+    /// which are left for the caller to expose.
+    pub(crate) fn verify(
         &self,
-        config: Self::Config,
+        goldilocks_chip_config: &GoldilocksChipConfig<Fr>,
         mut layouter: impl Layouter<Fr>,
-    ) -> Result<(), Error> {
-        let main_gate = MainGate::new(config.main_gate_config.clone());
-        let goldilocks_chip_config = GoldilocksChip::configure(&config.main_gate_config);
+    ) -> Result<
+        (
+            AssignedProofWithPisValues<Fr, 2>,
+            AssignedVerificationKeyValues<Fr>,
+        ),
+        Error,
+    > {
         let assigned_proof_with_pis = self.assign_proof_with_pis(
-            &goldilocks_chip_config,
+            goldilocks_chip_config,
             layouter.namespace(|| "Assign proof and public inputs"),
             &self.proof,
             &self.instances,
         )?;
+        if self.strict_canonical {
+            layouter.assign_region(
+                || "Check canonical encoding",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let goldilocks_chip = GoldilocksChip::new(goldilocks_chip_config);
+                    for element in assigned_proof_with_pis.elements() {
+                        goldilocks_chip.assert_canonical(ctx, element)?;
+                    }
+                    Ok(())
+                },
+            )?;
+        }
         let assigned_vk = self.assign_verification_key(
-            &goldilocks_chip_config,
+            goldilocks_chip_config,
             layouter.namespace(|| "Assign verification key"),
             &self.vk,
         )?;
@@ -178,12 +433,19 @@ impl Circuit<Fr> for Verifier {
             || "Verify proof",
             |region| {
                 let ctx = &mut RegionCtx::new(region, 0);
-                let plonk_verifier_chip = PlonkVerifierChip::construct(&goldilocks_chip_config);
+                let goldilocks_chip_config = &if self.constants_table {
+                    goldilocks_chip_config.with_constants_table()
+                } else {
+                    goldilocks_chip_config.clone()
+                };
+                let plonk_verifier_chip = PlonkVerifierChip::construct(goldilocks_chip_config);
+                let start = ctx.offset();
                 let public_inputs_hash = plonk_verifier_chip.get_public_inputs_hash(
                     ctx,
                     &assigned_proof_with_pis.public_inputs,
                     &self.spec,
                 )?;
+                let hash_end = ctx.offset();
                 let challenges = plonk_verifier_chip.get_challenges(
                     ctx,
                     &public_inputs_hash,
@@ -193,22 +455,202 @@ impl Circuit<Fr> for Verifier {
                     self.common_data.config.num_challenges,
                     &self.spec,
                 )?;
-                plonk_verifier_chip.verify_proof_with_challenges(
+                let challenges_end = ctx.offset();
+                #[cfg(feature = "debug")]
+                if let Some(path) = &self.challenge_dump {
+                    let lde_bits = self.common_data.fri_params.lde_bits();
+                    if let Some(dump) = ChallengeDump::from_assigned(&challenges, lde_bits) {
+                        dump.write(path).map_err(|_| Error::Synthesis)?;
+                    }
+                }
+                plonk_verifier_chip.check_vanishing_poly(
                     ctx,
                     &assigned_proof_with_pis.proof,
                     &public_inputs_hash,
                     &challenges,
+                    &self.common_data,
+                )?;
+                let constraints_end = ctx.offset();
+                plonk_verifier_chip.verify_openings(
+                    ctx,
+                    &assigned_proof_with_pis.proof,
+                    &challenges,
                     &assigned_vk,
                     &self.common_data,
                     &self.spec,
-                )
+                )?;
+                if let Some(profile) = &self.profile {
+                    *profile.lock().unwrap() = RegionProfile {
+                        public_inputs_hash: hash_end - start,
+                        transcript: challenges_end - hash_end,
+                        gate_constraints: constraints_end - challenges_end,
+                        fri: ctx.offset() - constraints_end,
+                        other: 0,
+                    };
+                }
+                Ok(())
             },
         )?;
-        for (row, public_input) in
-            (0..self.instances.len()).zip_eq(assigned_proof_with_pis.public_inputs)
-        {
+        Ok((assigned_proof_with_pis, assigned_vk))
+    }
+}
+
+impl<E: EmulationConfig> Verifier<E> {
+    pub fn ordered_instances(&self) -> OrderedInstances {
+        OrderedInstances::new(self.instances())
+    }
+}
+
+impl<E: EmulationConfig> Circuit<Fr> for Verifier<E> {
+    type Config = MainGateWithRangeConfig<Fr>;
+    type FloorPlanner = V1;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            proof: self.proof.clone(),
+            instances: self.instances.clone(),
+            vk: self.vk.clone(),
+            common_data: self.common_data.clone(),
+            spec: self.spec.clone(),
+            nonce: self.nonce,
+            layout: self.layout.clone(),
+            strict_canonical: self.strict_canonical,
+            disclosure: self.disclosure.clone(),
+            vk_commitment: self.vk_commitment,
+            padded_len: self.padded_len,
+            constants_table: self.constants_table,
+            profile: self.profile.clone(),
+            #[cfg(feature = "debug")]
+            challenge_dump: self.challenge_dump.clone(),
+            _emulation: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+        match E::RANGE_CHECK {
+            RangeCheckMode::Decomposition => MainGateWithRangeConfig::new(meta),
+            RangeCheckMode::Lookup => MainGateWithRangeConfig::with_range_table(meta),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fr>,
+    ) -> Result<(), Error> {
+        let main_gate = MainGate::new(config.main_gate_config.clone());
+        let goldilocks_chip_config = config.goldilocks_chip_config(E::RANGE_CHECK)?;
+        if let Some(range_table) = &goldilocks_chip_config.range_table {
+            range_table.load(&mut layouter)?;
+        }
+        let (assigned_proof_with_pis, assigned_vk) = self.verify(
+            &goldilocks_chip_config,
+            layouter.namespace(|| "Verify proof"),
+        )?;
+        // the commitment to the verification key comes first, so that contracts read it at a
+        // fixed row whatever the metadata of the layout
+        let mut offset = 0;
+        if self.vk_commitment {
+            let commitment = layouter.assign_region(
+                || "Commit to verification key",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let mut hasher_chip = HasherChip::<Fr, T, T_MINUS_ONE, RATE>::new(
+                        ctx,
+                        &self.spec,
+                        &goldilocks_chip_config,
+                    )?;
+                    let inputs = assigned_vk
+                        .constants_sigmas_cap
+                        .0
+                        .iter()
+                        .chain(std::iter::once(&assigned_vk.circuit_digest))
+                        .flat_map(|hash| hash.elements.clone())
+                        .collect();
+                    hasher_chip.hash(ctx, inputs, VK_COMMITMENT_LEN)
+                },
+            )?;
+            for (row, value) in (VK_COMMITMENT_OFFSET..).zip(commitment) {
+                main_gate.expose_public(layouter.namespace(|| ""), value, row)?;
+            }
+            offset += VK_COMMITMENT_LEN;
+        }
+        if !self.layout.metadata().is_empty() {
+            let metadata = layouter.assign_region(
+                || "Assign instance metadata",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    self.layout
+                        .metadata_values()
+                        .into_iter()
+                        .map(|value| main_gate.assign_constant(ctx, value))
+                        .collect::<Result<Vec<AssignedValue<Fr>>, Error>>()
+                },
+            )?;
+            for (row, value) in (offset..).zip(metadata) {
+                main_gate.expose_public(layouter.namespace(|| ""), value, row)?;
+            }
+        }
+        offset += self.layout.metadata().len();
+        let public_inputs = match &self.disclosure {
+            Some(disclosure) => layouter.assign_region(
+                || "Commit to hidden public inputs",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    let goldilocks_chip = GoldilocksChip::new(&goldilocks_chip_config);
+                    let (disclosed, hidden) =
+                        disclosure.split(&assigned_proof_with_pis.public_inputs);
+                    let blinding = disclosure
+                        .blinding()
+                        .iter()
+                        .map(|e| goldilocks_chip.assign_constant(ctx, *e))
+                        .collect::<Result<Vec<AssignedValue<Fr>>, Error>>()?;
+                    let mut hasher_chip = HasherChip::<Fr, T, T_MINUS_ONE, RATE>::new(
+                        ctx,
+                        &self.spec,
+                        &goldilocks_chip_config,
+                    )?;
+                    let commitment = hasher_chip.hash(
+                        ctx,
+                        hidden.into_iter().chain(blinding).collect(),
+                        COMMITMENT_LEN,
+                    )?;
+                    Ok(disclosed.into_iter().chain(commitment).collect_vec())
+                },
+            )?,
+            None => assigned_proof_with_pis.public_inputs,
+        };
+        let num_exposed = public_inputs.len();
+        for (row, public_input) in (offset..offset + num_exposed).zip_eq(public_inputs) {
             main_gate.expose_public(layouter.namespace(|| ""), public_input, row)?;
         }
+        offset += num_exposed;
+        if let Some(nonce) = self.nonce {
+            let nonce = layouter.assign_region(
+                || "Assign nonce",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    main_gate.assign_value(ctx, Value::known(nonce))
+                },
+            )?;
+            main_gate.expose_public(layouter.namespace(|| ""), nonce, offset)?;
+            offset += 1;
+        }
+        let num_padding = self.padded_len.unwrap_or(0).saturating_sub(offset);
+        if num_padding > 0 {
+            let padding = layouter.assign_region(
+                || "Assign instance padding",
+                |region| {
+                    let ctx = &mut RegionCtx::new(region, 0);
+                    (0..num_padding)
+                        .map(|_| main_gate.assign_constant(ctx, Fr::zero()))
+                        .collect::<Result<Vec<AssignedValue<Fr>>, Error>>()
+                },
+            )?;
+            for (row, value) in (offset..).zip(padding) {
+                main_gate.expose_public(layouter.namespace(|| ""), value, row)?;
+            }
+        }
         Ok(())
     }
 }
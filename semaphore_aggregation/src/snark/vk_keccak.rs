@@ -0,0 +1,101 @@
+//! Identity of an EVM verifier computable on-chain: the Keccak256 of the constants its code is
+//! generated from, laid out as `bytes32` words. Unlike `envelope::vk_hash`, which only covers the
+//! transcript representation of the verifying key, it also binds the commitments of the key and
+//! the SRS points of the pairing check, so that two verifiers with the same hash accept the same
+//! proofs. On-chain registries compute it with the Solidity library of `vk_hash_library` over
+//! the words of `vk_words`.
+use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine};
+use halo2_proofs::halo2curves::CurveAffine;
+use halo2_proofs::plonk::VerifyingKey;
+use halo2_proofs::poly::commitment::ParamsProver;
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+
+use super::encoding::fr_to_bytes32;
+use super::g2_args::{fq_to_bytes32, g2_constructor_args};
+use super::utils::keccak256;
+
+/// `(x, y)` of `point`, `(0, 0)` for the identity like the EVM encodes it
+fn g1_words(point: &G1Affine) -> [[u8; 32]; 2] {
+    Option::from(point.coordinates()).map_or([[0; 32]; 2], |coordinates| {
+        [
+            fq_to_bytes32(coordinates.x()),
+            fq_to_bytes32(coordinates.y()),
+        ]
+    })
+}
+
+/// Constants of the verifier of `vk` generated from `params`, as big-endian words:
+/// - the transcript representation of `vk`
+/// - the fixed commitments of `vk`, then its permutation commitments, each as `(x, y)`
+/// - the generator of G1 of `params` as `(x, y)`
+/// - `g2` and `-s_g2` of `params`, as the words of `g2_args::g2_constructor_args`
+pub fn vk_words(vk: &VerifyingKey<G1Affine>, params: &ParamsKZG<Bn256>) -> Vec<[u8; 32]> {
+    let mut words = vec![fr_to_bytes32(&vk.transcript_repr())];
+    words.extend(
+        vk.fixed_commitments()
+            .iter()
+            .chain(vk.permutation().commitments())
+            .chain(std::iter::once(&params.get_g()[0]))
+            .flat_map(g1_words),
+    );
+    words.extend(g2_constructor_args(params));
+    words
+}
+
+/// Keccak256 of the words of `vk_words`, what `vkHash` of `vk_hash_library` returns for them
+pub fn vk_keccak_hash(vk: &VerifyingKey<G1Affine>, params: &ParamsKZG<Bn256>) -> [u8; 32] {
+    keccak256(&vk_words(vk, params).concat())
+}
+
+/// Source of the Solidity library hashing the words of `vk_words` as `vk_keccak_hash` does
+pub fn vk_hash_library(library_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+library {library_name}VkHash {{
+    /// Identity of the verifier generated from the constants `words`, laid out as big-endian
+    /// words: the transcript representation of the verifying key, its fixed and permutation
+    /// commitments as (x, y), the generator of G1, then g2 and -s_g2 as the inputs of the
+    /// pairing precompile
+    function vkHash(bytes32[] memory words) internal pure returns (bytes32) {{
+        return keccak256(abi.encodePacked(words));
+    }}
+}}
+"#
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use halo2_proofs::halo2curves::bn256::Bn256;
+    use halo2_proofs::plonk::keygen_vk;
+    use halo2_proofs::poly::commitment::Params;
+    use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::{vk_hash_library, vk_keccak_hash, vk_words};
+    use crate::snark::g2_args::NUM_G2_WORDS;
+    use crate::snark::test_utils;
+    use crate::snark::utils::keccak256;
+
+    #[test]
+    fn test_vk_keccak_hash() {
+        let params = ParamsKZG::<Bn256>::setup(8, StdRng::seed_from_u64(0));
+        let vk = keygen_vk(&params, &test_utils::FixedCircuit).unwrap();
+        let words = vk_words(&vk, &params);
+        let num_commitments = vk.fixed_commitments().len() + vk.permutation().commitments().len();
+        assert_eq!(words.len(), 1 + 2 * (num_commitments + 1) + NUM_G2_WORDS);
+        assert_eq!(vk_keccak_hash(&vk, &params), keccak256(&words.concat()));
+
+        // the same circuit with another SRS is another verifier
+        let other_params = ParamsKZG::<Bn256>::setup(params.k(), StdRng::seed_from_u64(1));
+        let other_vk = keygen_vk(&other_params, &test_utils::FixedCircuit).unwrap();
+        assert_ne!(
+            vk_keccak_hash(&vk, &params),
+            vk_keccak_hash(&other_vk, &other_params)
+        );
+        assert!(vk_hash_library("Semaphore").contains("library SemaphoreVkHash"));
+    }
+}
@@ -0,0 +1,217 @@
+//! Committed verification keys: the verifier circuit takes the Plonky2 verification key as a
+//! witness and exposes a Poseidon commitment to it, so that a single EVM verifier serves every
+//! upstream circuit with the same common data, the verifier contract checking the commitment
+//! against a registry of whitelisted verification keys.
+use std::collections::BTreeSet;
+use std::io;
+
+use halo2_proofs::arithmetic::FieldExt;
+use halo2_proofs::halo2curves::bn256::Fr;
+use halo2wrong_maingate::fe_to_big;
+use plonky2::field::goldilocks_field::GoldilocksField;
+use plonky2::field::types::{Field, PrimeField64};
+use plonky2::hash::poseidon::PoseidonHash;
+use plonky2::plonk::config::Hasher;
+use serde::{Deserialize, Serialize};
+
+use super::encoding::fr_to_bytes32;
+use super::envelope::hex_bytes32_vec;
+use super::types::verification_key::VerificationKeyValues;
+use super::utils::keccak256;
+
+pub use super::instances::{VK_COMMITMENT_LEN, VK_COMMITMENT_OFFSET};
+
+/// Commitment to `vk` exposed by the verifier circuit, `Poseidon(constants_sigmas_cap ||
+/// circuit_digest)`
+pub fn vk_commitment<F: FieldExt>(vk: &VerificationKeyValues<F>) -> [F; VK_COMMITMENT_LEN] {
+    let inputs = vk
+        .constants_sigmas_cap
+        .0
+        .iter()
+        .chain(std::iter::once(&vk.circuit_digest))
+        .flat_map(|hash| hash.elements)
+        .map(|e| GoldilocksField::from_noncanonical_biguint(fe_to_big(e)))
+        .collect::<Vec<_>>();
+    PoseidonHash::hash_no_pad(&inputs)
+        .elements
+        .map(|e| F::from(e.to_canonical_u64()))
+}
+
+/// Key of a verification key in the on-chain registry, the Keccak256 of the commitment instances
+/// as `bytes32` words, as the verifier contract reads them from calldata
+pub fn registry_key(commitment: &[Fr; VK_COMMITMENT_LEN]) -> [u8; 32] {
+    keccak256(
+        &commitment
+            .iter()
+            .flat_map(fr_to_bytes32)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Registry key of the verification key committed to by `instances`, the `bytes32` instances of
+/// a proof, or `None` if there are fewer than the commitment
+pub fn registry_key_of_instances(instances: &[[u8; 32]]) -> Option<[u8; 32]> {
+    let commitment =
+        instances.get(VK_COMMITMENT_OFFSET..VK_COMMITMENT_OFFSET + VK_COMMITMENT_LEN)?;
+    Some(keccak256(&commitment.concat()))
+}
+
+/// Source of the Solidity library reading the registry key of the verification key from the
+/// calldata of the verifier, where the commitment is always the first `VK_COMMITMENT_LEN` words
+pub fn registry_library(library_name: &str) -> String {
+    format!(
+        r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.0;
+
+library {library_name}VkRegistry {{
+    uint256 internal constant VK_COMMITMENT_OFFSET = {VK_COMMITMENT_OFFSET};
+    uint256 internal constant VK_COMMITMENT_LEN = {VK_COMMITMENT_LEN};
+
+    /// Registry key of the verification key of the proof in `verifierCalldata`, the Keccak256 of
+    /// the words of its commitment
+    function registryKey(bytes calldata verifierCalldata) internal pure returns (bytes32) {{
+        uint256 start = VK_COMMITMENT_OFFSET * 32;
+        uint256 end = start + VK_COMMITMENT_LEN * 32;
+        require(verifierCalldata.length >= end, "calldata too short");
+        return keccak256(verifierCalldata[start:end]);
+    }}
+}}
+"#
+    )
+}
+
+/// Source of the registry contract `{contract_name}Registry`, preceded by `registry_library`.
+/// It is deployed with the address of the verifier and the registry keys whitelisted initially,
+/// its owner registering further keys, and forwards the calldata of proofs of whitelisted
+/// verification keys to the verifier.
+pub fn registry_contract(contract_name: &str) -> String {
+    format!(
+        r#"{library}
+contract {contract_name}Registry {{
+    address public immutable verifier;
+    address public owner;
+    mapping(bytes32 => bool) public registered;
+
+    event Registered(bytes32 indexed key);
+
+    constructor(address verifier_, bytes32[] memory keys) {{
+        verifier = verifier_;
+        owner = msg.sender;
+        for (uint256 i = 0; i < keys.length; i++) {{
+            registered[keys[i]] = true;
+            emit Registered(keys[i]);
+        }}
+    }}
+
+    /// Whitelists the verification key of registry key `key`, see `{contract_name}VkRegistry`
+    function register(bytes32 key) external {{
+        require(msg.sender == owner, "not the owner");
+        registered[key] = true;
+        emit Registered(key);
+    }}
+
+    /// Whether the proof in `verifierCalldata` is of a whitelisted verification key and is
+    /// accepted by the verifier
+    function verify(bytes calldata verifierCalldata) external view returns (bool) {{
+        if (!registered[{contract_name}VkRegistry.registryKey(verifierCalldata)]) {{
+            return false;
+        }}
+        (bool success, ) = verifier.staticcall(verifierCalldata);
+        return success;
+    }}
+}}
+"#,
+        library = registry_library(contract_name)
+    )
+}
+
+/// Whitelist of verification keys, mirroring the registry of the verifier contract
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VkRegistry {
+    keys: BTreeSet<[u8; 32]>,
+}
+
+/// Registry keys as written by `VkRegistry::to_json`
+#[derive(Serialize, Deserialize)]
+struct RegistryKeys {
+    #[serde(with = "hex_bytes32_vec")]
+    keys: Vec<[u8; 32]>,
+}
+
+impl VkRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whitelists `vk`, returning its registry key
+    pub fn register(&mut self, vk: &VerificationKeyValues<Fr>) -> [u8; 32] {
+        let key = registry_key(&vk_commitment(vk));
+        self.keys.insert(key);
+        key
+    }
+
+    /// Whether the commitment exposed by a proof belongs to a whitelisted verification key
+    pub fn contains(&self, commitment: &[Fr; VK_COMMITMENT_LEN]) -> bool {
+        self.keys.contains(&registry_key(commitment))
+    }
+
+    /// Whether the verification key committed to by the `bytes32` instances of a proof is
+    /// whitelisted, as the registry contract checks it
+    pub fn contains_instances(&self, instances: &[[u8; 32]]) -> bool {
+        registry_key_of_instances(instances).map_or(false, |key| self.keys.contains(&key))
+    }
+
+    /// Registry keys, in increasing order, to be written to the verifier contract
+    pub fn keys(&self) -> impl Iterator<Item = &[u8; 32]> {
+        self.keys.iter()
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(&RegistryKeys {
+            keys: self.keys.iter().copied().collect(),
+        })
+    }
+
+    pub fn from_json(json: &str) -> io::Result<Self> {
+        let keys: RegistryKeys = serde_json::from_str(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        Ok(Self {
+            keys: keys.keys.into_iter().collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::Result;
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    use super::{registry_contract, registry_library, vk_commitment, VkRegistry};
+    use crate::snark::test_utils;
+    use crate::snark::types::verification_key::VerificationKeyValues;
+
+    #[test]
+    fn test_vk_registry() -> Result<()> {
+        let (_, vd, _) = test_utils::constant_proof()?;
+        let vk = VerificationKeyValues::<Fr>::try_from(vd)?;
+        let (_, other_vd, _) = test_utils::poseidon_proof()?;
+        let other_vk = VerificationKeyValues::<Fr>::try_from(other_vd)?;
+
+        let mut registry = VkRegistry::new();
+        registry.register(&vk);
+        assert!(registry.contains(&vk_commitment(&vk)));
+        assert!(!registry.contains(&vk_commitment(&other_vk)));
+
+        let json = registry.to_json()?;
+        assert_eq!(VkRegistry::from_json(&json)?, registry);
+
+        let contract = registry_contract("Semaphore");
+        assert!(contract.contains("contract SemaphoreRegistry"));
+        assert!(contract.contains("SemaphoreVkRegistry.registryKey(verifierCalldata)"));
+
+        let library = registry_library("Semaphore");
+        assert!(library.contains("library SemaphoreVkRegistry"));
+        assert!(library.contains("VK_COMMITMENT_OFFSET = 0;"));
+        Ok(())
+    }
+}
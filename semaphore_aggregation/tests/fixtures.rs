@@ -0,0 +1,53 @@
+//! Checks the fixtures committed under `tests/fixtures` by `gen_fixtures`: the Plonky2 proof
+//! they hold is the one of the fixture circuit, and the exported verifier accepts the wrapped
+//! proof, as recorded in the manifest. Needs neither the SRS nor the keys. Regenerate the
+//! fixtures with `cargo run --release --bin gen_fixtures` after a change of the verifier.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use semaphore_aggregation::snark::envelope::WrappedProofEnvelope;
+use semaphore_aggregation::snark::export::{verify_artifacts, Manifest, CONTRACT_NAME};
+use semaphore_aggregation::snark::fixtures::{
+    fixture_proof, load_fixture, FIXTURES_DIR, FIXTURE_PUBLIC_INPUTS,
+};
+use semaphore_aggregation::snark::instances::OrderedInstances;
+
+fn fixtures_dir() -> PathBuf {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(FIXTURES_DIR);
+    assert!(
+        dir.exists(),
+        "no fixtures at {}, run `cargo run --release --bin gen_fixtures` to write them",
+        dir.display()
+    );
+    dir
+}
+
+#[test]
+fn test_committed_fixtures() -> Result<()> {
+    let dir = fixtures_dir();
+    let (proof, verifier_only, common) = load_fixture(&dir)?;
+    let (expected, expected_verifier_only, expected_common) = fixture_proof()?;
+    assert_eq!(proof.public_inputs.len(), FIXTURE_PUBLIC_INPUTS);
+    assert_eq!(proof.to_bytes(), expected.to_bytes());
+    assert_eq!(verifier_only, expected_verifier_only);
+    assert_eq!(common, expected_common);
+
+    let artifacts = dir.join("artifacts");
+    let manifest: Manifest =
+        serde_json::from_str(&fs::read_to_string(artifacts.join("manifest.json"))?)?;
+    let envelope =
+        WrappedProofEnvelope::from_json(&fs::read_to_string(artifacts.join("proof.json"))?)?;
+    assert_eq!(manifest.contract_name, CONTRACT_NAME);
+    assert_eq!(manifest.vk_hash, envelope.vk_hash);
+    // the wrapped proof exposes the public inputs of the committed Plonky2 proof
+    assert_eq!(
+        envelope.instances()?,
+        OrderedInstances::try_from_public_inputs(&proof.public_inputs)?
+    );
+
+    let result = verify_artifacts(&artifacts)?;
+    assert!(result.success);
+    assert_eq!(result.gas_used, manifest.gas_used);
+    Ok(())
+}
@@ -0,0 +1,41 @@
+//! Fails when the EVM verifier of the fixture proof of `snark::fixtures` uses more gas than a
+//! budget, so that a regression of the on-chain cost, from an edit of the verifier circuit or an
+//! upgrade of snark-verifier or solc, is caught in CI instead of after deployment. The budget
+//! bounds the execution gas of the verifier, without the intrinsic gas of the transaction, and
+//! defaults to `DEFAULT_GAS_BUDGET`. Run with `GAS_BUDGET=<gas>` to check another one.
+use anyhow::{anyhow, Result};
+use semaphore_aggregation::snark::fixtures::fixture_proof;
+use semaphore_aggregation::snark::pipeline::{MultiOpenScheme, PipelineConfig};
+use semaphore_aggregation::snark::verifier_api::verify_inside_snark_with_config;
+
+/// Environment variable overriding `DEFAULT_GAS_BUDGET`
+const GAS_BUDGET_ENV: &str = "GAS_BUDGET";
+
+const DEFAULT_GAS_BUDGET: u64 = 550_000;
+
+fn gas_budget() -> Result<u64> {
+    match std::env::var(GAS_BUDGET_ENV) {
+        Ok(budget) => budget
+            .parse()
+            .map_err(|e| anyhow!("invalid {GAS_BUDGET_ENV} {budget}: {e}")),
+        Err(_) => Ok(DEFAULT_GAS_BUDGET),
+    }
+}
+
+#[test]
+fn test_gas_budget() -> Result<()> {
+    let budget = gas_budget()?;
+    for multiopen in [MultiOpenScheme::Gwc, MultiOpenScheme::Shplonk] {
+        let config = PipelineConfig::new()
+            .allow_insecure()
+            .with_multiopen(multiopen);
+        let report = verify_inside_snark_with_config(fixture_proof()?, &config)?;
+        let gas_used = report.gas_used.expect("the EVM verifier ran");
+        assert!(
+            gas_used <= budget,
+            "the {multiopen} verifier of the fixture proof uses {gas_used} gas, over the budget \
+             of {budget}; rerun with {GAS_BUDGET_ENV}=<gas> if the increase is intended"
+        );
+    }
+    Ok(())
+}
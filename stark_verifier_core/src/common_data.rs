@@ -0,0 +1,235 @@
+//! Configs of a Plonky2 circuit, mirroring the ones of
+//! `plonky2::plonk::circuit_data::CommonCircuitData`, with the checks of their consistency which
+//! don't depend on the gates
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::error::{check_consistent, ConversionError};
+
+/// Bits of the quadratic extension of Goldilocks the FRI challenges are drawn from
+pub const EXTENSION_FIELD_BITS: usize = 128;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriConfig {
+    /// `rate = 2^{-rate_bits}`.
+    pub rate_bits: usize,
+
+    /// Height of Merkle tree caps.
+    pub cap_height: usize,
+
+    pub proof_of_work_bits: u32,
+
+    /// Number of query rounds to perform.
+    pub num_query_rounds: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CircuitConfig {
+    pub num_wires: usize,
+    pub num_routed_wires: usize,
+    pub num_constants: usize,
+    /// Whether to use a dedicated gate for base field arithmetic, rather than using a single gate
+    /// for both base field and extension field arithmetic.
+    pub use_base_arithmetic_gate: bool,
+    pub security_bits: usize,
+    /// The number of challenge points to generate, for IOPs that have soundness errors of (roughly)
+    /// `degree / |F|`.
+    pub num_challenges: usize,
+    pub zero_knowledge: bool,
+    /// A cap on the quotient polynomial's degree factor. The actual degree factor is derived
+    /// systematically, but will never exceed this value.
+    pub max_quotient_degree_factor: usize,
+    pub fri_config: FriConfig,
+}
+
+impl CircuitConfig {
+    /// Number of partial products of the permutation argument with chunks of
+    /// `quotient_degree_factor` routed wires. The last chunk of the product is checked against
+    /// `Z(gx)` instead of a partial product.
+    pub fn num_partial_products(&self, quotient_degree_factor: usize) -> usize {
+        self.num_routed_wires.div_ceil(quotient_degree_factor) - 1
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriParams {
+    pub config: FriConfig,
+    pub hiding: bool,
+    pub degree_bits: usize,
+    pub reduction_arity_bits: Vec<usize>,
+}
+
+impl FriParams {
+    pub fn lde_bits(&self) -> usize {
+        self.degree_bits + self.config.rate_bits
+    }
+
+    /// Checks the parameters against the ones of the circuit `config`
+    pub fn check(&self, config: &CircuitConfig) -> Result<(), ConversionError> {
+        let fri_config = &self.config;
+        check_consistent(
+            "FRI rate bits",
+            config.fri_config.rate_bits,
+            fri_config.rate_bits,
+        )?;
+        check_consistent(
+            "FRI cap height",
+            config.fri_config.cap_height,
+            fri_config.cap_height,
+        )?;
+        check_consistent(
+            "FRI proof of work bits",
+            config.fri_config.proof_of_work_bits as usize,
+            fri_config.proof_of_work_bits as usize,
+        )?;
+        check_consistent(
+            "FRI query rounds",
+            config.fri_config.num_query_rounds,
+            fri_config.num_query_rounds,
+        )?;
+        check_consistent(
+            "FRI hiding",
+            config.zero_knowledge as usize,
+            self.hiding as usize,
+        )?;
+        let total_arity_bits = self.reduction_arity_bits.iter().sum::<usize>();
+        if total_arity_bits > self.degree_bits {
+            return Err(ConversionError::InconsistentCommonData {
+                quantity: "total FRI reduction arity bits",
+                expected: self.degree_bits,
+                found: total_arity_bits,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Security of the FRI proofs of a circuit in bits, estimated as in the ethSTARK paper. Each
+/// query adds `rate_bits` bits under its conjecture on the list decoding of Reed-Solomon codes,
+/// and `rate_bits / 2` bits up to the Johnson bound, which is proven. The proof of work adds its
+/// bits to both, and the errors of the challenges drawn from the extension field cap them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FriSoundness {
+    pub conjectured_bits: usize,
+    pub provable_bits: usize,
+}
+
+impl FriParams {
+    pub fn soundness(&self) -> FriSoundness {
+        let config = &self.config;
+        let pow_bits = config.proof_of_work_bits as usize;
+        let lde_bits = self.lde_bits();
+        FriSoundness {
+            conjectured_bits: (config.rate_bits * config.num_query_rounds + pow_bits)
+                .min(EXTENSION_FIELD_BITS.saturating_sub(lde_bits)),
+            provable_bits: (config.rate_bits * config.num_query_rounds / 2 + pow_bits)
+                .min(EXTENSION_FIELD_BITS.saturating_sub(2 * lde_bits)),
+        }
+    }
+}
+
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct SelectorsInfo {
+    pub selector_indices: Vec<usize>,
+    pub groups: Vec<Range<usize>>,
+}
+
+impl SelectorsInfo {
+    pub fn num_selectors(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Checks that the groups split the `num_gates` gates in contiguous ranges, and that every
+    /// gate has the selector of its group
+    pub fn check(&self, num_gates: usize) -> Result<(), ConversionError> {
+        check_consistent(
+            "number of selector indices",
+            num_gates,
+            self.selector_indices.len(),
+        )?;
+        let mut group_start = 0;
+        for (group, range) in self.groups.iter().enumerate() {
+            check_consistent("start of selector group", group_start, range.start)?;
+            for gate in range.clone() {
+                let index = self.selector_indices.get(gate).copied();
+                check_consistent("selector index", group, index.unwrap_or(usize::MAX))?;
+            }
+            group_start = range.end;
+        }
+        check_consistent("number of gates in selector groups", num_gates, group_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::{CircuitConfig, FriConfig, FriParams, FriSoundness, SelectorsInfo};
+    use crate::error::ConversionError;
+
+    #[test]
+    fn test_selectors_info_check() {
+        let selectors_info = SelectorsInfo {
+            selector_indices: vec![0, 0, 1],
+            groups: vec![0..2, 2..3],
+        };
+        assert_eq!(selectors_info.check(3), Ok(()));
+        assert!(matches!(
+            selectors_info.check(4),
+            Err(ConversionError::InconsistentCommonData {
+                quantity: "number of selector indices",
+                ..
+            })
+        ));
+
+        let overlapping = SelectorsInfo {
+            groups: vec![0..2, 1..3],
+            ..selectors_info
+        };
+        assert!(matches!(
+            overlapping.check(3),
+            Err(ConversionError::InconsistentCommonData {
+                quantity: "start of selector group",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_fri_soundness() {
+        // the standard recursion config of Plonky2
+        let params = FriParams {
+            config: FriConfig {
+                rate_bits: 3,
+                cap_height: 4,
+                proof_of_work_bits: 16,
+                num_query_rounds: 28,
+            },
+            degree_bits: 12,
+            ..FriParams::default()
+        };
+        assert_eq!(
+            params.soundness(),
+            FriSoundness {
+                conjectured_bits: 100,
+                provable_bits: 58,
+            }
+        );
+        let large = FriParams {
+            degree_bits: 29,
+            ..params
+        };
+        assert_eq!(large.soundness().conjectured_bits, 96);
+        assert_eq!(large.soundness().provable_bits, 58);
+    }
+
+    #[test]
+    fn test_num_partial_products() {
+        let config = CircuitConfig {
+            num_routed_wires: 80,
+            ..CircuitConfig::default()
+        };
+        assert_eq!(config.num_partial_products(8), 9);
+        assert_eq!(config.num_partial_products(7), 11);
+    }
+}
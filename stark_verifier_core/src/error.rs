@@ -0,0 +1,165 @@
+use alloc::string::String;
+use core::fmt;
+
+use crate::field::{is_canonical, Element};
+
+/// Part of a Plonky2 proof, verification key or common data, or of a Plonky3 proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProofComponent {
+    WiresCap,
+    PlonkZsPartialProductsCap,
+    QuotientPolysCap,
+    Openings,
+    CommitPhaseMerkleCap(usize),
+    QueryRound(usize),
+    FinalPoly,
+    PowWitness,
+    ConstantsSigmasCap,
+    CircuitDigest,
+    Gates,
+    FriParams,
+    /// Shifts of the cosets of the permutation argument
+    KIs,
+    PublicInputs,
+    Commitments,
+    CommitPhaseCommitments,
+    QueryRounds,
+}
+
+impl fmt::Display for ProofComponent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProofComponent::WiresCap => write!(f, "wires cap"),
+            ProofComponent::PlonkZsPartialProductsCap => {
+                write!(f, "zs and partial products cap")
+            }
+            ProofComponent::QuotientPolysCap => write!(f, "quotient polynomials cap"),
+            ProofComponent::Openings => write!(f, "openings"),
+            ProofComponent::CommitPhaseMerkleCap(i) => {
+                write!(f, "FRI commit phase Merkle cap {i}")
+            }
+            ProofComponent::QueryRound(i) => write!(f, "FRI query round {i}"),
+            ProofComponent::FinalPoly => write!(f, "FRI final polynomial"),
+            ProofComponent::PowWitness => write!(f, "FRI proof of work witness"),
+            ProofComponent::ConstantsSigmasCap => write!(f, "constants and sigmas cap"),
+            ProofComponent::CircuitDigest => write!(f, "circuit digest"),
+            ProofComponent::Gates => write!(f, "gates"),
+            ProofComponent::FriParams => write!(f, "FRI parameters"),
+            ProofComponent::KIs => write!(f, "permutation coset shifts"),
+            ProofComponent::PublicInputs => write!(f, "public inputs"),
+            ProofComponent::Commitments => write!(f, "commitments"),
+            ProofComponent::CommitPhaseCommitments => write!(f, "FRI commit phase commitments"),
+            ProofComponent::QueryRounds => write!(f, "FRI query rounds"),
+        }
+    }
+}
+
+/// Errors of the checks of the shape of a proof, and of the conversions from Plonky2 types into
+/// the values of the verifier circuit
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    /// `component` holds a field element which is not less than the Goldilocks modulus
+    NonCanonical(ProofComponent),
+    /// `component` doesn't have the length implied by the rest of the proof
+    LengthMismatch {
+        component: ProofComponent,
+        expected: usize,
+        found: usize,
+    },
+    /// The gate with the given id has no constrainer in the verifier circuit
+    UnsupportedGate(String),
+    /// The verifier circuit only folds FRI polynomials with arity 2
+    UnsupportedReductionArity(usize),
+    /// The FRI domain of `2^lde_bits` points is larger than the largest power-of-two subgroup
+    /// of Goldilocks
+    DomainTooLarge { lde_bits: usize },
+    /// A quantity of the common data differs from the one derived from the circuit config
+    InconsistentCommonData {
+        quantity: &'static str,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::NonCanonical(component) => {
+                write!(f, "{component} holds a non-canonical Goldilocks element")
+            }
+            ConversionError::LengthMismatch {
+                component,
+                expected,
+                found,
+            } => write!(f, "{component} has length {found}, expected {expected}"),
+            ConversionError::UnsupportedGate(id) => write!(f, "unsupported gate {id}"),
+            ConversionError::UnsupportedReductionArity(arity_bits) => write!(
+                f,
+                "FRI reduction arity 2^{arity_bits} is not supported, only arity 2 is"
+            ),
+            ConversionError::DomainTooLarge { lde_bits } => {
+                write!(
+                    f,
+                    "FRI domain of 2^{lde_bits} points is too large for Goldilocks"
+                )
+            }
+            ConversionError::InconsistentCommonData {
+                quantity,
+                expected,
+                found,
+            } => write!(
+                f,
+                "common data has {quantity} = {found}, but its config implies {expected}"
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ConversionError {}
+
+/// Fails unless every element of `elements`, which are part of `component`, is canonical
+pub fn check_canonical<'a>(
+    component: ProofComponent,
+    elements: impl IntoIterator<Item = &'a Element>,
+) -> Result<(), ConversionError> {
+    if elements.into_iter().all(|e| is_canonical(*e)) {
+        Ok(())
+    } else {
+        Err(ConversionError::NonCanonical(component))
+    }
+}
+
+/// Fails unless `component` has length `expected`
+pub fn check_len(
+    component: ProofComponent,
+    expected: usize,
+    found: usize,
+) -> Result<(), ConversionError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(ConversionError::LengthMismatch {
+            component,
+            expected,
+            found,
+        })
+    }
+}
+
+/// Fails unless the `quantity` of the common data is the `expected` one
+pub fn check_consistent(
+    quantity: &'static str,
+    expected: usize,
+    found: usize,
+) -> Result<(), ConversionError> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(ConversionError::InconsistentCommonData {
+            quantity,
+            expected,
+            found,
+        })
+    }
+}
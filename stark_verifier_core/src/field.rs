@@ -0,0 +1,21 @@
+//! Goldilocks elements as `u64`, with the constants of the field the checks depend on
+
+/// `2^64 - 2^32 + 1`
+pub const GOLDILOCKS_MODULUS: u64 = 0xffff_ffff_0000_0001;
+
+/// `2^TWO_ADICITY` is the largest power of two dividing `GOLDILOCKS_MODULUS - 1`
+pub const TWO_ADICITY: usize = 32;
+
+/// Element of Goldilocks
+pub type Element = u64;
+
+/// Element of the quadratic extension of Goldilocks, as its coefficients
+pub type ExtensionElement = [Element; 2];
+
+/// Poseidon digest, as its elements
+pub type HashOut = [Element; 4];
+
+/// Whether `e` is less than the modulus, which the verifier circuit relies on
+pub fn is_canonical(e: Element) -> bool {
+    e < GOLDILOCKS_MODULUS
+}
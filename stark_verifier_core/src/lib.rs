@@ -0,0 +1,23 @@
+//! Data model of the Plonky2 proofs the Halo2 verifier of `semaphore_aggregation` checks, with
+//! the checks of their shape done before they are assigned in the verifier circuit. It only
+//! depends on `core` and `alloc`, so that embedded and wasm light clients can decode and check
+//! proofs with the same types as the wrapper. Elements of Goldilocks and of its quadratic
+//! extension are held as their canonical `u64` representations, see `field`.
+//!
+//! `plonky3` holds the proofs of the uni-stark of Plonky3 over Goldilocks, which can be decoded
+//! and checked for shape but not wrapped: the verifier has no circuit for them.
+//!
+//! The conversions from the Plonky2 types live in `semaphore_aggregation::snark::types`, which
+//! builds the values of the verifier circuit from the types of this crate.
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod common_data;
+pub mod error;
+pub mod field;
+pub mod plonky3;
+pub mod proof;
+pub mod verification_key;
@@ -0,0 +1,256 @@
+//! Proofs of the uni-stark of Plonky3 over Goldilocks, committed with Poseidon2 Merkle trees and
+//! opened with its two-adic FRI folding with arity 2 down to a constant, mirroring
+//! `p3_uni_stark::Proof`. The trace and the quotient chunks are the two batches of matrices
+//! opened by every FRI query.
+//!
+//! Only the data model and the checks of its shape are implemented. These proofs can't be
+//! wrapped: there is no `InnerProofSystem` nor circuit verifying them in the Halo2 verifier,
+//! which would need a Poseidon2 chip and the constraints of the AIR of the proven program.
+use alloc::vec::Vec;
+
+use crate::error::{check_canonical, check_len, ConversionError, ProofComponent};
+use crate::field::{Element, ExtensionElement, HashOut, TWO_ADICITY};
+
+/// Number of batches of matrices opened by a FRI query: the trace and the quotient chunks
+pub const NUM_BATCHES: usize = 2;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriConfig {
+    /// `rate = 2^{-log_blowup}`
+    pub log_blowup: usize,
+    pub num_queries: usize,
+    pub proof_of_work_bits: usize,
+}
+
+/// Poseidon2 Merkle roots of the trace and of the quotient chunks
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Commitments {
+    pub trace: HashOut,
+    pub quotient_chunks: HashOut,
+}
+
+/// Values opened at the out-of-domain point `zeta`, and at `zeta` times the generator of the
+/// trace domain for `trace_next`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpenedValues {
+    pub trace_local: Vec<ExtensionElement>,
+    pub trace_next: Vec<ExtensionElement>,
+    pub quotient_chunks: Vec<Vec<ExtensionElement>>,
+}
+
+/// Rows of the matrices of a batch at a queried index, with their Merkle path
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchOpening {
+    /// Row of every matrix of the batch
+    pub opened_values: Vec<Vec<Element>>,
+    pub opening_proof: Vec<HashOut>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CommitPhaseProofStep {
+    /// Evaluation of the folded polynomial at the sibling of the queried point
+    pub sibling_value: ExtensionElement,
+    pub opening_proof: Vec<HashOut>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct QueryProof {
+    /// Opening of every batch, in the order of `NUM_BATCHES`
+    pub input_proof: Vec<BatchOpening>,
+    pub commit_phase_openings: Vec<CommitPhaseProofStep>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriProof {
+    pub commit_phase_commits: Vec<HashOut>,
+    pub query_proofs: Vec<QueryProof>,
+    pub final_poly: ExtensionElement,
+    pub pow_witness: Element,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Proof {
+    pub commitments: Commitments,
+    pub opened_values: OpenedValues,
+    pub opening_proof: FriProof,
+    /// Log of the number of rows of the trace
+    pub degree_bits: usize,
+}
+
+impl Proof {
+    /// Checks that every element of the proof is canonical and that the lengths of its
+    /// components are the ones implied by its degree and `config`
+    pub fn check(&self, config: &FriConfig) -> Result<(), ConversionError> {
+        let commitments = &self.commitments;
+        check_canonical(
+            ProofComponent::Commitments,
+            commitments.trace.iter().chain(&commitments.quotient_chunks),
+        )?;
+
+        let opened_values = &self.opened_values;
+        check_len(
+            ProofComponent::Openings,
+            opened_values.trace_local.len(),
+            opened_values.trace_next.len(),
+        )?;
+        check_canonical(
+            ProofComponent::Openings,
+            [&opened_values.trace_local, &opened_values.trace_next]
+                .into_iter()
+                .chain(&opened_values.quotient_chunks)
+                .flatten()
+                .flatten(),
+        )?;
+
+        let lde_bits = self.degree_bits + config.log_blowup;
+        if lde_bits > TWO_ADICITY {
+            return Err(ConversionError::DomainTooLarge { lde_bits });
+        }
+        let fri_proof = &self.opening_proof;
+        // every round folds the domain in half, down to `2^log_blowup` points
+        check_len(
+            ProofComponent::CommitPhaseCommitments,
+            self.degree_bits,
+            fri_proof.commit_phase_commits.len(),
+        )?;
+        check_canonical(
+            ProofComponent::CommitPhaseCommitments,
+            fri_proof.commit_phase_commits.iter().flatten(),
+        )?;
+        check_len(
+            ProofComponent::QueryRounds,
+            config.num_queries,
+            fri_proof.query_proofs.len(),
+        )?;
+        // rows of the trace batch, then of the quotient batch, in the widths of the openings
+        let widths = [opened_values.trace_local.len()]
+            .into_iter()
+            .chain(opened_values.quotient_chunks.iter().map(Vec::len));
+        for (i, query) in fri_proof.query_proofs.iter().enumerate() {
+            let component = ProofComponent::QueryRound(i);
+            check_len(component, NUM_BATCHES, query.input_proof.len())?;
+            let rows = query
+                .input_proof
+                .iter()
+                .flat_map(|batch| &batch.opened_values);
+            check_len(
+                component,
+                1 + opened_values.quotient_chunks.len(),
+                rows.clone().count(),
+            )?;
+            for (width, row) in widths.clone().zip(rows) {
+                check_len(component, width, row.len())?;
+            }
+            for batch in query.input_proof.iter() {
+                check_len(component, lde_bits, batch.opening_proof.len())?;
+                check_canonical(component, batch.opened_values.iter().flatten())?;
+                check_canonical(component, batch.opening_proof.iter().flatten())?;
+            }
+
+            check_len(
+                component,
+                self.degree_bits,
+                query.commit_phase_openings.len(),
+            )?;
+            for (round, step) in query.commit_phase_openings.iter().enumerate() {
+                // the folded codeword of the round has `2^{lde_bits - round - 1}` rows of pairs
+                check_len(component, lde_bits - round - 1, step.opening_proof.len())?;
+                check_canonical(component, step.sibling_value.iter())?;
+                check_canonical(component, step.opening_proof.iter().flatten())?;
+            }
+        }
+
+        check_canonical(ProofComponent::FinalPoly, fri_proof.final_poly.iter())?;
+        check_canonical(ProofComponent::PowWitness, [&fri_proof.pow_witness])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::{
+        BatchOpening, CommitPhaseProofStep, FriConfig, FriProof, OpenedValues, Proof, QueryProof,
+    };
+    use crate::error::{ConversionError, ProofComponent};
+    use crate::field::GOLDILOCKS_MODULUS;
+
+    const CONFIG: FriConfig = FriConfig {
+        log_blowup: 1,
+        num_queries: 2,
+        proof_of_work_bits: 0,
+    };
+
+    /// Proof of a trace of 3 columns and `2^degree_bits` rows, with 2 quotient chunks
+    fn proof(degree_bits: usize) -> Proof {
+        let lde_bits = degree_bits + CONFIG.log_blowup;
+        let batch = |widths: &[usize]| BatchOpening {
+            opened_values: widths.iter().map(|width| vec![1; *width]).collect(),
+            opening_proof: vec![[2; 4]; lde_bits],
+        };
+        let query = QueryProof {
+            input_proof: vec![batch(&[3]), batch(&[2, 2])],
+            commit_phase_openings: (0..degree_bits)
+                .map(|round| CommitPhaseProofStep {
+                    sibling_value: [3, 4],
+                    opening_proof: vec![[5; 4]; lde_bits - round - 1],
+                })
+                .collect(),
+        };
+        Proof {
+            opened_values: OpenedValues {
+                trace_local: vec![[1, 2]; 3],
+                trace_next: vec![[3, 4]; 3],
+                quotient_chunks: vec![vec![[5, 6]; 2]; 2],
+            },
+            opening_proof: FriProof {
+                commit_phase_commits: vec![[6; 4]; degree_bits],
+                query_proofs: vec![query; CONFIG.num_queries],
+                ..FriProof::default()
+            },
+            degree_bits,
+            ..Proof::default()
+        }
+    }
+
+    #[test]
+    fn test_proof_check() {
+        assert_eq!(proof(4).check(&CONFIG), Ok(()));
+
+        let mut missing_step = proof(4);
+        missing_step.opening_proof.query_proofs[1]
+            .commit_phase_openings
+            .pop();
+        assert_eq!(
+            missing_step.check(&CONFIG),
+            Err(ConversionError::LengthMismatch {
+                component: ProofComponent::QueryRound(1),
+                expected: 4,
+                found: 3,
+            })
+        );
+
+        let mut narrow_chunk = proof(4);
+        narrow_chunk.opening_proof.query_proofs[0].input_proof[1].opened_values[1].pop();
+        assert_eq!(
+            narrow_chunk.check(&CONFIG),
+            Err(ConversionError::LengthMismatch {
+                component: ProofComponent::QueryRound(0),
+                expected: 2,
+                found: 1,
+            })
+        );
+
+        let mut non_canonical = proof(4);
+        non_canonical.opening_proof.pow_witness = GOLDILOCKS_MODULUS;
+        assert_eq!(
+            non_canonical.check(&CONFIG),
+            Err(ConversionError::NonCanonical(ProofComponent::PowWitness))
+        );
+
+        assert_eq!(
+            proof(32).check(&CONFIG),
+            Err(ConversionError::DomainTooLarge { lde_bits: 33 })
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! Plonky2 proofs over Goldilocks with its quadratic extension, the only extension degree the
+//! verifier circuit emulates, mirroring `plonky2::plonk::proof::Proof`
+use alloc::vec::Vec;
+
+use crate::error::{check_canonical, check_len, ConversionError, ProofComponent};
+use crate::field::{Element, ExtensionElement, HashOut};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleCap(pub Vec<HashOut>);
+
+impl MerkleCap {
+    /// Fails unless the cap, part of `component`, has `cap_len` canonical digests
+    pub fn check(&self, component: ProofComponent, cap_len: usize) -> Result<(), ConversionError> {
+        check_len(component, cap_len, self.0.len())?;
+        check_canonical(component, self.0.iter().flatten())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub siblings: Vec<HashOut>,
+}
+
+impl MerkleProof {
+    fn check(&self, component: ProofComponent) -> Result<(), ConversionError> {
+        check_canonical(component, self.siblings.iter().flatten())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OpeningSet {
+    pub constants: Vec<ExtensionElement>,
+    pub plonk_sigmas: Vec<ExtensionElement>,
+    pub wires: Vec<ExtensionElement>,
+    pub plonk_zs: Vec<ExtensionElement>,
+    pub plonk_zs_next: Vec<ExtensionElement>,
+    pub partial_products: Vec<ExtensionElement>,
+    pub quotient_polys: Vec<ExtensionElement>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriInitialTreeProof {
+    pub evals_proofs: Vec<(Vec<Element>, MerkleProof)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriQueryStep {
+    pub evals: Vec<ExtensionElement>,
+    pub merkle_proof: MerkleProof,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriQueryRound {
+    pub initial_trees_proof: FriInitialTreeProof,
+    pub steps: Vec<FriQueryStep>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriProof {
+    pub commit_phase_merkle_caps: Vec<MerkleCap>,
+    pub query_round_proofs: Vec<FriQueryRound>,
+    /// Coefficients of the final polynomial
+    pub final_poly: Vec<ExtensionElement>,
+    pub pow_witness: Element,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Proof {
+    pub wires_cap: MerkleCap,
+    pub plonk_zs_partial_products_cap: MerkleCap,
+    pub quotient_polys_cap: MerkleCap,
+    pub openings: OpeningSet,
+    pub opening_proof: FriProof,
+}
+
+impl Proof {
+    /// Checks that every element of the proof is canonical and that the lengths of its
+    /// components are consistent with each other, so that the verifier circuit doesn't panic
+    /// while assigning it
+    pub fn check(&self) -> Result<(), ConversionError> {
+        let cap_len = self.wires_cap.0.len();
+        if !cap_len.is_power_of_two() {
+            return Err(ConversionError::LengthMismatch {
+                component: ProofComponent::WiresCap,
+                expected: cap_len.next_power_of_two(),
+                found: cap_len,
+            });
+        }
+        self.wires_cap.check(ProofComponent::WiresCap, cap_len)?;
+        self.plonk_zs_partial_products_cap
+            .check(ProofComponent::PlonkZsPartialProductsCap, cap_len)?;
+        self.quotient_polys_cap
+            .check(ProofComponent::QuotientPolysCap, cap_len)?;
+
+        let openings = &self.openings;
+        check_len(
+            ProofComponent::Openings,
+            openings.plonk_zs.len(),
+            openings.plonk_zs_next.len(),
+        )?;
+        check_canonical(
+            ProofComponent::Openings,
+            [
+                &openings.constants,
+                &openings.plonk_sigmas,
+                &openings.wires,
+                &openings.plonk_zs,
+                &openings.plonk_zs_next,
+                &openings.partial_products,
+                &openings.quotient_polys,
+            ]
+            .into_iter()
+            .flatten()
+            .flatten(),
+        )?;
+
+        let fri_proof = &self.opening_proof;
+        for (i, cap) in fri_proof.commit_phase_merkle_caps.iter().enumerate() {
+            cap.check(ProofComponent::CommitPhaseMerkleCap(i), cap_len)?;
+        }
+        let num_steps = fri_proof.commit_phase_merkle_caps.len();
+        let first_round = fri_proof.query_round_proofs.first();
+        for (i, round) in fri_proof.query_round_proofs.iter().enumerate() {
+            let component = ProofComponent::QueryRound(i);
+            check_len(component, num_steps, round.steps.len())?;
+            if let Some(first_round) = first_round {
+                check_len(
+                    component,
+                    first_round.initial_trees_proof.evals_proofs.len(),
+                    round.initial_trees_proof.evals_proofs.len(),
+                )?;
+                for (first_step, step) in first_round.steps.iter().zip(round.steps.iter()) {
+                    check_len(component, first_step.evals.len(), step.evals.len())?;
+                }
+            }
+            for (evals, merkle_proof) in round.initial_trees_proof.evals_proofs.iter() {
+                check_canonical(component, evals.iter())?;
+                merkle_proof.check(component)?;
+            }
+            for step in round.steps.iter() {
+                check_canonical(component, step.evals.iter().flatten())?;
+                step.merkle_proof.check(component)?;
+            }
+        }
+
+        let final_poly_len = fri_proof.final_poly.len();
+        if !final_poly_len.is_power_of_two() {
+            return Err(ConversionError::LengthMismatch {
+                component: ProofComponent::FinalPoly,
+                expected: final_poly_len.next_power_of_two(),
+                found: final_poly_len,
+            });
+        }
+        check_canonical(
+            ProofComponent::FinalPoly,
+            fri_proof.final_poly.iter().flatten(),
+        )?;
+        check_canonical(ProofComponent::PowWitness, [&fri_proof.pow_witness])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::{FriProof, MerkleCap, Proof};
+    use crate::error::{ConversionError, ProofComponent};
+    use crate::field::GOLDILOCKS_MODULUS;
+
+    fn proof(cap_len: usize) -> Proof {
+        let cap = MerkleCap(vec![[1, 2, 3, 4]; cap_len]);
+        Proof {
+            wires_cap: cap.clone(),
+            plonk_zs_partial_products_cap: cap.clone(),
+            quotient_polys_cap: cap,
+            opening_proof: FriProof {
+                final_poly: vec![[0, 1]; 2],
+                ..FriProof::default()
+            },
+            ..Proof::default()
+        }
+    }
+
+    #[test]
+    fn test_proof_check() {
+        assert_eq!(proof(2).check(), Ok(()));
+        assert_eq!(
+            proof(3).check(),
+            Err(ConversionError::LengthMismatch {
+                component: ProofComponent::WiresCap,
+                expected: 4,
+                found: 3,
+            })
+        );
+
+        let mut non_canonical = proof(2);
+        non_canonical.quotient_polys_cap.0[1][0] = GOLDILOCKS_MODULUS;
+        assert_eq!(
+            non_canonical.check(),
+            Err(ConversionError::NonCanonical(
+                ProofComponent::QuotientPolysCap
+            ))
+        );
+    }
+}
@@ -0,0 +1,34 @@
+//! Verifier-only data of a Plonky2 circuit, mirroring
+//! `plonky2::plonk::circuit_data::VerifierOnlyCircuitData`
+use alloc::vec::Vec;
+
+use crate::error::{check_canonical, ConversionError, ProofComponent};
+use crate::field::{Element, HashOut};
+use crate::proof::MerkleCap;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerificationKey {
+    pub constants_sigmas_cap: MerkleCap,
+    pub circuit_digest: HashOut,
+}
+
+impl VerificationKey {
+    /// Elements of the constants and sigmas cap, then of the circuit digest
+    pub fn elements(&self) -> Vec<Element> {
+        self.constants_sigmas_cap
+            .0
+            .iter()
+            .chain(core::iter::once(&self.circuit_digest))
+            .flatten()
+            .copied()
+            .collect()
+    }
+
+    /// Checks that every element of the key is canonical
+    pub fn check(&self) -> Result<(), ConversionError> {
+        let cap_len = self.constants_sigmas_cap.0.len();
+        self.constants_sigmas_cap
+            .check(ProofComponent::ConstantsSigmasCap, cap_len)?;
+        check_canonical(ProofComponent::CircuitDigest, self.circuit_digest.iter())
+    }
+}